@@ -0,0 +1,96 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timings of GF(256) inversion and of recovering large secrets from 16 shares.
+//! Run with `cargo bench --bench gf256`
+
+use rand::{thread_rng, RngCore};
+use sssmc39::{recover_secret_raw, split_secret_raw, Gf256};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20_000;
+
+fn time(name: &str, rounds: u32, mut f: impl FnMut()) -> Duration {
+	let start = Instant::now();
+	for _ in 0..rounds {
+		f();
+	}
+	let per_round = start.elapsed() / rounds;
+	println!("{:<48} {:>10.2?}", name, per_round);
+	per_round
+}
+
+// Lagrange basis weights at x = 255 for share indices 0..16, dividing per pair
+fn bases_by_division(xs: &[Gf256], x: Gf256) -> Vec<Gf256> {
+	xs.iter()
+		.enumerate()
+		.map(|(i, &xi)| {
+			let mut basis = Gf256::one();
+			for (j, &xj) in xs.iter().enumerate() {
+				if i != j {
+					basis *= (x - xj) / (xi - xj);
+				}
+			}
+			basis
+		})
+		.collect()
+}
+
+// the same weights, inverting the denominators in one batch
+fn bases_by_batch_inversion(xs: &[Gf256], x: Gf256) -> Vec<Gf256> {
+	let mut numerators = vec![Gf256::one(); xs.len()];
+	let mut bases = vec![Gf256::one(); xs.len()];
+	for (i, &xi) in xs.iter().enumerate() {
+		for (j, &xj) in xs.iter().enumerate() {
+			if i != j {
+				numerators[i] *= x - xj;
+				bases[i] *= xi - xj;
+			}
+		}
+	}
+	Gf256::batch_inv(&mut bases);
+	bases.iter().zip(numerators).map(|(b, n)| *b * n).collect()
+}
+
+fn main() {
+	let xs: Vec<Gf256> = (0..16).map(Gf256::from_byte).collect();
+	let x = Gf256::from_byte(255);
+	assert_eq!(bases_by_division(&xs, x), bases_by_batch_inversion(&xs, x));
+
+	let by_division = time("16 basis weights, dividing per pair", ROUNDS, || {
+		black_box(bases_by_division(black_box(&xs), x));
+	});
+	let by_batch = time("16 basis weights, batch inversion", ROUNDS, || {
+		black_box(bases_by_batch_inversion(black_box(&xs), x));
+	});
+	println!(
+		"{:<48} {:>10.2}x",
+		"speedup",
+		by_division.as_secs_f64() / by_batch.as_secs_f64()
+	);
+
+	for len in [256usize, 4096] {
+		let mut secret = vec![0u8; len];
+		thread_rng().fill_bytes(&mut secret);
+		let shares = split_secret_raw(16, 16, &secret, &mut thread_rng()).unwrap();
+		time(
+			&format!("recover a {} byte secret from 16 shares", len),
+			50,
+			|| {
+				black_box(recover_secret_raw(black_box(&shares), 16).unwrap());
+			},
+		);
+	}
+}
@@ -0,0 +1,209 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transcripts of a recovery for compliance logging, recording which share indices
+//! were interpolated at each level, whether the digests verified, the KDF parameters
+//! and how long each step took. A transcript holds only indices, thresholds,
+//! parameters, outcomes and timings: never share values, mnemonic words, the
+//! passphrase or any intermediate or recovered secret. Only available with the
+//! `audit` feature.
+
+use crate::error::Error;
+use crate::shamir::{decode_mnemonics, recover_ems_recorded};
+use crate::util::encrypt::MasterSecretEncConfig;
+use crate::util::secret::Passphrase;
+use crate::{Format, RecoveredSecret, SplitterConfig};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub use crate::shamir::InterpolationRecord;
+
+/// Parameters of the passphrase decryption
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KdfRecord {
+	/// KDF profile byte (0 for SLIP-39's PBKDF2)
+	pub profile: u8,
+	/// Iteration exponent of the share set
+	pub iteration_exponent: u8,
+	/// Rounds of the Feistel cipher
+	pub round_count: u8,
+	/// PBKDF2 iterations in each round
+	pub iterations_per_round: u32,
+	/// Time taken by the decryption
+	pub elapsed: Duration,
+}
+
+/// Non-secret record of a recovery
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuditTranscript {
+	/// Number of mnemonics supplied
+	pub mnemonic_count: usize,
+	/// Identifier of the share set, once the mnemonics have been decoded
+	pub identifier: Option<u16>,
+	/// Interpolations in the order performed: each group, then the master secret
+	pub interpolations: Vec<InterpolationRecord>,
+	/// The decryption, if the recovery got that far
+	pub kdf: Option<KdfRecord>,
+	/// Stable code of the error the recovery failed with (see
+	/// [`ErrorKind::code`](crate::ErrorKind::code)), or None if it succeeded
+	pub error_code: Option<u32>,
+	/// Time taken by the whole recovery
+	pub elapsed: Duration,
+}
+
+/// As [`combine_mnemonics`](crate::combine_mnemonics), also returning a transcript
+/// of the recovery. The transcript is returned whether or not the recovery succeeds
+pub fn combine_mnemonics_audited(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> (Result<RecoveredSecret, Error>, AuditTranscript) {
+	let start = Instant::now();
+	let mut transcript = AuditTranscript {
+		mnemonic_count: mnemonics.len(),
+		..Default::default()
+	};
+	let result = recover(mnemonics, passphrase.into(), &mut transcript);
+	transcript.error_code = result.as_ref().err().map(|e| e.code());
+	transcript.elapsed = start.elapsed();
+	(result, transcript)
+}
+
+fn recover(
+	mnemonics: &[Vec<String>],
+	passphrase: Passphrase,
+	transcript: &mut AuditTranscript,
+) -> Result<RecoveredSecret, Error> {
+	let shares = decode_mnemonics(mnemonics)?;
+	transcript.identifier = shares.iter().next().map(|s| s.identifier);
+	let ems = recover_ems_recorded(&shares, &SplitterConfig::new(), &mut |r| {
+		transcript.interpolations.push(r)
+	})?;
+
+	let mut enc_config = MasterSecretEncConfig::new();
+	if ems.format == Format::ExtendedV2 {
+		enc_config.profile = crate::KdfProfile::from_byte(ems.kdf_profile).unwrap_or_default();
+	}
+	let start = Instant::now();
+	let result = ems.decrypt_with_config(passphrase, &enc_config);
+	transcript.kdf = Some(KdfRecord {
+		profile: enc_config.profile.to_byte(),
+		iteration_exponent: ems.iteration_exponent,
+		round_count: enc_config.round_count,
+		iterations_per_round: (enc_config.min_iteration_count / u32::from(enc_config.round_count))
+			<< ems.iteration_exponent,
+		elapsed: start.elapsed(),
+	});
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::hex::to_hex;
+	use crate::{generate_mnemonics, ErrorKind};
+
+	// every trace of the secrets that could leak into a transcript
+	fn assert_no_key_material(transcript: &str, secrets: &[&[u8]], words: &[Vec<String>]) {
+		for s in secrets {
+			for w in s.windows(4) {
+				assert!(
+					!transcript.contains(&to_hex(w)),
+					"secret bytes in transcript"
+				);
+				let decimal: Vec<String> = w.iter().map(|b| b.to_string()).collect();
+				assert!(
+					!transcript.contains(&decimal.join(", ")),
+					"secret bytes in transcript"
+				);
+				assert!(
+					!transcript.contains(&decimal.join(",")),
+					"secret bytes in transcript"
+				);
+			}
+		}
+		for m in words {
+			for w in m.windows(2) {
+				assert!(
+					!transcript.contains(&w.join(" ")),
+					"mnemonic words in transcript"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn audit_transcript() -> Result<(), Error> {
+		let secret = b"\xde\xad\xbe\xef\x01\x23\x45\x67\x89\xab\xcd\xef\xfe\xdc\xba\x98";
+		let passphrase = "correct horse battery staple";
+		let groups = generate_mnemonics(2, &[(2, 3), (1, 1), (3, 5)], secret, passphrase, 0)?;
+		let mut mnemonics = groups[0].mnemonic_list()?[..2].to_vec();
+		mnemonics.extend(groups[2].mnemonic_list()?);
+
+		let (result, transcript) = combine_mnemonics_audited(&mnemonics, passphrase);
+		let recovered = result?;
+		assert_eq!(&*recovered.secret, &secret[..]);
+		assert_eq!(
+			transcript.identifier,
+			Some(groups[0].member_shares[0].identifier)
+		);
+		assert_eq!(transcript.interpolations.len(), 3);
+		assert_eq!(transcript.interpolations[0].x_coordinates, vec![0, 1]);
+		assert_eq!(
+			transcript.interpolations[1].x_coordinates,
+			vec![0, 1, 2, 3, 4]
+		);
+		assert_eq!(transcript.interpolations[2].group_index, None);
+		assert_eq!(transcript.interpolations[2].x_coordinates, vec![0, 2]);
+		assert!(transcript
+			.interpolations
+			.iter()
+			.all(|i| i.verified && i.digest_checked));
+		let kdf = transcript.kdf.clone().unwrap();
+		assert_eq!((kdf.round_count, kdf.iterations_per_round), (4, 2500));
+		assert_eq!(transcript.error_code, None);
+
+		let ems = crate::combine_to_ems(&mnemonics)?;
+		let all_words: Vec<Vec<String>> = groups
+			.iter()
+			.flat_map(|g| g.mnemonic_list().unwrap())
+			.collect();
+		let shares: Vec<Vec<u8>> = groups
+			.iter()
+			.flat_map(|g| g.member_shares.iter().map(|s| s.share_value.clone()))
+			.collect();
+		let mut secrets: Vec<&[u8]> = vec![&secret[..], &ems.value, passphrase.as_bytes()];
+		secrets.extend(shares.iter().map(|s| &s[..]));
+		assert_no_key_material(&format!("{:?}", transcript), &secrets, &all_words);
+		assert!(!format!("{:?}", transcript).contains("horse"));
+		#[cfg(feature = "serde")]
+		{
+			let json = serde_json::to_string(&transcript).unwrap();
+			assert_no_key_material(&json, &secrets, &all_words);
+		}
+
+		// a failed recovery still has a transcript up to the failure
+		let (result, transcript) = combine_mnemonics_audited(&mnemonics[..2], passphrase);
+		assert!(result.is_err());
+		assert_eq!(transcript.kdf, None);
+		assert_eq!(
+			transcript.error_code,
+			Some(ErrorKind::Mnemonic(String::new()).code())
+		);
+		Ok(())
+	}
+}
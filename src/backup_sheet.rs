@@ -0,0 +1,158 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Printable "backup card" documents, one per member share
+
+use crate::error::Error;
+use crate::shamir::{GroupShare, Share};
+
+/// Number of words per row in the mnemonic grid
+const GRID_COLUMNS: usize = 4;
+
+/// Output format of a backup sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetFormat {
+	/// Plain text, suitable for printing directly
+	PlainText,
+	/// Markdown, for rendering to HTML or PDF
+	Markdown,
+}
+
+/// Render one backup sheet for each member share of each group in the set
+pub fn backup_sheets(groups: &[GroupShare], format: SheetFormat) -> Result<Vec<String>, Error> {
+	let mut sheets = vec![];
+	for g in groups {
+		for s in &g.member_shares {
+			sheets.push(backup_sheet(g, s, format)?);
+		}
+	}
+	Ok(sheets)
+}
+
+/// Render the backup sheet for a single member share of the given group
+pub fn backup_sheet(
+	group: &GroupShare,
+	share: &Share,
+	format: SheetFormat,
+) -> Result<String, Error> {
+	let words = share.to_mnemonic()?;
+	let (h1, h2, bullet) = match format {
+		SheetFormat::PlainText => ("", "", "  * "),
+		SheetFormat::Markdown => ("# ", "## ", "- "),
+	};
+	let mut out = String::new();
+	out.push_str(&format!(
+		"{}SLIP-39 Backup Share - Set {:05}\n\n",
+		h1, group.group_id
+	));
+	if let Some(l) = &group.label {
+		out.push_str(&format!("{}Group: {}\n", bullet, l));
+	}
+	if let Some(l) = &share.label {
+		out.push_str(&format!("{}Custodian: {}\n", bullet, l));
+	}
+	out.push_str(&format!(
+		"{}Share {} of group {} (of {} groups)\n",
+		bullet,
+		share.member_index + 1,
+		group.group_index + 1,
+		group.group_count,
+	));
+	out.push_str(&format!(
+		"{}{} shares of this group are needed to restore the group\n",
+		bullet, group.member_threshold,
+	));
+	out.push_str(&format!(
+		"{}{} of the {} groups are needed to restore the secret\n\n",
+		bullet, group.group_threshold, group.group_count,
+	));
+
+	out.push_str(&format!("{}Mnemonic ({} words)\n\n", h2, words.len()));
+	let rows: Vec<&[String]> = words.chunks(GRID_COLUMNS).collect();
+	match format {
+		SheetFormat::PlainText => {
+			for (r, row) in rows.iter().enumerate() {
+				let cells: Vec<String> = row
+					.iter()
+					.enumerate()
+					.map(|(c, w)| format!("{:>2}. {:<10}", r * GRID_COLUMNS + c + 1, w))
+					.collect();
+				out.push_str(cells.join(" ").trim_end());
+				out.push('\n');
+			}
+		}
+		SheetFormat::Markdown => {
+			out.push_str(&format!("|{}\n", " # | Word |".repeat(GRID_COLUMNS)));
+			out.push_str(&format!("|{}\n", "--:|------|".repeat(GRID_COLUMNS)));
+			for (r, row) in rows.iter().enumerate() {
+				out.push('|');
+				for c in 0..GRID_COLUMNS {
+					match row.get(c) {
+						Some(w) => {
+							out.push_str(&format!(" {} | {} |", r * GRID_COLUMNS + c + 1, w))
+						}
+						None => out.push_str("  |  |"),
+					}
+				}
+				out.push('\n');
+			}
+		}
+	}
+
+	out.push_str(&format!("\n{}Custody\n\n", h2));
+	for field in &["Custodian signature", "Date", "Location"] {
+		out.push_str(&format!(
+			"{}{}: ______________________________\n",
+			bullet, field
+		));
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn render_backup_sheets() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let mut groups = generate_mnemonics(2, &[(2, 3), (1, 1)], &master_secret, "", 0)?;
+		groups[0].set_label("Family");
+		groups[0].set_member_label(0, "Alice")?;
+
+		let sheets = backup_sheets(&groups, SheetFormat::PlainText)?;
+		assert_eq!(sheets.len(), 4);
+		assert!(sheets[0].contains("Custodian: Alice"));
+		assert!(sheets[0].contains("Group: Family"));
+		assert!(sheets[0].contains("2 shares of this group are needed"));
+		let words = groups[0].member_shares[0].to_mnemonic()?;
+		assert!(sheets[0].contains(&format!("20. {}", words[19])));
+		assert!(sheets[0].contains("Custodian signature: ____"));
+
+		let md = backup_sheet(
+			&groups[1],
+			&groups[1].member_shares[0],
+			SheetFormat::Markdown,
+		)?;
+		assert!(md.starts_with("# SLIP-39 Backup Share"));
+		assert!(md.contains(&format!(
+			"| 1 | {} |",
+			groups[1].member_shares[0].to_mnemonic()?[0]
+		)));
+		// 20 words in 4 columns, plus header and separator rows
+		assert_eq!(md.lines().filter(|l| l.starts_with('|')).count(), 7);
+		Ok(())
+	}
+}
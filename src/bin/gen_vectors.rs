@@ -0,0 +1,42 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writes deterministic SLIP-39 test vectors in the upstream vectors.json format
+//! to stdout, for cross-testing other implementations.
+//!
+//! Usage: `gen_vectors [seed]` (the seed defaults to 0)
+
+use sssmc39::vectors::{self, VectorSpec};
+use std::process;
+
+fn main() {
+	let seed = match std::env::args().nth(1) {
+		Some(s) => match s.parse::<u64>() {
+			Ok(seed) => seed,
+			Err(_) => {
+				eprintln!("Usage: gen_vectors [seed]");
+				process::exit(2);
+			}
+		},
+		None => 0,
+	};
+	let output = vectors::generate(seed, &VectorSpec::default()).and_then(|v| vectors::to_json(&v));
+	match output {
+		Ok(json) => println!("{}", json),
+		Err(e) => {
+			eprintln!("Unable to generate test vectors: {}", e);
+			process::exit(1);
+		}
+	}
+}
@@ -0,0 +1,187 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BIP-32 key fingerprints of a recovered master secret, for checking a recovery
+//! against a known wallet ("does this recovery produce the wallet with fingerprint
+//! 3442193e?") without exposing any keys. The master secret is used directly as
+//! the BIP-32 seed, as SLIP-39 wallets do. Only available with the `bip32` feature.
+//! Curve arithmetic is from the `k256` crate
+
+use crate::error::{Error, ErrorKind};
+use crate::util::secret::zeroize;
+use crate::RecoveredSecret;
+
+use crate::util::crypto::{hmac_sha512, sha256};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::zeroize::Zeroizing;
+use k256::{NonZeroScalar, SecretKey};
+use ripemd::{Digest, Ripemd160};
+use std::convert::TryFrom;
+
+/// Length in bytes of a BIP-32 key fingerprint
+pub const KEY_FINGERPRINT_LEN: usize = 4;
+
+/// Offset of hardened child indices
+pub const HARDENED: u32 = 0x8000_0000;
+
+const MASTER_KEY_SALT: &[u8] = b"Bitcoin seed";
+
+// private key and chain code
+struct ExtendedKey {
+	key: SecretKey,
+	chain_code: [u8; 32],
+}
+
+impl Drop for ExtendedKey {
+	fn drop(&mut self) {
+		// the key zeroes itself
+		zeroize(&mut self.chain_code);
+	}
+}
+
+impl ExtendedKey {
+	// split an HMAC-SHA512 output into key and chain code, the key being the left
+	// half added to the parent key if there is one. None if the key is invalid
+	fn from_hmac(salt: &[u8], data: &[u8], parent: Option<&SecretKey>) -> Option<ExtendedKey> {
+		let mut i = hmac_sha512(salt, data);
+		let mut chain_code = [0u8; 32];
+		chain_code.copy_from_slice(&i[32..]);
+		let left = NonZeroScalar::try_from(&i[..32]).ok();
+		zeroize(&mut i);
+		let key = match parent {
+			None => left?,
+			Some(p) => Option::from(NonZeroScalar::new(*left? + *p.to_nonzero_scalar()))?,
+		};
+		Some(ExtendedKey {
+			key: SecretKey::from(key),
+			chain_code,
+		})
+	}
+
+	fn master(seed: &[u8]) -> Result<ExtendedKey, Error> {
+		if seed.len() < 16 || seed.len() > 64 {
+			return Err(ErrorKind::Value(format!(
+				"BIP-32 seed must be 16 to 64 bytes, got {}",
+				seed.len()
+			)))?;
+		}
+		match ExtendedKey::from_hmac(MASTER_KEY_SALT, seed, None) {
+			Some(k) => Ok(k),
+			None => Err(ErrorKind::Value("Invalid BIP-32 master key".to_string()))?,
+		}
+	}
+
+	fn child(&self, index: u32) -> Result<ExtendedKey, Error> {
+		let mut data = Vec::with_capacity(37);
+		if index >= HARDENED {
+			data.push(0);
+			data.extend_from_slice(&Zeroizing::new(self.key.to_bytes()));
+		} else {
+			data.extend_from_slice(&self.public_key());
+		}
+		data.extend_from_slice(&index.to_be_bytes());
+		let k = ExtendedKey::from_hmac(&self.chain_code, &data, Some(&self.key));
+		zeroize(&mut data);
+		match k {
+			Some(k) => Ok(k),
+			None => Err(ErrorKind::Value(format!(
+				"Invalid BIP-32 child key {}",
+				index
+			)))?,
+		}
+	}
+
+	// compressed SEC1 encoding of the public key
+	fn public_key(&self) -> Vec<u8> {
+		self.key
+			.public_key()
+			.to_encoded_point(true)
+			.as_bytes()
+			.to_vec()
+	}
+
+	// first 4 bytes of HASH160 of the public key
+	fn fingerprint(&self) -> [u8; KEY_FINGERPRINT_LEN] {
+		let id = Ripemd160::digest(sha256(&self.public_key()));
+		let mut fp = [0u8; KEY_FINGERPRINT_LEN];
+		fp.copy_from_slice(&id[..KEY_FINGERPRINT_LEN]);
+		fp
+	}
+}
+
+/// Parse a derivation path such as `m/44'/0'/0'` into child indices. Hardened
+/// indices may be marked with `'`, `h` or `H`
+pub fn parse_path(path: &str) -> Result<Vec<u32>, Error> {
+	let err = || ErrorKind::Value(format!("Invalid BIP-32 derivation path '{}'", path));
+	let mut parts = path.trim().split('/');
+	if parts.next() != Some("m") {
+		Err(err())?;
+	}
+	parts
+		.map(|p| {
+			let (num, hardened) = match p.strip_suffix(&['\'', 'h', 'H'][..]) {
+				Some(n) => (n, true),
+				None => (p, false),
+			};
+			match num.parse::<u32>() {
+				Ok(i) if i < HARDENED => Ok(if hardened { i + HARDENED } else { i }),
+				_ => Err(err().into()),
+			}
+		})
+		.collect()
+}
+
+/// Fingerprint of the BIP-32 key at the given derivation path (e.g. `m` for the
+/// master key fingerprint shown by most wallets), derived from the seed
+pub fn key_fingerprint(seed: &[u8], path: &str) -> Result<[u8; KEY_FINGERPRINT_LEN], Error> {
+	let mut key = ExtendedKey::master(seed)?;
+	for index in parse_path(path)? {
+		key = key.child(index)?;
+	}
+	Ok(key.fingerprint())
+}
+
+impl RecoveredSecret {
+	/// Fingerprint of the BIP-32 key at the given derivation path, using the
+	/// recovered master secret as the seed
+	pub fn bip32_fingerprint(&self, path: &str) -> Result<[u8; KEY_FINGERPRINT_LEN], Error> {
+		key_fingerprint(&self.secret, path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::hex::{from_hex, to_hex};
+	use crate::{combine_mnemonics, generate_mnemonics};
+
+	#[test]
+	fn bip32_fingerprints() -> Result<(), Error> {
+		// BIP-32 test vector 1
+		let seed = from_hex("000102030405060708090a0b0c0d0e0f").unwrap();
+		assert_eq!(to_hex(key_fingerprint(&seed, "m")?), "3442193e");
+		assert_eq!(to_hex(key_fingerprint(&seed, "m/0'")?), "5c1bd648");
+		assert_eq!(to_hex(key_fingerprint(&seed, "m/0h/1")?), "bef5a2f9");
+
+		let mns = generate_mnemonics(1, &[(2, 3)], &seed, "", 0)?[0].mnemonic_list()?;
+		let recovered = combine_mnemonics(&mns[1..], "")?;
+		assert_eq!(to_hex(recovered.bip32_fingerprint("m")?), "3442193e");
+
+		assert_eq!(parse_path("m/44'/0H/1")?, vec![44 + HARDENED, HARDENED, 1]);
+		assert!(parse_path("44'/0'").is_err());
+		assert!(parse_path("m/2147483648").is_err());
+		assert!(key_fingerprint(&seed[..15], "m").is_err());
+		Ok(())
+	}
+}
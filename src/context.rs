@@ -0,0 +1,316 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An explicit context holding every configuration the top-level operations use, so
+//! they can be customized together rather than through separate `_with_config`
+//! functions
+
+use crate::error::Error;
+use crate::shamir::share::ShareConfig;
+use crate::shamir::{
+	encrypt_with_config, recover_ems_from_set, split_ems_with, EncryptedMasterSecret, English,
+	GroupShare, RecoveredSecret, Share, ShareSet, Splitter, SplitterConfig, Wordlist,
+};
+use crate::util::encrypt::{KdfExecutor, MasterSecretEncConfig, SyncExecutor};
+use crate::util::secret::Passphrase;
+
+use rand::rngs::StdRng;
+use rand::{thread_rng, CryptoRng, Rng, RngCore, SeedableRng};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Configuration, wordlist and random source for generating and combining shares.
+/// Methods take `&self`, so a context can be shared between threads
+pub struct Sssmc39Context {
+	share_config: ShareConfig,
+	splitter_config: SplitterConfig,
+	enc_config: MasterSecretEncConfig,
+	wordlist: Arc<dyn Wordlist + Send + Sync>,
+	rng: Option<Mutex<Box<dyn RngCore + Send>>>,
+	executor: Arc<dyn KdfExecutor>,
+}
+
+impl Default for Sssmc39Context {
+	fn default() -> Self {
+		Sssmc39Context {
+			share_config: ShareConfig::new(),
+			splitter_config: SplitterConfig::new(),
+			enc_config: MasterSecretEncConfig::new(),
+			wordlist: Arc::new(English),
+			rng: None,
+			executor: Arc::new(SyncExecutor),
+		}
+	}
+}
+
+impl fmt::Debug for Sssmc39Context {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Sssmc39Context")
+			.field("share_config", &self.share_config)
+			.field("splitter_config", &self.splitter_config)
+			.field("enc_config", &self.enc_config)
+			.field("rng", &self.rng.as_ref().map(|_| "<custom>"))
+			.finish()
+	}
+}
+
+impl Sssmc39Context {
+	/// Standard SLIP-39 configuration, the English wordlist and the thread RNG
+	pub fn new() -> Self {
+		Sssmc39Context {
+			..Default::default()
+		}
+	}
+
+	/// Use the given splitter configuration
+	pub fn with_splitter_config(mut self, config: SplitterConfig) -> Self {
+		self.splitter_config = config;
+		self
+	}
+
+	/// Use the given encryption configuration
+	pub fn with_enc_config(mut self, config: MasterSecretEncConfig) -> Self {
+		self.enc_config = config;
+		self
+	}
+
+	/// Read and write mnemonics in the given wordlist
+	pub fn with_wordlist<W: Wordlist + Send + Sync + 'static>(mut self, wordlist: W) -> Self {
+		self.wordlist = Arc::new(wordlist);
+		self
+	}
+
+	/// Use `customization` in place of SLIP-39's `b"shamir"` in the mnemonic checksums
+	/// and the encryption salt, namespacing the shares of a private deployment. This
+	/// also sets the string of the encryption configuration, so call it after
+	/// [`with_enc_config`](Self::with_enc_config).
+	///
+	/// **Not interoperable:** the shares can only be combined by a context with the
+	/// same string. Standard SLIP-39 tools, and this crate's free functions, reject
+	/// them as having invalid checksums, and would derive a different secret even if
+	/// the checksums were ignored. Only the standard share format is namespaced;
+	/// extended format shares keep their own checksum string
+	pub fn with_customization_string(mut self, customization: &[u8]) -> Self {
+		self.share_config.customization_string = customization.to_vec();
+		self.enc_config.customization_string = customization.to_vec();
+		self
+	}
+
+	/// Run the KDF work of [`combine_mnemonics_on`](Self::combine_mnemonics_on) on
+	/// the given executor
+	pub fn with_kdf_executor<E: KdfExecutor + 'static>(mut self, executor: E) -> Self {
+		self.executor = Arc::new(executor);
+		self
+	}
+
+	/// Draw identifiers and share values from the given RNG, e.g. a seeded one for
+	/// reproducible output
+	pub fn with_rng<R: RngCore + CryptoRng + Send + 'static>(mut self, rng: R) -> Self {
+		self.rng = Some(Mutex::new(Box::new(rng)));
+		self
+	}
+
+	/// The share encoding configuration. Shares are encoded with the SLIP-39 values,
+	/// other than the customization string
+	pub fn share_config(&self) -> &ShareConfig {
+		&self.share_config
+	}
+
+	/// The splitter configuration
+	pub fn splitter_config(&self) -> &SplitterConfig {
+		&self.splitter_config
+	}
+
+	/// The encryption configuration
+	pub fn enc_config(&self) -> &MasterSecretEncConfig {
+		&self.enc_config
+	}
+
+	/// The wordlist
+	pub fn wordlist(&self) -> &dyn Wordlist {
+		&*self.wordlist
+	}
+
+	/// Encrypt a master secret under a new identifier
+	pub fn encrypt_master_secret(
+		&self,
+		master_secret: &[u8],
+		passphrase: impl Into<Passphrase>,
+		iteration_exponent: u8,
+	) -> Result<EncryptedMasterSecret, Error> {
+		let identifier =
+			self.draw(|rng| rng.gen::<u16>()) & ((1 << self.share_config.id_length_bits) - 1);
+		encrypt_with_config(
+			master_secret,
+			passphrase,
+			iteration_exponent,
+			&self.enc_config,
+			identifier,
+		)
+	}
+
+	/// Split an encrypted master secret into groups of shares
+	pub fn split_ems(
+		&self,
+		ems: &EncryptedMasterSecret,
+		group_threshold: u8,
+		groups: &[(u8, u8)],
+	) -> Result<Vec<GroupShare>, Error> {
+		let seed = self.draw(|rng| rng.gen());
+		let splitter = Splitter::with_rng(Some(&self.splitter_config), StdRng::from_seed(seed));
+		let mut groups = split_ems_with(ems, group_threshold, groups, &splitter)?;
+		for s in groups.iter_mut().flat_map(|g| g.member_shares.iter_mut()) {
+			s.config = self.share_config.clone();
+		}
+		Ok(groups)
+	}
+
+	/// Encrypt and split a master secret, as [`crate::generate_mnemonics`]
+	pub fn generate_mnemonics(
+		&self,
+		group_threshold: u8,
+		groups: &[(u8, u8)],
+		master_secret: &[u8],
+		passphrase: impl Into<Passphrase>,
+		iteration_exponent: u8,
+	) -> Result<Vec<GroupShare>, Error> {
+		crate::validate_layout(
+			group_threshold,
+			groups,
+			self.splitter_config.threshold_one_digest,
+		)?;
+		let ems = self.encrypt_master_secret(master_secret, passphrase, iteration_exponent)?;
+		self.split_ems(&ems, group_threshold, groups)
+	}
+
+	/// The mnemonics of a group's member shares, in the context's wordlist
+	pub fn mnemonics(&self, group: &GroupShare) -> Result<Vec<Vec<String>>, Error> {
+		group
+			.iter()
+			.map(|s| s.to_mnemonic_with_wordlist(self.wordlist()))
+			.collect()
+	}
+
+	/// Parse mnemonics in the context's wordlist into a set of shares
+	pub fn parse_mnemonics(&self, mnemonics: &[Vec<String>]) -> Result<ShareSet, Error> {
+		mnemonics
+			.iter()
+			.map(|m| Share::from_mnemonic_with_config(m, self.wordlist(), &self.share_config))
+			.collect()
+	}
+
+	/// Decode mnemonics and interpolate the encrypted master secret
+	pub fn combine_to_ems(
+		&self,
+		mnemonics: &[Vec<String>],
+	) -> Result<EncryptedMasterSecret, Error> {
+		recover_ems_from_set(&self.parse_mnemonics(mnemonics)?, &self.splitter_config)
+	}
+
+	/// Combine mnemonics into the master secret, as [`crate::combine_mnemonics`]
+	pub fn combine_mnemonics(
+		&self,
+		mnemonics: &[Vec<String>],
+		passphrase: impl Into<Passphrase>,
+	) -> Result<RecoveredSecret, Error> {
+		self.combine_to_ems(mnemonics)?
+			.decrypt_with_config(passphrase, &self.enc_config)
+	}
+
+	/// As [`combine_mnemonics`](Self::combine_mnemonics), decrypting on the context's
+	/// KDF executor and passing the result to `done`, which may be called on another
+	/// thread. Share errors are passed to `done` straight away
+	pub fn combine_mnemonics_on(
+		&self,
+		mnemonics: &[Vec<String>],
+		passphrase: impl Into<Passphrase>,
+		done: impl FnOnce(Result<RecoveredSecret, Error>) + Send + 'static,
+	) {
+		match self.combine_to_ems(mnemonics) {
+			Ok(ems) => ems.decrypt_on(&*self.executor, passphrase, &self.enc_config, done),
+			Err(e) => done(Err(e)),
+		}
+	}
+
+	// run f with the context's RNG, or the thread RNG if none was given
+	fn draw<T>(&self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+		match &self.rng {
+			Some(rng) => f(&mut **rng.lock().expect("rng lock")),
+			None => f(&mut thread_rng()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::CustomWordlist;
+
+	#[test]
+	fn context_round_trip() -> Result<(), Error> {
+		let ms = vec![0x24u8; 16];
+		let words = include_str!("shamir/wordlists/en.txt")
+			.split_whitespace()
+			.map(|w| format!("{}s", w))
+			.collect();
+		let ctx = Sssmc39Context::new()
+			.with_wordlist(CustomWordlist::from_words(words)?)
+			.with_rng(StdRng::seed_from_u64(1));
+		let groups = ctx.generate_mnemonics(1, &[(2, 3)], &ms, "pw", 0)?;
+		let mns = ctx.mnemonics(&groups[0])?;
+		assert!(mns[0].iter().all(|w| w.ends_with('s')));
+		assert_eq!(ctx.combine_mnemonics(&mns[..2], "pw")?.secret, ms);
+		assert!(crate::combine_mnemonics(&mns[..2], "pw").is_err());
+
+		// a private deployment's shares don't combine with standard ones
+		let private = Sssmc39Context::new().with_customization_string(b"acme-vault");
+		let groups = private.generate_mnemonics(1, &[(2, 3)], &ms, "pw", 0)?;
+		let mns = private.mnemonics(&groups[0])?;
+		assert_eq!(private.combine_mnemonics(&mns[1..], "pw")?.secret, ms);
+		assert!(crate::combine_mnemonics(&mns[1..], "pw").is_err());
+		assert!(Sssmc39Context::new()
+			.combine_mnemonics(&mns[1..], "pw")
+			.is_err());
+		let standard = Sssmc39Context::new().generate_mnemonics(1, &[(2, 3)], &ms, "pw", 0)?;
+		let mns = Sssmc39Context::new().mnemonics(&standard[0])?;
+		assert!(private.combine_mnemonics(&mns[1..], "pw").is_err());
+
+		// decrypting on another thread
+		struct Spawn;
+		impl KdfExecutor for Spawn {
+			fn execute(&self, job: crate::KdfJob) {
+				std::thread::spawn(job);
+			}
+		}
+		let (tx, rx) = std::sync::mpsc::channel();
+		let ctx = private.with_kdf_executor(Spawn);
+		let mns = ctx.mnemonics(&groups[0])?;
+		let tx2 = tx.clone();
+		ctx.combine_mnemonics_on(&mns[..2], "pw", move |r| tx2.send(r).unwrap());
+		ctx.combine_mnemonics_on(&mns[..1], "pw", move |r| tx.send(r).unwrap());
+		let mut results: Vec<_> = rx.iter().collect();
+		results.sort_by_key(|r| r.is_ok());
+		assert!(results[0].is_err());
+		assert_eq!(results[1].as_ref().unwrap().secret, ms);
+
+		// a seeded context is reproducible
+		let again = Sssmc39Context::new().with_rng(StdRng::seed_from_u64(1));
+		let first = Sssmc39Context::new().with_rng(StdRng::seed_from_u64(1));
+		assert_eq!(
+			first.generate_mnemonics(1, &[(2, 3)], &ms, "", 0)?,
+			again.generate_mnemonics(1, &[(2, 3)], &ms, "", 0)?
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,357 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encrypted envelopes for handing member shares to custodians over untrusted
+//! channels such as email. Each mnemonic is encrypted to the custodian's X25519
+//! public key (ephemeral-static ECDH, HKDF-SHA256, ChaCha20-Poly1305) and armored
+//! as text; a manifest records which custodian holds which share. The envelope
+//! format is specific to this crate (it is not age). Only available with the
+//! `distribution` feature
+
+use crate::error::{Error, ErrorKind};
+use crate::shamir::{normalize_mnemonic, GroupShare, Share};
+use crate::util::crypto::sha256;
+use crate::util::hex::{from_hex, to_hex};
+use crate::util::secret::zeroize;
+
+use rand::{CryptoRng, RngCore};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::hkdf;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use x25519_dalek::{EphemeralSecret, PublicKey, SharedSecret, StaticSecret};
+
+const ARMOR_BEGIN: &str = "-----BEGIN SSSMC39 SHARE ENVELOPE-----";
+const ARMOR_END: &str = "-----END SSSMC39 SHARE ENVELOPE-----";
+const ARMOR_LINE_LEN: usize = 64;
+const ENVELOPE_VERSION: u8 = 1;
+const KDF_INFO: &[u8] = b"sssmc39 share envelope v1";
+
+/// A custodian's X25519 public key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustodianPublicKey(pub [u8; 32]);
+
+impl CustodianPublicKey {
+	/// Key from its hex encoding
+	pub fn from_hex(hex: &str) -> Result<CustodianPublicKey, Error> {
+		let bytes = from_hex(hex.trim())?;
+		if bytes.len() != 32 {
+			return Err(ErrorKind::Value(
+				"Custodian public keys are 32 bytes".to_string(),
+			))?;
+		}
+		let mut key = [0u8; 32];
+		key.copy_from_slice(&bytes);
+		Ok(CustodianPublicKey(key))
+	}
+
+	/// Hex encoding of the key
+	pub fn to_hex(&self) -> String {
+		to_hex(self.0)
+	}
+}
+
+/// A custodian's X25519 private key, used to open envelopes. Zeroed on drop
+pub struct CustodianSecretKey(StaticSecret);
+
+impl CustodianSecretKey {
+	/// Generate a new key pair
+	pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> CustodianSecretKey {
+		CustodianSecretKey(StaticSecret::random_from_rng(rng))
+	}
+
+	/// Key from its raw bytes
+	pub fn from_bytes(bytes: [u8; 32]) -> CustodianSecretKey {
+		CustodianSecretKey(StaticSecret::from(bytes))
+	}
+
+	/// The public key envelopes are addressed to
+	pub fn public_key(&self) -> CustodianPublicKey {
+		CustodianPublicKey(PublicKey::from(&self.0).to_bytes())
+	}
+}
+
+impl fmt::Debug for CustodianSecretKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "CustodianSecretKey(<redacted>)")
+	}
+}
+
+/// A share holder and the key their envelope is encrypted to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Custodian {
+	/// Name, for the manifest
+	pub name: String,
+	/// Public key
+	pub public_key: CustodianPublicKey,
+}
+
+/// A member share encrypted to a custodian
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+	/// Name of the custodian the envelope is addressed to
+	pub custodian: String,
+	/// Group index of the enclosed share
+	pub group_index: u8,
+	/// Member index of the enclosed share
+	pub member_index: u8,
+	/// The armored envelope
+	pub armored: String,
+}
+
+/// Record of one envelope in a [`Manifest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestEntry {
+	/// Name of the custodian
+	pub custodian: String,
+	/// Hex encoded public key of the custodian
+	pub public_key: String,
+	/// Group index of the enclosed share
+	pub group_index: u8,
+	/// Member index of the enclosed share
+	pub member_index: u8,
+	/// Hex encoded SHA-256 of the armored envelope
+	pub envelope_sha256: String,
+}
+
+/// Who holds which share of a share set. Holds nothing secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Manifest {
+	/// Identifier of the share set
+	pub identifier: u16,
+	/// Number of groups needed to recover the secret
+	pub group_threshold: u8,
+	/// Member threshold and member count of each group
+	pub groups: Vec<(u8, u8)>,
+	/// One entry per envelope
+	pub entries: Vec<ManifestEntry>,
+}
+
+impl fmt::Display for Manifest {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"Share set {}: {} of {} groups required",
+			self.identifier,
+			self.group_threshold,
+			self.groups.len()
+		)?;
+		for (i, (t, n)) in self.groups.iter().enumerate() {
+			writeln!(f, "Group {}: {} of {} shares required", i + 1, t, n)?;
+			for e in self.entries.iter().filter(|e| e.group_index as usize == i) {
+				writeln!(
+					f,
+					"  Share {}: {} ({}) envelope sha256 {}",
+					e.member_index + 1,
+					e.custodian,
+					e.public_key,
+					e.envelope_sha256
+				)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+fn envelope_key(
+	shared: &SharedSecret,
+	ephemeral: &[u8; 32],
+	recipient: &[u8; 32],
+) -> Result<LessSafeKey, Error> {
+	// a non-contributory exchange means the peer key was a low order point
+	if !shared.was_contributory() {
+		Err(ErrorKind::Value("Invalid custodian public key".to_string()))?;
+	}
+	let salt = hkdf::Salt::new(
+		hkdf::HKDF_SHA256,
+		&[&ephemeral[..], &recipient[..]].concat(),
+	);
+	let prk = salt.extract(shared.as_bytes());
+	let okm = prk
+		.expand(&[KDF_INFO], &CHACHA20_POLY1305)
+		.map_err(|_| ErrorKind::Value("Envelope key derivation failed".to_string()))?;
+	Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+/// Encrypt a share's mnemonic to the given public key, returning the armored envelope
+pub fn seal_share<R: RngCore + CryptoRng>(
+	share: &Share,
+	recipient: &CustodianPublicKey,
+	rng: &mut R,
+) -> Result<String, Error> {
+	let ephemeral = EphemeralSecret::random_from_rng(rng);
+	let ephemeral_pub = PublicKey::from(&ephemeral);
+	let shared = ephemeral.diffie_hellman(&PublicKey::from(recipient.0));
+	let key = envelope_key(&shared, ephemeral_pub.as_bytes(), &recipient.0)?;
+
+	let mut header = vec![ENVELOPE_VERSION];
+	header.extend_from_slice(ephemeral_pub.as_bytes());
+	// each envelope has its own key, so a fixed nonce is safe
+	let mut in_out = share.to_mnemonic()?.join(" ").into_bytes();
+	key.seal_in_place_append_tag(
+		Nonce::assume_unique_for_key([0; 12]),
+		Aad::from(&header),
+		&mut in_out,
+	)
+	.map_err(|_| ErrorKind::Value("Envelope encryption failed".to_string()))?;
+	header.extend(in_out);
+
+	let hex = to_hex(header);
+	let mut armored = String::from(ARMOR_BEGIN);
+	armored.push('\n');
+	for line in hex.as_bytes().chunks(ARMOR_LINE_LEN) {
+		armored.push_str(std::str::from_utf8(line).expect("hex is ASCII"));
+		armored.push('\n');
+	}
+	armored.push_str(ARMOR_END);
+	armored.push('\n');
+	Ok(armored)
+}
+
+/// Decrypt an armored envelope with the custodian's key, returning the mnemonic
+pub fn open_envelope(armored: &str, key: &CustodianSecretKey) -> Result<Vec<String>, Error> {
+	let armor_err = || ErrorKind::Value("Malformed share envelope".to_string());
+	let body = armored
+		.trim()
+		.strip_prefix(ARMOR_BEGIN)
+		.and_then(|b| b.strip_suffix(ARMOR_END))
+		.ok_or_else(armor_err)?;
+	let hex: String = body.split_whitespace().collect();
+	let mut data = from_hex(hex).map_err(|_| armor_err())?;
+	if data.len() < 33 + CHACHA20_POLY1305.tag_len() || data[0] != ENVELOPE_VERSION {
+		Err(armor_err())?;
+	}
+	let mut in_out = data.split_off(33);
+	let mut ephemeral_pub = [0u8; 32];
+	ephemeral_pub.copy_from_slice(&data[1..]);
+
+	let recipient = key.public_key();
+	let shared = key.0.diffie_hellman(&PublicKey::from(ephemeral_pub));
+	let plaintext = envelope_key(&shared, &ephemeral_pub, &recipient.0)?
+		.open_in_place(
+			Nonce::assume_unique_for_key([0; 12]),
+			Aad::from(&data),
+			&mut in_out,
+		)
+		.map_err(|_| {
+			ErrorKind::Digest("Envelope can't be opened with this key or was altered".to_string())
+		})?;
+	let mnemonic = normalize_mnemonic(&String::from_utf8_lossy(plaintext));
+	zeroize(&mut in_out);
+	Ok(mnemonic)
+}
+
+/// Seal every member share of the share set to a custodian. Custodians are taken in
+/// order of group, then member share, so there must be one per share. Returns the
+/// envelopes and a manifest to keep alongside them
+pub fn seal_shares<R: RngCore + CryptoRng>(
+	groups: &[GroupShare],
+	custodians: &[Custodian],
+	rng: &mut R,
+) -> Result<(Vec<Envelope>, Manifest), Error> {
+	let share_count: usize = groups.iter().map(|g| g.member_shares.len()).sum();
+	if groups.is_empty() || custodians.len() != share_count {
+		Err(ErrorKind::Argument(format!(
+			"{} custodians given for {} shares",
+			custodians.len(),
+			share_count
+		)))?;
+	}
+	let mut envelopes = vec![];
+	let mut entries = vec![];
+	let shares = groups.iter().flat_map(|g| g.member_shares.iter());
+	for (share, custodian) in shares.zip(custodians) {
+		let armored = seal_share(share, &custodian.public_key, rng)?;
+		entries.push(ManifestEntry {
+			custodian: custodian.name.clone(),
+			public_key: custodian.public_key.to_hex(),
+			group_index: share.group_index,
+			member_index: share.member_index,
+			envelope_sha256: to_hex(sha256(armored.as_bytes())),
+		});
+		envelopes.push(Envelope {
+			custodian: custodian.name.clone(),
+			group_index: share.group_index,
+			member_index: share.member_index,
+			armored,
+		});
+	}
+	let manifest = Manifest {
+		identifier: groups[0].group_id,
+		group_threshold: groups[0].group_threshold,
+		groups: groups
+			.iter()
+			.map(|g| (g.member_threshold, g.member_shares.len() as u8))
+			.collect(),
+		entries,
+	};
+	Ok((envelopes, manifest))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{combine_mnemonics, generate_mnemonics};
+	use rand::thread_rng;
+
+	#[test]
+	fn seal_and_open() -> Result<(), Error> {
+		let mut rng = thread_rng();
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let groups = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+		let keys: Vec<CustodianSecretKey> = (0..3)
+			.map(|_| CustodianSecretKey::generate(&mut rng))
+			.collect();
+		let custodians: Vec<Custodian> = keys
+			.iter()
+			.enumerate()
+			.map(|(i, k)| Custodian {
+				name: format!("custodian {}", i),
+				public_key: k.public_key(),
+			})
+			.collect();
+		let (envelopes, manifest) = seal_shares(&groups, &custodians, &mut rng)?;
+		assert_eq!(envelopes.len(), 3);
+		assert_eq!(manifest.entries.len(), 3);
+		assert_eq!(manifest.groups, vec![(2, 3)]);
+		assert!(manifest.to_string().contains("custodian 2"));
+
+		let opened = vec![
+			open_envelope(&envelopes[0].armored, &keys[0])?,
+			open_envelope(&envelopes[2].armored, &keys[2])?,
+		];
+		assert_eq!(opened[0], groups[0].member_shares[0].to_mnemonic()?);
+		assert_eq!(combine_mnemonics(&opened, "")?.secret, master_secret);
+
+		// wrong key, tampering and mangled armor are all rejected
+		assert!(open_envelope(&envelopes[0].armored, &keys[1]).is_err());
+		let mut tampered = envelopes[1].armored.clone().into_bytes();
+		let pos = ARMOR_BEGIN.len() + 80;
+		tampered[pos] = if tampered[pos] == b'0' { b'1' } else { b'0' };
+		assert!(open_envelope(&String::from_utf8(tampered).unwrap(), &keys[1]).is_err());
+		assert!(open_envelope("not an envelope", &keys[1]).is_err());
+		// low order public keys are refused
+		let low_order = CustodianPublicKey([0; 32]);
+		assert!(seal_share(&groups[0].member_shares[0], &low_order, &mut rng).is_err());
+		assert!(seal_shares(&groups, &custodians[..2], &mut rng).is_err());
+		assert!(
+			CustodianPublicKey::from_hex(&custodians[0].public_key.to_hex())?
+				== custodians[0].public_key
+		);
+		Ok(())
+	}
+}
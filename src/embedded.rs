@@ -0,0 +1,286 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation-free splitting and recovery of secrets of up to 32 bytes, for
+//! firmware that must run with a static RAM budget. Shares are fixed-size arrays
+//! and the share count is a const generic, so nothing is allocated on the heap
+//! except when building an [`Error`]. Shares are interchangeable with those of
+//! [`split_secret_raw`](crate::split_secret_raw) and
+//! [`recover_secret_raw`](crate::recover_secret_raw). Only available with the
+//! `embedded` feature.
+//!
+//! Stack needs: a [`FixedShare`] is 34 bytes. Splitting into `N` shares returns
+//! `34 * N` bytes and uses at most 18 shares' worth (612 bytes) of working space
+//! plus an HMAC-SHA256 state, so under 2 KiB for `N = 16`. Recovery uses under
+//! 1 KiB beyond the caller's shares.
+
+use crate::error::{Error, ErrorKind};
+use crate::field::gf256::{slice_ops, Gf256};
+use crate::shamir::{MAX_SHARE_COUNT, MIN_SECRET_BYTES};
+use crate::util::crypto::hmac_sha256;
+use crate::util::ct::ct_eq;
+use crate::util::secret::zeroize;
+
+use rand::{CryptoRng, RngCore};
+/// Longest secret that can be split, in bytes
+pub const MAX_EMBEDDED_SECRET_LEN: usize = 32;
+
+const DIGEST_LEN: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const MAX_POINTS: usize = MAX_SHARE_COUNT as usize + 2;
+
+/// A secret or share value of up to [`MAX_EMBEDDED_SECRET_LEN`] bytes, zeroed on drop
+#[derive(Clone, PartialEq, Eq)]
+pub struct FixedSecret {
+	len: u8,
+	bytes: [u8; MAX_EMBEDDED_SECRET_LEN],
+}
+
+impl FixedSecret {
+	/// Copy the bytes into a fixed-size buffer
+	pub fn new(bytes: &[u8]) -> Result<FixedSecret, Error> {
+		if bytes.len() > MAX_EMBEDDED_SECRET_LEN {
+			return Err(ErrorKind::Argument(format!(
+				"Secret must be at most {} bytes for the embedded code path",
+				MAX_EMBEDDED_SECRET_LEN
+			)))?;
+		}
+		let mut ret = FixedSecret::zeroed(bytes.len());
+		ret.bytes[..bytes.len()].copy_from_slice(bytes);
+		Ok(ret)
+	}
+
+	fn zeroed(len: usize) -> FixedSecret {
+		FixedSecret {
+			len: len as u8,
+			bytes: [0; MAX_EMBEDDED_SECRET_LEN],
+		}
+	}
+
+	/// The value's bytes
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[..self.len as usize]
+	}
+
+	fn as_mut_bytes(&mut self) -> &mut [u8] {
+		&mut self.bytes[..self.len as usize]
+	}
+}
+
+impl Drop for FixedSecret {
+	fn drop(&mut self) {
+		zeroize(&mut self.bytes);
+	}
+}
+
+impl std::fmt::Debug for FixedSecret {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "FixedSecret({} bytes)", self.len)
+	}
+}
+
+/// A share: its x coordinate (member index) and value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedShare {
+	/// Member index of the share
+	pub index: u8,
+	/// Share value, the same length as the secret
+	pub value: FixedSecret,
+}
+
+// first DIGEST_LEN bytes of HMAC-SHA256(key, data)
+fn digest(key: &[u8], data: &[u8], out: &mut [u8]) {
+	let mut mac = hmac_sha256(key, data);
+	out.copy_from_slice(&mac[..DIGEST_LEN]);
+	zeroize(&mut mac);
+}
+
+// value at x of the polynomial through the points
+fn interpolate(points: &[FixedShare], x: u8) -> Result<FixedSecret, Error> {
+	if let Some(p) = points.iter().find(|p| p.index == x) {
+		return Ok(p.value.clone());
+	}
+	let len = points[0].value.len as usize;
+	for (i, p) in points.iter().enumerate() {
+		if p.value.len as usize != len {
+			Err(ErrorKind::Mnemonic(
+				"Invalid set of shares. All share values must have the same length".to_string(),
+			))?;
+		}
+		if points[..i].iter().any(|q| q.index == p.index) {
+			Err(ErrorKind::Value(
+				"Invalid set of shares. Share indices must be unique".to_string(),
+			))?;
+		}
+	}
+	let mut ret = FixedSecret::zeroed(len);
+	let x = Gf256::from_byte(x);
+	for p in points {
+		let xi = Gf256::from_byte(p.index);
+		let mut basis = Gf256::one();
+		for q in points.iter().filter(|q| q.index != p.index) {
+			let xj = Gf256::from_byte(q.index);
+			basis *= (x - xj) / (xi - xj);
+		}
+		slice_ops::mul_add(ret.as_mut_bytes(), p.value.as_bytes(), basis);
+	}
+	Ok(ret)
+}
+
+/// Split the secret into `N` shares, any `threshold` of which recover it, as
+/// [`split_secret_raw`](crate::split_secret_raw) does but without allocating
+pub fn split_secret_fixed<const N: usize, R: RngCore + CryptoRng>(
+	threshold: u8,
+	secret: &[u8],
+	rng: &mut R,
+) -> Result<[FixedShare; N], Error> {
+	if N == 0 || N > MAX_SHARE_COUNT as usize {
+		Err(ErrorKind::Argument(format!(
+			"Share count must be between 1 and {}",
+			MAX_SHARE_COUNT
+		)))?;
+	}
+	if threshold == 0 || threshold as usize > N {
+		Err(ErrorKind::Argument(format!(
+			"Threshold must be between 1 and {}",
+			N
+		)))?;
+	}
+	if secret.len() < MIN_SECRET_BYTES || !secret.len().is_multiple_of(2) {
+		Err(ErrorKind::Argument(
+			"Secret must be at least 16 bytes in length and a multiple of 2".to_string(),
+		))?;
+	}
+	let secret = FixedSecret::new(secret)?;
+	let len = secret.len as usize;
+	if threshold == 1 {
+		return Ok(std::array::from_fn(|i| FixedShare {
+			index: i as u8,
+			value: secret.clone(),
+		}));
+	}
+
+	// random shares, then the digest and secret base points
+	let random_count = threshold as usize - 2;
+	let mut points: [FixedShare; MAX_POINTS] = std::array::from_fn(|i| FixedShare {
+		index: i as u8,
+		value: FixedSecret::zeroed(len),
+	});
+	for p in &mut points[..random_count] {
+		rng.fill_bytes(p.value.as_mut_bytes());
+	}
+	let mut digest_value = FixedSecret::zeroed(len);
+	{
+		let (hash, random_part) = digest_value.as_mut_bytes().split_at_mut(DIGEST_LEN);
+		rng.fill_bytes(random_part);
+		digest(random_part, secret.as_bytes(), hash);
+	}
+	points[random_count] = FixedShare {
+		index: DIGEST_INDEX,
+		value: digest_value,
+	};
+	points[random_count + 1] = FixedShare {
+		index: SECRET_INDEX,
+		value: secret,
+	};
+	let base = &points[..threshold as usize];
+
+	let mut shares: [FixedShare; N] = std::array::from_fn(|i| FixedShare {
+		index: i as u8,
+		value: FixedSecret::zeroed(len),
+	});
+	for s in &mut shares {
+		s.value = interpolate(base, s.index)?;
+	}
+	Ok(shares)
+}
+
+/// Recover a secret from at least `threshold` shares, verifying the SLIP-39
+/// digest when the threshold is greater than 1
+pub fn recover_secret_fixed(shares: &[FixedShare], threshold: u8) -> Result<FixedSecret, Error> {
+	if shares.is_empty() || shares.len() > MAX_SHARE_COUNT as usize {
+		Err(ErrorKind::Value(format!(
+			"Share set must contain between 1 and {} shares",
+			MAX_SHARE_COUNT
+		)))?;
+	}
+	let secret = interpolate(shares, SECRET_INDEX)?;
+	if threshold != 1 {
+		if secret.len as usize <= DIGEST_LEN {
+			Err(ErrorKind::Value("Share values are too short".to_string()))?;
+		}
+		let digest_value = interpolate(shares, DIGEST_INDEX)?;
+		let (hash, random_part) = digest_value.as_bytes().split_at(DIGEST_LEN);
+		let mut expected = [0u8; DIGEST_LEN];
+		digest(random_part, secret.as_bytes(), &mut expected);
+		if !ct_eq(hash, &expected) {
+			Err(ErrorKind::Digest(
+				"Invalid digest of the shared secret".to_string(),
+			))?;
+		}
+	}
+	Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{recover_secret_raw, split_secret_raw, util};
+	use rand::thread_rng;
+
+	#[test]
+	fn fixed_split_recover() -> Result<(), Error> {
+		let secret = util::fill_vec_rand(32);
+		let shares: [FixedShare; 5] = split_secret_fixed(3, &secret, &mut thread_rng())?;
+		assert_eq!(
+			recover_secret_fixed(&shares[1..4], 3)?.as_bytes(),
+			&secret[..]
+		);
+		assert!(recover_secret_fixed(&shares[..2], 3).is_err());
+
+		// interchangeable with the allocating raw API
+		let raw: Vec<_> = shares
+			.iter()
+			.map(|s| (s.index, s.value.as_bytes().to_vec()))
+			.collect();
+		assert_eq!(recover_secret_raw(&raw[2..], 3)?, secret);
+		let raw = split_secret_raw(2, 3, &secret[..16], &mut thread_rng())?;
+		let fixed = raw
+			.iter()
+			.map(|(i, v)| {
+				Ok(FixedShare {
+					index: *i,
+					value: FixedSecret::new(v)?,
+				})
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+		assert_eq!(
+			recover_secret_fixed(&fixed[1..], 2)?.as_bytes(),
+			&secret[..16]
+		);
+
+		let single: [FixedShare; 2] = split_secret_fixed(1, &secret, &mut thread_rng())?;
+		assert_eq!(
+			recover_secret_fixed(&single[1..], 1)?.as_bytes(),
+			&secret[..]
+		);
+		assert!(
+			split_secret_fixed::<3, _>(2, &util::fill_vec_rand(34), &mut thread_rng()).is_err()
+		);
+		assert!(split_secret_fixed::<17, _>(2, &secret, &mut thread_rng()).is_err());
+		assert!(split_secret_fixed::<2, _>(3, &secret, &mut thread_rng()).is_err());
+		Ok(())
+	}
+}
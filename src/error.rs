@@ -14,108 +14,360 @@
 
 //! Error types for ssmc39 crate
 
-use failure::{Backtrace, Context, Fail};
-use std::env;
+use crate::shamir::{MAX_SECRET_BYTES, MIN_SECRET_BYTES};
+use crate::util::hex::HexError;
+#[cfg(feature = "backtrace")]
+use std::backtrace::{Backtrace, BacktraceStatus};
 use std::fmt::{self, Display};
 
-/// Error definition
-#[derive(Debug, Fail)]
+/// Error definition. Implements `std::error::Error + Send + Sync + 'static`, so it
+/// can be propagated as an `anyhow` or `eyre` error. With the `backtrace` feature a
+/// backtrace is captured when the error is created, subject to `RUST_BACKTRACE`
+#[derive(Debug)]
 pub struct Error {
-	inner: Context<ErrorKind>,
+	kind: ErrorKind,
+	#[cfg(feature = "backtrace")]
+	backtrace: Backtrace,
 }
 
 /// Wallet errors, mostly wrappers around underlying crypto or I/O errors.
-#[derive(Clone, Eq, PartialEq, Debug, Fail)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub enum ErrorKind {
 	/// Configuration error, with details
-	#[fail(display = "Configuration Error: {}", _0)]
 	Config(String),
 
 	/// Inconsistency between different arguments
-	#[fail(display = "Argument Error: {}", _0)]
 	Argument(String),
 
 	/// Problems with a mnemonic or inconsistent mnemonics
-	#[fail(display = "Mnemonic Error: {}", _0)]
 	Mnemonic(String),
 
 	/// Assembling the full master secret resulted in an incorrect checksum
-	#[fail(display = "Digest Error: {}", _0)]
 	Digest(String),
 
-	/// Invalid usage of BitPacker.add_uX (num_bits longer than the size of uX)
-	#[fail(display = "BitVec Error: {}", _0)]
+	/// Invalid usage of BitPacker.append/get (num_bits longer than the size of the type)
 	BitVec(String),
 
-	/// (unused currently)
-	#[fail(display = "Checksum Validation Error: {}", _0)]
+	/// A mnemonic failed RS1024 checksum validation
 	Checksum(String),
 
 	/// Invalid value of one of the arguments
-	#[fail(display = "Value Error: {}", _0)]
 	Value(String),
 
+	/// Invalid hex encoding
+	Hex(HexError),
+
 	/// Invalid usage of BitPacker.remove_padding (num_bits contained set bits)
-	#[fail(display = "Padding Error: All padding bits must be 0")]
 	Padding,
 
+	/// A share's padding bits are not all zero (identifier, group index, member index)
+	SharePadding(u16, u8, u8),
+
+	/// A share header field holds a value that can't be encoded (field name, value)
+	ShareHeader(String, u16),
+
+	/// The master secret is too short, too long or of odd length (length in bytes)
+	SecretLength(usize),
+
+	/// The number of groups is 0 or more than 16
+	GroupCount(usize),
+
+	/// The group threshold is 0 or exceeds the number of groups (threshold, group count)
+	GroupThreshold(u8, usize),
+
+	/// A member threshold is 0 or exceeds the member count, or the member count is
+	/// more than 16 (group index, member threshold, member count)
+	MemberThreshold(usize, u8, u8),
+
+	/// A group with member threshold 1 has more than one member, which SLIP-39 does not
+	/// allow (group index, member count)
+	SingleMemberThreshold(usize, u8),
+
+	/// Reading or writing a file failed, with details
+	Io(String),
+
+	/// Different shares of a group carry the same member index (group index, member index)
+	MemberIndexConflict(u8, u8),
+
+	/// A known-answer self-test of the crypto primitives failed (names of the tests)
+	SelfTest(String),
+
+	/// The operation was cancelled through its cancellation token
+	Cancelled,
+
+	/// Some member shares of a group don't lie on the same polynomial as a
+	/// threshold of the others, so are likely corrupt or from a different secret,
+	/// and the shares that agree aren't a majority of the group's shares
+	/// (group index, member indices of the inconsistent shares)
+	InconsistentShares(u8, Vec<u8>),
+
+	/// None of the shares belong to the requested share set (requested identifier,
+	/// identifiers of the shares that were found)
+	IdentifierNotFound(u16, Vec<u16>),
+
 	/// (unused currently)
-	#[fail(display = "Generic error: {}", _0)]
 	GenericError(String),
 }
 
+impl ErrorKind {
+	/// Numeric code of this kind of error, for FFI and WASM consumers that need to
+	/// branch on the error without parsing its message. Codes are stable: a code is
+	/// never changed or reused, and new kinds get new codes.
+	///
+	/// | Code | Kind |
+	/// |------|------|
+	/// | 1 | `Config` |
+	/// | 2 | `Argument` |
+	/// | 3 | `Mnemonic` |
+	/// | 4 | `Digest` |
+	/// | 5 | `BitVec` |
+	/// | 6 | `Checksum` |
+	/// | 7 | `Value` |
+	/// | 8 | `Hex` |
+	/// | 9 | `Padding` |
+	/// | 10 | `SharePadding` |
+	/// | 11 | `ShareHeader` |
+	/// | 12 | `SecretLength` |
+	/// | 13 | `GroupCount` |
+	/// | 14 | `GroupThreshold` |
+	/// | 15 | `MemberThreshold` |
+	/// | 16 | `SingleMemberThreshold` |
+	/// | 17 | `Io` |
+	/// | 18 | `SelfTest` |
+	/// | 19 | `MemberIndexConflict` |
+	/// | 20 | `Cancelled` |
+	/// | 21 | `InconsistentShares` |
+	/// | 22 | `IdentifierNotFound` |
+	/// | 999 | `GenericError` |
+	pub fn code(&self) -> u32 {
+		match self {
+			ErrorKind::Config(_) => 1,
+			ErrorKind::Argument(_) => 2,
+			ErrorKind::Mnemonic(_) => 3,
+			ErrorKind::Digest(_) => 4,
+			ErrorKind::BitVec(_) => 5,
+			ErrorKind::Checksum(_) => 6,
+			ErrorKind::Value(_) => 7,
+			ErrorKind::Hex(_) => 8,
+			ErrorKind::Padding => 9,
+			ErrorKind::SharePadding(..) => 10,
+			ErrorKind::ShareHeader(..) => 11,
+			ErrorKind::SecretLength(_) => 12,
+			ErrorKind::GroupCount(_) => 13,
+			ErrorKind::GroupThreshold(..) => 14,
+			ErrorKind::MemberThreshold(..) => 15,
+			ErrorKind::SingleMemberThreshold(..) => 16,
+			ErrorKind::Io(_) => 17,
+			ErrorKind::SelfTest(_) => 18,
+			ErrorKind::MemberIndexConflict(..) => 19,
+			ErrorKind::Cancelled => 20,
+			ErrorKind::InconsistentShares(..) => 21,
+			ErrorKind::IdentifierNotFound(..) => 22,
+			ErrorKind::GenericError(_) => 999,
+		}
+	}
+}
+
+impl Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ErrorKind::Config(s) => write!(f, "Configuration Error: {}", s),
+			ErrorKind::Argument(s) => write!(f, "Argument Error: {}", s),
+			ErrorKind::Mnemonic(s) => write!(f, "Mnemonic Error: {}", s),
+			ErrorKind::Digest(s) => write!(f, "Digest Error: {}", s),
+			ErrorKind::BitVec(s) => write!(f, "BitVec Error: {}", s),
+			ErrorKind::Checksum(s) => write!(f, "Checksum Validation Error: {}", s),
+			ErrorKind::Value(s) => write!(f, "Value Error: {}", s),
+			ErrorKind::Hex(e) => write!(f, "Hex Error: {}", e),
+			ErrorKind::Padding => write!(f, "Padding Error: All padding bits must be 0"),
+			ErrorKind::SharePadding(id, group, member) => write!(
+				f,
+				"Padding Error: padding bits of share {} in group {} (identifier {}) must be 0",
+				member, group, id
+			),
+			ErrorKind::ShareHeader(field, value) => {
+				write!(
+					f,
+					"Invalid share header: {} value {} out of range",
+					field, value
+				)
+			}
+			ErrorKind::SecretLength(len) => write!(
+				f,
+				"Invalid master secret length: {} bytes (must be an even number from {} to {})",
+				len, MIN_SECRET_BYTES, MAX_SECRET_BYTES
+			),
+			ErrorKind::GroupCount(n) => write!(f, "Invalid number of groups: {}", n),
+			ErrorKind::GroupThreshold(t, n) => {
+				write!(f, "Invalid group threshold {} for {} groups", t, n)
+			}
+			ErrorKind::MemberThreshold(group, t, n) => {
+				write!(
+					f,
+					"Invalid member threshold {} of {} in group {}",
+					t, n, group
+				)
+			}
+			ErrorKind::SingleMemberThreshold(group, n) => write!(
+				f,
+				"Group {} has member threshold 1 but {} members; use 1-of-1 instead",
+				group, n
+			),
+			ErrorKind::Io(s) => write!(f, "I/O Error: {}", s),
+			ErrorKind::MemberIndexConflict(group, member) => write!(
+				f,
+				"Conflicting shares with member index {} in group {}",
+				member, group
+			),
+			ErrorKind::SelfTest(s) => write!(f, "Self-test failed: {}", s),
+			ErrorKind::Cancelled => write!(f, "Operation cancelled"),
+			ErrorKind::InconsistentShares(group, members) => write!(
+				f,
+				"Member shares {:?} of group {} are inconsistent with the others",
+				members, group
+			),
+			ErrorKind::IdentifierNotFound(id, found) => write!(
+				f,
+				"No shares with identifier {} (found identifiers {:?})",
+				id, found
+			),
+			ErrorKind::GenericError(s) => write!(f, "Generic error: {}", s),
+		}
+	}
+}
+
+impl std::error::Error for ErrorKind {}
+
+/// Specific problem found when validating a single mnemonic in isolation
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum MnemonicIssue {
+	/// The mnemonic contains the wrong number of words
+	InvalidLength(usize),
+	/// The word at the given (0-based) position is not in the wordlist
+	InvalidWord(usize, String),
+	/// The RS1024 checksum does not match
+	InvalidChecksum,
+	/// The padding bits of the share value are not all zero
+	InvalidPadding,
+	/// The share header contains inconsistent values
+	InvalidHeader(String),
+}
+
+impl Display for MnemonicIssue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MnemonicIssue::InvalidLength(l) => write!(f, "Invalid mnemonic length: {} words", l),
+			MnemonicIssue::InvalidWord(i, w) => {
+				write!(f, "Word {} ('{}') is not an SSSMC39 word", i + 1, w)
+			}
+			MnemonicIssue::InvalidChecksum => write!(f, "Invalid mnemonic checksum"),
+			MnemonicIssue::InvalidPadding => write!(f, "Invalid mnemonic padding"),
+			MnemonicIssue::InvalidHeader(s) => write!(f, "Invalid mnemonic header: {}", s),
+		}
+	}
+}
+
+impl From<MnemonicIssue> for Error {
+	fn from(issue: MnemonicIssue) -> Error {
+		match issue {
+			MnemonicIssue::InvalidChecksum => ErrorKind::Checksum(format!("{}", issue)).into(),
+			MnemonicIssue::InvalidPadding => ErrorKind::Padding.into(),
+			_ => ErrorKind::Mnemonic(format!("{}", issue)).into(),
+		}
+	}
+}
+
 impl Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let show_bt = match env::var("RUST_BACKTRACE") {
-			Ok(r) => r == "1",
-			Err(_) => false,
+		// the alternate form ("{:#}") prefixes the stable error code
+		let output = match f.alternate() {
+			true => format!("[E{:03}] {}", self.code(), self.kind),
+			false => format!("{}", self.kind),
 		};
-		let backtrace = match self.backtrace() {
-			Some(b) => format!("{}", b),
-			None => String::from("Unknown"),
+		#[cfg(feature = "backtrace")]
+		let output = match self.backtrace() {
+			Some(b) => format!("{}\n Backtrace: {}", output, b),
+			None => output,
 		};
-		let inner_output = format!("{}", self.inner,);
-		let backtrace_output = format!("\n Backtrace: {}", backtrace);
-		let mut output = inner_output;
-		if show_bt {
-			output.push_str(&backtrace_output);
-		}
 		Display::fmt(&output, f)
 	}
 }
 
+impl std::error::Error for Error {}
+
 impl Error {
 	/// get kind
 	pub fn kind(&self) -> ErrorKind {
-		self.inner.get_context().clone()
+		self.kind.clone()
+	}
+	/// get the stable numeric code of the error kind, see [`ErrorKind::code`]
+	pub fn code(&self) -> u32 {
+		self.kind.code()
 	}
 	/// get cause string
 	pub fn cause_string(&self) -> String {
-		match self.cause() {
-			Some(k) => format!("{}", k),
-			None => "Unknown".to_string(),
-		}
-	}
-	/// get cause
-	pub fn cause(&self) -> Option<&dyn Fail> {
-		self.inner.cause()
+		format!("{}", self.kind)
 	}
-	/// get backtrace
+	/// get backtrace, if one was captured
+	#[cfg(feature = "backtrace")]
 	pub fn backtrace(&self) -> Option<&Backtrace> {
-		self.inner.backtrace()
+		match self.backtrace.status() {
+			BacktraceStatus::Captured => Some(&self.backtrace),
+			_ => None,
+		}
 	}
 }
 
 impl From<ErrorKind> for Error {
 	fn from(kind: ErrorKind) -> Error {
 		Error {
-			inner: Context::new(kind),
+			kind,
+			#[cfg(feature = "backtrace")]
+			backtrace: Backtrace::capture(),
 		}
 	}
 }
 
-impl From<Context<ErrorKind>> for Error {
-	fn from(inner: Context<ErrorKind>) -> Error {
-		Error { inner }
+impl From<HexError> for Error {
+	fn from(e: HexError) -> Error {
+		ErrorKind::Hex(e).into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn error_codes() {
+		// these are part of the public contract and must never change
+		assert_eq!(ErrorKind::Config("".into()).code(), 1);
+		assert_eq!(ErrorKind::Checksum("".into()).code(), 6);
+		assert_eq!(ErrorKind::Padding.code(), 9);
+		assert_eq!(ErrorKind::SingleMemberThreshold(0, 2).code(), 16);
+		assert_eq!(ErrorKind::Io("".into()).code(), 17);
+		assert_eq!(ErrorKind::GenericError("".into()).code(), 999);
+
+		let e: Error = ErrorKind::GroupCount(17).into();
+		assert_eq!(e.code(), 13);
+		// a backtrace may follow if RUST_BACKTRACE is set
+		assert!(format!("{}", e).starts_with("Invalid number of groups: 17"));
+		assert!(format!("{:#}", e).starts_with("[E013] Invalid number of groups: 17"));
+		assert_eq!(
+			ErrorKind::SecretLength(15).to_string(),
+			"Invalid master secret length: 15 bytes (must be an even number from 16 to 256)"
+		);
+	}
+
+	#[test]
+	fn std_error() {
+		fn assert_std_error<E: std::error::Error + Send + Sync + 'static>() {}
+		assert_std_error::<Error>();
+		assert_std_error::<ErrorKind>();
+
+		let boxed: Box<dyn std::error::Error + Send + Sync> =
+			Box::new(Error::from(ErrorKind::Cancelled));
+		assert!(boxed.to_string().starts_with("Operation cancelled"));
+		assert_eq!(boxed.downcast_ref::<Error>().map(|e| e.code()), Some(20));
 	}
 }
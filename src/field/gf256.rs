@@ -54,6 +54,8 @@
 use std::fmt;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
+pub mod slice_ops;
+
 #[derive(Copy, Clone)]
 pub struct Tables {
 	pub exp: [u8; 255],
@@ -114,9 +116,20 @@ impl Tables {
 	}
 }
 
+/// Generates the table of multiplicative inverses, with 0 mapped to 0
+pub fn generate_inverses(tabs: &Tables) -> [u8; 256] {
+	let mut inv = [0u8; 256];
+	for (inv, log) in inv.iter_mut().zip(tabs.log.iter()).skip(1) {
+		*inv = tabs.exp[(255 - *log as usize) % 255];
+	}
+	inv
+}
+
 lazy_static! {
 	/// Static reference to Generated tables
 	pub static ref TABLES: Tables = Tables::generate();
+	/// Static reference to the generated inverse table
+	pub static ref INVERSES: [u8; 256] = generate_inverses(&TABLES);
 }
 
 fn get_tables() -> &'static Tables {
@@ -126,6 +139,7 @@ fn get_tables() -> &'static Tables {
 /// Type for elements of a finite field with 256 elements
 #[derive(Copy, Debug, Clone, PartialEq, Eq)]
 pub struct Gf256 {
+	/// The element as a polynomial over GF(2), one bit per coefficient
 	pub poly: u8,
 }
 
@@ -140,18 +154,22 @@ impl Gf256 {
 	pub fn one() -> Gf256 {
 		Gf256 { poly: 1 }
 	}
+	/// Element with the given byte representation
 	#[inline]
 	pub fn from_byte(b: u8) -> Gf256 {
 		Gf256 { poly: b }
 	}
+	/// Byte representation of the element
 	#[inline]
 	pub fn to_byte(self) -> u8 {
 		self.poly
 	}
+	/// The generator (x + 1) raised to the given power
 	pub fn exp(power: u8) -> Gf256 {
 		let tabs = get_tables();
 		Gf256::from_byte(tabs.exp[power as usize])
 	}
+	/// Discrete logarithm to base x + 1, or None for zero
 	pub fn log(self) -> Option<u8> {
 		if self.poly == 0 {
 			None
@@ -160,6 +178,37 @@ impl Gf256 {
 			Some(tabs.log[self.poly as usize])
 		}
 	}
+	/// Multiplicative inverse, or None for zero
+	#[inline]
+	pub fn inv(self) -> Option<Gf256> {
+		match self.poly {
+			0 => None,
+			b => Some(Gf256::from_byte(INVERSES[b as usize])),
+		}
+	}
+	/// Inverts every element in place with a single table lookup, using Montgomery's
+	/// trick of inverting the running product then unwinding it. Zeros have no
+	/// inverse and are left as zero
+	pub fn batch_inv(values: &mut [Gf256]) {
+		let mut prefix = Vec::with_capacity(values.len());
+		let mut acc = Gf256::one();
+		for v in values.iter() {
+			prefix.push(acc);
+			if v.poly != 0 {
+				acc *= *v;
+			}
+		}
+		// the running product of non-zero elements is never zero
+		let mut acc_inv = acc.inv().unwrap_or_else(Gf256::one);
+		for (v, p) in values.iter_mut().zip(prefix).rev() {
+			if v.poly != 0 {
+				let inv = acc_inv * p;
+				acc_inv *= *v;
+				*v = inv;
+			}
+		}
+	}
+	/// The element raised to the given power
 	pub fn pow(mut self, mut exp: u8) -> Gf256 {
 		let mut acc = Self::one();
 
@@ -231,14 +280,9 @@ impl MulAssign<Gf256> for Gf256 {
 
 impl Div<Gf256> for Gf256 {
 	type Output = Gf256;
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn div(self, rhs: Gf256) -> Gf256 {
-		let l2 = rhs.log().expect("division by zero");
-		if let Some(l1) = self.log() {
-			let tmp = (u16::from(l1) + 255 - u16::from(l2)) % 255;
-			Gf256::exp(tmp as u8)
-		} else {
-			Gf256 { poly: 0 }
-		}
+		self * rhs.inv().expect("division by zero")
 	}
 }
 
@@ -397,6 +441,22 @@ mod tests {
 
 				TestResult::from_bool(left && right)
 			}
+
+			fn law_inverse_table(a: Gf256) -> bool {
+				match a.inv() {
+					None => a == Gf256::zero(),
+					Some(i) => a * i == Gf256::one(),
+				}
+			}
+
+			fn batch_inversion(values: Vec<Gf256>) -> bool {
+				let mut batch = values.clone();
+				Gf256::batch_inv(&mut batch);
+				values
+					.iter()
+					.zip(&batch)
+					.all(|(v, b)| *b == v.inv().unwrap_or_else(Gf256::zero))
+			}
 		}
 	}
 }
@@ -0,0 +1,87 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bulk GF(256) arithmetic over byte slices, so that whole share values can be
+//! operated on at once rather than byte by byte
+
+use super::{get_tables, Gf256};
+
+/// `dst[i] += src[i]` for each byte
+#[cfg(test)]
+pub fn add_assign_slices(dst: &mut [u8], src: &[u8]) {
+	assert_eq!(dst.len(), src.len(), "slice lengths must match");
+	for (d, s) in dst.iter_mut().zip(src) {
+		*d ^= s;
+	}
+}
+
+/// `dst[i] *= c` for each byte
+#[cfg(test)]
+pub fn mul_slice_by_scalar(dst: &mut [u8], c: Gf256) {
+	let c_log = match c.log() {
+		Some(l) => usize::from(l),
+		None => {
+			dst.iter_mut().for_each(|d| *d = 0);
+			return;
+		}
+	};
+	let tabs = get_tables();
+	for d in dst.iter_mut() {
+		if *d != 0 {
+			*d = tabs.exp[(usize::from(tabs.log[*d as usize]) + c_log) % 255];
+		}
+	}
+}
+
+/// Fused multiply-add: `dst[i] += c * src[i]` for each byte
+pub fn mul_add(dst: &mut [u8], src: &[u8], c: Gf256) {
+	assert_eq!(dst.len(), src.len(), "slice lengths must match");
+	let c_log = match c.log() {
+		Some(l) => usize::from(l),
+		None => return,
+	};
+	let tabs = get_tables();
+	for (d, s) in dst.iter_mut().zip(src) {
+		if *s != 0 {
+			*d ^= tabs.exp[(usize::from(tabs.log[*s as usize]) + c_log) % 255];
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use quickcheck::*;
+
+	quickcheck! {
+		fn slice_ops_match_scalar_ops(a: Vec<u8>, c: u8) -> bool {
+			let b: Vec<u8> = a.iter().map(|x| x.wrapping_mul(7).wrapping_add(3)).collect();
+			let c = Gf256::from_byte(c);
+
+			let mut sum = a.clone();
+			add_assign_slices(&mut sum, &b);
+			let mut prod = a.clone();
+			mul_slice_by_scalar(&mut prod, c);
+			let mut fused = b.clone();
+			mul_add(&mut fused, &a, c);
+
+			a.iter().zip(&b).enumerate().all(|(i, (&x, &y))| {
+				let (x, y) = (Gf256::from_byte(x), Gf256::from_byte(y));
+				sum[i] == (x + y).to_byte()
+					&& prod[i] == (x * c).to_byte()
+					&& fused[i] == (y + c * x).to_byte()
+			})
+		}
+	}
+}
@@ -0,0 +1,242 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Experimental GF(2^16) arithmetic, for splitting secrets into more shares than
+//! GF(256) allows. Not used by anything SLIP-39 specifies
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+
+/// x^16 + x^5 + x^3 + x^2 + 1, primitive, so x generates the multiplicative group
+const POLY: u32 = 0x1002d;
+
+/// Order of the multiplicative group
+const ORDER: usize = 65535;
+
+pub struct Tables {
+	pub exp: Vec<u16>,
+	pub log: Vec<u16>,
+}
+
+impl Tables {
+	/// Generates tables of discrete logarithms and exponents in GF(2^16) with x as
+	/// the base
+	pub fn generate() -> Tables {
+		let mut tabs = Tables {
+			exp: vec![0; ORDER],
+			log: vec![0; ORDER + 1],
+		};
+
+		let mut tmp: u32 = 1;
+		for power in 0..ORDER {
+			tabs.exp[power] = tmp as u16;
+			tabs.log[tmp as usize] = power as u16;
+			tmp <<= 1;
+			if tmp & 0x10000 > 0 {
+				tmp ^= POLY;
+			}
+		}
+		tabs
+	}
+}
+
+lazy_static! {
+	/// Static reference to Generated tables
+	pub static ref TABLES: Tables = Tables::generate();
+}
+
+/// Element of the finite field with 65536 elements
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub struct Gf65536 {
+	pub poly: u16,
+}
+
+impl Gf65536 {
+	/// returns the additive neutral element of the field
+	#[inline]
+	pub fn zero() -> Gf65536 {
+		Gf65536 { poly: 0 }
+	}
+	/// returns the multiplicative neutral element of the field
+	#[inline]
+	pub fn one() -> Gf65536 {
+		Gf65536 { poly: 1 }
+	}
+	#[inline]
+	pub fn from_u16(v: u16) -> Gf65536 {
+		Gf65536 { poly: v }
+	}
+	#[cfg(test)]
+	#[inline]
+	pub fn to_u16(self) -> u16 {
+		self.poly
+	}
+	pub fn exp(power: usize) -> Gf65536 {
+		Gf65536::from_u16(TABLES.exp[power % ORDER])
+	}
+	pub fn log(self) -> Option<usize> {
+		if self.poly == 0 {
+			None
+		} else {
+			Some(usize::from(TABLES.log[self.poly as usize]))
+		}
+	}
+}
+
+impl Add<Gf65536> for Gf65536 {
+	type Output = Gf65536;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn add(self, rhs: Gf65536) -> Gf65536 {
+		Gf65536::from_u16(self.poly ^ rhs.poly)
+	}
+}
+
+impl AddAssign<Gf65536> for Gf65536 {
+	#[inline]
+	fn add_assign(&mut self, rhs: Gf65536) {
+		*self = *self + rhs;
+	}
+}
+
+impl Sub<Gf65536> for Gf65536 {
+	type Output = Gf65536;
+	#[inline]
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn sub(self, rhs: Gf65536) -> Gf65536 {
+		Gf65536::from_u16(self.poly ^ rhs.poly)
+	}
+}
+
+impl SubAssign<Gf65536> for Gf65536 {
+	#[inline]
+	fn sub_assign(&mut self, rhs: Gf65536) {
+		*self = *self - rhs;
+	}
+}
+
+impl Mul<Gf65536> for Gf65536 {
+	type Output = Gf65536;
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn mul(self, rhs: Gf65536) -> Gf65536 {
+		if let (Some(l1), Some(l2)) = (self.log(), rhs.log()) {
+			Gf65536::exp(l1 + l2)
+		} else {
+			Gf65536::zero()
+		}
+	}
+}
+
+impl MulAssign<Gf65536> for Gf65536 {
+	fn mul_assign(&mut self, rhs: Gf65536) {
+		*self = *self * rhs;
+	}
+}
+
+impl Div<Gf65536> for Gf65536 {
+	type Output = Gf65536;
+	#[allow(clippy::suspicious_arithmetic_impl)]
+	fn div(self, rhs: Gf65536) -> Gf65536 {
+		let l2 = rhs.log().expect("division by zero");
+		if let Some(l1) = self.log() {
+			Gf65536::exp(l1 + ORDER - l2)
+		} else {
+			Gf65536::zero()
+		}
+	}
+}
+
+impl DivAssign<Gf65536> for Gf65536 {
+	fn div_assign(&mut self, rhs: Gf65536) {
+		*self = *self / rhs;
+	}
+}
+
+/// Fused multiply-add over whole values: `dst[i] += c * src[i]` for each element
+pub fn mul_add(dst: &mut [u16], src: &[u16], c: Gf65536) {
+	assert_eq!(dst.len(), src.len(), "slice lengths must match");
+	let c_log = match c.log() {
+		Some(l) => l,
+		None => return,
+	};
+	let tabs = &*TABLES;
+	for (d, s) in dst.iter_mut().zip(src) {
+		if *s != 0 {
+			*d ^= tabs.exp[(usize::from(tabs.log[*s as usize]) + c_log) % ORDER];
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use quickcheck::*;
+
+	#[test]
+	fn tables_cover_field() {
+		let tabs = &*TABLES;
+		let mut seen = vec![false; ORDER + 1];
+		for &e in &tabs.exp {
+			assert!(!seen[e as usize]);
+			seen[e as usize] = true;
+		}
+		assert!(!seen[0]);
+		for v in 1..=ORDER {
+			assert_eq!(tabs.exp[tabs.log[v] as usize] as usize, v);
+		}
+	}
+
+	// carry-less multiplication reduced by POLY, bit by bit
+	fn mul_slow(a: u16, b: u16) -> u16 {
+		let (mut a, mut acc) = (u32::from(a), 0u32);
+		for i in 0..16 {
+			if b & (1 << i) != 0 {
+				acc ^= a;
+			}
+			a <<= 1;
+			if a & 0x10000 != 0 {
+				a ^= POLY;
+			}
+		}
+		acc as u16
+	}
+
+	quickcheck! {
+		fn mul_matches_reference(a: u16, b: u16) -> bool {
+			(Gf65536::from_u16(a) * Gf65536::from_u16(b)).to_u16() == mul_slow(a, b)
+		}
+
+		fn div_inverts_mul(a: u16, b: u16) -> TestResult {
+			if b == 0 {
+				return TestResult::discard();
+			}
+			let (a, b) = (Gf65536::from_u16(a), Gf65536::from_u16(b));
+			TestResult::from_bool(a * b / b == a)
+		}
+
+		fn distributive(a: u16, b: u16, c: u16) -> bool {
+			let (a, b, c) = (Gf65536::from_u16(a), Gf65536::from_u16(b), Gf65536::from_u16(c));
+			a * (b + c) == a * b + a * c
+		}
+
+		fn mul_add_matches_scalar(a: Vec<u16>, c: u16) -> bool {
+			let b: Vec<u16> = a.iter().map(|x| x.wrapping_mul(31).wrapping_add(7)).collect();
+			let c = Gf65536::from_u16(c);
+			let mut fused = b.clone();
+			mul_add(&mut fused, &a, c);
+			a.iter().zip(&b).zip(&fused).all(|((&x, &y), &f)| {
+				f == (Gf65536::from_u16(y) + c * Gf65536::from_u16(x)).to_u16()
+			})
+		}
+	}
+}
@@ -48,54 +48,67 @@
 //
 
 use crate::field::gf256::Gf256;
+#[cfg(test)]
 use crate::field::poly::Poly;
 
-/// Evaluates an interpolated polynomial at `Gf256::zero()` where
-/// the polynomial is determined using barycentric Lagrange
-/// interpolation based on the given `points` in
-/// the G(2^8) Galois field.
-pub fn _interpolate_at(k: u8, points: &[(u8, u8)]) -> u8 {
-	_barycentric_interpolate_at(k as usize, points)
+/// Evaluates the polynomial interpolated through `points` at `x`, without
+/// computing its coefficients. The x coordinates of `points` must be distinct
+/// and non-empty.
+#[cfg(test)]
+pub fn interpolate_at(x: u8, points: &[(u8, u8)]) -> u8 {
+	if let Some(p) = points.iter().find(|p| p.0 == x) {
+		return p.1;
+	}
+	let xs: Vec<Gf256> = points.iter().map(|p| Gf256::from_byte(p.0)).collect();
+	let basis = basis_at(&xs, Gf256::from_byte(x));
+	let mut y = Gf256::zero();
+	for (b, p) in basis.iter().zip(points) {
+		y += *b * Gf256::from_byte(p.1);
+	}
+	y.to_byte()
 }
 
-/// Barycentric Lagrange interpolation algorithm from "Polynomial
-/// Interpolation: Langrange vs Newton" by Wilhelm Werner. Evaluates
-/// the polynomial at `Gf256::zero()`.
-#[inline]
-fn _barycentric_interpolate_at(k: usize, points: &[(u8, u8)]) -> u8 {
-	// Compute the barycentric weights `w`.
-	let mut w = vec![Gf256::zero(); k];
-	w[0] = Gf256::one();
-
-	let mut x = Vec::with_capacity(k);
-	x.push(Gf256::from_byte(points[0].0));
-
-	for i in 1..k {
-		x.push(Gf256::from_byte(points[i].0));
-		for j in 0..i {
-			let delta = x[j] - x[i];
-			assert_ne!(delta.poly, 0, "Duplicate shares");
-			w[j] /= delta;
-			let n = w[j];
-			w[i] -= n;
-		}
+/// Values at `x` of the Lagrange basis polynomials for the distinct points `xs`,
+/// so a polynomial through `(xs[i], ys[i])` is `sum(ys[i] * basis[i])` at `x`.
+/// `x` must not be one of `xs`.
+///
+/// Uses the second or "true" form of the barycentric formula from "Polynomial
+/// Interpolation: Langrange vs Newton" by Wilhelm Werner, which needs a single
+/// product per point and one batch inversion.
+pub fn basis_at(xs: &[Gf256], x: Gf256) -> Vec<Gf256> {
+	// (x - xs[i]) / w[i], where w[i] = 1 / prod(xs[i] - xs[j]) for j != i
+	let mut basis: Vec<Gf256> = xs
+		.iter()
+		.enumerate()
+		.map(|(i, &xi)| {
+			assert_ne!(x, xi, "Evaluating at an interpolation point");
+			let mut d = x - xi;
+			for (j, &xj) in xs.iter().enumerate() {
+				if i != j {
+					assert_ne!(xi, xj, "Duplicate points");
+					d *= xi - xj;
+				}
+			}
+			d
+		})
+		.collect();
+	Gf256::batch_inv(&mut basis);
+
+	// each term is divided by the sum of the terms, 1 / prod(x - xs[j])
+	let mut denom = Gf256::zero();
+	for b in &basis {
+		denom += *b;
 	}
-
-	// Evaluate the second or "true" form of the barycentric
-	// interpolation formula at `Gf256::zero()`.
-	let (mut num, mut denom) = (Gf256::zero(), Gf256::zero());
-	for i in 0..k {
-		assert_ne!(x[i].poly, 0, "Invalid share x = 0");
-		let diff = w[i] / x[i];
-		num += diff * Gf256::from_byte(points[i].1);
-		denom += diff;
+	let scale = denom.inv().expect("x is not an interpolation point");
+	for b in basis.iter_mut() {
+		*b *= scale;
 	}
-
-	(num / denom).to_byte()
+	basis
 }
 
 /// Computeds the coefficient of the Lagrange polynomial interpolated
 /// from the given `points`, in the G(2^8) Galois field.
+#[cfg(test)]
 pub fn interpolate(points: &[(Gf256, Gf256)]) -> Poly {
 	let len = points.len();
 
@@ -110,23 +123,20 @@ pub fn interpolate(points: &[(Gf256, Gf256)]) -> Poly {
 			if x != x1 {
 				prod *= x - x1;
 
+				// multiply by (X - x1) in place
 				let mut prec = Gf256::zero();
-				coeffs = coeffs
-					.into_iter()
-					.map(|coeff| {
-						let new_coeff = coeff * (-x1) + prec;
-						prec = coeff;
-						new_coeff
-					})
-					.collect();
+				for coeff in coeffs.iter_mut() {
+					let old = *coeff;
+					*coeff = old * (-x1) + prec;
+					prec = old;
+				}
 			}
 		}
 
-		poly = poly
-			.iter()
-			.zip(coeffs.iter())
-			.map(|(&old_coeff, &add)| old_coeff + add / prod)
-			.collect();
+		let inv = prod.inv().expect("Duplicate points");
+		for (p, &c) in poly.iter_mut().zip(&coeffs) {
+			*p += c * inv;
+		}
 	}
 
 	Poly::new(poly)
@@ -139,17 +149,16 @@ mod tests {
 	use super::*;
 	use crate::field::gf256::*;
 	use quickcheck::*;
-	use std;
 
 	quickcheck! {
 
 		fn interpolate_evaluate_at_works(ys: Vec<Gf256>) -> TestResult {
-			if ys.is_empty() || ys.len() > std::u8::MAX as usize {
+			if ys.is_empty() || ys.len() > u8::MAX as usize {
 				return TestResult::discard();
 			}
 
 			let points = ys.into_iter()
-						   .zip(1..std::u8::MAX)
+						   .zip(1..u8::MAX)
 						   .map(|(y, x)| (gf256!(x), y))
 						   .collect::<Vec<_>>();
 			let poly = interpolate(&points);
@@ -163,13 +172,13 @@ mod tests {
 			TestResult::passed()
 		}
 
-		fn interpolate_evaluate_at_0_eq_evaluate_at(ys: Vec<u8>) -> TestResult {
-			if ys.is_empty() || ys.len() > std::u8::MAX as usize {
+		fn interpolate_evaluate_at_eq_interpolate_at(ys: Vec<u8>, x: u8) -> TestResult {
+			if ys.is_empty() || ys.len() > u8::MAX as usize {
 				return TestResult::discard();
 			}
 
 			let points = ys.into_iter()
-						   .zip(1..std::u8::MAX)
+						   .zip(1..u8::MAX)
 						   .map(|(y, x)| (x, y))
 						   .collect::<Vec<_>>();
 
@@ -180,8 +189,7 @@ mod tests {
 
 			let poly = interpolate(&elems);
 
-			let equals = poly.evaluate_at(Gf256::zero()).to_byte()
-				== _interpolate_at(points.len() as u8, points.as_slice());
+			let equals = poly.evaluate_at(gf256!(x)).to_byte() == interpolate_at(x, &points);
 
 			TestResult::from_bool(equals)
 		}
@@ -17,5 +17,6 @@
 
 #[macro_use]
 pub mod gf256;
+pub mod gf65536;
 pub mod lagrange;
-mod poly;
+pub mod poly;
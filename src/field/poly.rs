@@ -48,12 +48,16 @@
 // limitations under the License.
 //
 
+//! Polynomials over GF(256), the machinery behind Shamir secret sharing
+
 use crate::field::gf256::Gf256;
 use std::fmt;
+use std::ops::{Add, Mul};
 
-static MAX_COEFFS: usize = 256;
-
+/// Polynomial over GF(256), as its coefficients from the constant term up
+#[derive(Clone)]
 pub struct Poly {
+	/// Coefficients, lowest degree first. May have trailing zeros
 	pub coeffs: Vec<Gf256>,
 }
 
@@ -68,23 +72,134 @@ impl fmt::Debug for Poly {
 }
 
 impl Poly {
+	/// Polynomial with the given coefficients, lowest degree first
 	pub fn new(coeffs: Vec<Gf256>) -> Self {
 		Self { coeffs }
 	}
 
-	pub fn _evaluate_at_zero(&self) -> Gf256 {
-		self.coeffs[0]
+	/// The zero polynomial
+	pub fn zero() -> Self {
+		Self { coeffs: vec![] }
 	}
 
+	/// Degree of the polynomial, ignoring trailing zero coefficients. None for the
+	/// zero polynomial
+	pub fn degree(&self) -> Option<usize> {
+		self.coeffs.iter().rposition(|c| *c != Gf256::zero())
+	}
+
+	/// Value of the constant term, i.e. the shared secret
+	pub fn evaluate_at_zero(&self) -> Gf256 {
+		self.coeffs.first().copied().unwrap_or_else(Gf256::zero)
+	}
+
+	/// Value of the polynomial at x, by Horner's rule
 	pub fn evaluate_at(&self, x: Gf256) -> Gf256 {
-		assert!(self.coeffs.len() < MAX_COEFFS);
+		self.coeffs
+			.iter()
+			.rev()
+			.fold(Gf256::zero(), |acc, c| acc * x + *c)
+	}
+
+	/// Polynomial with every coefficient multiplied by `c`
+	pub fn scale(&self, c: Gf256) -> Poly {
+		Poly::new(self.coeffs.iter().map(|a| *a * c).collect())
+	}
+}
+
+/// Equal if the coefficients match, ignoring trailing zeros
+impl PartialEq for Poly {
+	fn eq(&self, other: &Poly) -> bool {
+		let len = |p: &Poly| p.degree().map(|d| d + 1).unwrap_or(0);
+		self.coeffs[..len(self)] == other.coeffs[..len(other)]
+	}
+}
+
+impl Eq for Poly {}
+
+impl<'a> Add<&'a Poly> for &'a Poly {
+	type Output = Poly;
+	fn add(self, rhs: &Poly) -> Poly {
+		let (long, short) = if self.coeffs.len() >= rhs.coeffs.len() {
+			(self, rhs)
+		} else {
+			(rhs, self)
+		};
+		let mut coeffs = long.coeffs.clone();
+		for (a, b) in coeffs.iter_mut().zip(&short.coeffs) {
+			*a += *b;
+		}
+		Poly::new(coeffs)
+	}
+}
 
-		let mut result = Gf256::zero();
+impl Add<Poly> for Poly {
+	type Output = Poly;
+	fn add(self, rhs: Poly) -> Poly {
+		&self + &rhs
+	}
+}
+
+impl Mul<Gf256> for &Poly {
+	type Output = Poly;
+	fn mul(self, rhs: Gf256) -> Poly {
+		self.scale(rhs)
+	}
+}
+
+impl Mul<Gf256> for Poly {
+	type Output = Poly;
+	fn mul(self, rhs: Gf256) -> Poly {
+		self.scale(rhs)
+	}
+}
 
-		for (i, c) in self.coeffs.iter().enumerate() {
-			result += *c * x.pow(i as u8);
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use quickcheck::*;
+
+	fn poly(coeffs: &[u8]) -> Poly {
+		Poly::new(coeffs.iter().map(|&c| Gf256::from_byte(c)).collect())
+	}
+
+	#[test]
+	fn degree() {
+		assert_eq!(Poly::zero().degree(), None);
+		assert_eq!(poly(&[0, 0]).degree(), None);
+		assert_eq!(poly(&[5]).degree(), Some(0));
+		assert_eq!(poly(&[5, 0, 3, 0, 0]).degree(), Some(2));
+		assert_eq!(poly(&[5, 0, 3, 0, 0]), poly(&[5, 0, 3]));
+		assert_eq!((poly(&[1, 2, 3]) + poly(&[1, 2, 3])).degree(), None);
+	}
+
+	#[test]
+	fn evaluate() {
+		// 7 + 3x + x^2 at x = 2: 7 + 6 + 4 in GF(256)
+		let p = poly(&[7, 3, 1]);
+		assert_eq!(p.evaluate_at(Gf256::from_byte(2)).to_byte(), 7 ^ 6 ^ 4);
+		assert_eq!(p.evaluate_at_zero().to_byte(), 7);
+		assert_eq!(Poly::zero().evaluate_at(Gf256::from_byte(9)), Gf256::zero());
+	}
+
+	quickcheck! {
+		fn evaluation_is_linear(a: Vec<u8>, b: Vec<u8>, c: u8, x: u8) -> bool {
+			let (a, b) = (poly(&a), poly(&b));
+			let (c, x) = (Gf256::from_byte(c), Gf256::from_byte(x));
+			(&a + &b).evaluate_at(x) == a.evaluate_at(x) + b.evaluate_at(x)
+				&& (&a * c).evaluate_at(x) == a.evaluate_at(x) * c
 		}
 
-		result
+		fn evaluation_matches_power_sum(a: Vec<u8>, x: u8) -> bool {
+			let p = poly(&a);
+			let x = Gf256::from_byte(x);
+			let sum = p
+				.coeffs
+				.iter()
+				.enumerate()
+				.fold(Gf256::zero(), |acc, (i, c)| acc + *c * x.pow((i % 255) as u8));
+			// x^255 = 1 for non-zero x, but pow(0) is 1 for x = 0 too
+			x == Gf256::zero() || p.evaluate_at(x) == sum
+		}
 	}
 }
@@ -0,0 +1,186 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interoperability with the reference python `shamir_mnemonic` CLI (`shamir`),
+//! reading and writing the output of its `create` and `recover` commands so
+//! shares can be exchanged between the two tools in recovery drills. The JSON
+//! test vector format used by the reference implementation is handled by
+//! [`crate::vectors`].
+
+use crate::error::{Error, ErrorKind};
+use crate::shamir::{normalize_mnemonic, GroupShare};
+use crate::util::hex;
+use crate::util::secret::SecretBytes;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const MASTER_SECRET_PREFIX: &str = "Using master secret: ";
+const RECOVERED_PREFIX: &str = "Your master secret is: ";
+
+/// A group of mnemonics as listed by `shamir create`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CliGroup {
+	/// Number of member shares required to reconstruct the group
+	pub member_threshold: u8,
+	/// Mnemonics of the group's member shares
+	pub mnemonics: Vec<Vec<String>>,
+}
+
+/// Parsed output of `shamir create`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CreateOutput {
+	/// Master secret in hex, if it was printed
+	pub master_secret: Option<String>,
+	/// Groups in the order they were listed
+	pub groups: Vec<CliGroup>,
+}
+
+impl CreateOutput {
+	/// All mnemonics in the output, in a form suitable for
+	/// [`combine_mnemonics`](crate::combine_mnemonics)
+	pub fn mnemonics(&self) -> Vec<Vec<String>> {
+		self.groups
+			.iter()
+			.flat_map(|g| g.mnemonics.iter().cloned())
+			.collect()
+	}
+}
+
+/// Formats groups of shares as printed by `shamir create`, optionally including the
+/// master secret line
+pub fn format_create(groups: &[GroupShare], master_secret: Option<&[u8]>) -> Result<String, Error> {
+	let mut out = String::new();
+	if let Some(ms) = master_secret {
+		out.push_str(&format!("{}{}\n", MASTER_SECRET_PREFIX, hex::to_hex(ms)));
+	}
+	for (i, g) in groups.iter().enumerate() {
+		out.push_str(&format!(
+			"Group {} of {} - {} of {} shares required:\n",
+			i + 1,
+			groups.len(),
+			g.member_threshold,
+			g.member_shares.len()
+		));
+		for s in g.mnemonic_list()? {
+			out.push_str(&s.join(" "));
+			out.push('\n');
+		}
+	}
+	Ok(out)
+}
+
+/// Parses the output of `shamir create`
+pub fn parse_create(output: &str) -> Result<CreateOutput, Error> {
+	let mut master_secret = None;
+	let mut groups: Vec<CliGroup> = vec![];
+	for (i, line) in output.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() {
+			continue;
+		}
+		if let Some(ms) = line.strip_prefix(MASTER_SECRET_PREFIX) {
+			hex::from_hex(ms)?;
+			master_secret = Some(ms.trim().to_owned());
+		} else if let Some(header) = line.strip_prefix("Group ") {
+			groups.push(CliGroup {
+				member_threshold: parse_group_header(header).ok_or_else(|| {
+					ErrorKind::Value(format!("Line {}: invalid group header '{}'", i + 1, line))
+				})?,
+				mnemonics: vec![],
+			});
+		} else {
+			match groups.last_mut() {
+				Some(g) => g.mnemonics.push(normalize_mnemonic(line)),
+				None => {
+					return Err(ErrorKind::Value(format!(
+						"Line {}: mnemonic found before any group header",
+						i + 1
+					)))?
+				}
+			}
+		}
+	}
+	if groups.is_empty() {
+		Err(ErrorKind::Value("No share groups found".to_string()))?;
+	}
+	Ok(CreateOutput {
+		master_secret,
+		groups,
+	})
+}
+
+/// Parses `X of Y - T of N shares required:`, returning T
+fn parse_group_header(header: &str) -> Option<u8> {
+	let (_, member) = header.split_once(" - ")?;
+	let (threshold, rest) = member.split_once(" of ")?;
+	if !rest.ends_with("shares required:") {
+		return None;
+	}
+	threshold.parse().ok()
+}
+
+/// Formats a recovered master secret as printed by `shamir recover`
+pub fn format_recover(master_secret: &[u8]) -> String {
+	format!(
+		"SUCCESS!\n{}{}\n",
+		RECOVERED_PREFIX,
+		hex::to_hex(master_secret)
+	)
+}
+
+/// Parses the master secret from the output of `shamir recover`
+pub fn parse_recover(output: &str) -> Result<SecretBytes, Error> {
+	match output
+		.lines()
+		.find_map(|l| l.trim().strip_prefix(RECOVERED_PREFIX))
+	{
+		Some(ms) => Ok(SecretBytes::new(hex::from_hex(ms)?)),
+		None => Err(ErrorKind::Value(
+			"No recovered master secret found in output".to_string(),
+		))?,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{combine_mnemonics, generate_mnemonics};
+
+	#[test]
+	fn create_round_trip() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let groups = generate_mnemonics(2, &[(1, 1), (2, 3), (3, 5)], &master_secret, "", 0)?;
+		let out = format_create(&groups, Some(&master_secret))?;
+		assert!(out.contains("Group 2 of 3 - 2 of 3 shares required:\n"));
+		let parsed = parse_create(&out)?;
+		assert_eq!(parsed.master_secret, Some(hex::to_hex(&master_secret)));
+		assert_eq!(parsed.groups.len(), 3);
+		assert_eq!(parsed.groups[2].member_threshold, 3);
+		assert_eq!(parsed.groups[2].mnemonics.len(), 5);
+		assert_eq!(
+			combine_mnemonics(&parsed.mnemonics(), "")?.secret,
+			master_secret
+		);
+
+		assert!(parse_create("academic academic").is_err());
+		assert!(parse_create("Group 1 of 1 - lots of shares required:").is_err());
+		assert_eq!(
+			parse_recover(&format_recover(&master_secret))?,
+			master_secret
+		);
+		Ok(())
+	}
+}
@@ -24,16 +24,103 @@
 
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod backup_sheet;
+#[cfg(feature = "bip32")]
+pub mod bip32;
+mod context;
+#[cfg(feature = "distribution")]
+pub mod distribution;
+#[cfg(feature = "embedded")]
+pub mod embedded;
 mod error;
 mod field;
+#[cfg(feature = "trezor-cli")]
+pub mod interop;
+#[cfg(feature = "passphrase-search")]
+pub mod recovery;
+pub mod self_test;
 mod shamir;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod util;
+pub mod vectors;
+#[cfg(feature = "vss")]
+pub mod vss;
+
+pub use context::Sssmc39Context;
+
+/// Experimental functionality outside of SLIP-39. Nothing here produces mnemonics or
+/// shares that other implementations can recover
+pub mod experimental {
+	pub use crate::shamir::wide::{
+		recover_secret_wide, split_secret_wide, Gf65536Sharer, WideShare, MAX_WIDE_SHARE_COUNT,
+	};
+}
+pub use error::{Error, ErrorKind, MnemonicIssue};
+pub use field::gf256::Gf256;
+pub use field::poly::Poly;
+pub use self_test::{self_test, SelfTestReport};
+pub use shamir::codec;
+pub use shamir::splitter;
+pub use shamir::{
+	combine_mnemonics_as, combine_mnemonics_cancellable, combine_mnemonics_for_identifier,
+	combine_mnemonics_with_options, combine_mnemonics_with_pins, header_layout,
+	header_layout_table, header_length_bits, lookup_word, max_mnemonic_words,
+	max_secret_len_for_mnemonic_words, min_mnemonic_words, mnemonic_word_count_for_secret,
+	normalize_mnemonic, plan_recovery, plan_recovery_from, recover_secret_raw,
+	recover_secret_raw_with_config, secret_len_for_word_count, split_secret_raw,
+	split_secret_raw_with_config, topologies, validate_layout, validate_mnemonic, CustomWordlist,
+	DigestHash, EncryptedMasterSecret, English, EntryHeader, EntryStatus, ExportedShare,
+	Fingerprint, Format, FormatOptions, GeneratedShares, GroupPlan, GroupShare, GroupSpec,
+	HeaderField, HeaderFieldId, IdentifierPolicy, MnemonicEntry, OutputFormat, ParseDiagnostics,
+	ParseOptions, ParseProfile, RawShare, ReceiptGroup, RecoveredGroup, RecoveredSecret,
+	RecoveryPlan, RecoverySimulation, RepairedMnemonic, SecretSharer, SecretStrength,
+	SecretWarning, Share, ShareBuilder, ShareConfig, ShareFileLayout, ShareInfo, ShareOrdering,
+	ShareSet, SplitProgress, SplitReceipt, Splitter, SplitterConfig, Topology, WordFix, Wordlist,
+	BIP39_WORDLIST_LEN, ENGLISH_SHA256, EXTENDED_HEADER_LAYOUT, FINGERPRINT_LEN, HEADER_LAYOUT,
+	MAX_GROUP_COUNT, MAX_IDENTIFIER, MAX_ITERATION_EXPONENT, MAX_SECRET_BYTES, MAX_SHARE_COUNT,
+	MIN_SECRET_BYTES, URI_SCHEME, WORDLIST_LEN,
+};
+pub use util::base32;
+pub use util::base64;
+pub use util::cancel::CancellationToken;
+#[cfg(feature = "kdf-argon2")]
+pub use util::encrypt::Argon2Cost;
+pub use util::encrypt::{
+	ExternalKdf, KdfBackend, KdfExecutor, KdfJob, KdfProfile, MasterSecretEncConfig,
+	PassphraseNormalization, SyncExecutor,
+};
+pub use util::hex;
+pub use util::rs1024;
+pub use util::secret::{Passphrase, SecretBytes};
 
-pub use error::{Error, ErrorKind};
-pub use shamir::{GroupShare, Share};
-// TODO: only exposed for tests
-pub use util::hex::{from_hex, to_hex};
+/// The types and functions most uses of the crate need, for glob import:
+/// `use sssmc39::prelude::*;`
+pub mod prelude {
+	pub use crate::{
+		combine_mnemonics, combine_mnemonics_with_options, generate_mnemonics, Error, ErrorKind,
+		Format, FormatOptions, GroupShare, MasterSecretEncConfig, ParseOptions, ParseProfile,
+		Passphrase, RecoveredSecret, SecretBytes, Share, ShareBuilder, ShareSet, SplitterConfig,
+		Sssmc39Context,
+	};
+}
+
+/// Encode the provided bytes into a lowercase hex string
+#[deprecated(since = "0.0.4", note = "use `sssmc39::hex::to_hex`")]
+pub fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+	hex::to_hex(bytes)
+}
+
+/// Decode a hex string into bytes
+#[deprecated(since = "0.0.4", note = "use `sssmc39::hex::from_hex`")]
+pub fn from_hex(hex_str: impl AsRef<str>) -> Result<Vec<u8>, hex::HexError> {
+	hex::from_hex(hex_str)
+}
 
 //TODO: Proper docs
 /// Generates shares from the provided master secret (e.g. BIP39 entropy)
@@ -41,7 +128,7 @@ pub fn generate_mnemonics(
 	group_threshold: u8,
 	groups: &[(u8, u8)],
 	master_secret: &[u8],
-	passphrase: &str,
+	passphrase: impl Into<Passphrase>,
 	iteration_exponent: u8,
 ) -> Result<Vec<GroupShare>, Error> {
 	shamir::generate_mnemonics(
@@ -55,18 +142,205 @@ pub fn generate_mnemonics(
 
 // TODO: Proper docs
 // should allow for different input formats
-/// Combines shares into a master secret (e.g. BIP39 entropy)
-pub fn combine_mnemonics(mnemonics: &[Vec<String>], passphrase: &str) -> Result<Vec<u8>, Error> {
+/// Combines shares into a master secret (e.g. BIP39 entropy), reporting which groups
+/// and member shares took part in the recovery
+pub fn combine_mnemonics(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
 	shamir::combine_mnemonics(mnemonics, passphrase)
 }
 
+/// Generates shares as [`generate_mnemonics`] does, also returning a [`SplitReceipt`]
+/// recording the share set's shape and share fingerprints, without the secret
+pub fn generate_mnemonics_with_receipt(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<(Vec<GroupShare>, SplitReceipt), Error> {
+	shamir::generate_mnemonics_with_receipt(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+	)
+}
+
+/// Generates shares using the given splitter and encryption configurations. The
+/// splitter configuration may enable non-interoperable, crate-specific extensions
+pub fn generate_mnemonics_with_config(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	config: &SplitterConfig,
+	enc_config: &MasterSecretEncConfig,
+) -> Result<Vec<GroupShare>, Error> {
+	shamir::generate_mnemonics_with_config(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		config,
+		enc_config,
+	)
+}
+
+/// Generates shares as [`generate_mnemonics`] does, calling `progress` as each share
+/// (and each chunk of a long share) is computed
+pub fn generate_mnemonics_with_progress(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	progress: impl Fn(&SplitProgress) + 'static,
+) -> Result<Vec<GroupShare>, Error> {
+	shamir::generate_mnemonics_with_progress(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		progress,
+	)
+}
+
+/// Combines shares that were generated with the given splitter configuration, decrypting
+/// with the given encryption configuration
+pub fn combine_mnemonics_with_config(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	config: &SplitterConfig,
+	enc_config: &MasterSecretEncConfig,
+) -> Result<RecoveredSecret, Error> {
+	shamir::combine_mnemonics_with_config(mnemonics, passphrase, config, enc_config)
+}
+
+/// Decodes shares into the encrypted master secret, which can then be decrypted with
+/// [`decrypt_ems`], possibly on another device
+pub fn combine_to_ems(mnemonics: &[Vec<String>]) -> Result<EncryptedMasterSecret, Error> {
+	shamir::combine_to_ems(mnemonics)
+}
+
+/// Decrypts an encrypted master secret obtained from [`combine_to_ems`]
+pub fn decrypt_ems(
+	ems: &EncryptedMasterSecret,
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	shamir::decrypt_ems(ems, passphrase)
+}
+
+/// Combines shares once and decrypts the result with each of the given passphrases,
+/// e.g. to show the secret protected by a decoy passphrase next to the real one
+pub fn decrypt_with_each<P: Into<Passphrase>>(
+	mnemonics: &[Vec<String>],
+	passphrases: impl IntoIterator<Item = P>,
+) -> Result<Vec<RecoveredSecret>, Error> {
+	shamir::decrypt_with_each(mnemonics, passphrases)
+}
+
+/// Checks that the given mnemonics are sufficient and consistent to recover a master
+/// secret, without the slow passphrase decryption step
+pub fn simulate_recovery(mnemonics: &[Vec<String>]) -> Result<RecoverySimulation, Error> {
+	shamir::simulate_recovery(mnemonics)
+}
+
+/// Recovers all secrets that can be recovered from a mixed pile of mnemonics belonging
+/// to one or more share sets, with one result per share set identifier
+pub fn combine_all(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> Vec<Result<RecoveredSecret, Error>> {
+	shamir::combine_all(mnemonics, passphrase)
+}
+
+/// Combines shares provided as whole mnemonic strings into a master secret, tolerating
+/// differences in case, whitespace, punctuation and word numbering. The passphrase
+/// is NFKD normalized
+pub fn combine_mnemonic_strings(
+	mnemonics: &[String],
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	shamir::combine_mnemonic_strings(mnemonics, passphrase)
+}
+
+/// Generates shares from a hex-encoded master secret, failing with a [`ErrorKind::Hex`]
+/// error if the string is not valid hex
+pub fn generate_mnemonics_from_hex(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	hex_secret: &str,
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<Vec<GroupShare>, Error> {
+	shamir::generate_mnemonics_from_hex(
+		group_threshold,
+		groups,
+		hex_secret,
+		passphrase,
+		iteration_exponent,
+	)
+}
+
+/// Generates shares as with [`generate_mnemonics`], choosing the identifier according
+/// to the given policy (e.g. a fixed identifier, or one that avoids existing share sets)
+pub fn generate_mnemonics_with_identifier(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	identifier: &IdentifierPolicy,
+) -> Result<GeneratedShares, Error> {
+	shamir::generate_mnemonics_with_identifier(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		identifier,
+	)
+}
+
+/// Encrypts a master secret with the passphrase, to be split into shares later with
+/// [`split_ems`], possibly on another device
+pub fn encrypt_master_secret(
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<EncryptedMasterSecret, Error> {
+	shamir::encrypt_master_secret(master_secret, passphrase, iteration_exponent)
+}
+
+/// Splits an encrypted master secret obtained from [`encrypt_master_secret`] into
+/// groups of shares
+pub fn split_ems(
+	ems: &EncryptedMasterSecret,
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+) -> Result<Vec<GroupShare>, Error> {
+	shamir::split_ems(ems, group_threshold, groups)
+}
+
+/// Checks that a master secret is suitable for splitting, reporting any signs of low
+/// entropy (such as a hex string passed as ASCII bytes)
+pub fn validate_master_secret(master_secret: &[u8]) -> Result<SecretStrength, Error> {
+	shamir::validate_master_secret(master_secret)
+}
+
 // TODO: Proper docs
 /// Generate a random master secret (e.g. BIP39 entropy) and returns the shares from it
 pub fn generate_mnemonics_random(
 	group_threshold: u8,
 	groups: &[(u8, u8)],
 	strength_bits: u16,
-	passphrase: &str,
+	passphrase: impl Into<Passphrase>,
 	iteration_exponent: u8,
 ) -> Result<Vec<GroupShare>, Error> {
 	shamir::generate_mnemonics_random(
@@ -77,3 +351,28 @@ pub fn generate_mnemonics_random(
 		iteration_exponent,
 	)
 }
+
+/// Combines shares gathered into a [`ShareSet`] (e.g. over several recovery steps)
+/// into a master secret
+pub fn combine_share_set(
+	shares: &ShareSet,
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	shamir::combine_share_set(shares, passphrase)
+}
+
+/// Reconstructs the unreadable word at `position` of a damaged mnemonic, checking
+/// candidates against the checksum and, with the other shares, the secret's digest
+pub fn recover_erased_word(
+	others: &[Vec<String>],
+	damaged: &[String],
+	position: usize,
+) -> Result<RepairedMnemonic, Error> {
+	shamir::recover_erased_word(others, damaged, position)
+}
+
+/// Repairs a mnemonic in which two adjacent words were swapped during transcription,
+/// reporting which words were swapped back
+pub fn repair_swapped_words(mnemonic: &[String]) -> Result<RepairedMnemonic, Error> {
+	shamir::repair_swapped_words(mnemonic)
+}
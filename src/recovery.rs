@@ -0,0 +1,126 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recovery of a partially remembered passphrase. Since any passphrase decrypts
+//! a share set to some secret, candidates are tested with a caller-supplied
+//! check, e.g. deriving a wallet and looking for a known address
+
+use crate::error::Error;
+use crate::shamir::{combine_to_ems, RecoveredSecret};
+use crate::util::secret::Passphrase;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A candidate passphrase that passed the check, with the secret it decrypts to
+#[derive(Debug, Clone)]
+pub struct PassphraseMatch {
+	/// The matching passphrase
+	pub passphrase: Passphrase,
+	/// The secret recovered with it
+	pub recovered: RecoveredSecret,
+}
+
+/// Try each candidate passphrase against the given mnemonics on all available
+/// cores, returning the first one whose decrypted secret satisfies `checker`.
+/// The shares are combined once up front, so each guess only costs a decryption
+pub fn passphrase_search<I, F>(
+	mnemonics: &[Vec<String>],
+	candidates: I,
+	checker: F,
+) -> Result<Option<PassphraseMatch>, Error>
+where
+	I: Iterator<Item = String> + Send,
+	F: Fn(&[u8]) -> bool + Sync,
+{
+	let threads = thread::available_parallelism()
+		.map(|n| n.get())
+		.unwrap_or(1);
+	passphrase_search_with_threads(mnemonics, candidates, checker, threads)
+}
+
+/// As [`passphrase_search`], using the given number of worker threads
+pub fn passphrase_search_with_threads<I, F>(
+	mnemonics: &[Vec<String>],
+	candidates: I,
+	checker: F,
+	threads: usize,
+) -> Result<Option<PassphraseMatch>, Error>
+where
+	I: Iterator<Item = String> + Send,
+	F: Fn(&[u8]) -> bool + Sync,
+{
+	let ems = combine_to_ems(mnemonics)?;
+	let candidates = Mutex::new(candidates);
+	let found = AtomicBool::new(false);
+	let result: Mutex<Option<Result<PassphraseMatch, Error>>> = Mutex::new(None);
+
+	thread::scope(|s| {
+		for _ in 0..threads.max(1) {
+			s.spawn(|| {
+				while !found.load(Ordering::Relaxed) {
+					let candidate = match candidates.lock().expect("candidates lock").next() {
+						Some(c) => Passphrase::new(c),
+						None => return,
+					};
+					let outcome = ems
+						.decrypt(&candidate)
+						.map(|recovered| {
+							if checker(&recovered.secret) {
+								Some(recovered)
+							} else {
+								None
+							}
+						})
+						.transpose();
+					if let Some(outcome) = outcome {
+						found.store(true, Ordering::Relaxed);
+						let mut result = result.lock().expect("result lock");
+						if result.is_none() {
+							*result = Some(outcome.map(|recovered| PassphraseMatch {
+								passphrase: candidate,
+								recovered,
+							}));
+						}
+						return;
+					}
+				}
+			});
+		}
+	});
+
+	result.into_inner().expect("result lock").transpose()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn passphrase_search_test() -> Result<(), Error> {
+		let ms = vec![0x42u8; 16];
+		let mns = generate_mnemonics(1, &[(2, 3)], &ms, "hunter7", 0)?;
+		let input = mns[0].mnemonic_list()?[..2].to_vec();
+		let candidates = (0..10).map(|i| format!("hunter{}", i));
+		let found = passphrase_search_with_threads(&input, candidates, |s| s == &ms[..], 3)?;
+		let found = found.expect("passphrase should be found");
+		assert_eq!(found.passphrase.as_str(), "hunter7");
+		assert_eq!(found.recovered.secret, ms);
+
+		let candidates = vec!["nope".to_string()].into_iter();
+		assert!(passphrase_search(&input, candidates, |s| s == &ms[..])?.is_none());
+		Ok(())
+	}
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Known-answer self-tests of the cryptographic primitives, so deployments can
+//! confirm at runtime that they behave as expected on the target platform. With
+//! the `self-test` feature, the tests run once before the first shares are
+//! generated and generation fails if any of them does
+
+#[cfg(feature = "self-test")]
+use crate::error::{Error, ErrorKind};
+use crate::field::gf256::slice_ops;
+use crate::field::gf256::Gf256;
+use crate::shamir::{combine_mnemonics, lookup_word, normalize_mnemonic};
+use crate::util::crypto;
+use crate::util::encrypt::KdfBackend;
+use crate::util::hex::from_hex;
+use crate::util::rs1024;
+
+use std::fmt;
+
+/// First SLIP-39 test vector, a single 1-of-1 share
+const SPEC_MNEMONIC: &str = "duckling enlarge academic academic agency result length solution \
+	fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+
+/// Outcome of a single known-answer test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestResult {
+	/// What was tested
+	pub name: &'static str,
+	/// Whether the output matched the known answer
+	pub passed: bool,
+}
+
+/// Outcome of all known-answer tests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+	/// Result of each test, in the order run
+	pub results: Vec<SelfTestResult>,
+}
+
+impl SelfTestReport {
+	/// Whether every test passed
+	pub fn passed(&self) -> bool {
+		self.results.iter().all(|r| r.passed)
+	}
+
+	/// Names of the tests that failed
+	pub fn failures(&self) -> Vec<&'static str> {
+		self.results
+			.iter()
+			.filter(|r| !r.passed)
+			.map(|r| r.name)
+			.collect()
+	}
+}
+
+impl fmt::Display for SelfTestReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for r in &self.results {
+			writeln!(f, "{}: {}", r.name, if r.passed { "ok" } else { "FAILED" })?;
+		}
+		Ok(())
+	}
+}
+
+// RFC 4231, test case 2
+fn hmac_sha256() -> bool {
+	let mac = crypto::hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+	let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+	from_hex(expected).ok().as_deref() == Some(&mac[..])
+}
+
+// "password" salted with "salt" over 2 iterations, from the widely published SHA-256
+// counterpart of the RFC 6070 vectors
+fn pbkdf2_sha256() -> bool {
+	let mut out = [0u8; 32];
+	KdfBackend::default().derive(2, b"salt", b"password", &mut out);
+	let expected = "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43";
+	from_hex(expected).ok().as_deref() == Some(&out[..])
+}
+
+// the log and exp tables are inverse bijections, and a known AES field inverse
+// pair multiplies to one through both the scalar and slice paths
+fn gf256_tables() -> bool {
+	let tables_ok = (1..=255u8).all(|b| {
+		let x = Gf256::from_byte(b);
+		matches!(x.log(), Some(l) if Gf256::exp(l) == x)
+	});
+	let mut dst = [0u8];
+	slice_ops::mul_add(&mut dst, &[0x53], Gf256::from_byte(0xca));
+	tables_ok && (Gf256::from_byte(0x53) * Gf256::from_byte(0xca)).to_byte() == 1 && dst[0] == 1
+}
+
+// the spec mnemonic's checksum verifies, and fails with a changed word
+fn rs1024_checksum() -> bool {
+	let mut indices: Vec<u32> = match normalize_mnemonic(SPEC_MNEMONIC)
+		.iter()
+		.map(|w| lookup_word(w).map(|i| i as u32))
+		.collect()
+	{
+		Some(i) => i,
+		None => return false,
+	};
+	let valid = rs1024::verify_checksum(b"shamir", &indices).is_ok();
+	indices[4] ^= 1;
+	valid && rs1024::verify_checksum(b"shamir", &indices).is_err()
+}
+
+// recovering the spec vector exercises decoding, interpolation and the Feistel cipher
+fn slip39_vector() -> bool {
+	match combine_mnemonics(&[normalize_mnemonic(SPEC_MNEMONIC)], "TREZOR") {
+		Ok(r) => {
+			from_hex("bb54aac4b89dc868ba37d9cc21b2cece").ok().as_deref() == Some(&r.secret[..])
+		}
+		Err(_) => false,
+	}
+}
+
+/// A named known-answer test
+type KnownAnswerTest = (&'static str, fn() -> bool);
+
+const TESTS: &[KnownAnswerTest] = &[
+	("HMAC-SHA256", hmac_sha256),
+	("PBKDF2-HMAC-SHA256", pbkdf2_sha256),
+	("GF(256) tables", gf256_tables),
+	("RS1024 checksum", rs1024_checksum),
+	("SLIP-39 test vector", slip39_vector),
+];
+
+/// Run the known-answer tests for HMAC-SHA256, PBKDF2, the GF(256) tables, the
+/// RS1024 checksum and a SLIP-39 test vector
+pub fn self_test() -> SelfTestReport {
+	SelfTestReport {
+		results: TESTS
+			.iter()
+			.map(|(name, test)| SelfTestResult {
+				name,
+				passed: test(),
+			})
+			.collect(),
+	}
+}
+
+#[cfg(feature = "self-test")]
+lazy_static! {
+	/// Report of the tests run before the first generation
+	static ref STARTUP_REPORT: SelfTestReport = self_test();
+}
+
+/// Fail unless the self-tests passed, running them on first use
+#[cfg(feature = "self-test")]
+pub(crate) fn ensure_passed() -> Result<(), Error> {
+	if !STARTUP_REPORT.passed() {
+		Err(ErrorKind::SelfTest(STARTUP_REPORT.failures().join(", ")))?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn self_test_passes() {
+		let report = self_test();
+		assert!(report.passed(), "{}", report);
+		assert_eq!(report.results.len(), TESTS.len());
+		assert!(report.failures().is_empty());
+		assert!(format!("{}", report).contains("RS1024 checksum: ok"));
+		assert!(ensure_passed().is_ok());
+	}
+}
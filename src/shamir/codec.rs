@@ -0,0 +1,161 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Binary encoding of a single share, for storage or transport where mnemonic
+//! words aren't needed. All multi-byte values are big-endian:
+//!
+//! | offset  | size | field                                                   |
+//! |---------|------|---------------------------------------------------------|
+//! | 0       | 1    | layout version ([`LAYOUT_VERSION`])                     |
+//! | 1       | 2    | identifier (15 bits)                                    |
+//! | 3       | 1    | iteration exponent (5 bits)                             |
+//! | 4       | 1    | group index (high nibble), group threshold - 1 (low)    |
+//! | 5       | 1    | group count - 1 (high nibble), member index (low)       |
+//! | 6       | 1    | member threshold - 1 (high nibble), format (low)        |
+//! | 7       | 1    | KDF profile (0 unless the format is extended)           |
+//! | 8       | n    | share value                                             |
+//! | 8 + n   | 4    | RS1024 checksum of the equivalent mnemonic (30 bits)    |
+//!
+//! The format nibble is 0 for SLIP-39 and 2 for the crate-specific extended format,
+//! with bit 3 set for a PIN protected share.
+//! The checksum is the one carried by the last words of the share's mnemonic, so
+//! a decoded share is verified exactly as a mnemonic would be
+
+use crate::error::{Error, ErrorKind};
+use crate::shamir::share::{Format, Share, ShareBuilder, FLAG_PIN};
+
+/// Version of the byte layout
+pub const LAYOUT_VERSION: u8 = 1;
+/// Length in bytes of the header preceding the share value
+pub const HEADER_LEN: usize = 8;
+/// Length in bytes of the trailing checksum
+pub const CHECKSUM_LEN: usize = 4;
+
+/// Encode a share in the binary layout
+pub fn encode(share: &Share) -> Result<Vec<u8>, Error> {
+	encode_with_indices(share, &share.mnemonic_indices()?)
+}
+
+// encode a share, given the word indices of its mnemonic
+pub(crate) fn encode_with_indices(share: &Share, indices: &[u32]) -> Result<Vec<u8>, Error> {
+	let format = match share.format() {
+		Format::Slip39 => 0u8,
+		Format::ExtendedV2 => 2u8,
+	} | if share.is_pin_protected() { 0x08 } else { 0 };
+	let checksum = indices_checksum(share, indices);
+	let mut out = Vec::with_capacity(HEADER_LEN + share.share_value().len() + CHECKSUM_LEN);
+	out.push(LAYOUT_VERSION);
+	out.extend_from_slice(&share.identifier().to_be_bytes());
+	out.push(share.iteration_exponent());
+	out.push(share.group_index() << 4 | (share.group_threshold() - 1));
+	out.push((share.group_count() - 1) << 4 | share.member_index());
+	out.push((share.member_threshold() - 1) << 4 | format);
+	out.push(share.kdf_profile());
+	out.extend_from_slice(share.share_value());
+	out.extend_from_slice(&checksum.to_be_bytes());
+	Ok(out)
+}
+
+/// Decode a share from the binary layout, validating the header values and checksum
+pub fn decode(input: &[u8]) -> Result<Share, Error> {
+	if input.len() < HEADER_LEN + CHECKSUM_LEN {
+		Err(ErrorKind::Value(format!(
+			"Encoded share too short: {} bytes",
+			input.len()
+		)))?;
+	}
+	if input[0] != LAYOUT_VERSION {
+		Err(ErrorKind::Value(format!(
+			"Unsupported share layout version {}",
+			input[0]
+		)))?;
+	}
+	let pin_protected = input[6] & 0x08 != 0;
+	let format = match input[6] & 0x07 {
+		0 => Format::Slip39,
+		2 => Format::ExtendedV2,
+		f => {
+			return Err(ErrorKind::Value(format!("Unknown share format {}", f)))?;
+		}
+	};
+	let (body, checksum) = input.split_at(input.len() - CHECKSUM_LEN);
+	let mut share = ShareBuilder::new()
+		.with_identifier(u16::from_be_bytes([input[1], input[2]]))
+		.with_iteration_exponent(input[3])
+		.with_group(input[4] >> 4, (input[4] & 0x0f) + 1, (input[5] >> 4) + 1)
+		.with_member(input[5] & 0x0f, (input[6] >> 4) + 1)
+		.with_format(format, input[7])
+		.with_share_value(&body[HEADER_LEN..])
+		.build()?;
+	if pin_protected {
+		if format != Format::ExtendedV2 {
+			Err(ErrorKind::Value(
+				"PIN protected shares must use the extended format".to_string(),
+			))?;
+		}
+		share.flags |= FLAG_PIN;
+	}
+	let expected = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+	if mnemonic_checksum(&share)? != expected {
+		Err(ErrorKind::Checksum(
+			"Invalid checksum of encoded share".to_string(),
+		))?;
+	}
+	Ok(share)
+}
+
+// the checksum words of the share's mnemonic, as a single value
+fn mnemonic_checksum(share: &Share) -> Result<u32, Error> {
+	Ok(indices_checksum(share, &share.mnemonic_indices()?))
+}
+
+fn indices_checksum(share: &Share, indices: &[u32]) -> u32 {
+	let words = share.config().checksum_length_words as usize;
+	let radix_bits = u32::from(share.config().radix_bits);
+	indices[indices.len() - words..]
+		.iter()
+		.fold(0, |acc, w| acc << radix_bits | w)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn codec_round_trip() -> Result<(), Error> {
+		let share = ShareBuilder::new()
+			.with_identifier(21219)
+			.with_iteration_exponent(3)
+			.with_group(1, 2, 3)
+			.with_member(4, 3)
+			.with_share_value(&[0x5a; 16])
+			.build()?;
+		let bytes = encode(&share)?;
+		assert_eq!(bytes.len(), HEADER_LEN + 16 + CHECKSUM_LEN);
+		assert_eq!(
+			&bytes[..HEADER_LEN],
+			&[1, 0x52, 0xe3, 3, 0x11, 0x24, 0x20, 0]
+		);
+		assert_eq!(decode(&bytes)?, share);
+
+		let mut bad = bytes.clone();
+		bad[10] ^= 1;
+		assert!(decode(&bad).is_err());
+		let mut bad = bytes.clone();
+		bad[0] = 2;
+		assert!(decode(&bad).is_err());
+		assert!(decode(&bytes[..HEADER_LEN]).is_err());
+		Ok(())
+	}
+}
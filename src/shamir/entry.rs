@@ -0,0 +1,231 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Assistance for entering a mnemonic one word at a time, e.g. on
+//! keyboard-constrained devices
+
+use super::header::{header_length_bits, read_field, HEADER_LAYOUT};
+use super::share::{validate_mnemonic, Format, ShareConfig, ShareInfo};
+use super::wordlist::{lookup_word, WORDLIST};
+use crate::error::{Error, ErrorKind};
+use crate::util::bitpacker::BitPacker;
+use crate::util::rs1024::Checksummer;
+
+/// Header values that can be read once the first few words have been entered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryHeader {
+	/// Identifier common to all shares of a set
+	pub identifier: u16,
+	/// Iteration exponent
+	pub iteration_exponent: u8,
+	/// Group index
+	pub group_index: u8,
+	/// Group threshold
+	pub group_threshold: u8,
+	/// Group count
+	pub group_count: u8,
+	/// Member index
+	pub member_index: u8,
+	/// Member threshold
+	pub member_threshold: u8,
+}
+
+/// State of the entry after a word has been accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryStatus {
+	/// More words are required
+	Incomplete,
+	/// The mnemonic is complete and its checksum is valid
+	Complete(ShareInfo),
+}
+
+/// Accepts the words of a mnemonic one at a time
+#[derive(Debug, Clone, Default)]
+pub struct MnemonicEntry {
+	words: Vec<String>,
+	// checksum state after each entered word, so completion is detected without
+	// re-checksumming the whole mnemonic
+	checksums: Vec<Checksummer>,
+	expected_length: Option<usize>,
+	config: ShareConfig,
+}
+
+impl MnemonicEntry {
+	/// Create a new, empty entry
+	pub fn new() -> Self {
+		MnemonicEntry {
+			words: vec![],
+			checksums: vec![],
+			expected_length: None,
+			config: ShareConfig::new(),
+		}
+	}
+
+	/// Create a new entry expecting a mnemonic of the given length, e.g. the
+	/// length of a previously entered mnemonic from the same share set
+	pub fn with_expected_length(len: usize) -> Self {
+		let mut e = MnemonicEntry::new();
+		e.expected_length = Some(len);
+		e
+	}
+
+	/// All words in the wordlist beginning with the given prefix
+	pub fn candidates(prefix: &str) -> Vec<&'static str> {
+		WORDLIST
+			.iter()
+			.filter(|w| w.starts_with(prefix))
+			.copied()
+			.collect()
+	}
+
+	/// Accept the next word, or an unambiguous prefix of it
+	pub fn push(&mut self, word: &str) -> Result<EntryStatus, Error> {
+		if let Some(l) = self.expected_length {
+			if self.words.len() >= l {
+				return Err(ErrorKind::Mnemonic(format!(
+					"Mnemonic is already complete ({} words)",
+					l
+				)))?;
+			}
+		}
+		let candidates = MnemonicEntry::candidates(word);
+		let resolved = match candidates.iter().find(|c| **c == word) {
+			Some(w) => *w,
+			None => match candidates.len() {
+				1 => candidates[0],
+				0 => {
+					return Err(ErrorKind::Mnemonic(format!(
+						"'{}' is not a prefix of any SSSMC39 word.",
+						word
+					)))?;
+				}
+				n => {
+					return Err(ErrorKind::Mnemonic(format!(
+						"'{}' is ambiguous ({} possible words).",
+						word, n
+					)))?;
+				}
+			},
+		};
+		let mut checksum = match self.checksums.last() {
+			Some(c) => *c,
+			None => Checksummer::new(&self.config.customization_string),
+		};
+		checksum.push(u32::from(
+			lookup_word(resolved).expect("word from wordlist"),
+		));
+		self.checksums.push(checksum);
+		self.words.push(resolved.to_owned());
+		Ok(self.status())
+	}
+
+	/// Remove the last entered word
+	pub fn pop(&mut self) -> Option<String> {
+		self.checksums.pop();
+		self.words.pop()
+	}
+
+	/// Words entered so far
+	pub fn words(&self) -> &[String] {
+		&self.words
+	}
+
+	/// Total expected number of words, if known
+	pub fn expected_length(&self) -> Option<usize> {
+		self.expected_length
+	}
+
+	/// Header values, available once the header words have been entered
+	pub fn header(&self) -> Option<EntryHeader> {
+		let radix_bits = self.config.radix_bits as usize;
+		let header_words = header_length_bits(Format::Slip39).div_ceil(radix_bits);
+		if self.words.len() < header_words {
+			return None;
+		}
+		let mut bp = BitPacker::new();
+		for w in &self.words[..header_words] {
+			let i = lookup_word(w)?;
+			bp.append(i, self.config.radix_bits).ok()?;
+		}
+		let value = |i: usize| read_field(&bp, &HEADER_LAYOUT[i]).ok();
+		Some(EntryHeader {
+			identifier: value(0)?,
+			iteration_exponent: value(1)? as u8,
+			group_index: value(2)? as u8,
+			group_threshold: value(3)? as u8,
+			group_count: value(4)? as u8,
+			member_index: value(5)? as u8,
+			member_threshold: value(6)? as u8,
+		})
+	}
+
+	/// Current status of the entry. Without a known expected length, the entry
+	/// is considered complete as soon as the entered words form a valid mnemonic
+	pub fn status(&self) -> EntryStatus {
+		if let Some(l) = self.expected_length {
+			if self.words.len() < l {
+				return EntryStatus::Incomplete;
+			}
+		}
+		match self.checksums.last() {
+			Some(c) if c.verify().is_ok() => {}
+			_ => return EntryStatus::Incomplete,
+		}
+		match validate_mnemonic(&self.words) {
+			Ok(info) => EntryStatus::Complete(info),
+			Err(_) => EntryStatus::Incomplete,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::Share;
+
+	#[test]
+	fn word_by_word_entry() -> Result<(), Error> {
+		let mn: Vec<String> = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"
+			.split(' ')
+			.map(|s| s.to_owned())
+			.collect();
+		assert_eq!(MnemonicEntry::candidates("acad"), vec!["academic"]);
+		assert!(MnemonicEntry::candidates("ac").len() > 1);
+
+		let mut entry = MnemonicEntry::with_expected_length(mn.len());
+		assert!(entry.push("zzzz").is_err());
+		assert!(entry.push("ac").is_err());
+		for (i, w) in mn.iter().enumerate() {
+			let status = entry.push(&w[..4.min(w.len())])?;
+			if i == 3 {
+				let header = entry.header().unwrap();
+				assert_eq!(header.group_count, 1);
+				assert_eq!(header.member_threshold, 1);
+			}
+			if i < mn.len() - 1 {
+				assert_eq!(status, EntryStatus::Incomplete);
+			} else {
+				let share = Share::from_mnemonic(&mn)?;
+				assert_eq!(status, EntryStatus::Complete(ShareInfo::from(&share)));
+			}
+		}
+		assert_eq!(entry.words(), &mn[..]);
+		assert!(entry.push("academic").is_err());
+
+		// wrong final word fails the checksum
+		entry.pop();
+		assert_eq!(entry.push("acid")?, EntryStatus::Incomplete);
+		Ok(())
+	}
+}
@@ -0,0 +1,85 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of a group's member shares in every output format at once, e.g. for
+//! backup tools printing multi-format cards
+
+use super::share::words_in;
+use super::wordlist::English;
+use super::{codec, GroupShare};
+use crate::error::Error;
+use crate::util::hex::to_hex;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A member share in each of the crate's output formats
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExportedShare {
+	/// Member index of the share
+	pub member_index: u8,
+	/// Mnemonic words
+	pub words: Vec<String>,
+	/// Mnemonic as a single space separated string
+	pub mnemonic: String,
+	/// Binary encoding, see [`codec`]
+	pub bytes: Vec<u8>,
+	/// Binary encoding as lowercase hex
+	pub hex: String,
+}
+
+impl GroupShare {
+	/// Every member share as words, mnemonic string, bytes and hex, packing each
+	/// share's bits only once
+	pub fn export(&self) -> Result<Vec<ExportedShare>, Error> {
+		self.member_shares
+			.iter()
+			.map(|s| {
+				let indices = s.mnemonic_indices()?;
+				let words = words_in(&indices, &English)?;
+				let bytes = codec::encode_with_indices(s, &indices)?;
+				Ok(ExportedShare {
+					member_index: s.member_index,
+					mnemonic: words.join(" "),
+					words,
+					hex: to_hex(&bytes),
+					bytes,
+				})
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{generate_mnemonics, Share};
+
+	#[test]
+	fn export_formats() -> Result<(), Error> {
+		let groups = generate_mnemonics(1, &[(2, 3)], &[7u8; 16], "", 0)?;
+		let exported = groups[0].export()?;
+		assert_eq!(exported.len(), 3);
+		for (e, s) in exported.iter().zip(&groups[0].member_shares) {
+			assert_eq!(e.member_index, s.member_index);
+			assert_eq!(e.words, s.to_mnemonic()?);
+			assert_eq!(e.mnemonic, e.words.join(" "));
+			assert_eq!(e.bytes, codec::encode(s)?);
+			assert_eq!(e.hex, to_hex(&e.bytes));
+			assert_eq!(&Share::from_u8_vec(&e.bytes)?, s);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,112 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Short fingerprints of shares and share sets, so custodians can confirm they hold
+//! the intended share (e.g. by reading the fingerprint out over the phone) without
+//! revealing it
+
+use super::{codec, GroupShare, Share, ShareSet};
+use crate::error::Error;
+use crate::util::crypto::{sha256, Sha256Hasher};
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of bytes of the SHA-256 digest kept in a fingerprint
+pub const FINGERPRINT_LEN: usize = 4;
+
+/// The first bytes of a SHA-256 digest over the binary encoding (see [`codec`]) of
+/// one or more shares. Displayed as two groups of four hex digits, e.g. `1A2B-3C4D`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fingerprint(pub [u8; FINGERPRINT_LEN]);
+
+impl Fingerprint {
+	// fingerprint of the shares, each length prefixed, in the given order
+	fn of<'a>(shares: impl IntoIterator<Item = &'a Share>) -> Result<Fingerprint, Error> {
+		let mut hasher = Sha256Hasher::new();
+		for s in shares {
+			let bytes = codec::encode(s)?;
+			hasher.update(&(bytes.len() as u16).to_be_bytes());
+			hasher.update(&bytes);
+		}
+		let mut fp = [0u8; FINGERPRINT_LEN];
+		fp.copy_from_slice(&hasher.finalize()[..FINGERPRINT_LEN]);
+		Ok(Fingerprint(fp))
+	}
+}
+
+impl fmt::Display for Fingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let b = &self.0;
+		write!(f, "{:02X}{:02X}-{:02X}{:02X}", b[0], b[1], b[2], b[3])
+	}
+}
+
+impl Share {
+	/// Fingerprint of the share, which can be compared without revealing the share
+	pub fn fingerprint(&self) -> Result<Fingerprint, Error> {
+		let digest = sha256(&codec::encode(self)?);
+		let mut fp = [0u8; FINGERPRINT_LEN];
+		fp.copy_from_slice(&digest[..FINGERPRINT_LEN]);
+		Ok(Fingerprint(fp))
+	}
+}
+
+impl GroupShare {
+	/// Fingerprint of the group's member shares, independent of their order
+	pub fn fingerprint(&self) -> Result<Fingerprint, Error> {
+		let mut shares: Vec<&Share> = self.member_shares.iter().collect();
+		shares.sort_by_key(|s| s.member_index);
+		Fingerprint::of(shares)
+	}
+}
+
+impl ShareSet {
+	/// Fingerprint of all shares in the set, independent of the order they were added
+	pub fn fingerprint(&self) -> Result<Fingerprint, Error> {
+		Fingerprint::of(self.iter())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn fingerprints() -> Result<(), Error> {
+		let groups = generate_mnemonics(1, &[(2, 3)], &[4u8; 16], "", 0)?;
+		let shares = &groups[0].member_shares;
+		let fp = shares[0].fingerprint()?;
+		assert_eq!(
+			fp,
+			Share::from_mnemonic(&shares[0].to_mnemonic()?)?.fingerprint()?
+		);
+		assert_ne!(fp, shares[1].fingerprint()?);
+		let text = fp.to_string();
+		assert_eq!(text.len(), 9);
+		assert_eq!(&text[4..5], "-");
+
+		let mut reversed = groups[0].clone();
+		reversed.member_shares.reverse();
+		assert_eq!(reversed.fingerprint()?, groups[0].fingerprint()?);
+		let set: ShareSet = shares.iter().rev().cloned().collect();
+		assert_eq!(set.fingerprint()?, groups[0].fingerprint()?);
+		let partial: ShareSet = shares[..2].iter().cloned().collect();
+		assert_ne!(partial.fingerprint()?, set.fingerprint()?);
+		Ok(())
+	}
+}
@@ -0,0 +1,210 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bit layout of the share header, the single definition used to encode and decode
+//! mnemonics. Offsets are in bits from the start of the mnemonic's data; counts
+//! and thresholds are stored as their value minus one:
+//!
+//! | offset | width | field              | stored as |
+//! |--------|-------|--------------------|-----------|
+//! | 0      | 15    | identifier         | value     |
+//! | 15     | 5     | iteration exponent | value     |
+//! | 20     | 4     | group index        | value     |
+//! | 24     | 4     | group threshold    | value - 1 |
+//! | 28     | 4     | group count        | value - 1 |
+//! | 32     | 4     | member index       | value     |
+//! | 36     | 4     | member threshold   | value - 1 |
+//! | 40     | 4     | extended version   | value     |
+//! | 44     | 8     | KDF profile        | value     |
+//! | 52     | 8     | flags              | value     |
+//!
+//! The last three fields are only present in extended format shares. The table
+//! above is generated by [`header_layout_table`] and checked against it in the tests
+
+use super::share::Format;
+use crate::error::Error;
+use crate::util::bitpacker::BitPacker;
+
+/// Identifies a header field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFieldId {
+	/// Identifier common to all shares of a set
+	Identifier,
+	/// Iteration exponent of the passphrase KDF
+	IterationExponent,
+	/// Group index
+	GroupIndex,
+	/// Group threshold
+	GroupThreshold,
+	/// Group count
+	GroupCount,
+	/// Member index
+	MemberIndex,
+	/// Member threshold
+	MemberThreshold,
+	/// Version of the extended format
+	ExtendedVersion,
+	/// KDF profile of the extended format
+	KdfProfile,
+	/// Flags of the extended format
+	Flags,
+}
+
+/// Position and width of a header field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderField {
+	/// Which field this is
+	pub id: HeaderFieldId,
+	/// Name of the field, as used in error messages
+	pub name: &'static str,
+	/// Offset in bits from the start of the mnemonic's data
+	pub offset: usize,
+	/// Width in bits
+	pub width: u8,
+	/// Whether the field holds its value minus one (counts and thresholds, 1-16)
+	pub minus_one: bool,
+}
+
+const fn field(
+	id: HeaderFieldId,
+	name: &'static str,
+	offset: usize,
+	width: u8,
+	minus_one: bool,
+) -> HeaderField {
+	HeaderField {
+		id,
+		name,
+		offset,
+		width,
+		minus_one,
+	}
+}
+
+/// Header fields common to all shares
+pub const HEADER_LAYOUT: [HeaderField; 7] = [
+	field(HeaderFieldId::Identifier, "identifier", 0, 15, false),
+	field(
+		HeaderFieldId::IterationExponent,
+		"iteration_exponent",
+		15,
+		5,
+		false,
+	),
+	field(HeaderFieldId::GroupIndex, "group_index", 20, 4, false),
+	field(
+		HeaderFieldId::GroupThreshold,
+		"group_threshold",
+		24,
+		4,
+		true,
+	),
+	field(HeaderFieldId::GroupCount, "group_count", 28, 4, true),
+	field(HeaderFieldId::MemberIndex, "member_index", 32, 4, false),
+	field(
+		HeaderFieldId::MemberThreshold,
+		"member_threshold",
+		36,
+		4,
+		true,
+	),
+];
+
+/// Header fields following [`HEADER_LAYOUT`] in extended format shares
+pub const EXTENDED_HEADER_LAYOUT: [HeaderField; 3] = [
+	field(
+		HeaderFieldId::ExtendedVersion,
+		"extended_version",
+		40,
+		4,
+		false,
+	),
+	field(HeaderFieldId::KdfProfile, "kdf_profile", 44, 8, false),
+	field(HeaderFieldId::Flags, "flags", 52, 8, false),
+];
+
+/// Header fields of a share in the given format, in order
+pub fn header_layout(format: Format) -> impl Iterator<Item = &'static HeaderField> {
+	let extended: &[HeaderField] = match format {
+		Format::Slip39 => &[],
+		Format::ExtendedV2 => &EXTENDED_HEADER_LAYOUT,
+	};
+	HEADER_LAYOUT.iter().chain(extended.iter())
+}
+
+/// Length in bits of the header of a share in the given format
+pub fn header_length_bits(format: Format) -> usize {
+	header_layout(format)
+		.last()
+		.map(|f| f.offset + f.width as usize)
+		.unwrap_or(0)
+}
+
+/// The layout as a Markdown table, as included in this module's documentation
+pub fn header_layout_table() -> String {
+	let mut out = String::from(
+		"| offset | width | field              | stored as |\n\
+		 |--------|-------|--------------------|-----------|\n",
+	);
+	for f in header_layout(Format::ExtendedV2) {
+		out.push_str(&format!(
+			"| {:<6} | {:<5} | {:<18} | {:<9} |\n",
+			f.offset,
+			f.width,
+			match f.id {
+				HeaderFieldId::KdfProfile => "KDF profile".to_string(),
+				_ => f.name.replace('_', " "),
+			},
+			if f.minus_one { "value - 1" } else { "value" }
+		));
+	}
+	out
+}
+
+/// Read a field's value from packed mnemonic data, adding back the one subtracted
+/// from counts and thresholds
+pub(crate) fn read_field(bp: &BitPacker, f: &HeaderField) -> Result<u16, Error> {
+	let v = bp.get::<u16>(f.offset, f.width as usize)?;
+	Ok(if f.minus_one { v + 1 } else { v })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::share::ShareConfig;
+
+	#[test]
+	fn layout_is_contiguous() {
+		let mut offset = 0;
+		for f in header_layout(Format::ExtendedV2) {
+			assert_eq!(f.offset, offset, "{}", f.name);
+			offset += f.width as usize;
+		}
+		let config = ShareConfig::new();
+		let radix = config.radix_bits as usize;
+		assert_eq!(
+			header_length_bits(Format::Slip39),
+			(config.id_exp_length_words as usize + 2) * radix
+		);
+		assert_eq!(header_length_bits(Format::ExtendedV2) % radix, 0);
+	}
+
+	#[test]
+	fn documented_layout() {
+		let source = include_str!("header.rs");
+		for line in header_layout_table().lines() {
+			assert!(source.contains(&format!("//! {}\n", line)), "{}", line);
+		}
+	}
+}
@@ -0,0 +1,109 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits imposed by the SLIP-39 share format, for validating input up front
+
+use super::share::{mnemonic_word_count_for_secret, ShareConfig};
+
+/// Maximum number of member shares in a group (4 bit member index)
+pub const MAX_SHARE_COUNT: u8 = 16;
+/// Maximum number of groups (4 bit group index)
+pub const MAX_GROUP_COUNT: u8 = 16;
+/// Minimum length of a master secret in bytes (128 bits)
+pub const MIN_SECRET_BYTES: usize = 16;
+/// Maximum accepted length of a master secret in bytes
+pub const MAX_SECRET_BYTES: usize = 256;
+/// Largest iteration exponent (5 bits)
+pub const MAX_ITERATION_EXPONENT: u8 = 31;
+/// Largest share set identifier (15 bits)
+pub const MAX_IDENTIFIER: u16 = 0x7fff;
+
+/// Number of words in a mnemonic for the shortest master secret
+pub fn min_mnemonic_words() -> usize {
+	mnemonic_word_count_for_secret(MIN_SECRET_BYTES)
+}
+
+/// Number of words in a mnemonic for the longest master secret
+pub fn max_mnemonic_words() -> usize {
+	mnemonic_word_count_for_secret(MAX_SECRET_BYTES)
+}
+
+/// Length in bytes of the longest master secret whose mnemonics fit in the given
+/// number of words, for sizing secrets to a UI or backup medium. None if even the
+/// shortest secret needs more words
+pub fn max_secret_len_for_mnemonic_words(words: usize) -> Option<usize> {
+	let config = ShareConfig::new();
+	let value_bits =
+		words.checked_sub(config.metadata_length_words as usize)? * config.radix_bits as usize;
+	let len = (value_bits / 16 * 2).min(MAX_SECRET_BYTES);
+	if len < MIN_SECRET_BYTES {
+		return None;
+	}
+	Some(len)
+}
+
+impl ShareConfig {
+	/// Minimum length of a master secret in bytes
+	pub fn min_secret_bytes(&self) -> usize {
+		self.min_strength_bits as usize / 8
+	}
+
+	/// Largest iteration exponent that fits the share header
+	pub fn max_iteration_exponent(&self) -> u8 {
+		((1u16 << self.iteration_exp_length_bits) - 1) as u8
+	}
+
+	/// Largest identifier that fits the share header
+	pub fn max_identifier(&self) -> u16 {
+		((1u32 << self.id_length_bits) - 1) as u16
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::SplitterConfig;
+
+	#[test]
+	fn limits_match_config() {
+		let config = ShareConfig::new();
+		assert_eq!(config.min_secret_bytes(), MIN_SECRET_BYTES);
+		assert_eq!(config.max_iteration_exponent(), MAX_ITERATION_EXPONENT);
+		assert_eq!(config.max_identifier(), MAX_IDENTIFIER);
+		assert_eq!(SplitterConfig::new().max_share_count, MAX_SHARE_COUNT);
+		assert_eq!(
+			min_mnemonic_words(),
+			config.min_mnemonic_length_words as usize
+		);
+		assert_eq!(max_mnemonic_words(), 212);
+	}
+
+	#[test]
+	fn secret_len_for_words() {
+		assert_eq!(max_secret_len_for_mnemonic_words(0), None);
+		assert_eq!(max_secret_len_for_mnemonic_words(19), None);
+		assert_eq!(max_secret_len_for_mnemonic_words(20), Some(16));
+		assert_eq!(max_secret_len_for_mnemonic_words(32), Some(30));
+		assert_eq!(max_secret_len_for_mnemonic_words(33), Some(32));
+		assert_eq!(
+			max_secret_len_for_mnemonic_words(1000),
+			Some(MAX_SECRET_BYTES)
+		);
+		for len in (MIN_SECRET_BYTES..=MAX_SECRET_BYTES).step_by(2) {
+			let words = mnemonic_word_count_for_secret(len);
+			assert_eq!(max_secret_len_for_mnemonic_words(words), Some(len));
+			assert!(max_secret_len_for_mnemonic_words(words - 1) < Some(len));
+		}
+	}
+}
@@ -15,12 +15,73 @@
 //! lower-level wallet functions which build upon core::libtx to perform wallet
 //! operations
 
-mod share;
-mod splitter;
+pub mod codec;
+mod entry;
+mod export;
+mod fingerprint;
+mod header;
+mod limits;
+mod output;
+mod parse;
+mod pin;
+mod plan;
+mod receipt;
+mod repair;
+pub(crate) mod share;
+mod share_files;
+mod share_set;
+pub mod splitter;
 mod sssmc39_scheme;
+mod strength;
+mod terminal;
+mod topology;
+mod uri;
+pub(crate) mod wide;
+pub(crate) mod wordlist;
 
-pub use share::Share;
-pub use splitter::Splitter;
+pub use entry::{EntryHeader, EntryStatus, MnemonicEntry};
+pub use export::ExportedShare;
+pub use fingerprint::{Fingerprint, FINGERPRINT_LEN};
+pub use header::{
+	header_layout, header_layout_table, header_length_bits, HeaderField, HeaderFieldId,
+	EXTENDED_HEADER_LAYOUT, HEADER_LAYOUT,
+};
+pub use limits::{
+	max_mnemonic_words, max_secret_len_for_mnemonic_words, min_mnemonic_words, MAX_GROUP_COUNT,
+	MAX_IDENTIFIER, MAX_ITERATION_EXPONENT, MAX_SECRET_BYTES, MAX_SHARE_COUNT, MIN_SECRET_BYTES,
+};
+pub use output::{combine_mnemonics_as, OutputFormat, BIP39_WORDLIST_LEN};
+pub use parse::ParseDiagnostics;
+pub use plan::{plan_recovery, plan_recovery_from, GroupPlan, RecoveryPlan};
+pub use receipt::{generate_mnemonics_with_receipt, ReceiptGroup, SplitReceipt};
+pub use repair::{recover_erased_word, repair_swapped_words, RepairedMnemonic, WordFix};
+pub use share::{
+	mnemonic_word_count_for_secret, secret_len_for_word_count, validate_mnemonic, Format,
+	ParseOptions, ParseProfile, Share, ShareBuilder, ShareConfig, ShareInfo,
+};
+pub use share_files::ShareFileLayout;
+pub use share_set::ShareSet;
+pub use splitter::{
+	recover_secret_raw, recover_secret_raw_with_config, split_secret_raw,
+	split_secret_raw_with_config, DigestHash, RawShare, SecretSharer, ShareOrdering, SplitProgress,
+	Splitter, SplitterConfig,
+};
+#[cfg(feature = "audit")]
+pub use sssmc39_scheme::InterpolationRecord;
 pub use sssmc39_scheme::{
-	combine_mnemonics, generate_mnemonics, generate_mnemonics_random, GroupShare,
+	combine_all, combine_mnemonic_strings, combine_mnemonics, combine_mnemonics_cancellable,
+	combine_mnemonics_for_identifier, combine_mnemonics_with_config,
+	combine_mnemonics_with_options, combine_mnemonics_with_pins, combine_share_set, combine_to_ems,
+	decrypt_ems, decrypt_with_each, encrypt_master_secret, generate_mnemonics,
+	generate_mnemonics_from_hex, generate_mnemonics_random, generate_mnemonics_with_config,
+	generate_mnemonics_with_identifier, generate_mnemonics_with_progress, normalize_mnemonic,
+	simulate_recovery, split_ems, EncryptedMasterSecret, FormatOptions, GeneratedShares,
+	GroupShare, IdentifierPolicy, RecoveredGroup, RecoveredSecret, RecoverySimulation,
 };
+#[cfg(feature = "audit")]
+pub(crate) use sssmc39_scheme::{decode_mnemonics, recover_ems_recorded};
+pub(crate) use sssmc39_scheme::{encrypt_with_config, recover_ems_from_set, split_ems_with};
+pub use strength::{validate_master_secret, SecretStrength, SecretWarning};
+pub use topology::{topologies, validate_layout, GroupSpec, Topology};
+pub use uri::URI_SCHEME;
+pub use wordlist::{lookup_word, CustomWordlist, English, Wordlist, ENGLISH_SHA256, WORDLIST_LEN};
@@ -0,0 +1,137 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encodings of a recovered master secret for output, so callers don't each
+//! reimplement them
+
+use super::{combine_mnemonics, RecoveredSecret};
+use crate::error::{Error, ErrorKind};
+use crate::util::base64::to_base64;
+use crate::util::hex::to_hex;
+use crate::util::secret::{Passphrase, SecretBytes};
+
+use crate::util::crypto::sha256;
+
+/// Number of words in a BIP-39 wordlist
+pub const BIP39_WORDLIST_LEN: usize = 2048;
+
+/// How a recovered master secret is encoded by [`RecoveredSecret::encode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat<'a> {
+	/// The secret bytes, unchanged
+	Raw,
+	/// Lowercase hex
+	Hex,
+	/// Standard base64 with padding
+	Base64,
+	/// Space separated BIP-39 mnemonic, treating the secret as BIP-39 entropy
+	/// (16 to 32 bytes, in multiples of 4). The 2048 word BIP-39 wordlist of the
+	/// desired language must be provided, as it isn't bundled with this crate
+	Bip39(&'a [&'a str]),
+}
+
+impl RecoveredSecret {
+	/// Encode the recovered secret in the given format. Text formats are returned
+	/// as UTF-8 bytes, so the output is zeroed on drop whatever the format
+	pub fn encode(&self, format: OutputFormat) -> Result<SecretBytes, Error> {
+		let secret = self.secret.as_bytes();
+		Ok(match format {
+			OutputFormat::Raw => self.secret.clone(),
+			OutputFormat::Hex => to_hex(secret).into_bytes().into(),
+			OutputFormat::Base64 => to_base64(secret).into_bytes().into(),
+			OutputFormat::Bip39(wordlist) => bip39_mnemonic(secret, wordlist)?.into_bytes().into(),
+		})
+	}
+}
+
+/// As [`combine_mnemonics`], returning only the secret encoded in the given format
+pub fn combine_mnemonics_as(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	format: OutputFormat,
+) -> Result<SecretBytes, Error> {
+	combine_mnemonics(mnemonics, passphrase)?.encode(format)
+}
+
+// BIP-39 mnemonic of the entropy: the entropy followed by the first len/4 bits of
+// its SHA-256 checksum, split into 11 bit word indices
+fn bip39_mnemonic(entropy: &[u8], wordlist: &[&str]) -> Result<String, Error> {
+	if wordlist.len() != BIP39_WORDLIST_LEN {
+		Err(ErrorKind::Value(format!(
+			"BIP-39 wordlist must contain {} words, got {}",
+			BIP39_WORDLIST_LEN,
+			wordlist.len()
+		)))?;
+	}
+	if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+		Err(ErrorKind::Value(format!(
+			"BIP-39 entropy must be 16 to 32 bytes in multiples of 4, got {}",
+			entropy.len()
+		)))?;
+	}
+	let checksum = sha256(entropy)[0];
+	let bit = |i: usize| -> u16 {
+		let byte = if i < entropy.len() * 8 {
+			entropy[i / 8]
+		} else {
+			checksum
+		};
+		(byte >> (7 - i % 8) & 1) as u16
+	};
+	let word_count = entropy.len() * 3 / 4;
+	let words: Vec<&str> = (0..word_count)
+		.map(|w| wordlist[(0..11).fold(0, |acc, b| acc << 1 | bit(w * 11 + b)) as usize])
+		.collect();
+	Ok(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn output_formats() -> Result<(), Error> {
+		let secret = b"\x00\x11\x22\x33\x44\x55\x66\x77\x88\x99\xaa\xbb\xcc\xdd\xee\xff".to_vec();
+		let mns = generate_mnemonics(1, &[(1, 1)], &secret, "", 0)?[0].mnemonic_list()?;
+		let encoded = |f| combine_mnemonics_as(&mns, "", f);
+		assert_eq!(encoded(OutputFormat::Raw)?, secret);
+		assert_eq!(
+			encoded(OutputFormat::Hex)?.as_bytes(),
+			b"00112233445566778899aabbccddeeff"
+		);
+		assert_eq!(
+			encoded(OutputFormat::Base64)?.as_bytes(),
+			b"ABEiM0RVZneImaq7zN3u/w=="
+		);
+
+		// stand-in wordlist of the word indices
+		let indices: Vec<String> = (0..BIP39_WORDLIST_LEN).map(|i| i.to_string()).collect();
+		let wordlist: Vec<&str> = indices.iter().map(|s| s.as_str()).collect();
+		// BIP-39 test vector 00000000000000000000000000000000 ->
+		// "abandon abandon ... abandon about"
+		assert_eq!(
+			bip39_mnemonic(&[0u8; 16], &wordlist)?,
+			format!("{}3", "0 ".repeat(11))
+		);
+		let mnemonic = encoded(OutputFormat::Bip39(&wordlist))?;
+		assert_eq!(
+			std::str::from_utf8(&mnemonic).unwrap().split(' ').count(),
+			12
+		);
+		assert!(bip39_mnemonic(&[0u8; 17], &wordlist).is_err());
+		assert!(encoded(OutputFormat::Bip39(&wordlist[..2047])).is_err());
+		Ok(())
+	}
+}
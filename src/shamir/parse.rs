@@ -0,0 +1,202 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of mnemonics under a [`ParseProfile`], reporting what had to be
+//! corrected to make sense of them
+
+use super::repair::WordFix;
+use super::share::{ParseOptions, ParseProfile, Share, ShareConfig, EXTENDED_CUSTOMIZATION_STRING};
+use super::wordlist::WORDLIST;
+use super::{Format, ShareSet};
+use crate::error::{Error, ErrorKind};
+use crate::util::rs1024::Polymod;
+
+/// What parsing a set of mnemonics had to correct
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParseDiagnostics {
+	/// Profile of the options used, if they were created from one
+	pub profile: Option<ParseProfile>,
+	/// Words that were expanded or replaced, with the position of their mnemonic in
+	/// the input
+	pub fixes: Vec<(usize, WordFix)>,
+	/// Shares moved to another member index (group index, old index, new index)
+	pub reindexed: Vec<(u8, u8, u8)>,
+}
+
+impl ShareSet {
+	/// As [`ShareSet::from_mnemonics_with_options`], also returning what was corrected
+	pub fn from_mnemonics_with_diagnostics(
+		mnemonics: &[Vec<String>],
+		opts: &ParseOptions,
+	) -> Result<(ShareSet, ParseDiagnostics), Error> {
+		let mut diagnostics = ParseDiagnostics {
+			profile: opts.profile,
+			..Default::default()
+		};
+		let mut set = ShareSet::new();
+		for (i, m) in mnemonics.iter().enumerate() {
+			let (share, fixes) = parse_share(m, opts)?;
+			diagnostics.fixes.extend(fixes.into_iter().map(|f| (i, f)));
+			set.insert(share);
+		}
+		if opts.reindex_members {
+			let (reindexed, moves) = set.reindex_members_tracked()?;
+			set = reindexed;
+			diagnostics.reindexed = moves;
+		}
+		Ok((set, diagnostics))
+	}
+}
+
+/// Parse a single mnemonic according to the options, returning the fixes made
+pub(crate) fn parse_share(
+	mn: &[String],
+	opts: &ParseOptions,
+) -> Result<(Share, Vec<WordFix>), Error> {
+	let (share, fixes) = match Share::from_mnemonic_exact(mn, opts) {
+		Ok(s) => (s, expansions(mn, opts)),
+		Err(e) => match opts.repair_words {
+			true => repair_word(mn, opts).ok_or(e)?,
+			false => return Err(e),
+		},
+	};
+	if opts.standard_only && share.format != Format::Slip39 {
+		Err(ErrorKind::Mnemonic(
+			"Only standard SLIP-39 shares are accepted".to_string(),
+		))?;
+	}
+	Ok((share, fixes))
+}
+
+// words given as a prefix or in another case
+fn expansions(mn: &[String], opts: &ParseOptions) -> Vec<WordFix> {
+	mn.iter()
+		.enumerate()
+		.filter_map(|(position, w)| {
+			let word = WORDLIST[opts.resolve_word(w)?];
+			match word == w {
+				true => None,
+				false => Some(WordFix::Expansion {
+					position,
+					word: word.to_string(),
+				}),
+			}
+		})
+		.collect()
+}
+
+// replace the single unknown word or, if all words are known, any single word so
+// that the checksum is valid, provided exactly one replacement does
+fn repair_word(mn: &[String], opts: &ParseOptions) -> Option<(Share, Vec<WordFix>)> {
+	let indices: Vec<Option<u32>> = mn
+		.iter()
+		.map(|w| opts.resolve_word(w).map(|i| i as u32))
+		.collect();
+	let unknown: Vec<usize> = (0..mn.len()).filter(|p| indices[*p].is_none()).collect();
+	let positions = match unknown.len() {
+		0 => (0..mn.len()).collect(),
+		1 => unknown,
+		_ => return None,
+	};
+	let customization_string = ShareConfig::new().customization_string;
+	let data: Vec<u32> = indices.iter().map(|i| i.unwrap_or(0)).collect();
+	let standard = Polymod::of(&customization_string, &data);
+	let extended = Polymod::of(EXTENDED_CUSTOMIZATION_STRING, &data);
+	let mut found = None;
+	for p in positions {
+		let distance = data.len() - 1 - p;
+		for w in 0..WORDLIST.len() as u32 {
+			if indices[p] == Some(w) {
+				continue;
+			}
+			if standard.with_word_changed(distance, data[p], w).is_valid()
+				|| extended.with_word_changed(distance, data[p], w).is_valid()
+			{
+				if found.is_some() {
+					return None;
+				}
+				found = Some((p, w));
+			}
+		}
+	}
+	let (position, w) = found?;
+	let mut repaired: Vec<String> = data
+		.iter()
+		.map(|i| WORDLIST[*i as usize].to_string())
+		.collect();
+	repaired[position] = WORDLIST[w as usize].to_string();
+	let share = Share::from_mnemonic_exact(&repaired, &ParseOptions::new()).ok()?;
+	let mut fixes = expansions(mn, opts);
+	fixes.retain(|f| !matches!(f, WordFix::Expansion { position: p, .. } if *p == position));
+	fixes.push(WordFix::Substitution {
+		position,
+		word: repaired[position].clone(),
+	});
+	Some((share, fixes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn parse_profiles() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(2, 3)], &[9u8; 16], "", 0)?;
+		let originals: Vec<Vec<String>> = mns[0]
+			.member_shares
+			.iter()
+			.map(|s| s.to_mnemonic())
+			.collect::<Result<_, _>>()?;
+		let strict = ParseOptions::for_profile(ParseProfile::Strict);
+		let lenient = ParseOptions::for_profile(ParseProfile::Lenient);
+
+		let mut sloppy = originals[0].clone();
+		sloppy[2] = sloppy[2].to_uppercase();
+		// cut a word longer than four letters to its (unique) four letter prefix
+		let short = (3..sloppy.len()).find(|&i| sloppy[i].len() > 4).unwrap();
+		sloppy[short] = sloppy[short][..4].to_string();
+		let mut damaged = originals[1].clone();
+		damaged[7] = if damaged[7] == "academic" {
+			"acid"
+		} else {
+			"academic"
+		}
+		.to_string();
+
+		let input = vec![sloppy.clone(), damaged.clone()];
+		assert!(ShareSet::from_mnemonics_with_diagnostics(&input, &strict).is_err());
+		let (set, diagnostics) = ShareSet::from_mnemonics_with_diagnostics(&input, &lenient)?;
+		assert_eq!(diagnostics.profile, Some(ParseProfile::Lenient));
+		assert!(diagnostics.reindexed.is_empty());
+		let fixed: Vec<(usize, usize)> = diagnostics
+			.fixes
+			.iter()
+			.map(|(i, f)| match f {
+				WordFix::Expansion { position, .. } | WordFix::Substitution { position, .. } => {
+					(*i, *position)
+				}
+				_ => panic!("unexpected fix {:?}", f),
+			})
+			.collect();
+		assert_eq!(fixed, vec![(0, 2), (0, short), (1, 7)]);
+		assert_eq!(set, ShareSet::from_mnemonics(&originals[..2])?);
+
+		// strict parsing rejects crate-specific formats
+		let extended = mns[0].member_shares[0].to_mnemonic_as(Format::ExtendedV2)?;
+		assert!(Share::from_mnemonic(&extended).is_ok());
+		assert!(Share::from_mnemonic_with_options(&extended, &strict).is_err());
+		Ok(())
+	}
+}
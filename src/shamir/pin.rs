@@ -0,0 +1,168 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PIN protected ("two-factor") shares. The share value is encrypted under a
+//! per-custodian PIN before mnemonic encoding, so the written share alone is not
+//! enough to take part in recovery. Protected shares use the extended format with
+//! the PIN flag set in its header and are NOT interoperable with SLIP-39.
+//!
+//! The value is encrypted with the same Feistel cipher as the master secret,
+//! keyed by the PIN and salted with the share's identifier, group and member
+//! index, at the share's iteration exponent. A wrong PIN goes unnoticed until the
+//! recovered master secret fails its digest check
+
+use super::share::FLAG_PIN;
+use super::{Format, Share, ShareSet};
+use crate::error::{Error, ErrorKind};
+use crate::util::encrypt::{MasterSecretEnc, MasterSecretEncConfig};
+
+/// Customization string prefix of the PIN cipher's salt
+const PIN_CUSTOMIZATION_STRING: &[u8] = b"shamir-pin";
+
+// the cipher for the given share, salted with its position in the split
+fn pin_cipher(share: &Share) -> Result<MasterSecretEnc, Error> {
+	if !share.share_value.len().is_multiple_of(2) {
+		Err(ErrorKind::Value(format!(
+			"Cannot PIN protect a share value of odd length {}",
+			share.share_value.len()
+		)))?;
+	}
+	let mut config = MasterSecretEncConfig::new();
+	config.customization_string = PIN_CUSTOMIZATION_STRING.to_vec();
+	config
+		.customization_string
+		.extend_from_slice(&[share.group_index, share.member_index]);
+	Ok(MasterSecretEnc::with_config(&config))
+}
+
+impl Share {
+	/// Whether the share value is encrypted under a PIN
+	pub fn is_pin_protected(&self) -> bool {
+		self.flags & FLAG_PIN != 0
+	}
+
+	/// Encrypt the share value under the given PIN, returning a protected share in the
+	/// extended format. The share must be unlocked with
+	/// [`unprotect_with_pin`](Share::unprotect_with_pin) before recovery
+	pub fn protect_with_pin(&self, pin: &str) -> Result<Share, Error> {
+		if self.is_pin_protected() {
+			return Err(ErrorKind::Argument(
+				"Share is already PIN protected".to_string(),
+			))?;
+		}
+		let mut s = self.clone();
+		s.share_value = pin_cipher(self)?.encrypt(
+			&self.share_value,
+			pin,
+			self.iteration_exponent,
+			self.identifier,
+		);
+		s.format = Format::ExtendedV2;
+		s.flags |= FLAG_PIN;
+		Ok(s)
+	}
+
+	/// Decrypt a protected share's value with its PIN. The share reverts to the
+	/// SLIP-39 format unless its KDF profile needs the extended one
+	pub fn unprotect_with_pin(&self, pin: &str) -> Result<Share, Error> {
+		if !self.is_pin_protected() {
+			return Err(ErrorKind::Argument(
+				"Share is not PIN protected".to_string(),
+			))?;
+		}
+		let mut s = self.clone();
+		s.share_value = pin_cipher(self)?
+			.decrypt(
+				&self.share_value,
+				pin,
+				self.iteration_exponent,
+				self.identifier,
+			)
+			.into_vec();
+		s.flags &= !FLAG_PIN;
+		if s.kdf_profile == 0 && s.flags == 0 {
+			s.format = Format::Slip39;
+		}
+		Ok(s)
+	}
+}
+
+impl ShareSet {
+	/// Unlock every PIN protected share in the set, asking `pin_for` for the PIN of
+	/// each one in turn. Unprotected shares are kept as they are
+	pub fn unprotect_with_pins(
+		&self,
+		pin_for: &mut dyn FnMut(&Share) -> Result<String, Error>,
+	) -> Result<ShareSet, Error> {
+		self.iter()
+			.map(|s| match s.is_pin_protected() {
+				true => s.unprotect_with_pin(&pin_for(s)?),
+				false => Ok(s.clone()),
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{combine_mnemonics, combine_mnemonics_with_pins, generate_mnemonics};
+
+	#[test]
+	fn pin_round_trip() -> Result<(), Error> {
+		let gs = generate_mnemonics(1, &[(2, 3)], &[0x5a; 16], "", 0)?;
+		let share = gs[0].member_shares[0].clone();
+		let protected = share.protect_with_pin("1234")?;
+		assert!(protected.is_pin_protected());
+		assert_eq!(protected.format(), Format::ExtendedV2);
+		assert_ne!(protected.share_value(), share.share_value());
+		assert!(protected.protect_with_pin("1234").is_err());
+
+		// the flag survives the mnemonic and binary encodings
+		let decoded = Share::from_mnemonic(&protected.to_mnemonic()?)?;
+		assert!(decoded.is_pin_protected());
+		assert_eq!(Share::from_u8_vec(&protected.to_u8_vec()?)?, decoded);
+
+		assert_eq!(decoded.unprotect_with_pin("1234")?, share);
+		assert_ne!(decoded.unprotect_with_pin("4321")?, share);
+		assert!(share.unprotect_with_pin("1234").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn combine_with_pins() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P";
+		let gs = generate_mnemonics(1, &[(2, 3)], master_secret, "", 0)?;
+		let shares = &gs[0].member_shares;
+		let mns = vec![
+			shares[0].protect_with_pin("1111")?.to_mnemonic()?,
+			shares[1].to_mnemonic()?,
+		];
+
+		// the protected share can't be used without its PIN
+		assert!(combine_mnemonics(&mns, "").is_err());
+
+		let mut asked = vec![];
+		let recovered = combine_mnemonics_with_pins(&mns, "", &mut |s| {
+			asked.push(s.member_index());
+			Ok("1111".to_string())
+		})?;
+		assert_eq!(&recovered.secret[..], &master_secret[..]);
+		assert_eq!(asked, vec![shares[0].member_index()]);
+
+		let res = combine_mnemonics_with_pins(&mns, "", &mut |_| Ok("2222".to_string()));
+		assert!(res.is_err());
+		Ok(())
+	}
+}
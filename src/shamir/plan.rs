@@ -0,0 +1,196 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Planning which further shares to fetch for a recovery ("which safe do I need to
+//! open?"), working only on share metadata, never on secrets
+
+use super::ShareInfo;
+use crate::error::{Error, ErrorKind};
+
+use std::collections::BTreeMap;
+
+/// What a single group needs to meet its member threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupPlan {
+	/// Index of the group
+	pub group_index: u8,
+	/// Number of member shares needed to recover the group secret
+	pub member_threshold: u8,
+	/// Member indices of the shares already held
+	pub held: Vec<u8>,
+	/// Member indices of shares known to exist but not held
+	pub obtainable: Vec<u8>,
+	/// Fewest further shares to fetch to meet the threshold, or None if not enough
+	/// shares are known to exist
+	pub additional: Option<usize>,
+}
+
+/// The cheapest way to gather enough shares to recover a master secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryPlan {
+	/// Identifier of the share set
+	pub identifier: u16,
+	/// Number of groups needed
+	pub group_threshold: u8,
+	/// Every group with at least one known share, in order of group index
+	pub groups: Vec<GroupPlan>,
+	/// The shares to fetch, as (group index, member index), fetching as few as
+	/// possible. Empty if the held shares already suffice; None if the known shares
+	/// can't meet the group threshold
+	pub fetch: Option<Vec<(u8, u8)>>,
+}
+
+impl RecoveryPlan {
+	/// Whether the known shares are enough to recover the secret
+	pub fn is_feasible(&self) -> bool {
+		self.fetch.is_some()
+	}
+}
+
+/// Plan a recovery from the metadata of shares known to exist, none of which are
+/// held yet
+pub fn plan_recovery(available: &[ShareInfo]) -> Result<RecoveryPlan, Error> {
+	plan_recovery_from(&[], available)
+}
+
+/// Plan a recovery given the shares already held and further shares known to exist.
+/// Groups needing the fewest further shares are chosen, and within a group the
+/// lowest member indices
+pub fn plan_recovery_from(
+	held: &[ShareInfo],
+	available: &[ShareInfo],
+) -> Result<RecoveryPlan, Error> {
+	let first = match held.iter().chain(available).next() {
+		Some(f) => f,
+		None => {
+			return Err(ErrorKind::Value(
+				"No shares to plan a recovery from".to_string(),
+			))?
+		}
+	};
+	let mut groups: BTreeMap<u8, GroupPlan> = BTreeMap::new();
+	for (is_held, s) in held
+		.iter()
+		.map(|s| (true, s))
+		.chain(available.iter().map(|s| (false, s)))
+	{
+		if s.identifier != first.identifier
+			|| s.group_threshold != first.group_threshold
+			|| s.group_count != first.group_count
+		{
+			Err(ErrorKind::Value(format!(
+				"Share {} of group {} doesn't belong to share set {}",
+				s.member_index, s.group_index, first.identifier
+			)))?;
+		}
+		let g = groups.entry(s.group_index).or_insert_with(|| GroupPlan {
+			group_index: s.group_index,
+			member_threshold: s.member_threshold,
+			held: vec![],
+			obtainable: vec![],
+			additional: None,
+		});
+		if s.member_threshold != g.member_threshold {
+			Err(ErrorKind::Value(format!(
+				"Shares of group {} disagree on the member threshold",
+				s.group_index
+			)))?;
+		}
+		if is_held {
+			g.held.push(s.member_index);
+		} else {
+			g.obtainable.push(s.member_index);
+		}
+	}
+	for g in groups.values_mut() {
+		g.held.sort_unstable();
+		g.held.dedup();
+		g.obtainable.sort_unstable();
+		g.obtainable.dedup();
+		let held = &g.held;
+		g.obtainable.retain(|m| !held.contains(m));
+		let missing = (g.member_threshold as usize).saturating_sub(g.held.len());
+		if missing <= g.obtainable.len() {
+			g.additional = Some(missing);
+		}
+	}
+
+	let mut candidates: Vec<&GroupPlan> =
+		groups.values().filter(|g| g.additional.is_some()).collect();
+	candidates.sort_by_key(|g| (g.additional, g.group_index));
+	let fetch = if candidates.len() >= first.group_threshold as usize {
+		Some(
+			candidates[..first.group_threshold as usize]
+				.iter()
+				.flat_map(|g| {
+					g.obtainable
+						.iter()
+						.take(g.additional.unwrap_or(0))
+						.map(move |m| (g.group_index, *m))
+				})
+				.collect(),
+		)
+	} else {
+		None
+	};
+	Ok(RecoveryPlan {
+		identifier: first.identifier,
+		group_threshold: first.group_threshold,
+		groups: groups.into_values().collect(),
+		fetch,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn recovery_plan() -> Result<(), Error> {
+		let groups = generate_mnemonics(2, &[(2, 3), (3, 5), (1, 1)], &[3u8; 16], "", 0)?;
+		let info = |g: usize, m: usize| ShareInfo::from(&groups[g].member_shares[m]);
+		let all: Vec<ShareInfo> = groups
+			.iter()
+			.flat_map(|g| g.member_shares.iter().map(ShareInfo::from))
+			.collect();
+
+		// nothing held: the 1-of-1 group and the 2-of-3 group are cheapest
+		let plan = plan_recovery(&all)?;
+		assert_eq!(plan.fetch, Some(vec![(2, 0), (0, 0), (0, 1)]));
+		assert_eq!(plan.groups[1].additional, Some(3));
+
+		// holding two shares of the 3-of-5 group makes it a single fetch
+		let held = vec![info(1, 0), info(1, 4)];
+		let plan = plan_recovery_from(&held, &all)?;
+		assert_eq!(plan.fetch, Some(vec![(1, 1), (2, 0)]));
+		assert_eq!(plan.groups[1].held, vec![0, 4]);
+		assert_eq!(plan.groups[1].obtainable, vec![1, 2, 3]);
+
+		// enough already held
+		let held = vec![info(0, 0), info(0, 2), info(2, 0)];
+		assert_eq!(plan_recovery_from(&held, &[])?.fetch, Some(vec![]));
+
+		// only one group can be completed
+		let plan = plan_recovery(&[info(0, 0), info(0, 1), info(1, 0)])?;
+		assert!(!plan.is_feasible());
+		assert_eq!(plan.groups[1].additional, None);
+
+		let mut foreign = info(0, 0);
+		foreign.identifier ^= 1;
+		assert!(plan_recovery(&[info(0, 1), foreign]).is_err());
+		assert!(plan_recovery(&[]).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,220 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Receipts recording that a share set was created and what shape it had, for an
+//! organization's records. A receipt holds share fingerprints but nothing secret, and
+//! can later be checked against shares brought back for recovery
+
+use super::{generate_mnemonics, Fingerprint, GroupShare, Share, ShareSet};
+use crate::error::{Error, ErrorKind};
+use crate::util::secret::Passphrase;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Record of one group in a [`SplitReceipt`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReceiptGroup {
+	/// Index of the group
+	pub group_index: u8,
+	/// Number of member shares needed to recover the group secret
+	pub member_threshold: u8,
+	/// Member index and fingerprint of each member share, in order of member index
+	pub shares: Vec<(u8, Fingerprint)>,
+}
+
+/// Non-secret record of a share set: its identifier, iteration exponent, group
+/// layout and share fingerprints, and when it was created
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SplitReceipt {
+	/// Identifier of the share set
+	pub identifier: u16,
+	/// Iteration exponent of the share set
+	pub iteration_exponent: u8,
+	/// Number of groups needed to recover the secret
+	pub group_threshold: u8,
+	/// The groups, in order of group index
+	pub groups: Vec<ReceiptGroup>,
+	/// Creation time, in seconds since the Unix epoch
+	pub created: u64,
+}
+
+impl SplitReceipt {
+	/// Receipt for freshly generated groups of shares, timestamped now
+	pub fn new(groups: &[GroupShare]) -> Result<SplitReceipt, Error> {
+		let created = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or(0);
+		SplitReceipt::at(groups, created)
+	}
+
+	/// Receipt for the groups with the given creation time
+	pub fn at(groups: &[GroupShare], created: u64) -> Result<SplitReceipt, Error> {
+		let first = match groups.first() {
+			Some(g) => g,
+			None => return Err(ErrorKind::Argument("No groups to record".to_string()))?,
+		};
+		let mut recorded = Vec::with_capacity(groups.len());
+		for g in groups {
+			let mut shares = g
+				.member_shares
+				.iter()
+				.map(|s| Ok((s.member_index, s.fingerprint()?)))
+				.collect::<Result<Vec<_>, Error>>()?;
+			shares.sort_by_key(|s| s.0);
+			recorded.push(ReceiptGroup {
+				group_index: g.group_index,
+				member_threshold: g.member_threshold,
+				shares,
+			});
+		}
+		recorded.sort_by_key(|g| g.group_index);
+		Ok(SplitReceipt {
+			identifier: first.group_id,
+			iteration_exponent: first.iteration_exponent,
+			group_threshold: first.group_threshold,
+			groups: recorded,
+			created,
+		})
+	}
+
+	/// Check that every share in the set is one this receipt recorded, failing with
+	/// a [`ErrorKind::Value`] error naming the first share that isn't
+	pub fn verify(&self, shares: &ShareSet) -> Result<(), Error> {
+		shares.iter().try_for_each(|s| self.verify_share(s))
+	}
+
+	/// Check that the share is one this receipt recorded
+	pub fn verify_share(&self, share: &Share) -> Result<(), Error> {
+		let mismatch = |what: &str| {
+			Err(ErrorKind::Value(format!(
+				"Share {}.{} does not match the receipt: {}",
+				share.group_index + 1,
+				share.member_index + 1,
+				what
+			)))
+		};
+		if share.identifier != self.identifier {
+			return mismatch("different identifier")?;
+		}
+		if share.iteration_exponent != self.iteration_exponent
+			|| share.group_threshold != self.group_threshold
+			|| share.group_count as usize != self.groups.len()
+		{
+			return mismatch("different share set parameters")?;
+		}
+		let group = match self
+			.groups
+			.iter()
+			.find(|g| g.group_index == share.group_index)
+		{
+			Some(g) => g,
+			None => return mismatch("unknown group")?,
+		};
+		if share.member_threshold != group.member_threshold {
+			return mismatch("different member threshold")?;
+		}
+		match group.shares.iter().find(|s| s.0 == share.member_index) {
+			Some((_, fp)) if *fp == share.fingerprint()? => Ok(()),
+			Some(_) => mismatch("different fingerprint")?,
+			None => mismatch("unknown member")?,
+		}
+	}
+}
+
+impl fmt::Display for SplitReceipt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"Share set {} created {}: iteration exponent {}, {} of {} groups required",
+			self.identifier,
+			self.created,
+			self.iteration_exponent,
+			self.group_threshold,
+			self.groups.len()
+		)?;
+		for g in &self.groups {
+			writeln!(
+				f,
+				"Group {}: {} of {} shares required",
+				g.group_index + 1,
+				g.member_threshold,
+				g.shares.len()
+			)?;
+			for (m, fp) in &g.shares {
+				writeln!(f, "  Share {}: {}", m + 1, fp)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// As [`generate_mnemonics`], also returning a receipt for the share set
+pub fn generate_mnemonics_with_receipt(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<(Vec<GroupShare>, SplitReceipt), Error> {
+	let groups = generate_mnemonics(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+	)?;
+	let receipt = SplitReceipt::new(&groups)?;
+	Ok((groups, receipt))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn receipt() -> Result<(), Error> {
+		let (groups, receipt) =
+			generate_mnemonics_with_receipt(2, &[(2, 3), (1, 1)], &[7u8; 16], "", 0)?;
+		assert_eq!(receipt.identifier, groups[0].group_id);
+		assert_eq!(receipt.groups[0].shares.len(), 3);
+		assert!(receipt.created > 0);
+		assert!(receipt
+			.to_string()
+			.contains("Group 1: 2 of 3 shares required"));
+
+		// shares brought back for recovery, in any order
+		let set: ShareSet = groups[0].member_shares[1..]
+			.iter()
+			.chain(&groups[1].member_shares)
+			.rev()
+			.cloned()
+			.collect();
+		receipt.verify(&set)?;
+
+		// a tampered share, or one from another set, is rejected
+		let mut bad = groups[0].member_shares[0].clone();
+		bad.share_value[0] ^= 1;
+		assert!(receipt.verify_share(&bad).is_err());
+		let other = generate_mnemonics(2, &[(2, 3), (1, 1)], &[7u8; 16], "", 0)?;
+		assert!(receipt.verify_share(&other[0].member_shares[0]).is_err());
+		assert!(SplitReceipt::new(&[]).is_err());
+		Ok(())
+	}
+}
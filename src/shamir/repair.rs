@@ -0,0 +1,207 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Repair of damaged or mis-transcribed mnemonics, using the RS1024 checksum and,
+//! where the other shares allow, the digest of the shared secret to confirm a fix
+
+use super::share::Share;
+use super::sssmc39_scheme::recover_ems;
+use super::wordlist::WORDLIST;
+use super::SplitterConfig;
+use crate::error::{Error, ErrorKind};
+
+/// A change made to a mnemonic by a repair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordFix {
+	/// The unreadable word at the position was reconstructed
+	Erasure {
+		/// Position of the word in the mnemonic
+		position: usize,
+		/// The reconstructed word
+		word: String,
+	},
+	/// The word at the position was given as a prefix or in another letter case
+	Expansion {
+		/// Position of the word in the mnemonic
+		position: usize,
+		/// The full word
+		word: String,
+	},
+	/// The wrong word at the position was replaced, as the only one giving a valid
+	/// checksum
+	Substitution {
+		/// Position of the word in the mnemonic
+		position: usize,
+		/// The replacement word
+		word: String,
+	},
+	/// The words at the position and the one after it had been swapped
+	Transposition {
+		/// Position of the first of the two words
+		position: usize,
+	},
+}
+
+/// A repaired mnemonic, with a report of what was changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedMnemonic {
+	/// The corrected mnemonic
+	pub mnemonic: Vec<String>,
+	/// The changes made
+	pub fixes: Vec<WordFix>,
+}
+
+/// Reconstruct the unreadable word at `position` of `damaged` (whatever is at that
+/// position is ignored). Each of the 1024 candidate words is tried against the
+/// RS1024 checksum, then together with `others` against the digest of the recovered
+/// secret. `others` should hold enough shares for the damaged one to take part in the
+/// recovery; if its group is not needed to meet the thresholds, only the checksum
+/// confirms the word
+pub fn recover_erased_word(
+	others: &[Vec<String>],
+	damaged: &[String],
+	position: usize,
+) -> Result<RepairedMnemonic, Error> {
+	if position >= damaged.len() {
+		Err(ErrorKind::Argument(format!(
+			"Position {} is outside of the mnemonic ({} words)",
+			position,
+			damaged.len()
+		)))?;
+	}
+	let config = SplitterConfig::new();
+	let mut candidate = damaged.to_vec();
+	let mut found: Vec<Vec<String>> = vec![];
+	for word in WORDLIST.iter() {
+		candidate[position] = word.to_string();
+		if Share::from_mnemonic(&candidate).is_err() {
+			continue;
+		}
+		let mut all = others.to_vec();
+		all.push(candidate.clone());
+		if recover_ems(&all, &config).is_ok() {
+			found.push(candidate.clone());
+		}
+	}
+	match found.len() {
+		0 => Err(ErrorKind::Mnemonic(format!(
+			"No word at position {} makes the mnemonic valid",
+			position
+		)))?,
+		1 => {
+			let mnemonic = found.remove(0);
+			Ok(RepairedMnemonic {
+				fixes: vec![WordFix::Erasure {
+					position,
+					word: mnemonic[position].clone(),
+				}],
+				mnemonic,
+			})
+		}
+		n => Err(ErrorKind::Mnemonic(format!(
+			"{} words at position {} make the mnemonic valid",
+			n, position
+		)))?,
+	}
+}
+
+/// Repair a mnemonic in which two adjacent words were swapped, by trying each
+/// transposition against the RS1024 checksum. A mnemonic that is already valid is
+/// returned unchanged, with no fixes
+pub fn repair_swapped_words(mnemonic: &[String]) -> Result<RepairedMnemonic, Error> {
+	if Share::from_mnemonic(mnemonic).is_ok() {
+		return Ok(RepairedMnemonic {
+			mnemonic: mnemonic.to_vec(),
+			fixes: vec![],
+		});
+	}
+	let mut found = vec![];
+	for position in 0..mnemonic.len().saturating_sub(1) {
+		if mnemonic[position] == mnemonic[position + 1] {
+			continue;
+		}
+		let mut candidate = mnemonic.to_vec();
+		candidate.swap(position, position + 1);
+		if Share::from_mnemonic(&candidate).is_ok() {
+			found.push(RepairedMnemonic {
+				mnemonic: candidate,
+				fixes: vec![WordFix::Transposition { position }],
+			});
+		}
+	}
+	match found.len() {
+		0 => Err(ErrorKind::Mnemonic(
+			"No swap of adjacent words makes the mnemonic valid".to_string(),
+		))?,
+		1 => Ok(found.remove(0)),
+		n => Err(ErrorKind::Mnemonic(format!(
+			"{} different swaps of adjacent words make the mnemonic valid",
+			n
+		)))?,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn erased_word() -> Result<(), Error> {
+		let mns = generate_mnemonics(2, &[(2, 3), (2, 3)], &[5u8; 16], "", 0)?;
+		let others = vec![
+			mns[0].member_shares[0].to_mnemonic()?,
+			mns[1].member_shares[0].to_mnemonic()?,
+			mns[1].member_shares[1].to_mnemonic()?,
+		];
+		let original = mns[0].member_shares[2].to_mnemonic()?;
+		for position in [0, 7, original.len() - 1] {
+			let mut damaged = original.clone();
+			damaged[position] = "???".to_string();
+			let repaired = recover_erased_word(&others, &damaged, position)?;
+			assert_eq!(repaired.mnemonic, original);
+			assert_eq!(
+				repaired.fixes,
+				vec![WordFix::Erasure {
+					position,
+					word: original[position].clone()
+				}]
+			);
+		}
+		assert!(recover_erased_word(&others, &original, original.len()).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn swapped_words() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(2, 3)], &[6u8; 16], "", 0)?;
+		let original = mns[0].member_shares[1].to_mnemonic()?;
+		assert!(repair_swapped_words(&original)?.fixes.is_empty());
+		for position in 0..original.len() - 1 {
+			if original[position] == original[position + 1] {
+				continue;
+			}
+			let mut swapped = original.clone();
+			swapped.swap(position, position + 1);
+			let repaired = repair_swapped_words(&swapped)?;
+			assert_eq!(repaired.mnemonic, original);
+			assert_eq!(repaired.fixes, vec![WordFix::Transposition { position }]);
+		}
+		let mut garbled = original.clone();
+		garbled.swap(0, 5);
+		garbled.swap(8, 12);
+		assert!(repair_swapped_words(&garbled).is_err());
+		Ok(())
+	}
+}
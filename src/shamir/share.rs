@@ -15,27 +15,25 @@
 //! Definition of a share, as well as functions to
 //! convert it to and from a given wordlist
 
-use crate::error::{Error, ErrorKind};
+use crate::error::{Error, ErrorKind, MnemonicIssue};
+use crate::shamir::header::{
+	header_layout, header_length_bits, read_field, HeaderFieldId, HEADER_LAYOUT,
+};
+use crate::shamir::limits::MAX_SECRET_BYTES;
+use crate::shamir::wordlist::{lookup_word, English, Wordlist, WORDLIST};
+use crate::shamir::{codec, parse, Fingerprint};
 use crate::util::bitpacker::BitPacker;
-use crate::util::rs1024;
+use crate::util::rs1024::{self, Checksummer};
 
 use rand::{thread_rng, Rng};
-use std::collections::HashMap;
-
-lazy_static! {
-	/// List of ssmc words
-	pub static ref WORDLIST: Vec<String> = include_str!("wordlists/en.txt").split_whitespace().map(|s| s.into()).collect();
-	pub static ref WORD_INDEX_MAP: HashMap<String, usize> = {
-		let mut retval = HashMap::new();
-		for (i, item) in WORDLIST.iter().enumerate() {
-			retval.insert(item.to_owned(), i);
-		}
-		retval
-	};
-}
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "debug-secrets"))]
+use std::fmt;
 
 /// Share-specific configuration values
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ShareConfig {
 	/// The length of the random Identifier in bits
 	pub id_length_bits: u8,
@@ -62,8 +60,8 @@ pub struct ShareConfig {
 impl Default for ShareConfig {
 	fn default() -> Self {
 		let radix_bits = 10;
-		let id_length_bits = 15;
-		let iteration_exp_length_bits = 5;
+		let id_length_bits = HEADER_LAYOUT[0].width;
+		let iteration_exp_length_bits = HEADER_LAYOUT[1].width;
 		let checksum_length_words = 3;
 		let customization_string = b"shamir".to_vec();
 		let min_strength_bits = 128;
@@ -97,44 +95,265 @@ impl ShareConfig {
 			..Default::default()
 		}
 	}
+
+	/// Number of words in a (standard format) mnemonic for a secret of the given length
+	pub fn mnemonic_word_count_for_secret(&self, len_bytes: usize) -> usize {
+		self.metadata_length_words as usize + (len_bytes * 8).div_ceil(self.radix_bits as usize)
+	}
+
+	/// Length in bytes of the secret held by a (standard format) mnemonic with the given
+	/// number of words, or None if no valid mnemonic has that many words
+	pub fn secret_len_for_word_count(&self, words: usize) -> Option<usize> {
+		if words < self.min_mnemonic_length_words as usize {
+			return None;
+		}
+		self.value_len(words - self.metadata_length_words as usize)
+	}
+
+	// length in bytes of a share value encoded in the given number of words, which
+	// must leave less than a word of padding
+	fn value_len(&self, value_words: usize) -> Option<usize> {
+		let value_bits = value_words * self.radix_bits as usize;
+		if value_bits % 16 > 8 {
+			return None;
+		}
+		Some(value_bits / 16 * 2)
+	}
+}
+
+/// Number of words in a mnemonic for a secret of the given length in bytes
+pub fn mnemonic_word_count_for_secret(len_bytes: usize) -> usize {
+	ShareConfig::new().mnemonic_word_count_for_secret(len_bytes)
+}
+
+/// Length in bytes of the secret held by a mnemonic with the given number of words,
+/// or None if no valid mnemonic has that many words
+pub fn secret_len_for_word_count(words: usize) -> Option<usize> {
+	ShareConfig::new().secret_len_for_word_count(words)
+}
+
+/// Customization string of the RS1024 checksum for extended format shares
+pub(crate) const EXTENDED_CUSTOMIZATION_STRING: &[u8] = b"shamir-extended";
+/// Version field value of extended format shares
+const EXTENDED_VERSION: u8 = 2;
+/// Extended header flag marking a PIN protected share value
+pub(crate) const FLAG_PIN: u8 = 0x01;
+/// Number of words the extended format adds to the header
+const EXTENDED_HEADER_WORDS: u8 = 2;
+
+/// Mnemonic encoding of a share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Format {
+	/// Standard SLIP-39, interoperable with other implementations
+	#[default]
+	Slip39,
+	/// Crate-specific extended format, NOT interoperable with SLIP-39. The checksum
+	/// uses its own customization string (so extended shares are never mistaken for
+	/// standard ones) and the header carries two extra words: a 4 bit version, the
+	/// 8 bit KDF profile and 8 bits of flags (bit 0 marks a PIN protected share, the
+	/// rest are reserved)
+	ExtendedV2,
 }
 
 /// Main definition of a share and its mnemonic serialization
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The Debug output redacts the share value unless the `debug-secrets` feature is enabled.
+/// Fields are read through getters; shares are decoded from mnemonics or assembled
+/// with a [`ShareBuilder`], which rejects out-of-range header values
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "debug-secrets", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Share {
 	/// Random 15 bit value which is the same for all shares and is used to verify
 	/// that the shares belong together; it is also used as salt in the encryption
 	/// of the master secret. (15 bits)
-	pub identifier: u16,
+	pub(crate) identifier: u16,
 	/// Indicates the total number of iterations to be used in PBKDF2. The number of
 	/// iterations is calculated as 10000x2^e. (5 bits)
-	pub iteration_exponent: u8,
+	pub(crate) iteration_exponent: u8,
 	/// The x value of the group share (4 bits)
-	pub group_index: u8,
+	pub(crate) group_index: u8,
 	/// indicates how many group shares are needed to reconstruct the master secret.
 	/// The actual value is endoded as Gt = GT - 1, so a value of 0 indicates that a
 	/// single group share is needed (GT = 1), a value of 1 indicates that two group shares
 	/// are needed (GT = 2) etc. (4 bits)
-	pub group_threshold: u8,
+	pub(crate) group_threshold: u8,
 	/// indicates the total number of groups. The actual value is encoded as g = G - 1
 	/// (4 bits)
-	pub group_count: u8,
+	pub(crate) group_count: u8,
 	/// Member index, or x value of the member share in the given group (4 bits)
-	pub member_index: u8,
+	pub(crate) member_index: u8,
 	/// indicates how many member shares are needed to reconstruct the group share. The actual value
 	/// is encoded as t = T − 1. (4 bits)
-	pub member_threshold: u8,
+	pub(crate) member_threshold: u8,
 	/// corresponds to a list of the SSS part's fk(x) values 1 ≤ k ≤ n. Each fk(x) value is encoded
 	/// as a string of eight bits in big-endian order. The concatenation of these bit strings is
 	/// the share value. This value is left-padded with "0" bits so that the length of the padded
 	/// share value in bits becomes the nearest multiple of 10. (padding + 8n bits)
-	pub share_value: Vec<u8>,
+	pub(crate) share_value: Vec<u8>,
 	/// an RS1024 checksum of the data part of the share
 	/// (that is id || e || GI || Gt || g || I || t || ps). The customization string (cs) of
 	/// RS1024 is "shamir". (30 bits)
-	pub checksum: u32,
+	pub(crate) checksum: u32,
 	/// configuration values
-	pub config: ShareConfig,
+	pub(crate) config: ShareConfig,
+	/// Optional non-secret label for the share's custodian (e.g. "Alice").
+	/// Never encoded into the mnemonic
+	pub label: Option<String>,
+	/// Mnemonic encoding format, detected on decode
+	pub(crate) format: Format,
+	/// KDF profile byte (see `KdfProfile`), only encoded in the extended format
+	pub(crate) kdf_profile: u8,
+	/// Header flags, only encoded in the extended format
+	pub(crate) flags: u8,
+}
+
+/// Preset tolerance of mnemonic parsing, for [`ParseOptions::for_profile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParseProfile {
+	/// Exact SLIP-39 conformance: full lowercase words only, no repairs and no
+	/// crate-specific formats
+	Strict,
+	/// Accept word prefixes and any letter case, repair a single wrong word using
+	/// the checksum and re-index conflicting member shares
+	Lenient,
+}
+
+/// Options controlling how mnemonics are parsed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+	/// Profile the options were created from, if any, recorded in diagnostics
+	pub profile: Option<ParseProfile>,
+	/// Accept unambiguous prefixes of at least 4 letters in place of full words
+	/// (e.g. "acad" for "academic")
+	pub allow_prefixes: bool,
+	/// When parsing a set of shares, move shares that conflict on a member index (as
+	/// some buggy generators produce) to the free index their value is consistent
+	/// with, see [`ShareSet::reindex_members`](crate::ShareSet::reindex_members)
+	pub reindex_members: bool,
+	/// Accept words in any letter case
+	pub fold_case: bool,
+	/// Replace a single unknown or wrong word when exactly one replacement gives a
+	/// valid checksum
+	pub repair_words: bool,
+	/// Reject shares in a crate-specific (non SLIP-39) format
+	pub standard_only: bool,
+}
+
+impl ParseOptions {
+	/// Strict parsing, only full words are accepted
+	pub fn new() -> Self {
+		ParseOptions {
+			..Default::default()
+		}
+	}
+
+	/// Options for the given profile
+	pub fn for_profile(profile: ParseProfile) -> Self {
+		let lenient = profile == ParseProfile::Lenient;
+		ParseOptions {
+			profile: Some(profile),
+			allow_prefixes: lenient,
+			reindex_members: lenient,
+			fold_case: lenient,
+			repair_words: lenient,
+			standard_only: !lenient,
+		}
+	}
+
+	/// Resolve a word (or unambiguous prefix, if allowed) to its wordlist index
+	pub fn resolve_word(&self, word: &str) -> Option<usize> {
+		self.resolve_word_in(word, &English)
+	}
+
+	/// As [`ParseOptions::resolve_word`], in the given wordlist
+	pub fn resolve_word_in(&self, word: &str, wordlist: &dyn Wordlist) -> Option<usize> {
+		if let Some(i) = wordlist.index_of(word) {
+			return Some(i);
+		}
+		let folded;
+		let word = match self.fold_case {
+			true => {
+				folded = word.to_lowercase();
+				if let Some(i) = wordlist.index_of(&folded) {
+					return Some(i);
+				}
+				&folded
+			}
+			false => word,
+		};
+		if !self.allow_prefixes || word.len() < 4 {
+			return None;
+		}
+		wordlist.resolve_prefix(word)
+	}
+}
+
+/// Non-secret metadata of a single share, as returned by mnemonic validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShareInfo {
+	/// Identifier common to all shares of a set
+	pub identifier: u16,
+	/// Iteration exponent used in the passphrase KDF
+	pub iteration_exponent: u8,
+	/// Index of the group the share belongs to
+	pub group_index: u8,
+	/// Number of groups needed to recover the master secret
+	pub group_threshold: u8,
+	/// Total number of groups
+	pub group_count: u8,
+	/// Index of the share within its group
+	pub member_index: u8,
+	/// Number of member shares needed to recover the group secret
+	pub member_threshold: u8,
+	/// Length of the share value in bytes
+	pub share_value_length: usize,
+	/// Fingerprint of the share, see [`Share::fingerprint`]
+	pub fingerprint: Option<Fingerprint>,
+}
+
+impl From<&Share> for ShareInfo {
+	fn from(s: &Share) -> ShareInfo {
+		ShareInfo {
+			identifier: s.identifier,
+			iteration_exponent: s.iteration_exponent,
+			group_index: s.group_index,
+			group_threshold: s.group_threshold,
+			group_count: s.group_count,
+			member_index: s.member_index,
+			member_threshold: s.member_threshold,
+			share_value_length: s.share_value.len(),
+			fingerprint: s.fingerprint().ok(),
+		}
+	}
+}
+
+/// Checks a single mnemonic in isolation (word membership, length, checksum
+/// and header consistency), returning the share's metadata if it is valid
+pub fn validate_mnemonic(words: &[String]) -> Result<ShareInfo, MnemonicIssue> {
+	let config = ShareConfig::new();
+	if config.secret_len_for_word_count(words.len()).is_none() {
+		return Err(MnemonicIssue::InvalidLength(words.len()));
+	}
+	let mut indices = vec![];
+	for (i, w) in words.iter().enumerate() {
+		match lookup_word(w) {
+			Some(v) => indices.push(v as u32),
+			None => return Err(MnemonicIssue::InvalidWord(i, w.to_owned())),
+		}
+	}
+	if rs1024::verify_checksum(&config.customization_string, &indices).is_err() {
+		return Err(MnemonicIssue::InvalidChecksum);
+	}
+	match Share::from_mnemonic(words) {
+		Ok(s) => Ok(ShareInfo::from(&s)),
+		Err(e) => match e.kind() {
+			ErrorKind::Padding | ErrorKind::SharePadding(..) => Err(MnemonicIssue::InvalidPadding),
+			k => Err(MnemonicIssue::InvalidHeader(format!("{}", k))),
+		},
+	}
 }
 
 impl Default for Share {
@@ -150,10 +369,39 @@ impl Default for Share {
 			share_value: vec![],
 			checksum: 0,
 			config: ShareConfig::new(),
+			label: None,
+			format: Format::Slip39,
+			kdf_profile: 0,
+			flags: 0,
 		}
 	}
 }
 
+#[cfg(not(feature = "debug-secrets"))]
+impl fmt::Debug for Share {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Share")
+			.field("identifier", &self.identifier)
+			.field("iteration_exponent", &self.iteration_exponent)
+			.field("group_index", &self.group_index)
+			.field("group_threshold", &self.group_threshold)
+			.field("group_count", &self.group_count)
+			.field("member_index", &self.member_index)
+			.field("member_threshold", &self.member_threshold)
+			.field(
+				"share_value",
+				&format_args!("<redacted, {} bytes>", self.share_value.len()),
+			)
+			.field("checksum", &self.checksum)
+			.field("label", &self.label)
+			.field("format", &self.format)
+			.field("kdf_profile", &self.kdf_profile)
+			.field("flags", &self.flags)
+			.field("fingerprint", &self.fingerprint().ok())
+			.finish()
+	}
+}
+
 impl Share {
 	/// Create a new share with defaults
 	pub fn new() -> Result<Share, Error> {
@@ -169,46 +417,142 @@ impl Share {
 		Ok(s)
 	}
 
+	/// Share set identifier
+	pub fn identifier(&self) -> u16 {
+		self.identifier
+	}
+
+	/// Iteration exponent used in the passphrase KDF
+	pub fn iteration_exponent(&self) -> u8 {
+		self.iteration_exponent
+	}
+
+	/// Index of the group the share belongs to
+	pub fn group_index(&self) -> u8 {
+		self.group_index
+	}
+
+	/// Number of groups needed to recover the master secret
+	pub fn group_threshold(&self) -> u8 {
+		self.group_threshold
+	}
+
+	/// Total number of groups
+	pub fn group_count(&self) -> u8 {
+		self.group_count
+	}
+
+	/// Index of the share within its group
+	pub fn member_index(&self) -> u8 {
+		self.member_index
+	}
+
+	/// Number of member shares needed to recover the group secret
+	pub fn member_threshold(&self) -> u8 {
+		self.member_threshold
+	}
+
+	/// The share value
+	pub fn share_value(&self) -> &[u8] {
+		&self.share_value
+	}
+
+	/// RS1024 checksum value, if set
+	pub fn checksum(&self) -> u32 {
+		self.checksum
+	}
+
+	/// Configuration values
+	pub fn config(&self) -> &ShareConfig {
+		&self.config
+	}
+
+	/// Mnemonic encoding format
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	/// KDF profile byte, only encoded in the extended format
+	pub fn kdf_profile(&self) -> u8 {
+		self.kdf_profile
+	}
+
 	/// convenience to create new from Mnemonic
 	pub fn from_mnemonic(mn: &[String]) -> Result<Self, Error> {
+		Share::from_mnemonic_with_options(mn, &ParseOptions::new())
+	}
+
+	/// create new from Mnemonic, parsed according to the given options
+	pub fn from_mnemonic_with_options(mn: &[String], opts: &ParseOptions) -> Result<Self, Error> {
+		Ok(parse::parse_share(mn, opts)?.0)
+	}
+
+	// parse the words as given, without repairs
+	pub(crate) fn from_mnemonic_exact(mn: &[String], opts: &ParseOptions) -> Result<Self, Error> {
 		let mut s = Share::new()?;
-		s.fill_with_mnemonic(mn)?;
+		s.fill_with_mnemonic(mn, &English, opts)?;
 		Ok(s)
 	}
 
-	/// Convert from a u8 vec
-	pub fn from_u8_vec(input: &[u8]) -> Result<Self, Error> {
+	/// create new from a Mnemonic in the given wordlist
+	pub fn from_mnemonic_with_wordlist(
+		mn: &[String],
+		wordlist: &dyn Wordlist,
+	) -> Result<Self, Error> {
+		Share::from_mnemonic_with_config(mn, wordlist, &ShareConfig::new())
+	}
+
+	// parse a mnemonic in the given wordlist, checksummed under the config's
+	// customization string
+	pub(crate) fn from_mnemonic_with_config(
+		mn: &[String],
+		wordlist: &dyn Wordlist,
+		config: &ShareConfig,
+	) -> Result<Self, Error> {
 		let mut s = Share::new()?;
-		let mut bp = BitPacker::new();
-		bp.append_vec_u8(input)?;
-		bp.normalize(s.config.radix_bits as usize);
-		s.parse_bp(&mut bp)?;
+		s.config = config.clone();
+		s.fill_with_mnemonic(mn, wordlist, &ParseOptions::new())?;
 		Ok(s)
 	}
 
+	/// Decode from the binary layout described in [`codec`](crate::codec)
+	pub fn from_u8_vec(input: &[u8]) -> Result<Self, Error> {
+		codec::decode(input)
+	}
+
 	// create the packed bit array
 	fn pack_bits(&self) -> Result<BitPacker, Error> {
-		let mut padding_bit_count = self.config.radix_bits
-			- (self.share_value.len() * 8 % self.config.radix_bits as usize) as u8;
-		if padding_bit_count == 10 {
-			padding_bit_count = 0;
-		}
+		self.pack_bits_with_padding(false)
+	}
+
+	// create the packed bit array, optionally setting the first padding bit
+	fn pack_bits_with_padding(&self, invalid_padding: bool) -> Result<BitPacker, Error> {
+		let padding_bit_count = self.padding_bit_count();
 		let mut bp = BitPacker::new();
 
-		bp.append_u16(self.identifier, self.config.id_length_bits)?;
-		bp.append_u8(
-			self.iteration_exponent,
-			self.config.iteration_exp_length_bits,
-		)?;
-		bp.append_u8(self.group_index, 4)?;
-		bp.append_u8(self.group_threshold - 1, 4)?;
-		bp.append_u8(self.group_count - 1, 4)?;
-		bp.append_u8(self.member_index, 4)?;
-		bp.append_u8(self.member_threshold - 1, 4)?;
-		bp.append_padding(padding_bit_count);
+		for f in header_layout(self.format) {
+			let value = self.header_value(f.id);
+			let stored = if f.minus_one {
+				threshold_field(f.name, value as u8)?.into()
+			} else {
+				header_field(f.name, value, f.width)?
+			};
+			bp.append(stored, f.width)?;
+		}
+		if invalid_padding {
+			if padding_bit_count == 0 {
+				return Err(ErrorKind::Argument(
+					"Share value has no padding bits".to_string(),
+				))?;
+			}
+			bp.append(1u8, 1)?;
+			bp.append_padding(padding_bit_count - 1);
+		} else {
+			bp.append_padding(padding_bit_count);
+		}
 		bp.append_vec_u8(&self.share_value)?;
 
-		if bp.len() % self.config.radix_bits as usize != 0 {
+		if !bp.len().is_multiple_of(self.config.radix_bits as usize) {
 			return Err(ErrorKind::Mnemonic(format!(
 				"Incorrect share bit length. Must be a multiple of {}, actual length: {}",
 				self.config.radix_bits,
@@ -217,41 +561,98 @@ impl Share {
 		}
 
 		// Create checksum
-		let mut sum_data: Vec<u32> = vec![];
-		for i in (0..bp.len()).step_by(self.config.radix_bits as usize) {
-			sum_data.push(bp.get_u32(i, self.config.radix_bits as usize)?);
-		}
-
-		let checksum = rs1024::create_checksum(
-			&self.config.customization_string,
-			&sum_data,
-			self.config.checksum_length_words,
-		);
+		let mut checksummer = Checksummer::new(self.checksum_customization_string());
+		bp.chunks(self.config.radix_bits as usize)
+			.for_each(|w| checksummer.push(w));
+		let checksum = checksummer.finalize(self.config.checksum_length_words);
 
 		for c in checksum {
-			bp.append_u32(c, self.config.radix_bits)?;
+			bp.append(c, self.config.radix_bits)?;
 		}
 
 		Ok(bp)
 	}
 
-	/// Convert share data to a share mnemonic
-	pub fn to_mnemonic(&self) -> Result<Vec<String>, Error> {
-		let bp = self.pack_bits()?;
+	// value of a header field, as stored in the share
+	fn header_value(&self, id: HeaderFieldId) -> u16 {
+		match id {
+			HeaderFieldId::Identifier => self.identifier,
+			HeaderFieldId::IterationExponent => self.iteration_exponent.into(),
+			HeaderFieldId::GroupIndex => self.group_index.into(),
+			HeaderFieldId::GroupThreshold => self.group_threshold.into(),
+			HeaderFieldId::GroupCount => self.group_count.into(),
+			HeaderFieldId::MemberIndex => self.member_index.into(),
+			HeaderFieldId::MemberThreshold => self.member_threshold.into(),
+			HeaderFieldId::ExtendedVersion => EXTENDED_VERSION.into(),
+			HeaderFieldId::KdfProfile => self.kdf_profile.into(),
+			HeaderFieldId::Flags => self.flags.into(),
+		}
+	}
 
-		// Read bits as u32 vec
-		let mut ret_vec: Vec<u32> = vec![];
-		for i in (0..bp.len()).step_by(self.config.radix_bits as usize) {
-			ret_vec.push(bp.get_u32(i, self.config.radix_bits as usize)?);
+	// set a header field from a decoded value, which fits the field's width
+	fn set_header_value(&mut self, id: HeaderFieldId, value: u16) -> Result<(), Error> {
+		let byte = value as u8;
+		match id {
+			HeaderFieldId::Identifier => self.identifier = value,
+			HeaderFieldId::IterationExponent => self.iteration_exponent = byte,
+			HeaderFieldId::GroupIndex => self.group_index = byte,
+			HeaderFieldId::GroupThreshold => self.group_threshold = byte,
+			HeaderFieldId::GroupCount => self.group_count = byte,
+			HeaderFieldId::MemberIndex => self.member_index = byte,
+			HeaderFieldId::MemberThreshold => self.member_threshold = byte,
+			HeaderFieldId::ExtendedVersion => {
+				if byte != EXTENDED_VERSION {
+					return Err(ErrorKind::Mnemonic(format!(
+						"Unsupported extended share format version {}",
+						byte
+					)))?;
+				}
+			}
+			HeaderFieldId::KdfProfile => self.kdf_profile = byte,
+			HeaderFieldId::Flags => {
+				if byte & !FLAG_PIN != 0 {
+					return Err(ErrorKind::Mnemonic(
+						"Reserved bits of extended share header must be 0".to_string(),
+					))?;
+				}
+				self.flags = byte;
+			}
 		}
+		Ok(())
+	}
 
-		Ok(ret_vec
-			.iter()
-			.map(|d| WORDLIST[*d as usize].to_owned())
-			.collect())
+	// number of zero bits needed to pad the share value to a whole number of words
+	fn padding_bit_count(&self) -> u8 {
+		let radix_bits = self.config.radix_bits as usize;
+		((radix_bits - self.share_value.len() * 8 % radix_bits) % radix_bits) as u8
+	}
+
+	fn checksum_customization_string(&self) -> &[u8] {
+		match self.format {
+			Format::Slip39 => &self.config.customization_string,
+			Format::ExtendedV2 => EXTENDED_CUSTOMIZATION_STRING,
+		}
 	}
 
-	/// Convert share data to a share mnemonic (flattened string)
+	/// Convert share data to a share mnemonic in the given format
+	pub fn to_mnemonic_as(&self, format: Format) -> Result<Vec<String>, Error> {
+		let mut s = self.clone();
+		s.format = format;
+		s.to_mnemonic()
+	}
+
+	/// Convert share data to a share mnemonic in the given wordlist
+	pub fn to_mnemonic_with_wordlist(&self, wordlist: &dyn Wordlist) -> Result<Vec<String>, Error> {
+		wordlist.verify()?;
+		words_in(&self.mnemonic_indices()?, wordlist)
+	}
+
+	/// Convert share data to a share mnemonic
+	pub fn to_mnemonic(&self) -> Result<Vec<String>, Error> {
+		words_in(&self.mnemonic_indices()?, &English)
+	}
+
+	// Convert share data to a share mnemonic (flattened string)
 	/*pub fn to_mnemonic_flat(&self) -> Result<String, Error> {
 		self.to_mnemonic()?.iter().fold(String::new(), |mut acc, s| {
 			acc.push_str(s);
@@ -260,20 +661,47 @@ impl Share {
 		})
 	}*/
 
-	/// Convert share data to u8 vec
+	/// Convert share data to a share mnemonic whose checksum is deliberately
+	/// invalid, for generating negative test vectors
+	pub fn with_invalid_checksum(&self) -> Result<Vec<String>, Error> {
+		let mut indices = self.mnemonic_indices()?;
+		if let Some(w) = indices.last_mut() {
+			*w ^= 1;
+		}
+		words_in(&indices, &English)
+	}
+
+	/// Convert share data to a share mnemonic (with a valid checksum) whose padding
+	/// bits are not all zero, for generating negative test vectors. Fails if the
+	/// share value length leaves no padding bits
+	pub fn with_invalid_padding(&self) -> Result<Vec<String>, Error> {
+		let indices: Vec<u32> = self
+			.pack_bits_with_padding(true)?
+			.chunks(self.config.radix_bits as usize)
+			.collect();
+		words_in(&indices, &English)
+	}
+
+	/// Encode in the binary layout described in [`codec`](crate::codec)
 	pub fn to_u8_vec(&self) -> Result<Vec<u8>, Error> {
-		let bp = self.pack_bits()?;
+		codec::encode(self)
+	}
 
-		// Read bits as u8 vec
-		let mut ret_vec: Vec<u8> = vec![];
-		for i in (0..bp.len()).step_by(8) {
-			ret_vec.push(bp.get_u8(i, 8)?);
-		}
-		Ok(ret_vec)
+	// wordlist indices of the share's mnemonic
+	pub(crate) fn mnemonic_indices(&self) -> Result<Vec<u32>, Error> {
+		Ok(self
+			.pack_bits()?
+			.chunks(self.config.radix_bits as usize)
+			.collect())
 	}
 
 	/// convert mnemonic back to share
-	fn fill_with_mnemonic(&mut self, mn: &[String]) -> Result<(), Error> {
+	fn fill_with_mnemonic(
+		&mut self,
+		mn: &[String],
+		wordlist: &dyn Wordlist,
+		opts: &ParseOptions,
+	) -> Result<(), Error> {
 		if mn.len() < self.config.min_mnemonic_length_words as usize {
 			return Err(ErrorKind::Mnemonic(format!(
 				"Invalid mnemonic length. The length of each mnemonic must be at least {} words.",
@@ -282,46 +710,59 @@ impl Share {
 		}
 		let mut bp = BitPacker::new();
 		for s in mn {
-			if !WORD_INDEX_MAP.contains_key(s) {
-				return Err(ErrorKind::Mnemonic(format!(
-					"Invalid mnemonic. '{}' is not an SSSMC39 word.",
-					s,
-				)))?;
-			}
-			bp.append_u16(WORD_INDEX_MAP[s] as u16, self.config.radix_bits)?;
+			let i = match opts.resolve_word_in(s, wordlist) {
+				Some(i) => i,
+				None => {
+					return Err(ErrorKind::Mnemonic(format!(
+						"Invalid mnemonic. '{}' is not an SSSMC39 word.",
+						s,
+					)))?;
+				}
+			};
+			bp.append(i as u16, self.config.radix_bits)?;
 		}
 		self.parse_bp(&mut bp)
 	}
 
 	fn parse_bp(&mut self, bp: &mut BitPacker) -> Result<(), Error> {
-		let mut sum_data: Vec<u32> = vec![];
-		for i in (0..bp.len()).step_by(self.config.radix_bits as usize) {
-			sum_data.push(bp.get_u32(i, self.config.radix_bits as usize)?);
+		// detect the format from the checksum
+		let mut standard = Checksummer::new(&self.config.customization_string);
+		let mut extended = Checksummer::new(EXTENDED_CUSTOMIZATION_STRING);
+		for w in bp.chunks(self.config.radix_bits as usize) {
+			standard.push(w);
+			extended.push(w);
+		}
+		self.format = Format::Slip39;
+		if standard.verify().is_err() {
+			if extended.verify().is_err() {
+				standard.verify()?;
+			}
+			self.format = Format::ExtendedV2;
 		}
+		let word_count = standard.len();
 
-		if (self.config.radix_bits as usize
-			* (sum_data.len() - self.config.metadata_length_words as usize))
-			% 16 > 8
+		let mut metadata_length_words = self.config.metadata_length_words as usize;
+		if self.format == Format::ExtendedV2 {
+			metadata_length_words += EXTENDED_HEADER_WORDS as usize;
+		}
+		if word_count < metadata_length_words
+			|| self
+				.config
+				.value_len(word_count - metadata_length_words)
+				.is_none()
 		{
 			return Err(ErrorKind::Mnemonic("Invalid mnemonic length.".to_string()))?;
 		}
+		if let Some(len) = self.config.value_len(word_count - metadata_length_words) {
+			if len > MAX_SECRET_BYTES {
+				return Err(ErrorKind::SecretLength(len))?;
+			}
+		}
 
-		rs1024::verify_checksum(&self.config.customization_string, &sum_data)?;
-
-		//TODO: iterator on bitpacker
-		self.identifier = bp.get_u16(0, self.config.id_length_bits as usize)?;
-		self.iteration_exponent = bp.get_u8(
-			self.config.id_length_bits as usize,
-			self.config.iteration_exp_length_bits as usize,
-		)?;
-		self.group_index = bp.get_u8(
-			(self.config.id_length_bits + self.config.iteration_exp_length_bits) as usize,
-			4,
-		)?;
-		self.group_threshold = bp.get_u8(24, 4)? + 1;
-		self.group_count = bp.get_u8(28, 4)? + 1;
-		self.member_index = bp.get_u8(32, 4)?;
-		self.member_threshold = bp.get_u8(36, 4)? + 1;
+		for f in header_layout(self.format) {
+			self.set_header_value(f.id, read_field(bp, f)?)?;
+		}
+		let header_length_bits = header_length_bits(self.format);
 
 		if self.group_count < self.group_threshold {
 			return Err(ErrorKind::Mnemonic(
@@ -331,11 +772,20 @@ impl Share {
 
 		// remove padding and recover data
 		bp.split_out(
-			40,
+			header_length_bits,
 			bp.len() - self.config.radix_bits as usize * self.config.checksum_length_words as usize,
 		);
 
-		bp.remove_padding(bp.len() % 16)?;
+		// the length check above guarantees the padding is shorter than a word
+		let padding_bit_count = bp.len() % 16;
+		debug_assert!(padding_bit_count < self.config.radix_bits as usize);
+		if bp.remove_padding(padding_bit_count).is_err() {
+			return Err(ErrorKind::SharePadding(
+				self.identifier,
+				self.group_index,
+				self.member_index,
+			))?;
+		}
 
 		self.share_value = bp.get_vec_u8(0, bp.len() / 8)?;
 
@@ -348,6 +798,175 @@ impl Share {
 	}
 }
 
+// map wordlist indices to words
+pub(crate) fn words_in(indices: &[u32], wordlist: &dyn Wordlist) -> Result<Vec<String>, Error> {
+	indices
+		.iter()
+		.map(|i| match wordlist.word(*i as usize) {
+			Some(w) => Ok(w.to_owned()),
+			None => Err(ErrorKind::Config(format!("Wordlist has no word {}", i)))?,
+		})
+		.collect()
+}
+
+// check a header value fits in the given number of bits
+fn header_field(name: &str, value: u16, bits: u8) -> Result<u16, Error> {
+	if u32::from(value) >= 1 << bits {
+		Err(ErrorKind::ShareHeader(name.to_owned(), value))?;
+	}
+	Ok(value)
+}
+
+// encode a threshold or count (1-16) as its value minus one
+fn threshold_field(name: &str, value: u8) -> Result<u8, Error> {
+	match value.checked_sub(1) {
+		Some(v) if v < 16 => Ok(v),
+		_ => Err(ErrorKind::ShareHeader(name.to_owned(), value.into()))?,
+	}
+}
+
+/// Assembles a [`Share`] from its header values and share value, checking each value
+/// fits its field. Thresholds and counts are given as actual values (1-16)
+#[derive(Debug, Clone)]
+pub struct ShareBuilder {
+	share: Share,
+}
+
+impl Default for ShareBuilder {
+	fn default() -> Self {
+		ShareBuilder {
+			share: Share {
+				group_threshold: 1,
+				group_count: 1,
+				member_threshold: 1,
+				..Default::default()
+			},
+		}
+	}
+}
+
+impl ShareBuilder {
+	/// A 1-of-1 share in a single group, with identifier 0 and no share value
+	pub fn new() -> Self {
+		ShareBuilder {
+			..Default::default()
+		}
+	}
+
+	/// Set the share set identifier (15 bits)
+	pub fn with_identifier(mut self, identifier: u16) -> Self {
+		self.share.identifier = identifier;
+		self
+	}
+
+	/// Set the iteration exponent (5 bits)
+	pub fn with_iteration_exponent(mut self, iteration_exponent: u8) -> Self {
+		self.share.iteration_exponent = iteration_exponent;
+		self
+	}
+
+	/// Set the group index, threshold and count
+	pub fn with_group(mut self, index: u8, threshold: u8, count: u8) -> Self {
+		self.share.group_index = index;
+		self.share.group_threshold = threshold;
+		self.share.group_count = count;
+		self
+	}
+
+	/// Set the member index and threshold
+	pub fn with_member(mut self, index: u8, threshold: u8) -> Self {
+		self.share.member_index = index;
+		self.share.member_threshold = threshold;
+		self
+	}
+
+	/// Set the share value
+	pub fn with_share_value(mut self, share_value: &[u8]) -> Self {
+		self.share.share_value = share_value.to_vec();
+		self
+	}
+
+	/// Set the mnemonic encoding format and KDF profile
+	pub fn with_format(mut self, format: Format, kdf_profile: u8) -> Self {
+		self.share.format = format;
+		self.share.kdf_profile = kdf_profile;
+		self
+	}
+
+	/// Set the custodian label
+	pub fn with_label(mut self, label: &str) -> Self {
+		self.share.label = Some(label.to_owned());
+		self
+	}
+
+	/// Validate the values and build the share
+	pub fn build(self) -> Result<Share, Error> {
+		let s = self.share;
+		let check = |ok: bool, msg: String| -> Result<(), Error> {
+			if !ok {
+				return Err(ErrorKind::Argument(msg))?;
+			}
+			Ok(())
+		};
+		check(
+			s.identifier < 1 << s.config.id_length_bits,
+			format!(
+				"Identifier {} exceeds {} bits",
+				s.identifier, s.config.id_length_bits
+			),
+		)?;
+		check(
+			s.iteration_exponent < 1 << s.config.iteration_exp_length_bits,
+			format!("Iteration exponent {} is too large", s.iteration_exponent),
+		)?;
+		check(
+			(1..=16).contains(&s.group_count),
+			format!("Group count {} must be between 1 and 16", s.group_count),
+		)?;
+		check(
+			s.group_threshold >= 1 && s.group_threshold <= s.group_count,
+			format!(
+				"Group threshold {} must be between 1 and the group count {}",
+				s.group_threshold, s.group_count
+			),
+		)?;
+		check(
+			s.group_index < s.group_count,
+			format!(
+				"Group index {} must be less than the group count {}",
+				s.group_index, s.group_count
+			),
+		)?;
+		check(
+			(1..=16).contains(&s.member_threshold),
+			format!(
+				"Member threshold {} must be between 1 and 16",
+				s.member_threshold
+			),
+		)?;
+		check(
+			s.member_index < 16,
+			format!("Member index {} must be less than 16", s.member_index),
+		)?;
+		check(
+			!s.share_value.is_empty() && s.share_value.len().is_multiple_of(2),
+			format!(
+				"Share value length {} must be a non-zero even number of bytes",
+				s.share_value.len()
+			),
+		)?;
+		check(
+			s.share_value.len() <= MAX_SECRET_BYTES,
+			format!(
+				"Share value length {} exceeds the maximum of {} bytes",
+				s.share_value.len(),
+				MAX_SECRET_BYTES
+			),
+		)?;
+		Ok(s)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -400,4 +1019,284 @@ mod tests {
 		assert_eq!(share, dec_share);
 		Ok(())
 	}
+
+	#[cfg(not(feature = "debug-secrets"))]
+	#[test]
+	fn share_debug_redacted() {
+		let share = Share {
+			share_value: vec![0xde, 0xad, 0xbe, 0xef],
+			..Default::default()
+		};
+		let output = format!("{:?}", share);
+		assert!(output.contains("share_value: <redacted, 4 bytes>"));
+		assert!(!output.contains("222"));
+	}
+
+	#[test]
+	fn validate_single_mnemonic() -> Result<(), Error> {
+		let share = Share {
+			identifier: 21219,
+			group_threshold: 1,
+			group_count: 1,
+			member_index: 4,
+			member_threshold: 3,
+			share_value: b"\x84\x06\xce\xa0p\xbfe~\rA\x01\t5\xaf\xd3Z".to_vec(),
+			..Default::default()
+		};
+		let m = share.to_mnemonic()?;
+		let info = validate_mnemonic(&m).unwrap();
+		assert_eq!(info, ShareInfo::from(&share));
+		assert_eq!(info.share_value_length, 16);
+
+		let mut bad = m.clone();
+		bad[3] = "notaword".to_owned();
+		assert_eq!(
+			validate_mnemonic(&bad),
+			Err(MnemonicIssue::InvalidWord(3, "notaword".to_owned()))
+		);
+		let mut bad = m.clone();
+		bad[19] = "academic".to_owned();
+		assert_eq!(validate_mnemonic(&bad), Err(MnemonicIssue::InvalidChecksum));
+		assert_eq!(
+			validate_mnemonic(&m[..10]),
+			Err(MnemonicIssue::InvalidLength(10))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn mnemonic_prefixes() -> Result<(), Error> {
+		let mn: Vec<String> = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"
+			.split(' ')
+			.map(|s| s.to_owned())
+			.collect();
+		let prefixes: Vec<String> = mn.iter().map(|w| w.chars().take(4).collect()).collect();
+		let mut opts = ParseOptions::new();
+		assert!(Share::from_mnemonic_with_options(&prefixes, &opts).is_err());
+		opts.allow_prefixes = true;
+		assert_eq!(
+			Share::from_mnemonic_with_options(&prefixes, &opts)?,
+			Share::from_mnemonic(&mn)?
+		);
+		// too short or ambiguous prefixes are rejected
+		assert_eq!(opts.resolve_word("aca"), None);
+		assert_eq!(opts.resolve_word("acad"), Some(0));
+		Ok(())
+	}
+
+	#[test]
+	fn extended_format() -> Result<(), Error> {
+		let share = Share {
+			identifier: 21219,
+			group_threshold: 1,
+			group_count: 1,
+			member_index: 4,
+			member_threshold: 3,
+			share_value: b"\x84\x06\xce\xa0p\xbfe~\rA\x01\t5\xaf\xd3Z".to_vec(),
+			format: Format::ExtendedV2,
+			kdf_profile: 0x4f,
+			..Default::default()
+		};
+		let m = share.to_mnemonic()?;
+		assert_eq!(m.len(), 22);
+		let dec_share = Share::from_mnemonic(&m)?;
+		assert_eq!(share, dec_share);
+		// strict SLIP-39 validation doesn't accept it
+		assert_eq!(validate_mnemonic(&m), Err(MnemonicIssue::InvalidChecksum));
+
+		let m = share.to_mnemonic_as(Format::Slip39)?;
+		assert_eq!(m.len(), 20);
+		let dec_share = Share::from_mnemonic(&m)?;
+		assert_eq!(dec_share.format, Format::Slip39);
+		assert_eq!(dec_share.kdf_profile, 0);
+		Ok(())
+	}
+
+	#[test]
+	fn padding_all_lengths() -> Result<(), Error> {
+		for len in 16..=32usize {
+			let mut share = Share::new()?;
+			share.group_threshold = 1;
+			share.group_count = 1;
+			share.member_threshold = 1;
+			share.share_value = (0..len as u8).map(|b| b.wrapping_mul(37) ^ 0xa5).collect();
+			let mnemonic = share.to_mnemonic()?;
+			let padding = (10 - len * 8 % 10) % 10;
+			if len % 2 == 1 {
+				// odd lengths aren't allowed: parsing either fails on the padding length or
+				// reads the padding as a leading zero byte of an even length value
+				if let Ok(parsed) = Share::from_mnemonic(&mnemonic) {
+					assert_eq!(parsed.share_value.len(), len + 1);
+					assert_eq!(parsed.share_value[0], 0);
+				}
+				continue;
+			}
+			let parsed = Share::from_mnemonic(&mnemonic)?;
+			assert_eq!(parsed.share_value, share.share_value);
+			match share.with_invalid_padding() {
+				Ok(m) => {
+					assert!(padding > 0);
+					let e = Share::from_mnemonic(&m).unwrap_err();
+					assert_eq!(
+						e.kind(),
+						ErrorKind::SharePadding(
+							share.identifier,
+							share.group_index,
+							share.member_index
+						)
+					);
+				}
+				Err(_) => assert_eq!(padding, 0),
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn length_calculators() {
+		assert_eq!(mnemonic_word_count_for_secret(16), 20);
+		assert_eq!(mnemonic_word_count_for_secret(32), 33);
+		assert_eq!(secret_len_for_word_count(20), Some(16));
+		assert_eq!(secret_len_for_word_count(33), Some(32));
+		assert_eq!(secret_len_for_word_count(19), None);
+		assert_eq!(secret_len_for_word_count(21), None);
+		for len in (16..=64).step_by(2) {
+			let words = mnemonic_word_count_for_secret(len);
+			assert_eq!(secret_len_for_word_count(words), Some(len));
+		}
+	}
+
+	#[test]
+	fn share_builder() -> Result<(), Error> {
+		let share = ShareBuilder::new()
+			.with_identifier(21219)
+			.with_group(0, 1, 1)
+			.with_member(4, 3)
+			.with_share_value(b"\x84\x06\xce\xa0p\xbfe~\rA\x01\t5\xaf\xd3Z")
+			.build()?;
+		assert_eq!(share.member_index(), 4);
+		assert_eq!(share.member_threshold(), 3);
+		let m = share.to_mnemonic()?;
+		assert_eq!(Share::from_mnemonic(&m)?, share);
+
+		let value = [0u8; 16];
+		let base = ShareBuilder::new().with_share_value(&value);
+		assert!(base.clone().build().is_ok());
+		assert!(base.clone().with_identifier(1 << 15).build().is_err());
+		assert!(base.clone().with_iteration_exponent(32).build().is_err());
+		assert!(base.clone().with_group(0, 0, 1).build().is_err());
+		assert!(base.clone().with_group(0, 3, 2).build().is_err());
+		assert!(base.clone().with_group(16, 1, 17).build().is_err());
+		assert!(base.clone().with_group(2, 1, 2).build().is_err());
+		assert!(base.clone().with_member(0, 0).build().is_err());
+		assert!(base.clone().with_member(16, 2).build().is_err());
+		assert!(ShareBuilder::new().build().is_err());
+		assert!(ShareBuilder::new()
+			.with_share_value(&[0u8; 15])
+			.build()
+			.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn pack_bits_invalid_header() {
+		let kind = |s: Share| s.to_mnemonic().unwrap_err().kind();
+		let value = vec![0u8; 16];
+		assert_eq!(
+			kind(Share {
+				share_value: value.clone(),
+				..Default::default()
+			}),
+			ErrorKind::ShareHeader("group_threshold".to_string(), 0)
+		);
+		let valid = ShareBuilder::new()
+			.with_share_value(&value)
+			.build()
+			.unwrap();
+		let mut s = valid.clone();
+		s.member_threshold = 17;
+		assert_eq!(
+			kind(s),
+			ErrorKind::ShareHeader("member_threshold".to_string(), 17)
+		);
+		let mut s = valid.clone();
+		s.member_index = 16;
+		assert_eq!(
+			kind(s),
+			ErrorKind::ShareHeader("member_index".to_string(), 16)
+		);
+		let mut s = valid;
+		s.identifier = 1 << 15;
+		assert_eq!(
+			kind(s),
+			ErrorKind::ShareHeader("identifier".to_string(), 1 << 15)
+		);
+	}
+
+	mod prop {
+		use super::*;
+		use quickcheck::*;
+
+		quickcheck! {
+			fn header_round_trip(
+				identifier: u16,
+				iteration_exponent: u8,
+				group: (u8, u8, u8),
+				member: (u8, u8),
+				value: Vec<u8>
+			) -> TestResult {
+				let group_count = group.2 % 16 + 1;
+				let group_threshold = group.1 % group_count + 1;
+				let mut share_value = value;
+				share_value.truncate(32);
+				if share_value.len() % 2 == 1 {
+					share_value.pop();
+				}
+				share_value.resize(share_value.len().max(16), 0xa5);
+				let share = ShareBuilder::new()
+					.with_identifier(identifier >> 1)
+					.with_iteration_exponent(iteration_exponent % 32)
+					.with_group(group.0 % group_count, group_threshold, group_count)
+					.with_member(member.0 % 16, member.1 % 16 + 1)
+					.with_share_value(&share_value)
+					.build()
+					.unwrap();
+				let m = share.to_mnemonic().unwrap();
+				TestResult::from_bool(Share::from_mnemonic(&m).unwrap() == share)
+			}
+		}
+	}
+
+	#[test]
+	fn custom_wordlist_round_trip() -> Result<(), Error> {
+		use crate::shamir::wordlist::CustomWordlist;
+		// a "translated" list: each English word with a common suffix
+		let words = WORDLIST.iter().map(|w| format!("{}q", w)).collect();
+		let list = CustomWordlist::from_words(words)?;
+		let share = ShareBuilder::new()
+			.with_identifier(7)
+			.with_member(2, 3)
+			.with_share_value(&[0x11; 16])
+			.build()?;
+		let m = share.to_mnemonic_with_wordlist(&list)?;
+		assert!(m.iter().all(|w| w.ends_with('q')));
+		assert_eq!(Share::from_mnemonic_with_wordlist(&m, &list)?, share);
+		assert!(Share::from_mnemonic(&m).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn encoding_paths_agree() -> Result<(), Error> {
+		let mns = crate::shamir::generate_mnemonics(2, &[(2, 3), (1, 1)], &[0xc3; 32], "", 1)?;
+		for s in mns.iter().flat_map(|g| g.iter()) {
+			let m = s.to_mnemonic()?;
+			assert_eq!(m, s.to_mnemonic_with_wordlist(&English)?);
+			assert_eq!(Share::from_mnemonic(&m)?, *s);
+			assert_eq!(Share::from_u8_vec(&s.to_u8_vec()?)?, *s);
+			let bad = s.with_invalid_checksum()?;
+			assert_eq!(bad[..bad.len() - 1], m[..m.len() - 1]);
+			assert!(Share::from_mnemonic(&bad).is_err());
+		}
+		Ok(())
+	}
 }
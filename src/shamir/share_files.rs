@@ -0,0 +1,147 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storing member shares as individual text files, one share per file
+
+use super::{normalize_mnemonic, GroupShare, Share, ShareSet};
+use crate::error::{Error, ErrorKind};
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+/// How the words of a share are laid out in its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShareFileLayout {
+	/// All words on one line, separated by spaces
+	#[default]
+	Flat,
+	/// One word per line
+	WordPerLine,
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+	ErrorKind::Io(format!("{}: {}", path.display(), e)).into()
+}
+
+impl GroupShare {
+	/// Write each member share to its own file in `dir`, named after the share's
+	/// identifier, group and member (e.g. `share-07945-g1-m2.txt`). Files are
+	/// readable by their owner only (mode 0600 on unix), and existing files are never
+	/// overwritten. Returns the paths written
+	pub fn to_files<P: AsRef<Path>>(
+		&self,
+		dir: P,
+		layout: ShareFileLayout,
+	) -> Result<Vec<PathBuf>, Error> {
+		let dir = dir.as_ref();
+		let mut paths = vec![];
+		for s in &self.member_shares {
+			let separator = match layout {
+				ShareFileLayout::Flat => " ",
+				ShareFileLayout::WordPerLine => "\n",
+			};
+			let mut contents = s.to_mnemonic()?.join(separator);
+			contents.push('\n');
+			let path = dir.join(format!(
+				"share-{:05}-g{}-m{}.txt",
+				s.identifier,
+				s.group_index + 1,
+				s.member_index + 1
+			));
+			let mut options = OpenOptions::new();
+			options.write(true).create_new(true);
+			#[cfg(unix)]
+			options.mode(0o600);
+			let mut file = options.open(&path).map_err(|e| io_error(&path, e))?;
+			file.write_all(contents.as_bytes())
+				.and_then(|_| file.sync_all())
+				.map_err(|e| io_error(&path, e))?;
+			paths.push(path);
+		}
+		Ok(paths)
+	}
+
+	/// Read every share file in `dir` (in either layout, skipping hidden files and
+	/// subdirectories) into a share set, ready for
+	/// [`combine_share_set`](crate::combine_share_set)
+	pub fn from_files<P: AsRef<Path>>(dir: P) -> Result<ShareSet, Error> {
+		let dir = dir.as_ref();
+		let mut paths = vec![];
+		for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+			let path = entry.map_err(|e| io_error(dir, e))?.path();
+			let hidden = path
+				.file_name()
+				.map(|n| n.to_string_lossy().starts_with('.'))
+				.unwrap_or(true);
+			if path.is_file() && !hidden {
+				paths.push(path);
+			}
+		}
+		paths.sort();
+		let mut set = ShareSet::new();
+		for path in paths {
+			let text = fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+			let share = Share::from_mnemonic(&normalize_mnemonic(&text))
+				.map_err(|e| ErrorKind::Mnemonic(format!("{}: {}", path.display(), e)))?;
+			set.insert(share);
+		}
+		Ok(set)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{combine_share_set, generate_mnemonics};
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("sssmc39-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn share_files_round_trip() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let groups = generate_mnemonics(2, &[(2, 3), (1, 1)], &master_secret, "", 0)?;
+		let dir = temp_dir("share-files");
+		let written = groups[0].to_files(&dir, ShareFileLayout::WordPerLine)?;
+		assert_eq!(written.len(), 3);
+		groups[1].to_files(&dir, ShareFileLayout::Flat)?;
+		// never overwrite an existing share
+		assert!(groups[1].to_files(&dir, ShareFileLayout::Flat).is_err());
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			let mode = fs::metadata(&written[0]).unwrap().permissions().mode();
+			assert_eq!(mode & 0o777, 0o600);
+		}
+		fs::write(dir.join(".notes"), "not a share").unwrap();
+
+		// drop one share of the first group, which is still recoverable
+		fs::remove_file(&written[1]).unwrap();
+		let set = GroupShare::from_files(&dir)?;
+		assert_eq!(set.len(), 3);
+		assert_eq!(combine_share_set(&set, "")?.secret, master_secret);
+
+		fs::write(dir.join("stray.txt"), "not a share").unwrap();
+		assert!(GroupShare::from_files(&dir).is_err());
+		fs::remove_dir_all(&dir).unwrap();
+		Ok(())
+	}
+}
@@ -0,0 +1,433 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A collection of parsed shares with set operations, for recoveries that gather
+//! shares over several steps
+
+use super::{GroupShare, ParseOptions, SecretSharer, Share, Splitter};
+use crate::error::{Error, ErrorKind};
+use crate::util::trace::trace_event;
+
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+// shares moved to another member index (group index, old index, new index)
+pub(crate) type MemberMoves = Vec<(u8, u8, u8)>;
+
+/// A set of distinct shares, kept in order of group index then member index
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShareSet {
+	shares: Vec<Share>,
+}
+
+impl ShareSet {
+	/// An empty set
+	pub fn new() -> Self {
+		ShareSet {
+			..Default::default()
+		}
+	}
+
+	/// Parse each mnemonic into the set, failing on the first invalid mnemonic
+	pub fn from_mnemonics(mnemonics: &[Vec<String>]) -> Result<ShareSet, Error> {
+		let mut set = ShareSet::new();
+		for m in mnemonics {
+			set.insert(Share::from_mnemonic(m)?);
+		}
+		Ok(set)
+	}
+
+	/// As [`ShareSet::from_mnemonics`], with the given parsing options
+	pub fn from_mnemonics_with_options(
+		mnemonics: &[Vec<String>],
+		opts: &ParseOptions,
+	) -> Result<ShareSet, Error> {
+		Ok(ShareSet::from_mnemonics_with_diagnostics(mnemonics, opts)?.0)
+	}
+
+	/// Move shares that conflict on a member index within their group to the free
+	/// member index their value is consistent with. Each candidate index is checked
+	/// by recovering the group secret, and its digest, from the candidate and shares
+	/// whose indices aren't in conflict, so enough of those are needed to meet the
+	/// member threshold. Conflicts that can't be resolved are reported as
+	/// [`ErrorKind::MemberIndexConflict`]
+	pub fn reindex_members(&self) -> Result<ShareSet, Error> {
+		Ok(self.reindex_members_tracked()?.0)
+	}
+
+	// as reindex_members, also returning the moves made (group, old index, new index)
+	pub(crate) fn reindex_members_tracked(&self) -> Result<(ShareSet, MemberMoves), Error> {
+		let mut groups: BTreeMap<u8, Vec<Share>> = BTreeMap::new();
+		for s in self.iter() {
+			groups.entry(s.group_index).or_default().push(s.clone());
+		}
+		let mut reindexed = ShareSet::new();
+		let mut moves = vec![];
+		for shares in groups.into_values() {
+			let (shares, group_moves) = reindex_group(shares)?;
+			for s in shares {
+				reindexed.insert(s);
+			}
+			moves.extend(group_moves);
+		}
+		Ok((reindexed, moves))
+	}
+
+	/// Add a share, returning false if it was already present
+	pub fn insert(&mut self, share: Share) -> bool {
+		if self.contains(&share) {
+			return false;
+		}
+		let pos = self
+			.shares
+			.iter()
+			.position(|s| (s.group_index, s.member_index) > (share.group_index, share.member_index))
+			.unwrap_or(self.shares.len());
+		self.shares.insert(pos, share);
+		true
+	}
+
+	/// Whether the share is in the set
+	pub fn contains(&self, share: &Share) -> bool {
+		self.shares.contains(share)
+	}
+
+	/// Number of shares in the set
+	pub fn len(&self) -> usize {
+		self.shares.len()
+	}
+
+	/// Whether the set is empty
+	pub fn is_empty(&self) -> bool {
+		self.shares.is_empty()
+	}
+
+	/// Iterate over the shares
+	pub fn iter(&self) -> std::slice::Iter<'_, Share> {
+		self.shares.iter()
+	}
+
+	/// Shares in either set
+	pub fn union(&self, other: &ShareSet) -> ShareSet {
+		let mut set = self.clone();
+		for s in other.iter() {
+			set.insert(s.clone());
+		}
+		set
+	}
+
+	/// Shares in this set but not the other
+	pub fn difference(&self, other: &ShareSet) -> ShareSet {
+		ShareSet {
+			shares: self
+				.iter()
+				.filter(|s| !other.contains(s))
+				.cloned()
+				.collect(),
+		}
+	}
+
+	/// Shares in both sets
+	pub fn intersection(&self, other: &ShareSet) -> ShareSet {
+		ShareSet {
+			shares: self.iter().filter(|s| other.contains(s)).cloned().collect(),
+		}
+	}
+
+	/// Indices of the groups holding enough member shares to meet their threshold
+	pub fn satisfied_groups(&self) -> Vec<u8> {
+		let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+		for s in self.iter() {
+			groups.entry(s.group_index).or_default().push(s);
+		}
+		groups
+			.into_iter()
+			.filter(|(_, members)| {
+				let mut indices: Vec<u8> = members.iter().map(|s| s.member_index).collect();
+				indices.sort_unstable();
+				indices.dedup();
+				indices.len() >= members[0].member_threshold as usize
+			})
+			.map(|(i, _)| i)
+			.collect()
+	}
+
+	/// Whether the shares are consistent and sufficient to recover the master secret
+	pub fn is_recoverable(&self) -> bool {
+		self.group_shares().is_ok()
+	}
+
+	/// Check the shares belong together and arrange them into the groups that meet
+	/// their member threshold, failing if too few groups do
+	pub fn group_shares(&self) -> Result<Vec<GroupShare>, Error> {
+		if self.is_empty() {
+			return Err(ErrorKind::Mnemonic(
+				"List of mnemonics is empty.".to_string(),
+			))?;
+		}
+		let check_share = &self.shares[0];
+		for s in self.iter() {
+			if s.identifier != check_share.identifier
+				|| s.iteration_exponent != check_share.iteration_exponent
+			{
+				return Err(ErrorKind::Mnemonic(format!(
+					"Invalid set of mnemonics. All mnemonics must begin with the same {} words. \
+					 (Identifier and iteration exponent must be the same).",
+					s.config.id_exp_length_words,
+				)))?;
+			}
+			if s.format != check_share.format || s.kdf_profile != check_share.kdf_profile {
+				return Err(ErrorKind::Mnemonic(
+					"Invalid set of mnemonics. All mnemonics must have the same format".to_string(),
+				))?;
+			}
+			if s.group_threshold != check_share.group_threshold {
+				return Err(ErrorKind::Mnemonic(
+					"Invalid set of mnemonics. All mnemonics must have the same group threshold"
+						.to_string(),
+				))?;
+			}
+			if s.group_count != check_share.group_count {
+				return Err(ErrorKind::Mnemonic(
+					"Invalid set of mnemonics. All mnemonics must have the same group count"
+						.to_string(),
+				))?;
+			}
+		}
+
+		let mut group_index_map: BTreeMap<u8, GroupShare> = BTreeMap::new();
+		for s in self.iter() {
+			group_index_map
+				.entry(s.group_index)
+				.or_insert_with(|| GroupShare {
+					group_id: s.identifier,
+					group_index: s.group_index,
+					group_threshold: s.group_threshold,
+					iteration_exponent: s.iteration_exponent,
+					group_count: s.group_count,
+					member_shares: vec![],
+					member_threshold: s.member_threshold,
+					label: None,
+				})
+				.member_shares
+				.push(s.clone());
+		}
+
+		if group_index_map.len() < check_share.group_threshold as usize {
+			return Err(ErrorKind::Mnemonic(format!(
+				"Insufficient number of mnemonic groups ({}). The required number \
+				 of groups is {}.",
+				group_index_map.len(),
+				check_share.group_threshold,
+			)))?;
+		}
+
+		let groups: Vec<GroupShare> = group_index_map
+			.into_values()
+			// remove groups where number of shares is below the member threshold
+			.filter(|g| {
+				let met = g.member_shares.len() >= g.member_threshold as usize;
+				trace_event!(
+					"combine: group {} {}: {}/{}",
+					g.group_index + 1,
+					if met {
+						"meets threshold"
+					} else {
+						"under threshold"
+					},
+					g.member_shares.len(),
+					g.member_threshold
+				);
+				met
+			})
+			.collect();
+
+		if groups.len() < check_share.group_threshold as usize {
+			return Err(ErrorKind::Mnemonic(
+				"Insufficient number of groups with member counts that meet member threshold."
+					.to_string(),
+			))?;
+		}
+
+		for g in groups.iter() {
+			if let Some(i) = conflicting_indices(&g.member_shares).first() {
+				return Err(ErrorKind::MemberIndexConflict(g.group_index, *i))?;
+			}
+			if g.iter().any(|ms| ms.member_threshold != g.member_threshold) {
+				return Err(ErrorKind::Mnemonic(
+					"Mismatching member thresholds".to_string(),
+				))?;
+			}
+		}
+
+		Ok(groups)
+	}
+}
+
+// member indices held by more than one of a group's shares
+fn conflicting_indices(shares: &[Share]) -> Vec<u8> {
+	let mut indices: Vec<u8> = shares
+		.iter()
+		.enumerate()
+		.filter(|(i, s)| {
+			shares[..*i]
+				.iter()
+				.any(|o| o.member_index == s.member_index)
+		})
+		.map(|(_, s)| s.member_index)
+		.collect();
+	indices.dedup();
+	indices
+}
+
+// move the conflicting shares of a single group to indices verified by the digest
+fn reindex_group(shares: Vec<Share>) -> Result<(Vec<Share>, MemberMoves), Error> {
+	let conflicts = conflicting_indices(&shares);
+	if conflicts.is_empty() {
+		return Ok((shares, vec![]));
+	}
+	let mut moves = vec![];
+	let (mut anchors, conflicting): (Vec<Share>, Vec<Share>) = shares
+		.into_iter()
+		.partition(|s| !conflicts.contains(&s.member_index));
+	let sp = Splitter::new(None);
+	for s in conflicting {
+		let threshold = s.member_threshold;
+		// a threshold of 1 has no digest to check a candidate against
+		if threshold == 1 || anchors.len() + 1 < threshold as usize {
+			Err(ErrorKind::MemberIndexConflict(
+				s.group_index,
+				s.member_index,
+			))?;
+		}
+		let free: Vec<u8> = (0..16)
+			.filter(|x| anchors.iter().all(|a| a.member_index != *x))
+			.collect();
+		let found = free.into_iter().find_map(|x| {
+			let mut candidate = s.clone();
+			candidate.member_index = x;
+			let mut subset = vec![candidate.clone()];
+			subset.extend_from_slice(&anchors[..threshold as usize - 1]);
+			sp.recover_secret(&subset, threshold)
+				.ok()
+				.map(|_| candidate)
+		});
+		match found {
+			Some(c) => {
+				if c.member_index != s.member_index {
+					moves.push((s.group_index, s.member_index, c.member_index));
+				}
+				anchors.push(c);
+			}
+			None => {
+				Err(ErrorKind::MemberIndexConflict(
+					s.group_index,
+					s.member_index,
+				))?;
+			}
+		}
+	}
+	Ok((anchors, moves))
+}
+
+impl<'a> IntoIterator for &'a ShareSet {
+	type Item = &'a Share;
+	type IntoIter = std::slice::Iter<'a, Share>;
+	fn into_iter(self) -> Self::IntoIter {
+		self.shares.iter()
+	}
+}
+
+impl FromIterator<Share> for ShareSet {
+	fn from_iter<I: IntoIterator<Item = Share>>(iter: I) -> Self {
+		let mut set = ShareSet::new();
+		for s in iter {
+			set.insert(s);
+		}
+		set
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn share_set_ops() -> Result<(), Error> {
+		let mns = generate_mnemonics(2, &[(2, 3), (1, 1), (3, 5)], &[7u8; 16], "", 0)?;
+		let first: ShareSet = mns[0].member_shares[..2].iter().cloned().collect();
+		let mut second: ShareSet = mns[0].member_shares[1..].iter().cloned().collect();
+		assert_eq!(first.union(&second).len(), 3);
+		assert_eq!(first.intersection(&second).len(), 1);
+		assert_eq!(first.difference(&second).len(), 1);
+		assert_eq!(first.satisfied_groups(), vec![0]);
+		assert!(!first.is_recoverable());
+
+		assert!(!second.insert(mns[0].member_shares[2].clone()));
+		assert!(second.insert(mns[2].member_shares[4].clone()));
+		assert_eq!(second.satisfied_groups(), vec![0]);
+		let all = first
+			.union(&second)
+			.union(&mns[1].member_shares.iter().cloned().collect());
+		assert_eq!(all.satisfied_groups(), vec![0, 1]);
+		assert!(all.is_recoverable());
+		let indices: Vec<(u8, u8)> = all
+			.iter()
+			.map(|s| (s.group_index, s.member_index))
+			.collect();
+		assert_eq!(indices, vec![(0, 0), (0, 1), (0, 2), (1, 0), (2, 4)]);
+		assert_eq!(all.group_shares()?.len(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn member_index_conflicts() -> Result<(), Error> {
+		let master_secret = [3u8; 16];
+		let mns = generate_mnemonics(1, &[(3, 5)], &master_secret, "", 0)?;
+		let mut shares = mns[0].member_shares[..4].to_vec();
+		// share 3 mislabelled as member 1
+		shares[3].member_index = 1;
+		let mnemonics: Vec<Vec<String>> = shares
+			.iter()
+			.map(|s| s.to_mnemonic())
+			.collect::<Result<_, _>>()?;
+
+		let e = ShareSet::from_mnemonics(&mnemonics)?
+			.group_shares()
+			.unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::MemberIndexConflict(0, 1));
+
+		let mut opts = ParseOptions::new();
+		opts.reindex_members = true;
+		let set = ShareSet::from_mnemonics_with_options(&mnemonics, &opts)?;
+		let indices: Vec<u8> = set.iter().map(|s| s.member_index).collect();
+		assert_eq!(indices, vec![0, 1, 2, 3]);
+		let recovered = crate::shamir::combine_share_set(&set, "")?;
+		assert_eq!(&recovered.secret[..], &master_secret[..]);
+
+		// too few unconflicted shares to check candidates against
+		let e = ShareSet::from_mnemonics_with_options(
+			&[
+				mnemonics[0].clone(),
+				mnemonics[1].clone(),
+				mnemonics[3].clone(),
+			],
+			&opts,
+		)
+		.unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::MemberIndexConflict(0, 1));
+		Ok(())
+	}
+}
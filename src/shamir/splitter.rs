@@ -12,33 +12,163 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Shamir secret sharing over GF(256) as used at both levels of SLIP-39, for callers
+//! who want to drive splitting themselves rather than through `generate_mnemonics`.
+//!
+//! A [`Splitter`] splits and recovers raw secrets through the [`SecretSharer`] trait,
+//! following the configuration in a [`SplitterConfig`]. Configurations are built
+//! from [`SplitterConfig::new`] and its `with_*` methods; new options may be added in
+//! minor releases, so the struct can't be constructed with a literal.
+//!
+//! Splitting a group secret into 3 member shares, any 2 of which recover it:
+//!
+//! ```
+//! use sssmc39::splitter::{SecretSharer, Splitter};
+//!
+//! let group_secret = [7u8; 16];
+//! let sp = Splitter::new(None);
+//! let shares = sp.split(2, 3, &group_secret).unwrap();
+//! assert_eq!(shares.len(), 3);
+//! let recovered = sp.recover(&shares[1..], 2).unwrap();
+//! assert_eq!(recovered, group_secret);
+//! ```
+//!
+//! With a custom configuration and a seeded random source, e.g. for reproducible
+//! test vectors. Shares split with a non-standard digest only recover with the
+//! same configuration:
+//!
+//! ```
+//! use rand::{rngs::StdRng, SeedableRng};
+//! use sssmc39::splitter::{DigestHash, SecretSharer, Splitter, SplitterConfig};
+//!
+//! let config = SplitterConfig::new().with_digest(8, DigestHash::Sha512);
+//! let sp = Splitter::with_rng(Some(&config), StdRng::seed_from_u64(42));
+//! let shares = sp.split(3, 5, &[1u8; 32]).unwrap();
+//!
+//! let recovered = Splitter::new(Some(&config)).recover(&shares[..3], 3).unwrap();
+//! assert_eq!(recovered, vec![1u8; 32]);
+//! assert!(Splitter::new(None).recover(&shares[..3], 3).is_err());
+//! ```
+
 use crate::error::{Error, ErrorKind};
 use crate::shamir::Share;
 use crate::util;
+use crate::util::crypto::{hmac_sha256, hmac_sha512};
+use crate::util::secret::SecretBytes;
 
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, CryptoRng, RngCore};
+use std::cell::{Cell, RefCell};
 
+use crate::field::gf256::slice_ops;
 use crate::field::gf256::Gf256;
 use crate::field::lagrange;
 
-// Create alias for HMAC-SHA256
-type HmacSha256 = Hmac<Sha256>;
+/// Hash function used for the HMAC digest of the shared secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestHash {
+	/// HMAC-SHA256, as specified by SLIP-39
+	Sha256,
+	/// HMAC-SHA512 (non-standard)
+	Sha512,
+	/// BLAKE3 in keyed mode (non-standard), keyed with the BLAKE3 hash of the HMAC key
+	/// since keyed mode takes exactly 32 bytes
+	#[cfg(feature = "digest-blake3")]
+	Blake3,
+}
+
+impl DigestHash {
+	/// Length of the full HMAC output in bytes
+	pub fn output_len(self) -> usize {
+		match self {
+			DigestHash::Sha256 => 32,
+			DigestHash::Sha512 => 64,
+			#[cfg(feature = "digest-blake3")]
+			DigestHash::Blake3 => blake3::OUT_LEN,
+		}
+	}
+
+	/// Writes the leading `out.len()` bytes of the HMAC of `data` into `out`
+	fn hmac_into(self, key: &[u8], data: &[u8], out: &mut [u8]) {
+		match self {
+			DigestHash::Sha256 => out.copy_from_slice(&hmac_sha256(key, data)[..out.len()]),
+			DigestHash::Sha512 => out.copy_from_slice(&hmac_sha512(key, data)[..out.len()]),
+			#[cfg(feature = "digest-blake3")]
+			DigestHash::Blake3 => {
+				let key = blake3::hash(key);
+				let tag = blake3::keyed_hash(key.as_bytes(), data);
+				out.copy_from_slice(&tag.as_bytes()[..out.len()])
+			}
+		}
+	}
+}
+
+/// Order of the member shares within each generated group. Groups themselves are
+/// always returned in order of group index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShareOrdering {
+	/// Sorted by member index
+	#[default]
+	Sorted,
+	/// Shuffled, so that the position of a share when handed out reveals nothing
+	/// about how it was created
+	Shuffled,
+}
+
+/// HMAC key for the non-standard threshold 1 integrity tag
+const THRESHOLD_ONE_TAG_KEY: &[u8] = b"sssmc39 threshold-1 integrity tag";
+
+/// Longest digest `SplitterConfig::validate` accepts, and the size of the buffers
+/// digests are checked against
+const MAX_DIGEST_LEN: usize = 32;
+
+/// Number of bytes of a share value computed between progress reports
+const PROGRESS_CHUNK_LEN: usize = 256;
+
+/// Progress of a split, as reported to the callback given to
+/// [`Splitter::with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitProgress {
+	/// Group whose member shares are being created, or None while the secret is
+	/// split into group shares
+	pub group_index: Option<u8>,
+	/// Shares of the current split that are complete
+	pub shares_done: u8,
+	/// Shares the current split produces
+	pub share_count: u8,
+	/// Bytes of the current share's value computed so far
+	pub bytes_done: usize,
+	/// Length of the share value
+	pub byte_count: usize,
+}
 
-/// Share split configuration values
+/// Share split configuration values. Create with [`SplitterConfig::new`] and adjust
+/// with the `with_*` methods
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct SplitterConfig {
 	/// The length of the random Identifier in bits
 	// TODO: Redundant with ShareConfig value
 	pub id_length_bits: u8,
 	/// The maximum number of shares that can be created
 	pub max_share_count: u8,
-	/// The length of the digest of the shared secret in bytes
+	/// The length of the digest of the shared secret in bytes. SLIP-39 specifies 4,
+	/// raw mode accepts 4 to 32
 	pub digest_length_bytes: u8,
+	/// HMAC hash function for the digest. SLIP-39 specifies SHA-256
+	pub digest_hash: DigestHash,
 	/// The index of the share containing the shared secret
 	pub secret_index: u8,
 	/// The index of the share containing the digest of the shared secret
 	pub digest_index: u8,
+	/// Crate-specific extension, NOT interoperable with other SLIP-39 implementations.
+	/// When set, shares split with a threshold of 1 (which SLIP-39 leaves without a
+	/// digest) carry an auxiliary integrity tag appended to the share value, which is
+	/// verified and removed on recovery. Shares created with this option can only be
+	/// recovered with it enabled.
+	pub threshold_one_digest: bool,
+	/// Order of the member shares within each generated group
+	pub ordering: ShareOrdering,
 }
 
 impl Default for SplitterConfig {
@@ -46,55 +176,238 @@ impl Default for SplitterConfig {
 		let id_length_bits = 15;
 		let max_share_count = 16;
 		let digest_length_bytes = 4;
+		let digest_hash = DigestHash::Sha256;
 		let secret_index = 255;
 		let digest_index = 254;
+		let threshold_one_digest = false;
+		let ordering = ShareOrdering::Sorted;
 
 		SplitterConfig {
 			id_length_bits,
 			max_share_count,
 			digest_length_bytes,
+			digest_hash,
 			secret_index,
 			digest_index,
+			threshold_one_digest,
+			ordering,
 		}
 	}
 }
 
 impl SplitterConfig {
-	/// Just use defaults for now
+	/// The SLIP-39 standard configuration
 	pub fn new() -> Self {
 		SplitterConfig {
 			..Default::default()
 		}
 	}
+
+	/// Require an integrity tag on shares split with a threshold of 1
+	/// (see [`SplitterConfig::threshold_one_digest`])
+	pub fn with_threshold_one_digest(mut self, enabled: bool) -> Self {
+		self.threshold_one_digest = enabled;
+		self
+	}
+
+	/// Set the order of member shares within generated groups
+	pub fn with_ordering(mut self, ordering: ShareOrdering) -> Self {
+		self.ordering = ordering;
+		self
+	}
+
+	/// Use a non-standard digest length and hash, for raw mode only
+	pub fn with_digest(mut self, length_bytes: u8, hash: DigestHash) -> Self {
+		self.digest_length_bytes = length_bytes;
+		self.digest_hash = hash;
+		self
+	}
+
+	/// Check configuration values are usable
+	pub fn validate(&self) -> Result<(), Error> {
+		if !(4..=MAX_DIGEST_LEN).contains(&(self.digest_length_bytes as usize)) {
+			return Err(ErrorKind::Config(format!(
+				"Digest length must be between 4 and {} bytes, got {}",
+				MAX_DIGEST_LEN, self.digest_length_bytes
+			)))?;
+		}
+		Ok(())
+	}
+
+	/// Check the configuration keeps the values SLIP-39 mandates for the share digest,
+	/// so that shares are interoperable with other implementations
+	pub fn validate_slip39(&self) -> Result<(), Error> {
+		self.validate()?;
+		let standard = SplitterConfig::new();
+		if self.digest_length_bytes != standard.digest_length_bytes
+			|| self.digest_hash != standard.digest_hash
+		{
+			return Err(ErrorKind::Config(
+				"SLIP-39 shares require a 4 byte HMAC-SHA256 digest".to_string(),
+			))?;
+		}
+		Ok(())
+	}
+}
+
+/// A share of a secret as its x coordinate (index) and value
+pub type RawShare = (u8, Vec<u8>);
+
+/// A backend implementing Shamir's secret sharing over byte slices, as used at both
+/// the group and member levels of the scheme.
+pub trait SecretSharer {
+	/// Splits `shared_secret` into `share_count` shares, any `threshold` of which
+	/// can recover it
+	fn split(
+		&self,
+		threshold: u8,
+		share_count: u8,
+		shared_secret: &[u8],
+	) -> Result<Vec<RawShare>, Error>;
+
+	/// Recovers a secret from shares produced by [`SecretSharer::split`]
+	fn recover(&self, shares: &[RawShare], threshold: u8) -> Result<Vec<u8>, Error>;
+
+	/// split secret into shares based on the given `proto_share`
+	fn split_secret(
+		&self,
+		proto_share: &Share,
+		threshold: u8,
+		share_count: u8,
+		shared_secret: &[u8],
+	) -> Result<Vec<Share>, Error> {
+		Ok(self
+			.split(threshold, share_count, shared_secret)?
+			.into_iter()
+			.map(|(member_index, share_value)| {
+				let mut s = proto_share.clone();
+				s.member_index = member_index;
+				s.member_threshold = threshold;
+				s.share_value = share_value;
+				s
+			})
+			.collect())
+	}
+
+	/// recover a secret from shares, returned as a share based on the first given share
+	fn recover_secret(&self, shares: &[Share], threshold: u8) -> Result<Share, Error> {
+		if shares.is_empty() {
+			return Err(ErrorKind::Value("Share set must not be empty.".to_string()))?;
+		}
+		let raw: Vec<RawShare> = shares
+			.iter()
+			.map(|s| (s.member_index, s.share_value.clone()))
+			.collect();
+		let mut shared_secret = shares[0].clone();
+		shared_secret.share_value = self.recover(&raw, threshold)?;
+		Ok(shared_secret)
+	}
 }
 
-/// Main Struct
+type ProgressCallback = Box<dyn Fn(&SplitProgress)>;
+
+/// Splits and recovers secrets according to a [`SplitterConfig`], using the built-in
+/// GF(256) implementation unless given another [`SecretSharer`] backend
 pub struct Splitter {
 	/// Configuration values
 	config: SplitterConfig,
+	/// Alternative backend, if the built-in GF(256) implementation isn't being used
+	backend: Option<Box<dyn SecretSharer>>,
+	/// Random source for the built-in implementation, if not the thread RNG
+	rng: Option<RefCell<Box<dyn RngCore>>>,
+	/// Callback for progress reports
+	progress: Option<ProgressCallback>,
+	/// Group whose member shares are currently being split, for progress reports
+	progress_group: Cell<Option<u8>>,
 }
 
 impl Splitter {
-	/// Create new
+	/// Create a splitter with the given configuration, or the SLIP-39 standard one
 	pub fn new(config: Option<&SplitterConfig>) -> Splitter {
 		Splitter {
 			config: match config {
 				Some(c) => c.to_owned(),
 				None => SplitterConfig::new(),
 			},
+			backend: None,
+			rng: None,
+			progress: None,
+			progress_group: Cell::new(None),
 		}
 	}
 
-	/// split secret
-	/// member_threshold, share_count, shared_secret at least 128 bits and a multiple of 16
-	/// returns shares
-	pub fn split_secret(
+	/// Report progress of each split to the given callback, share by share and, for
+	/// long secrets, every few hundred bytes of each share
+	pub fn with_progress(mut self, callback: impl Fn(&SplitProgress) + 'static) -> Splitter {
+		self.progress = Some(Box::new(callback));
+		self
+	}
+
+	/// Attribute subsequent progress reports to the given group
+	pub(crate) fn set_progress_group(&self, group_index: Option<u8>) {
+		self.progress_group.set(group_index);
+	}
+
+	fn report(&self, shares_done: u8, share_count: u8, bytes_done: usize, byte_count: usize) {
+		if let Some(callback) = &self.progress {
+			callback(&SplitProgress {
+				group_index: self.progress_group.get(),
+				shares_done,
+				share_count,
+				bytes_done,
+				byte_count,
+			});
+		}
+	}
+
+	/// Create a splitter that draws its randomness from the given RNG, e.g. a seeded
+	/// one to produce reproducible test vectors
+	pub fn with_rng<R: RngCore + CryptoRng + 'static>(
+		config: Option<&SplitterConfig>,
+		rng: R,
+	) -> Splitter {
+		Splitter {
+			rng: Some(RefCell::new(Box::new(rng))),
+			..Splitter::new(config)
+		}
+	}
+
+	/// The splitter's configuration
+	pub fn config(&self) -> &SplitterConfig {
+		&self.config
+	}
+
+	/// Sort member shares by index, then shuffle them with the splitter's random
+	/// source if the configuration asks for it
+	pub(crate) fn order_shares(&self, shares: &mut [Share]) {
+		shares.sort_by_key(|s| s.member_index);
+		if self.config.ordering == ShareOrdering::Shuffled {
+			match &self.rng {
+				Some(rng) => shares.shuffle(&mut **rng.borrow_mut()),
+				None => shares.shuffle(&mut thread_rng()),
+			}
+		}
+	}
+
+	/// Create a splitter that delegates splitting and recovery to the given backend
+	/// after validating arguments against the configuration
+	pub fn with_backend(
+		config: Option<&SplitterConfig>,
+		backend: Box<dyn SecretSharer>,
+	) -> Splitter {
+		Splitter {
+			backend: Some(backend),
+			..Splitter::new(config)
+		}
+	}
+
+	fn check_split_args(
 		&self,
-		proto_share: &Share,
 		threshold: u8,
 		share_count: u8,
 		shared_secret: &[u8],
-	) -> Result<Vec<Share>, Error> {
+	) -> Result<(), Error> {
+		self.config.validate()?;
 		if threshold == 0 || threshold > self.config.max_share_count {
 			return Err(ErrorKind::Argument(format!(
 				"Threshold must be between 1 and {}",
@@ -107,147 +420,272 @@ impl Splitter {
 				threshold, self.config.max_share_count
 			)))?;
 		}
-		if shared_secret.len() < 16 || shared_secret.len() % 2 != 0 {
+		if shared_secret.len() < 16 || !shared_secret.len().is_multiple_of(2) {
 			return Err(ErrorKind::Argument(
 				"Secret must be at least 16 bytes in length and a multiple of 2".to_string(),
 			))?;
 		}
+		if self.config.digest_length_bytes as usize >= shared_secret.len() {
+			return Err(ErrorKind::Argument(
+				"Secret must be longer than the configured digest".to_string(),
+			))?;
+		}
+		Ok(())
+	}
 
-		let mut shares = vec![];
+	fn split_gf256(
+		&self,
+		threshold: u8,
+		share_count: u8,
+		shared_secret: &[u8],
+		rng: &mut dyn RngCore,
+	) -> Result<Vec<RawShare>, Error> {
+		let mut shares = Vec::with_capacity(share_count as usize);
 		// if the threshold is 1, then the digest of the shared secret is not used
 		if threshold == 1 {
+			let tag_len = if self.config.threshold_one_digest {
+				self.config.digest_length_bytes as usize
+			} else {
+				0
+			};
+			let mut share_value = vec![0u8; shared_secret.len() + tag_len];
+			let (value, tag) = share_value.split_at_mut(shared_secret.len());
+			value.copy_from_slice(shared_secret);
+			self.create_tag(shared_secret, tag);
 			for i in 0..share_count {
-				let mut s = proto_share.clone();
-				s.member_index = i;
-				s.member_threshold = threshold;
-				s.share_value = shared_secret.to_owned();
-				shares.push(s);
+				shares.push((i, share_value.clone()));
+				self.report(i + 1, share_count, share_value.len(), share_value.len());
 			}
 			return Ok(shares);
 		}
 
+		let len = shared_secret.len();
 		let random_share_count = threshold - 2;
 
 		for i in 0..random_share_count {
-			let mut s = proto_share.clone();
-			s.member_index = i;
-			s.member_threshold = threshold;
-			s.share_value = util::fill_vec_rand(shared_secret.len());
-			shares.push(s);
+			let mut v = vec![0u8; len];
+			rng.fill_bytes(&mut v);
+			shares.push((i, v));
+			self.report(i + 1, share_count, len, len);
 		}
 
-		let random_part =
-			util::fill_vec_rand(shared_secret.len() - self.config.digest_length_bytes as usize);
-		let mut digest = self.create_digest(&random_part.to_vec(), shared_secret);
-		digest.append(&mut random_part.to_vec());
-
-		let mut base_shares = shares.clone();
-		let mut s = proto_share.clone();
-		s.member_index = self.config.digest_index;
-		s.member_threshold = threshold;
-		s.share_value = digest;
-		base_shares.push(s);
-
-		let mut s = proto_share.clone();
-		s.member_index = self.config.secret_index;
-		s.member_threshold = threshold;
-		s.share_value = shared_secret.to_owned();
-		base_shares.push(s);
-
-		for i in random_share_count..share_count {
-			let mut r = self.interpolate(&base_shares, i, proto_share)?;
-			r.member_index = i;
-			r.member_threshold = threshold;
-			shares.push(r);
-		}
-
-		//self.check_digest(&shares, &shared_secret, &proto_share)?;
+		// digest share is the truncated HMAC followed by the random data it keys
+		let mut digest = vec![0u8; shared_secret.len()];
+		let (hash, random_part) = digest.split_at_mut(self.config.digest_length_bytes as usize);
+		rng.fill_bytes(random_part);
+		self.create_digest(random_part, shared_secret, hash);
+
+		// base points are interpolated alongside the random shares, then dropped
+		shares.push((self.config.digest_index, digest));
+		shares.push((self.config.secret_index, shared_secret.to_owned()));
+		let derived = (random_share_count..share_count)
+			.map(|i| {
+				let value = self.interpolate_with(&shares, i, &mut |bytes_done| {
+					self.report(i, share_count, bytes_done, len)
+				})?;
+				self.report(i + 1, share_count, len, len);
+				Ok((i, value))
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+		shares.truncate(random_share_count as usize);
+		shares.extend(derived);
 
 		Ok(shares)
 	}
 
-	/// recover a secret
-	pub fn recover_secret(&self, shares: &[Share], threshold: u8) -> Result<Share, Error> {
+	/// As [`SecretSharer::recover`], writing the secret into `out` rather than
+	/// allocating it, e.g. straight into locked memory. `out` must be exactly the
+	/// length of the secret, and is zeroed if recovery fails
+	pub fn recover_secret_into(
+		&self,
+		shares: &[RawShare],
+		threshold: u8,
+		out: &mut [u8],
+	) -> Result<(), Error> {
+		self.config.validate()?;
 		if shares.is_empty() {
 			return Err(ErrorKind::Value("Share set must not be empty.".to_string()))?;
 		}
-		let mut proto_share = shares[0].clone();
-		proto_share.share_value = vec![];
+		let res = match &self.backend {
+			Some(b) => b
+				.recover(shares, threshold)
+				.map(SecretBytes::new)
+				.and_then(|s| {
+					check_output_len(out, s.len())?;
+					out.copy_from_slice(&s);
+					Ok(())
+				}),
+			None => self.recover_gf256_into(shares, threshold, out),
+		};
+		if res.is_err() {
+			util::secret::zeroize(out);
+		}
+		res
+	}
 
-		let shared_secret = self.interpolate(shares, self.config.secret_index, &proto_share)?;
+	fn recover_gf256(&self, shares: &[RawShare], threshold: u8) -> Result<Vec<u8>, Error> {
+		let mut shared_secret = vec![0; shares[0].1.len().saturating_sub(self.tag_len(threshold))];
+		self.recover_gf256_into(shares, threshold, &mut shared_secret)?;
+		Ok(shared_secret)
+	}
+
+	// length of the integrity tag on shares split with the given threshold
+	fn tag_len(&self, threshold: u8) -> usize {
+		match threshold == 1 && self.config.threshold_one_digest {
+			true => self.config.digest_length_bytes as usize,
+			false => 0,
+		}
+	}
+
+	fn recover_gf256_into(
+		&self,
+		shares: &[RawShare],
+		threshold: u8,
+		out: &mut [u8],
+	) -> Result<(), Error> {
+		let value_len = shares[0].1.len();
+		let tag_len = self.tag_len(threshold);
+		if value_len < tag_len {
+			return Err(ErrorKind::Digest(
+				"Share value is too short to contain an integrity tag".to_string(),
+			))?;
+		}
+		let digest_len = self.config.digest_length_bytes as usize;
+		if threshold != 1 && value_len < digest_len {
+			return Err(ErrorKind::Value(format!(
+				"Share values must be at least {} bytes long to contain the digest.",
+				digest_len
+			)))?;
+		}
+		check_output_len(out, value_len - tag_len)?;
+		let x = self.config.secret_index;
+		self.interpolate_into(shares, x, 0, out, &mut |_| ())?;
 
 		if threshold != 1 {
-			self.check_digest(shares, &shared_secret, &proto_share)?;
+			self.check_digest(shares, out)?;
+		} else if tag_len > 0 {
+			let mut tag = [0u8; MAX_DIGEST_LEN];
+			self.interpolate_into(
+				shares,
+				x,
+				value_len - tag_len,
+				&mut tag[..tag_len],
+				&mut |_| (),
+			)?;
+			self.check_tag(out, &tag[..tag_len])?;
 		}
+		Ok(())
+	}
 
-		Ok(shared_secret)
+	pub(crate) fn interpolate(&self, shares: &[RawShare], x: u8) -> Result<Vec<u8>, Error> {
+		self.interpolate_with(shares, x, &mut |_| ())
 	}
 
-	fn interpolate(&self, shares: &[Share], x: u8, proto_share: &Share) -> Result<Share, Error> {
-		let x_coords: Vec<u8> = shares.iter().map(|s| s.member_index).collect();
+	/// Interpolate the value at x, calling `on_chunk` with the number of bytes done
+	/// after each chunk of the result
+	fn interpolate_with(
+		&self,
+		shares: &[RawShare],
+		x: u8,
+		on_chunk: &mut dyn FnMut(usize),
+	) -> Result<Vec<u8>, Error> {
+		if let Some(s) = shares.iter().find(|s| s.0 == x) {
+			return Ok(s.1.clone());
+		}
+		let mut ret_value = vec![0; shares[0].1.len()];
+		self.interpolate_into(shares, x, 0, &mut ret_value, on_chunk)?;
+		Ok(ret_value)
+	}
+
+	/// Interpolate bytes `offset..offset + out.len()` of the value at x into `out`,
+	/// calling `on_chunk` with the number of bytes done after each chunk
+	fn interpolate_into(
+		&self,
+		shares: &[RawShare],
+		x: u8,
+		offset: usize,
+		out: &mut [u8],
+		on_chunk: &mut dyn FnMut(usize),
+	) -> Result<(), Error> {
+		let x_coords: Vec<u8> = shares.iter().map(|s| s.0).collect();
+		let range = offset..offset + out.len();
 
-		if x_coords.contains(&x) {
-			for s in shares {
-				if s.member_index == x {
-					let mut ret_s = proto_share.clone();
-					ret_s.member_index = x;
-					ret_s.share_value = s.share_value.clone();
-					return Ok(ret_s);
-				}
+		if let Some(s) = shares.iter().find(|s| s.0 == x) {
+			match s.1.get(range) {
+				Some(v) => out.copy_from_slice(v),
+				None => return Err(ErrorKind::Value("Share value too short".to_string()))?,
 			}
+			return Ok(());
 		}
 
-		let share_value_lengths = shares[0].share_value.len();
+		let share_value_lengths = shares[0].1.len();
 		for s in shares {
-			if s.share_value.len() != share_value_lengths {
+			if s.1.len() != share_value_lengths {
 				return Err(ErrorKind::Mnemonic(
 					"Invalid set of shares. All share values must have the same length".to_string(),
 				))?;
 			}
 		}
 
-		let mut ret_share = proto_share.clone();
-		ret_share.member_index = x;
-
-		for i in 0..share_value_lengths {
-			let points: Vec<(Gf256, Gf256)> = shares
-				.iter()
-				.map(|s| {
-					(
-						Gf256::from_byte(s.member_index),
-						Gf256::from_byte(s.share_value[i]),
-					)
-				})
-				.collect();
-			let poly = lagrange::interpolate(&points);
-			let y = poly.evaluate_at(Gf256::from_byte(x));
-			ret_share.share_value.push(y.to_byte());
+		for (i, xi) in x_coords.iter().enumerate() {
+			if x_coords[..i].contains(xi) {
+				return Err(ErrorKind::Value(
+					"Invalid set of shares. Share indices must be unique".to_string(),
+				))?;
+			}
+		}
+		if range.end > share_value_lengths {
+			return Err(ErrorKind::Value("Share value too short".to_string()))?;
+		}
+		out.fill(0);
+
+		// Evaluate directly at x rather than interpolating coefficients: the
+		// basis is the same for every byte, so each byte is a weighted sum
+		let xs: Vec<Gf256> = x_coords.iter().map(|&xi| Gf256::from_byte(xi)).collect();
+		let bases = lagrange::basis_at(&xs, Gf256::from_byte(x));
+		for start in (0..out.len()).step_by(PROGRESS_CHUNK_LEN) {
+			let end = (start + PROGRESS_CHUNK_LEN).min(out.len());
+			for (s, &basis) in shares.iter().zip(&bases) {
+				let src = &s.1[offset + start..offset + end];
+				slice_ops::mul_add(&mut out[start..end], src, basis);
+			}
+			on_chunk(end);
 		}
 
-		Ok(ret_share)
+		Ok(())
 	}
 
-	fn create_digest(&self, random_data: &[u8], shared_secret: &[u8]) -> Vec<u8> {
-		let mut mac = HmacSha256::new_from_slice(random_data).expect("HMAC error");
-		mac.update(shared_secret);
-		let result = mac.finalize().into_bytes();
-		// let mut result = [0u8; 32];
-		// result.copy_from_slice(mac.finalize().into_bytes());
-		let mut ret_vec = result.to_vec();
-		ret_vec.truncate(4);
-		ret_vec
+	fn create_digest(&self, random_data: &[u8], shared_secret: &[u8], out: &mut [u8]) {
+		self.config
+			.digest_hash
+			.hmac_into(random_data, shared_secret, out);
 	}
 
-	fn check_digest(
-		&self,
-		shares: &[Share],
-		shared_secret: &Share,
-		proto_share: &Share,
-	) -> Result<(), Error> {
-		let digest_share = self.interpolate(shares, self.config.digest_index, proto_share)?;
-		let mut digest = digest_share.share_value;
-		let random_part = digest.split_off(self.config.digest_length_bytes as usize);
-		if digest != self.create_digest(&random_part, &shared_secret.share_value) {
+	fn create_tag(&self, shared_secret: &[u8], out: &mut [u8]) {
+		self.config
+			.digest_hash
+			.hmac_into(THRESHOLD_ONE_TAG_KEY, shared_secret, out);
+	}
+
+	/// Verifies and strips the integrity tag from a threshold 1 share value
+	fn check_tag(&self, value: &[u8], tag: &[u8]) -> Result<(), Error> {
+		let mut expected = [0u8; MAX_DIGEST_LEN];
+		self.create_tag(value, &mut expected[..tag.len()]);
+		if !util::ct::ct_eq(tag, &expected[..tag.len()]) {
+			return Err(ErrorKind::Digest(
+				"Invalid integrity tag of the shared secret".to_string(),
+			))?;
+		}
+		Ok(())
+	}
+
+	fn check_digest(&self, shares: &[RawShare], shared_secret: &[u8]) -> Result<(), Error> {
+		let digest_len = self.config.digest_length_bytes as usize;
+		let digest = self.interpolate(shares, self.config.digest_index)?;
+		let (hash, random_part) = digest.split_at(digest_len);
+		let mut expected = [0u8; MAX_DIGEST_LEN];
+		self.create_digest(random_part, shared_secret, &mut expected[..digest_len]);
+		if !util::ct::ct_eq(hash, &expected[..digest_len]) {
 			return Err(ErrorKind::Digest(
 				"Invalid digest of the shared secret".to_string(),
 			))?;
@@ -256,6 +694,105 @@ impl Splitter {
 	}
 }
 
+// checks an output buffer is the length of the recovered secret
+fn check_output_len(out: &[u8], len: usize) -> Result<(), Error> {
+	if out.len() != len {
+		Err(ErrorKind::Argument(format!(
+			"Output buffer is {} bytes, recovered secret is {} bytes",
+			out.len(),
+			len
+		)))?;
+	}
+	Ok(())
+}
+
+impl SecretSharer for Splitter {
+	/// split secret
+	/// member_threshold, share_count, shared_secret at least 128 bits and a multiple of 16
+	/// returns shares
+	fn split(
+		&self,
+		threshold: u8,
+		share_count: u8,
+		shared_secret: &[u8],
+	) -> Result<Vec<RawShare>, Error> {
+		self.check_split_args(threshold, share_count, shared_secret)?;
+		match &self.backend {
+			Some(b) => {
+				let shares = b.split(threshold, share_count, shared_secret)?;
+				let len = shares.first().map(|s| s.1.len()).unwrap_or(0);
+				self.report(share_count, share_count, len, len);
+				Ok(shares)
+			}
+			None => match &self.rng {
+				Some(rng) => self.split_gf256(
+					threshold,
+					share_count,
+					shared_secret,
+					&mut *rng.borrow_mut(),
+				),
+				None => self.split_gf256(threshold, share_count, shared_secret, &mut thread_rng()),
+			},
+		}
+	}
+
+	/// recover a secret
+	fn recover(&self, shares: &[RawShare], threshold: u8) -> Result<Vec<u8>, Error> {
+		self.config.validate()?;
+		if shares.is_empty() {
+			return Err(ErrorKind::Value("Share set must not be empty.".to_string()))?;
+		}
+		match &self.backend {
+			Some(b) => b.recover(shares, threshold),
+			None => self.recover_gf256(shares, threshold),
+		}
+	}
+}
+
+/// Splits a secret into `share_count` shares, any `threshold` of which can recover it,
+/// using SLIP-39's Shamir secret sharing over GF(256) without the mnemonic encoding or
+/// encryption layers. The secret must be at least 16 bytes and of even length.
+/// Returns a list of `(index, value)` pairs.
+pub fn split_secret_raw<R: RngCore + CryptoRng>(
+	threshold: u8,
+	share_count: u8,
+	secret: &[u8],
+	rng: &mut R,
+) -> Result<Vec<RawShare>, Error> {
+	split_secret_raw_with_config(threshold, share_count, secret, rng, &SplitterConfig::new())
+}
+
+/// Recovers a secret from at least `threshold` shares created by [`split_secret_raw`],
+/// verifying the SLIP-39 digest when the threshold is greater than 1
+pub fn recover_secret_raw(shares: &[RawShare], threshold: u8) -> Result<SecretBytes, Error> {
+	recover_secret_raw_with_config(shares, threshold, &SplitterConfig::new())
+}
+
+/// As [`split_secret_raw`], with a configuration that may select a non-standard
+/// digest length and hash
+pub fn split_secret_raw_with_config<R: RngCore + CryptoRng>(
+	threshold: u8,
+	share_count: u8,
+	secret: &[u8],
+	rng: &mut R,
+	config: &SplitterConfig,
+) -> Result<Vec<RawShare>, Error> {
+	let sp = Splitter::new(Some(config));
+	sp.check_split_args(threshold, share_count, secret)?;
+	sp.split_gf256(threshold, share_count, secret, rng)
+}
+
+/// As [`recover_secret_raw`], with the configuration the shares were split with
+pub fn recover_secret_raw_with_config(
+	shares: &[RawShare],
+	threshold: u8,
+	config: &SplitterConfig,
+) -> Result<SecretBytes, Error> {
+	Ok(SecretBytes::new(
+		Splitter::new(Some(config)).recover(shares, threshold)?,
+	))
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -319,4 +856,145 @@ mod tests {
 		split_recover_impl(4096, 10, 16)?;
 		Ok(())
 	}
+
+	#[test]
+	fn threshold_one_digest() -> Result<(), Error> {
+		let config = SplitterConfig::new().with_threshold_one_digest(true);
+		let sp = Splitter::new(Some(&config));
+		let secret = util::fill_vec_rand(16);
+		let proto_share = Share::new()?;
+		let shares = sp.split_secret(&proto_share, 1, 3, &secret)?;
+		assert_eq!(shares[0].share_value.len(), 20);
+		assert_eq!(sp.recover_secret(&shares[1..2], 1)?.share_value, secret);
+		let mut bad = shares[2].clone();
+		bad.share_value[3] ^= 0x10;
+		assert!(sp.recover_secret(&[bad], 1).is_err());
+		// without the option, the tag isn't stripped
+		let sp = Splitter::new(None);
+		assert_ne!(sp.recover_secret(&shares[0..1], 1)?.share_value, secret);
+		Ok(())
+	}
+
+	#[test]
+	fn recover_into() -> Result<(), Error> {
+		let sp = Splitter::new(None);
+		let secret = util::fill_vec_rand(4096);
+		let shares = sp.split(3, 5, &secret)?;
+		let mut out = vec![0u8; 4096];
+		sp.recover_secret_into(&shares[2..], 3, &mut out)?;
+		assert_eq!(out, secret);
+		// wrong length output, or too few shares
+		assert!(sp
+			.recover_secret_into(&shares[2..], 3, &mut out[1..])
+			.is_err());
+		assert!(sp.recover_secret_into(&shares[3..], 3, &mut out).is_err());
+		assert!(out.iter().all(|b| *b == 0));
+		// threshold one tag is checked and not written out
+		let config = SplitterConfig::new().with_threshold_one_digest(true);
+		let sp = Splitter::new(Some(&config));
+		let shares = sp.split(1, 2, &secret[..16])?;
+		let mut out = [0u8; 16];
+		sp.recover_secret_into(&shares[1..], 1, &mut out)?;
+		assert_eq!(out[..], secret[..16]);
+		let mut bad = shares[0].clone();
+		bad.1[18] ^= 1;
+		assert!(sp.recover_secret_into(&[bad], 1, &mut out).is_err());
+		Ok(())
+	}
+
+	// A trivial (and insecure) backend, just to check delegation
+	struct Repeater;
+
+	impl SecretSharer for Repeater {
+		fn split(&self, _: u8, share_count: u8, secret: &[u8]) -> Result<Vec<RawShare>, Error> {
+			Ok((0..share_count).map(|i| (i, secret.to_vec())).collect())
+		}
+
+		fn recover(&self, shares: &[RawShare], _: u8) -> Result<Vec<u8>, Error> {
+			Ok(shares[0].1.clone())
+		}
+	}
+
+	#[test]
+	fn alternative_backend() -> Result<(), Error> {
+		let sp = Splitter::with_backend(None, Box::new(Repeater));
+		let secret = util::fill_vec_rand(16);
+		let shares = sp.split(3, 5, &secret)?;
+		assert!(shares.iter().all(|s| s.1 == secret));
+		assert_eq!(sp.recover(&shares[4..], 3)?, secret);
+		// arguments are still validated against the config
+		assert!(sp.split(3, 17, &secret).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn raw_api() -> Result<(), Error> {
+		let secret = util::fill_vec_rand(32);
+		let shares = split_secret_raw(3, 5, &secret, &mut thread_rng())?;
+		assert_eq!(shares.len(), 5);
+		assert_eq!(recover_secret_raw(&shares[1..4], 3)?, secret);
+		assert!(recover_secret_raw(&shares[1..3], 3).is_err());
+		assert!(split_secret_raw(3, 5, &secret[..15], &mut thread_rng()).is_err());
+		// shares too short to hold the digest
+		match recover_secret_raw(&[(1, vec![1]), (2, vec![2])], 2) {
+			Err(e) => assert!(matches!(e.kind(), ErrorKind::Value(_))),
+			Ok(_) => panic!("recovered from shares shorter than the digest"),
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn raw_digest_config() -> Result<(), Error> {
+		let secret = util::fill_vec_rand(64);
+		let hashes = [
+			DigestHash::Sha256,
+			DigestHash::Sha512,
+			#[cfg(feature = "digest-blake3")]
+			DigestHash::Blake3,
+		];
+		for hash in hashes {
+			let config = SplitterConfig::new().with_digest(32, hash);
+			assert!(config.validate_slip39().is_err());
+			let shares = split_secret_raw_with_config(3, 5, &secret, &mut thread_rng(), &config)?;
+			assert_eq!(
+				recover_secret_raw_with_config(&shares[2..], 3, &config)?,
+				secret
+			);
+			// digest doesn't verify with the standard config
+			assert!(recover_secret_raw(&shares[2..], 3).is_err());
+		}
+		let config = SplitterConfig::new().with_digest(33, DigestHash::Sha512);
+		assert!(split_secret_raw_with_config(3, 5, &secret, &mut thread_rng(), &config).is_err());
+		let config = SplitterConfig::new().with_digest(16, DigestHash::Sha256);
+		assert!(
+			split_secret_raw_with_config(3, 5, &secret[..16], &mut thread_rng(), &config).is_err()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn split_progress() -> Result<(), Error> {
+		use std::rc::Rc;
+		let reports = Rc::new(RefCell::new(vec![]));
+		let r = reports.clone();
+		let sp = Splitter::new(None).with_progress(move |p| r.borrow_mut().push(*p));
+		let secret = util::fill_vec_rand(600);
+		let shares = sp.split(3, 5, &secret)?;
+		assert_eq!(sp.recover(&shares[1..4], 3)?, secret);
+
+		let reports = reports.borrow();
+		// one random share, then 4 interpolated shares of 3 chunks each
+		assert_eq!(reports.len(), 1 + 4 * 4);
+		assert!(reports.iter().all(|p| p.group_index.is_none()));
+		assert!(reports
+			.iter()
+			.all(|p| p.share_count == 5 && p.byte_count == 600));
+		assert!(reports
+			.windows(2)
+			.all(|w| w[0].shares_done <= w[1].shares_done));
+		let chunks: Vec<usize> = reports[1..4].iter().map(|p| p.bytes_done).collect();
+		assert_eq!(chunks, vec![256, 512, 600]);
+		assert_eq!(reports.last().unwrap().shares_done, 5);
+		Ok(())
+	}
 }
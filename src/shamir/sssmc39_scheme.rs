@@ -14,16 +14,90 @@
 
 //! Functions and structs that specifically define the SLIPS-0039 scheme
 
-use super::{Share, Splitter};
+use super::limits::{MAX_IDENTIFIER, MAX_ITERATION_EXPONENT, MAX_SECRET_BYTES, MIN_SECRET_BYTES};
+use super::share::ShareConfig;
+use super::{
+	validate_layout, validate_master_secret, Format, ParseOptions, RawShare, SecretSharer, Share,
+	ShareSet, SplitProgress, Splitter, SplitterConfig,
+};
 use crate::error::{Error, ErrorKind};
 
-use std::collections::BTreeMap;
-use std::fmt;
+use rand::{thread_rng, Rng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::ops::Index;
+use std::time::{Duration, Instant};
 
 use crate::util;
+use crate::util::cancel::CancellationToken;
+use crate::util::ct;
+use crate::util::encrypt::{
+	KdfExecutor, KdfProfile, MasterSecretEnc, MasterSecretEncConfig, PassphraseNormalization,
+};
+use crate::util::secret::{Passphrase, SecretBytes};
+use crate::util::trace::{trace_event, Span};
+
+/// How the 15-bit identifier shared by all mnemonics of a share set is chosen
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum IdentifierPolicy {
+	/// A random identifier
+	#[default]
+	Random,
+	/// The given identifier, which must fit in 15 bits
+	Fixed(u16),
+	/// A random identifier that is not in the given list, e.g. the identifiers of
+	/// share sets already in use
+	Avoid(Vec<u16>),
+}
+
+impl IdentifierPolicy {
+	/// Choose an identifier according to the policy
+	pub fn resolve(&self) -> Result<u16, Error> {
+		let id_mask = (1u16 << ShareConfig::default().id_length_bits) - 1;
+		match self {
+			IdentifierPolicy::Random => Ok(thread_rng().gen::<u16>() & id_mask),
+			IdentifierPolicy::Fixed(id) => {
+				if *id > id_mask {
+					return Err(ErrorKind::Argument(format!(
+						"Identifier {} does not fit in 15 bits",
+						id
+					)))?;
+				}
+				Ok(*id)
+			}
+			IdentifierPolicy::Avoid(existing) => {
+				let existing: BTreeSet<u16> = existing.iter().cloned().collect();
+				let free: Vec<u16> = (0..=id_mask).filter(|id| !existing.contains(id)).collect();
+				if free.is_empty() {
+					return Err(ErrorKind::Argument(
+						"No identifiers left to choose from".to_string(),
+					))?;
+				}
+				Ok(free[thread_rng().gen_range(0..free.len())])
+			}
+		}
+	}
+}
+
+/// Result of [`generate_mnemonics_with_identifier`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedShares {
+	/// Identifier shared by all mnemonics of the set
+	pub identifier: u16,
+	/// Iteration exponent used for encryption
+	pub iteration_exponent: u8,
+	/// The groups of member shares
+	pub groups: Vec<GroupShare>,
+}
 
 /// Struct for returned shares
+/// Debug output relies on Share's, which redacts share values
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GroupShare {
 	/// Group id
 	pub group_id: u16,
@@ -39,29 +113,132 @@ pub struct GroupShare {
 	pub member_threshold: u8,
 	/// Member shares for the group
 	pub member_shares: Vec<Share>,
+	/// Optional non-secret label for the group (e.g. "Safe deposit box").
+	/// Never encoded into the mnemonics
+	pub label: Option<String>,
+}
+
+/// Options controlling how a GroupShare is rendered as text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+	/// Prefix each word with its 1-based position ("1. slavery 2. flea ...")
+	pub numbered: bool,
+	/// Lay each mnemonic out in rows of this many fixed-width columns,
+	/// instead of a single line. `Some(0)` is the same as `None`
+	pub columns: Option<usize>,
+	/// Separate member shares with a form feed, so each prints on its own page.
+	/// The group header (if included) is repeated on each page
+	pub page_breaks: bool,
+	/// Include the group header line
+	pub include_header: bool,
+	/// Follow each mnemonic with a line giving its fingerprint
+	pub fingerprints: bool,
+}
+
+impl Default for FormatOptions {
+	fn default() -> Self {
+		FormatOptions {
+			numbered: false,
+			columns: None,
+			page_breaks: false,
+			include_header: true,
+			fingerprints: false,
+		}
+	}
+}
+
+impl FormatOptions {
+	/// Defaults, equivalent to the Display output
+	pub fn new() -> Self {
+		FormatOptions {
+			..Default::default()
+		}
+	}
 }
 
 impl fmt::Display for GroupShare {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		writeln!(
-			f,
-			"Group {} of {} - {} of {} shares required: ",
+		match self.format(&FormatOptions::default()) {
+			Ok(s) => write!(f, "{}", s),
+			Err(_) => Err(fmt::Error),
+		}
+	}
+}
+
+impl GroupShare {
+	fn format_header(&self, out: &mut String) -> fmt::Result {
+		write!(
+			out,
+			"Group {} of {}",
 			self.group_index + 1,
-			self.group_count,
+			self.group_count
+		)?;
+		if let Some(l) = &self.label {
+			write!(out, " ({})", l)?;
+		}
+		writeln!(
+			out,
+			" - {} of {} shares required: ",
 			self.member_threshold,
 			self.member_shares.len()
-		)?;
-		for s in &self.member_shares {
-			for w in s.to_mnemonic().unwrap() {
-				write!(f, "{} ", w)?;
+		)
+	}
+
+	/// Render the group's member shares as text according to the given options
+	pub fn format(&self, opts: &FormatOptions) -> Result<String, Error> {
+		let fmt_err = |_| ErrorKind::Value("Unable to format group share".to_string());
+		let columns = opts.columns.filter(|&c| c > 0);
+		let mut out = String::new();
+		if opts.include_header {
+			self.format_header(&mut out).map_err(fmt_err)?;
+		}
+		for (i, s) in self.member_shares.iter().enumerate() {
+			if i > 0 && opts.page_breaks {
+				out.push('\x0c');
+				if opts.include_header {
+					self.format_header(&mut out).map_err(fmt_err)?;
+				}
+			}
+			if let Some(l) = &s.label {
+				write!(out, "[{}] ", l).map_err(fmt_err)?;
+				if columns.is_some() {
+					out.push('\n');
+				}
+			}
+			let words = s.to_mnemonic()?;
+			let num_width = words.len().to_string().len();
+			for (j, w) in words.iter().enumerate() {
+				let cell = if opts.numbered {
+					format!("{:>width$}. {}", j + 1, w, width = num_width)
+				} else {
+					w.to_owned()
+				};
+				match columns {
+					Some(c) => {
+						if (j + 1) % c == 0 || j + 1 == words.len() {
+							out.push_str(&cell);
+							out.push('\n');
+						} else {
+							write!(out, "{:<width$}  ", cell, width = num_width + 10)
+								.map_err(fmt_err)?;
+						}
+					}
+					None => {
+						out.push_str(&cell);
+						out.push(' ');
+					}
+				}
+			}
+			if columns.is_none() {
+				out.push('\n');
+			}
+			if opts.fingerprints {
+				writeln!(out, "Fingerprint: {}", s.fingerprint()?).map_err(fmt_err)?;
 			}
-			writeln!(f)?;
 		}
-		Ok(())
+		Ok(out)
 	}
-}
 
-impl GroupShare {
 	/// return list of mnemonics
 	pub fn mnemonic_list(&self) -> Result<Vec<Vec<String>>, Error> {
 		let mut ret_vec = vec![];
@@ -84,10 +261,200 @@ impl GroupShare {
 		Ok(ret_vec)
 	}
 
+	/// Attach a non-secret label to the group (e.g. "Safe deposit box")
+	pub fn set_label(&mut self, label: &str) {
+		self.label = Some(label.to_owned());
+	}
+
+	/// Attach a non-secret label to the member share with the given member index
+	/// (e.g. the name of the custodian holding it)
+	pub fn set_member_label(&mut self, member_index: u8, label: &str) -> Result<(), Error> {
+		match self
+			.member_shares
+			.iter_mut()
+			.find(|s| s.member_index == member_index)
+		{
+			Some(s) => {
+				s.label = Some(label.to_owned());
+				Ok(())
+			}
+			None => Err(ErrorKind::Argument(format!(
+				"Group {} contains no member share with index {}",
+				self.group_index, member_index,
+			)))?,
+		}
+	}
+
+	/// Iterate over the member shares
+	pub fn iter(&self) -> std::slice::Iter<'_, Share> {
+		self.member_shares.iter()
+	}
+
+	/// Number of member shares held
+	pub fn len(&self) -> usize {
+		self.member_shares.len()
+	}
+
+	/// Whether no member shares are held
+	pub fn is_empty(&self) -> bool {
+		self.member_shares.is_empty()
+	}
+
+	/// The member share with the given member index, if held
+	pub fn find_member(&self, member_index: u8) -> Option<&Share> {
+		self.member_shares
+			.iter()
+			.find(|s| s.member_index == member_index)
+	}
+
+	/// Number of further member shares needed to reach the member threshold. Shares
+	/// don't record the group's member count, so which members are missing can't
+	/// be known
+	pub fn missing_members(&self) -> usize {
+		let mut held: Vec<u8> = self.member_shares.iter().map(|s| s.member_index).collect();
+		held.sort_unstable();
+		held.dedup();
+		(self.member_threshold as usize).saturating_sub(held.len())
+	}
+
 	/// decode member shares to single share
 	pub fn decode_shares(&mut self) -> Result<Share, Error> {
+		self.decode_shares_with(&Splitter::new(None))
+			.map(|(s, _)| s)
+	}
+
+	/// Check the member shares still agree with each other, to catch corruption after
+	/// generation (e.g. bit flips in stored shares). The group secret is recovered
+	/// from the first `member_threshold` shares, verifying its digest, and every
+	/// further share is re-derived from them and compared. Returns the member indices
+	/// of the shares that don't match; an error means the reference shares themselves
+	/// are inconsistent
+	pub fn verify_integrity(&self) -> Result<Vec<u8>, Error> {
+		let threshold = self.member_threshold as usize;
+		if self.member_shares.len() < threshold {
+			return Err(ErrorKind::Value(format!(
+				"Group {} has {} of the {} member shares needed",
+				self.group_index + 1,
+				self.member_shares.len(),
+				threshold
+			)))?;
+		}
 		let sp = Splitter::new(None);
-		sp.recover_secret(&self.member_shares, self.member_threshold)
+		let (reference, rest) = self.member_shares.split_at(threshold);
+		sp.recover_secret(reference, self.member_threshold)?;
+		let raw: Vec<RawShare> = reference
+			.iter()
+			.map(|s| (s.member_index, s.share_value.clone()))
+			.collect();
+		let mut mismatched = vec![];
+		for s in rest {
+			let expected = sp.interpolate(&raw, s.member_index)?;
+			if !ct::ct_eq(&expected, &s.share_value) {
+				mismatched.push(s.member_index);
+			}
+		}
+		Ok(mismatched)
+	}
+
+	// decode the member shares, returning the group share and the member indices of
+	// any outliers. If the shares don't all agree but a strict majority of them do
+	// (and meet the threshold), the majority's secret is used
+	fn decode_shares_with(&self, sp: &dyn SecretSharer) -> Result<(Share, Vec<u8>), Error> {
+		let err = match sp.recover_secret(&self.member_shares, self.member_threshold) {
+			Ok(s) => return Ok((s, vec![])),
+			Err(e) => e,
+		};
+		let (secret, supporters) = match self.majority(sp) {
+			Some(m) => m,
+			None => return Err(err),
+		};
+		let outliers: Vec<u8> = (0..self.member_shares.len())
+			.filter(|i| supporters >> i & 1 == 0)
+			.map(|i| self.member_shares[i].member_index)
+			.collect();
+		if supporters.count_ones() as usize * 2 > self.member_shares.len() {
+			trace_event!(
+				"combine: group {} recovered by majority, outliers {:?}",
+				self.group_index,
+				outliers
+			);
+			Ok((secret, outliers))
+		} else {
+			Err(ErrorKind::InconsistentShares(self.group_index, outliers))?
+		}
+	}
+
+	// With more member shares than the threshold, recover from each threshold-sized
+	// subset whose digest verifies and find which shares agree with it, returning
+	// the secret agreed on by the most shares and a mask of those shares
+	fn majority(&self, sp: &dyn SecretSharer) -> Option<(Share, u32)> {
+		let threshold = self.member_threshold as usize;
+		let shares = &self.member_shares;
+		if shares.len() <= threshold || shares.len() > 16 {
+			return None;
+		}
+		let mut best: Option<(Share, u32)> = None;
+		let mut covered: Vec<u32> = vec![];
+		for mask in 0u32..1 << shares.len() {
+			if mask.count_ones() as usize != threshold || covered.iter().any(|c| mask & c == mask) {
+				continue;
+			}
+			let mut subset: Vec<Share> = (0..shares.len())
+				.filter(|i| mask >> i & 1 == 1)
+				.map(|i| shares[i].clone())
+				.collect();
+			let secret = match sp.recover_secret(&subset, self.member_threshold) {
+				Ok(s) => s,
+				Err(_) => continue,
+			};
+			// a share agrees if it can stand in for one of the subset's shares
+			let mut supporters = mask;
+			for (i, s) in shares.iter().enumerate() {
+				if mask >> i & 1 == 1 {
+					continue;
+				}
+				subset[threshold - 1] = s.clone();
+				if let Ok(r) = sp.recover_secret(&subset, self.member_threshold) {
+					if r.share_value == secret.share_value {
+						supporters |= 1 << i;
+					}
+				}
+			}
+			covered.push(supporters);
+			if best
+				.as_ref()
+				.is_none_or(|(_, b)| supporters.count_ones() > b.count_ones())
+			{
+				best = Some((secret, supporters));
+			}
+			if supporters.count_ones() as usize * 2 > shares.len() {
+				break;
+			}
+		}
+		best
+	}
+}
+
+impl Index<usize> for GroupShare {
+	type Output = Share;
+	fn index(&self, i: usize) -> &Share {
+		&self.member_shares[i]
+	}
+}
+
+impl IntoIterator for GroupShare {
+	type Item = Share;
+	type IntoIter = std::vec::IntoIter<Share>;
+	fn into_iter(self) -> Self::IntoIter {
+		self.member_shares.into_iter()
+	}
+}
+
+impl<'a> IntoIterator for &'a GroupShare {
+	type Item = &'a Share;
+	type IntoIter = std::slice::Iter<'a, Share>;
+	fn into_iter(self) -> Self::IntoIter {
+		self.member_shares.iter()
 	}
 }
 
@@ -99,57 +466,240 @@ impl GroupShare {
 /// master_secret: The master secret to split.
 /// passphrase: The passphrase used to encrypt the master secret.
 /// iteration_exponent: The iteration exponent.
-/// return: List of mnemonics.
+/// return: List of mnemonics, with groups in order of group index and member shares in
+/// order of member index (see [`ShareOrdering`](crate::ShareOrdering) to shuffle them).
 pub fn generate_mnemonics(
 	group_threshold: u8,
 	groups: &[(u8, u8)],
 	master_secret: &[u8],
-	passphrase: &str,
+	passphrase: impl Into<Passphrase>,
 	iteration_exponent: u8,
 ) -> Result<Vec<GroupShare>, Error> {
-	// Generate a 'proto share' so to speak, with identifer generated and group data filled
-	let mut proto_share = Share::new()?;
-	proto_share.group_threshold = group_threshold;
-	proto_share.group_count = groups.len() as u8;
+	generate_mnemonics_with_config(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		&SplitterConfig::new(),
+		&MasterSecretEncConfig::new(),
+	)
+}
 
-	if master_secret.len() * 8 < proto_share.config.min_strength_bits as usize {
-		return Err(ErrorKind::Value(format!(
-			"The length of the master secret ({} bytes) must be at least {} bytes.",
-			master_secret.len(),
-			(f64::from(proto_share.config.min_strength_bits) / 8f64).ceil(),
-		)))?;
-	}
+/// As [`generate_mnemonics`], splitting with the given splitter configuration and
+/// encrypting with the given encryption configuration (e.g. to select a KDF backend).
+/// Note that non-default options such as
+/// [`threshold_one_digest`](SplitterConfig::threshold_one_digest) produce shares
+/// that can only be combined with [`combine_mnemonics_with_config`] using the same
+/// configuration.
+pub fn generate_mnemonics_with_config(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	config: &SplitterConfig,
+	enc_config: &MasterSecretEncConfig,
+) -> Result<Vec<GroupShare>, Error> {
+	generate_shares(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		&Splitter::new(Some(config)),
+		enc_config,
+		IdentifierPolicy::Random.resolve()?,
+	)
+}
 
-	if master_secret.len() % 2 != 0 {
-		return Err(ErrorKind::Value(
-			"The length of the master secret in bytes must be an even number".to_string(),
-		))?;
-	}
+/// As [`generate_mnemonics`], calling `progress` as each share is computed (and, for
+/// long secrets, as each chunk of a share is), e.g. to drive a progress bar
+pub fn generate_mnemonics_with_progress(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	progress: impl Fn(&SplitProgress) + 'static,
+) -> Result<Vec<GroupShare>, Error> {
+	generate_shares(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		&Splitter::new(None).with_progress(progress),
+		&MasterSecretEncConfig::new(),
+		IdentifierPolicy::Random.resolve()?,
+	)
+}
 
-	if group_threshold as usize > groups.len() {
-		return Err(ErrorKind::Value(format!(
-			"The requested group threshold ({}) must not exceed the number of groups ({}).",
-			group_threshold,
-			groups.len()
-		)))?;
-	}
+/// As [`generate_mnemonics`], choosing the share set identifier according to the given
+/// policy. The identifier is returned alongside the groups
+pub fn generate_mnemonics_with_identifier(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	identifier: &IdentifierPolicy,
+) -> Result<GeneratedShares, Error> {
+	let identifier = identifier.resolve()?;
+	let groups = generate_shares(
+		group_threshold,
+		groups,
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		&Splitter::new(None),
+		&MasterSecretEncConfig::new(),
+		identifier,
+	)?;
+	Ok(GeneratedShares {
+		identifier,
+		iteration_exponent,
+		groups,
+	})
+}
 
-	let encoder = util::encrypt::MasterSecretEnc::new()?;
+#[allow(clippy::too_many_arguments)]
+fn generate_shares(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	splitter: &Splitter,
+	enc_config: &MasterSecretEncConfig,
+	identifier: u16,
+) -> Result<Vec<GroupShare>, Error> {
+	let _span = Span::enter("generate");
+	#[cfg(feature = "self-test")]
+	crate::self_test::ensure_passed()?;
+	let config = splitter.config();
+	// check the layout before spending time on encryption
+	config.validate_slip39()?;
+	validate_layout(group_threshold, groups, config.threshold_one_digest)?;
+	let ems = encrypt_with_config(
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		enc_config,
+		identifier,
+	)?;
+	split_ems_with(&ems, group_threshold, groups, splitter)
+}
 
-	let encrypted_master_secret = encoder.encrypt(
+/// First stage of [`generate_mnemonics`]: encrypt the master secret with the
+/// passphrase under a new random identifier. The result can be split into shares
+/// later, or on another device, with [`split_ems`]
+pub fn encrypt_master_secret(
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<EncryptedMasterSecret, Error> {
+	encrypt_with_config(
 		master_secret,
 		passphrase,
 		iteration_exponent,
-		proto_share.identifier,
+		&MasterSecretEncConfig::new(),
+		IdentifierPolicy::Random.resolve()?,
+	)
+}
+
+/// Second stage of [`generate_mnemonics`]: split an encrypted master secret into
+/// groups of shares
+pub fn split_ems(
+	ems: &EncryptedMasterSecret,
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+) -> Result<Vec<GroupShare>, Error> {
+	split_ems_with_config(ems, group_threshold, groups, &SplitterConfig::new())
+}
+
+pub(crate) fn encrypt_with_config(
+	master_secret: &[u8],
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+	enc_config: &MasterSecretEncConfig,
+	identifier: u16,
+) -> Result<EncryptedMasterSecret, Error> {
+	let strength = validate_master_secret(master_secret)?;
+	for w in &strength.warnings {
+		warn!("Splitting a possibly weak secret: {}", w);
+	}
+	let passphrase = passphrase.into();
+	enc_config
+		.passphrase_normalization
+		.check(passphrase.as_str())?;
+	enc_config.profile.check_exponent(iteration_exponent)?;
+	let encoder = MasterSecretEnc::with_config(enc_config);
+	let value = encoder.encrypt(
+		master_secret,
+		passphrase.as_str(),
+		iteration_exponent,
+		identifier,
 	);
+	// non-standard KDF profiles need the extended format to record them
+	let (format, kdf_profile) = match enc_config.profile {
+		KdfProfile::Slip39 => (Format::Slip39, 0),
+		#[cfg(feature = "kdf-argon2")]
+		p => (Format::ExtendedV2, p.to_byte()),
+	};
+	Ok(EncryptedMasterSecret {
+		identifier,
+		iteration_exponent,
+		format,
+		kdf_profile,
+		groups: vec![],
+		value: SecretBytes::new(value),
+	})
+}
+
+fn split_ems_with_config(
+	ems: &EncryptedMasterSecret,
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	config: &SplitterConfig,
+) -> Result<Vec<GroupShare>, Error> {
+	split_ems_with(ems, group_threshold, groups, &Splitter::new(Some(config)))
+}
+
+/// Split an encrypted master secret with the given splitter
+pub(crate) fn split_ems_with(
+	ems: &EncryptedMasterSecret,
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	splitter: &Splitter,
+) -> Result<Vec<GroupShare>, Error> {
+	let config = splitter.config();
+	config.validate_slip39()?;
+	validate_layout(group_threshold, groups, config.threshold_one_digest)?;
+	trace_event!(
+		"split: identifier {}, {} of {} groups",
+		ems.identifier,
+		group_threshold,
+		groups.len()
+	);
+	// Generate a 'proto share' so to speak, with group data filled
+	let mut proto_share = Share::new()?;
+	proto_share.identifier = ems.identifier;
+	proto_share.iteration_exponent = ems.iteration_exponent;
+	proto_share.group_threshold = group_threshold;
+	proto_share.group_count = groups.len() as u8;
+	proto_share.format = ems.format;
+	proto_share.kdf_profile = ems.kdf_profile;
+	let iteration_exponent = ems.iteration_exponent;
 
-	let sp = Splitter::new(None);
+	let sp: &dyn SecretSharer = splitter;
 
+	splitter.set_progress_group(None);
 	let group_shares = sp.split_secret(
 		&proto_share,
 		group_threshold,
 		groups.len() as u8,
-		&encrypted_master_secret,
+		&ems.value,
 	)?;
 
 	let mut retval: Vec<GroupShare> = vec![];
@@ -160,12 +710,20 @@ pub fn generate_mnemonics(
 		proto_share.group_threshold = group_threshold;
 		proto_share.group_count = gs_len as u8;
 		let (member_threshold, member_count) = groups[i];
-		let member_shares = sp.split_secret(
+		splitter.set_progress_group(Some(i as u8));
+		trace_event!(
+			"split: group {}, {} of {} members",
+			i + 1,
+			member_threshold,
+			member_count
+		);
+		let mut member_shares = sp.split_secret(
 			&proto_share,
 			member_threshold,
 			member_count,
 			&elem.share_value,
 		)?;
+		splitter.order_shares(&mut member_shares);
 		retval.push(GroupShare {
 			group_id: proto_share.identifier,
 			iteration_exponent,
@@ -174,8 +732,10 @@ pub fn generate_mnemonics(
 			group_count: gs_len as u8,
 			member_threshold,
 			member_shares,
+			label: None,
 		});
 	}
+	splitter.set_progress_group(None);
 
 	Ok(retval)
 }
@@ -184,18 +744,18 @@ pub fn generate_mnemonics_random(
 	group_threshold: u8,
 	groups: &[(u8, u8)],
 	strength_bits: u16,
-	passphrase: &str,
+	passphrase: impl Into<Passphrase>,
 	iteration_exponent: u8,
 ) -> Result<Vec<GroupShare>, Error> {
 	let proto_share = Share::new()?;
 	if strength_bits < proto_share.config.min_strength_bits {
-		return Err(ErrorKind::Value(format!(
+		Err(ErrorKind::Value(format!(
 			"The requested strength of the master secret({} bits) must be at least {} bits.",
 			strength_bits, proto_share.config.min_strength_bits,
 		)))?;
 	}
-	if strength_bits % 16 != 0 {
-		return Err(ErrorKind::Value(format!(
+	if !strength_bits.is_multiple_of(16) {
+		Err(ErrorKind::Value(format!(
 			"The requested strength of the master secret({} bits) must be a multiple of 16 bits.",
 			strength_bits,
 		)))?;
@@ -209,148 +769,676 @@ pub fn generate_mnemonics_random(
 	)
 }
 
+/// Decodes a hex-encoded master secret (as provided by wallet export formats) and splits it
+/// as with [`generate_mnemonics`]. Invalid characters and odd-length strings are reported
+/// as distinct [`ErrorKind::Hex`] errors
+pub fn generate_mnemonics_from_hex(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	hex_secret: &str,
+	passphrase: impl Into<Passphrase>,
+	iteration_exponent: u8,
+) -> Result<Vec<GroupShare>, Error> {
+	let master_secret = SecretBytes::new(util::hex::from_hex(hex_secret)?);
+	generate_mnemonics(
+		group_threshold,
+		groups,
+		&master_secret,
+		passphrase,
+		iteration_exponent,
+	)
+}
+
 /// Combines mnemonic shares to obtain the master secret which was previously split using
 /// Shamir's secret sharing scheme.
 /// mnemonics: List of mnemonics.
 /// passphrase: The passphrase used to encrypt the master secret.
-/// return: The master secret.
-pub fn combine_mnemonics(mnemonics: &[Vec<String>], passphrase: &str) -> Result<Vec<u8>, Error> {
-	let group_shares = decode_mnemonics(mnemonics)?;
-	let mut shares = vec![];
-	for mut gs in group_shares {
-		shares.push(gs.decode_shares()?);
-	}
-	let sp = Splitter::new(None);
-	// restore proper member index for groups
-	let shares = shares
-		.into_iter()
-		.map(|mut s| {
-			s.member_index = s.group_index;
-			s
-		})
-		.collect::<Vec<_>>();
-	let ems = sp.recover_secret(&shares, shares[0].group_threshold)?;
-	let encoder = util::encrypt::MasterSecretEnc::new()?;
-	let dms = encoder.decrypt(
-		&ems.share_value,
+/// return: The master secret, along with details of the shares that were used to recover it.
+pub fn combine_mnemonics(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	combine_mnemonics_with_config(
+		mnemonics,
 		passphrase,
-		ems.iteration_exponent,
-		ems.identifier,
-	);
-	Ok(dms)
+		&SplitterConfig::new(),
+		&MasterSecretEncConfig::new(),
+	)
 }
 
-/// Decodes all Mnemonics to a list of shares and performs error checking
-fn decode_mnemonics(mnemonics: &[Vec<String>]) -> Result<Vec<GroupShare>, Error> {
-	let mut shares = vec![];
-	if mnemonics.is_empty() {
-		return Err(ErrorKind::Mnemonic(
-			"List of mnemonics is empty.".to_string(),
-		))?;
+/// As [`combine_mnemonics`], recovering with the given splitter and encryption
+/// configurations
+pub fn combine_mnemonics_with_config(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	config: &SplitterConfig,
+	enc_config: &MasterSecretEncConfig,
+) -> Result<RecoveredSecret, Error> {
+	recover_ems(mnemonics, config)?.decrypt_with_config(passphrase, enc_config)
+}
+
+/// As [`combine_mnemonics`], parsing the mnemonics with the given options (e.g. those
+/// of a [`ParseProfile`](crate::ParseProfile))
+pub fn combine_mnemonics_with_options(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	opts: &ParseOptions,
+) -> Result<RecoveredSecret, Error> {
+	combine_share_set(
+		&ShareSet::from_mnemonics_with_options(mnemonics, opts)?,
+		passphrase,
+	)
+}
+
+/// As [`combine_mnemonics`], failing with [`ErrorKind::Cancelled`] soon after the
+/// token is cancelled from another thread, so long KDF runs can be aborted
+pub fn combine_mnemonics_cancellable(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	cancel: &CancellationToken,
+) -> Result<RecoveredSecret, Error> {
+	cancel.check()?;
+	recover_ems(mnemonics, &SplitterConfig::new())?.decrypt_cancellable(
+		passphrase,
+		&MasterSecretEncConfig::new(),
+		cancel,
+	)
+}
+
+/// As [`combine_mnemonics`], where some shares are PIN protected. `pin_for` is asked
+/// for the PIN of each protected share (e.g. by prompting its custodian)
+pub fn combine_mnemonics_with_pins(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+	pin_for: &mut dyn FnMut(&Share) -> Result<String, Error>,
+) -> Result<RecoveredSecret, Error> {
+	// protected mnemonics are longer, so lengths are only comparable once unlocked
+	let shares = ShareSet::from_mnemonics(mnemonics)?.unprotect_with_pins(pin_for)?;
+	combine_share_set(&shares, passphrase)
+}
+
+/// As [`combine_mnemonics`], for shares already gathered into a [`ShareSet`]
+pub fn combine_share_set(
+	shares: &ShareSet,
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	recover_ems_from_set(shares, &SplitterConfig::new())?.decrypt(passphrase)
+}
+
+/// Recover the encrypted master secret once, then decrypt it with each of the given
+/// passphrases in turn, returning one secret per passphrase. Every passphrase yields a
+/// valid-looking secret, which is what allows a decoy (duress) passphrase to protect a
+/// different wallet than the real one
+pub fn decrypt_with_each<P: Into<Passphrase>>(
+	mnemonics: &[Vec<String>],
+	passphrases: impl IntoIterator<Item = P>,
+) -> Result<Vec<RecoveredSecret>, Error> {
+	let ems = recover_ems(mnemonics, &SplitterConfig::new())?;
+	passphrases.into_iter().map(|p| ems.decrypt(p)).collect()
+}
+
+/// First stage of [`combine_mnemonics`]: decode the mnemonics and interpolate the
+/// encrypted master secret, without needing the passphrase. The result can be
+/// serialized and decrypted elsewhere (e.g. on an air-gapped device) with
+/// [`decrypt_ems`]
+pub fn combine_to_ems(mnemonics: &[Vec<String>]) -> Result<EncryptedMasterSecret, Error> {
+	recover_ems(mnemonics, &SplitterConfig::new())
+}
+
+/// Second stage of [`combine_mnemonics`]: decrypt an encrypted master secret with
+/// the passphrase
+pub fn decrypt_ems(
+	ems: &EncryptedMasterSecret,
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	ems.decrypt(passphrase)
+}
+
+/// Version byte of the [`EncryptedMasterSecret::to_bytes`] encoding
+const EMS_BYTES_VERSION: u8 = 1;
+/// Length of the header preceding the value in the byte encoding
+const EMS_HEADER_LEN: usize = 6;
+
+/// The encrypted master secret interpolated from a set of shares, along with the
+/// share set metadata needed to decrypt it. Decrypting is cheap to retry (e.g. with
+/// a corrected passphrase) since the shares don't need to be decoded again.
+/// Without a passphrase, the EMS is as sensitive as the master secret itself.
+/// Deserializing checks the fields as [`from_bytes`](EncryptedMasterSecret::from_bytes)
+/// does
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "serde",
+	derive(Serialize, Deserialize),
+	serde(try_from = "EmsFields")
+)]
+pub struct EncryptedMasterSecret {
+	/// Identifier of the share set
+	pub identifier: u16,
+	/// Iteration exponent of the share set
+	pub iteration_exponent: u8,
+	/// Share format, which determines how the KDF profile is chosen
+	pub format: Format,
+	/// KDF profile byte of extended format shares
+	pub kdf_profile: u8,
+	/// Groups (and their member shares) that contributed to the recovery. Not
+	/// included in the byte encoding
+	pub groups: Vec<RecoveredGroup>,
+	/// The encrypted master secret
+	pub value: SecretBytes,
+}
+
+// the fields of a deserialized EMS, before they're checked
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct EmsFields {
+	identifier: u16,
+	iteration_exponent: u8,
+	format: Format,
+	kdf_profile: u8,
+	#[serde(default)]
+	groups: Vec<RecoveredGroup>,
+	value: SecretBytes,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<EmsFields> for EncryptedMasterSecret {
+	type Error = Error;
+	fn try_from(f: EmsFields) -> Result<Self, Error> {
+		let ems = EncryptedMasterSecret {
+			identifier: f.identifier,
+			iteration_exponent: f.iteration_exponent,
+			format: f.format,
+			kdf_profile: f.kdf_profile,
+			groups: f.groups,
+			value: f.value,
+		};
+		ems.check()?;
+		Ok(ems)
 	}
-	let check_len = mnemonics[0].len();
-	for m in mnemonics {
-		if m.len() != check_len {
-			return Err(ErrorKind::Mnemonic(
-				"Invalid set of mnemonics. All mnemonics must have the same length.".to_string(),
+}
+
+impl EncryptedMasterSecret {
+	/// Encode as bytes: a version byte, the 2 byte big-endian identifier, iteration
+	/// exponent, format and KDF profile bytes, then the encrypted value
+	pub fn to_bytes(&self) -> SecretBytes {
+		let mut bytes = Vec::with_capacity(EMS_HEADER_LEN + self.value.len());
+		bytes.push(EMS_BYTES_VERSION);
+		bytes.extend_from_slice(&self.identifier.to_be_bytes());
+		bytes.push(self.iteration_exponent);
+		bytes.push(match self.format {
+			Format::Slip39 => 0,
+			Format::ExtendedV2 => 1,
+		});
+		bytes.push(self.kdf_profile);
+		bytes.extend_from_slice(&self.value);
+		SecretBytes::new(bytes)
+	}
+
+	/// Decode from the [`to_bytes`](EncryptedMasterSecret::to_bytes) encoding
+	pub fn from_bytes(bytes: &[u8]) -> Result<EncryptedMasterSecret, Error> {
+		if bytes.len() < EMS_HEADER_LEN || bytes[0] != EMS_BYTES_VERSION {
+			return Err(ErrorKind::Value(
+				"Invalid encrypted master secret encoding".to_string(),
 			))?;
 		}
-		shares.push(Share::from_mnemonic(m)?);
+		let format = match bytes[4] {
+			0 => Format::Slip39,
+			1 => Format::ExtendedV2,
+			f => {
+				return Err(ErrorKind::Value(format!(
+					"Unknown encrypted master secret format {}",
+					f
+				)))?
+			}
+		};
+		let ems = EncryptedMasterSecret {
+			identifier: u16::from_be_bytes([bytes[1], bytes[2]]),
+			iteration_exponent: bytes[3],
+			format,
+			kdf_profile: bytes[5],
+			groups: vec![],
+			value: SecretBytes::from(&bytes[EMS_HEADER_LEN..]),
+		};
+		ems.check()?;
+		Ok(ems)
 	}
 
-	let check_share = shares[0].clone();
-	for s in shares.iter() {
-		if s.identifier != check_share.identifier
-			|| s.iteration_exponent != check_share.iteration_exponent
-		{
-			return Err(ErrorKind::Mnemonic(format!(
-				"Invalid set of mnemonics. All mnemonics must begin with the same {} words. \
-				 (Identifier and iteration exponent must be the same).",
-				s.config.id_exp_length_words,
+	// check the fields are in range, as for a share set's EMS
+	fn check(&self) -> Result<(), Error> {
+		let len = self.value.len();
+		if !(MIN_SECRET_BYTES..=MAX_SECRET_BYTES).contains(&len) || !len.is_multiple_of(2) {
+			return Err(ErrorKind::SecretLength(len))?;
+		}
+		if self.identifier > MAX_IDENTIFIER {
+			return Err(ErrorKind::Value(format!(
+				"Identifier {} is out of range (maximum {})",
+				self.identifier, MAX_IDENTIFIER
 			)))?;
 		}
-		if s.group_threshold != check_share.group_threshold {
-			return Err(ErrorKind::Mnemonic(
-				"Invalid set of mnemonics. All mnemonics must have the same group threshold"
-					.to_string(),
-			))?;
+		if self.iteration_exponent > MAX_ITERATION_EXPONENT {
+			return Err(ErrorKind::Value(format!(
+				"Iteration exponent {} is out of range (maximum {})",
+				self.iteration_exponent, MAX_ITERATION_EXPONENT
+			)))?;
 		}
-		if s.group_count != check_share.group_count {
-			return Err(ErrorKind::Mnemonic(
-				"Invalid set of mnemonics. All mnemonics must have the same group count"
-					.to_string(),
-			))?;
+		let known_profile = match self.format {
+			Format::Slip39 => self.kdf_profile == 0,
+			Format::ExtendedV2 => KdfProfile::from_byte(self.kdf_profile).is_some(),
+		};
+		if !known_profile {
+			return Err(ErrorKind::Value(format!(
+				"Unsupported KDF profile {:#04x}",
+				self.kdf_profile
+			)))?;
 		}
+		Ok(())
+	}
+
+	/// Decrypt with the given passphrase
+	pub fn decrypt(&self, passphrase: impl Into<Passphrase>) -> Result<RecoveredSecret, Error> {
+		self.decrypt_with_config(passphrase, &MasterSecretEncConfig::new())
 	}
 
-	let mut group_index_map = BTreeMap::new();
+	/// Decrypt with the given passphrase and encryption configuration. The KDF
+	/// profile recorded in extended format shares overrides the configured one
+	pub fn decrypt_with_config(
+		&self,
+		passphrase: impl Into<Passphrase>,
+		enc_config: &MasterSecretEncConfig,
+	) -> Result<RecoveredSecret, Error> {
+		self.decrypt_with(passphrase.into(), enc_config, None)
+	}
 
-	for s in shares {
-		if !group_index_map.contains_key(&s.group_index) {
-			let group_share = GroupShare {
-				group_id: s.identifier,
-				group_index: s.group_index,
-				group_threshold: s.group_threshold,
-				iteration_exponent: s.iteration_exponent,
-				group_count: s.group_count,
-				member_shares: vec![s.clone()],
-				member_threshold: s.member_threshold,
+	/// As [`decrypt_with_config`](EncryptedMasterSecret::decrypt_with_config), failing
+	/// with [`ErrorKind::Cancelled`] soon after the token is cancelled, e.g. from a
+	/// recovery dialog's Cancel button
+	pub fn decrypt_cancellable(
+		&self,
+		passphrase: impl Into<Passphrase>,
+		enc_config: &MasterSecretEncConfig,
+		cancel: &CancellationToken,
+	) -> Result<RecoveredSecret, Error> {
+		self.decrypt_with(passphrase.into(), enc_config, Some(cancel))
+	}
+
+	/// As [`decrypt_with_config`](EncryptedMasterSecret::decrypt_with_config),
+	/// running the KDF rounds on `executor` and passing the result to `done`, which
+	/// may be called on another thread. Configuration and passphrase errors are
+	/// passed to `done` straight away
+	pub fn decrypt_on(
+		&self,
+		executor: &dyn KdfExecutor,
+		passphrase: impl Into<Passphrase>,
+		enc_config: &MasterSecretEncConfig,
+		done: impl FnOnce(Result<RecoveredSecret, Error>) + Send + 'static,
+	) {
+		let passphrase = passphrase.into();
+		let encoder = match self.encoder(&passphrase, enc_config) {
+			Ok(e) => e,
+			Err(e) => return done(Err(e)),
+		};
+		let ems = EncryptedMasterSecret {
+			value: SecretBytes::zeroed(0),
+			..self.clone()
+		};
+		encoder.decrypt_on(
+			executor,
+			&self.value,
+			passphrase.as_str(),
+			self.iteration_exponent,
+			self.identifier,
+			move |dms| done(Ok(ems.recovered(dms))),
+		);
+	}
+
+	fn decrypt_with(
+		&self,
+		passphrase: Passphrase,
+		enc_config: &MasterSecretEncConfig,
+		cancel: Option<&CancellationToken>,
+	) -> Result<RecoveredSecret, Error> {
+		let encoder = self.encoder(&passphrase, enc_config)?;
+		let dms = match cancel {
+			Some(c) => encoder.decrypt_cancellable(
+				&self.value,
+				passphrase.as_str(),
+				self.iteration_exponent,
+				self.identifier,
+				c,
+			)?,
+			None => encoder.decrypt(
+				&self.value,
+				passphrase.as_str(),
+				self.iteration_exponent,
+				self.identifier,
+			),
+		};
+		Ok(self.recovered(dms))
+	}
+
+	// the encoder for this EMS, checking the passphrase and profile
+	fn encoder(
+		&self,
+		passphrase: &Passphrase,
+		enc_config: &MasterSecretEncConfig,
+	) -> Result<MasterSecretEnc, Error> {
+		let mut enc_config = enc_config.clone();
+		if self.format == Format::ExtendedV2 {
+			enc_config.profile = match KdfProfile::from_byte(self.kdf_profile) {
+				Some(p) => p,
+				None => {
+					return Err(ErrorKind::Config(format!(
+						"Unsupported KDF profile {:#04x}",
+						self.kdf_profile
+					)))?
+				}
 			};
-			group_index_map.insert(group_share.group_index, group_share);
-		} else {
-			let e = group_index_map.get_mut(&s.group_index).unwrap();
-			e.member_shares.push(s);
 		}
+		enc_config
+			.passphrase_normalization
+			.check(passphrase.as_str())?;
+		enc_config.profile.check_exponent(self.iteration_exponent)?;
+		Ok(MasterSecretEnc::with_config(&enc_config))
 	}
 
-	if group_index_map.len() < check_share.group_threshold as usize {
-		return Err(ErrorKind::Mnemonic(format!(
-			"Insufficient number of mnemonic groups ({}). The required number \
-			 of groups is {}.",
-			group_index_map.len(),
-			check_share.group_threshold,
-		)))?;
+	// the recovery result for the decrypted master secret
+	fn recovered(&self, dms: SecretBytes) -> RecoveredSecret {
+		RecoveredSecret {
+			identifier: self.identifier,
+			iteration_exponent: self.iteration_exponent,
+			groups: self.groups.clone(),
+			secret: dms,
+		}
 	}
+}
+
+/// Decode the mnemonics and interpolate the encrypted master secret, checking the
+/// digests at both group and member level
+pub(crate) fn recover_ems(
+	mnemonics: &[Vec<String>],
+	config: &SplitterConfig,
+) -> Result<EncryptedMasterSecret, Error> {
+	recover_ems_from_set(&decode_mnemonics(mnemonics)?, config)
+}
 
-	let groups: Vec<GroupShare> = group_index_map
+/// One interpolation performed during a recovery: of a group secret from member
+/// shares, or of the encrypted master secret from group secrets. Holds only share
+/// indices, thresholds and outcomes, never share values
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InterpolationRecord {
+	/// Group whose secret was interpolated, or None for the encrypted master secret
+	pub group_index: Option<u8>,
+	/// Threshold of the interpolated secret
+	pub threshold: u8,
+	/// x-coordinates (member or group indices) of the shares used
+	pub x_coordinates: Vec<u8>,
+	/// x-coordinates of shares left out because they disagreed with the majority
+	pub outliers: Vec<u8>,
+	/// Whether a digest was checked (SLIP-39 has none for a threshold of 1)
+	pub digest_checked: bool,
+	/// Whether the interpolation succeeded, including the digest check
+	pub verified: bool,
+	/// Time taken
+	pub elapsed: Duration,
+}
+
+// interpolate the encrypted master secret from a set of shares
+pub(crate) fn recover_ems_from_set(
+	shares: &ShareSet,
+	config: &SplitterConfig,
+) -> Result<EncryptedMasterSecret, Error> {
+	recover_ems_recorded(shares, config, &mut |_| ())
+}
+
+// as recover_ems_from_set, passing a record of each interpolation to `record`
+pub(crate) fn recover_ems_recorded(
+	shares: &ShareSet,
+	config: &SplitterConfig,
+	record: &mut dyn FnMut(InterpolationRecord),
+) -> Result<EncryptedMasterSecret, Error> {
+	let _span = Span::enter("combine");
+	config.validate_slip39()?;
+	if let Some(s) = shares.iter().find(|s| s.is_pin_protected()) {
+		Err(ErrorKind::Argument(format!(
+			"Share {} of group {} is PIN protected and must be unlocked first",
+			s.member_index + 1,
+			s.group_index + 1
+		)))?;
+	}
+	let sp: &dyn SecretSharer = &Splitter::new(Some(config));
+	let group_shares = shares.group_shares()?;
+	trace_event!(
+		"combine: {} groups meet their member threshold",
+		group_shares.len()
+	);
+	let mut shares = vec![];
+	let mut groups = vec![];
+	for gs in group_shares {
+		let start = Instant::now();
+		let decoded = gs.decode_shares_with(sp);
+		let outliers = match &decoded {
+			Ok((_, o)) => o.clone(),
+			Err(_) => vec![],
+		};
+		let member_indices: Vec<u8> = gs
+			.member_shares
+			.iter()
+			.map(|s| s.member_index)
+			.filter(|i| !outliers.contains(i))
+			.collect();
+		record(InterpolationRecord {
+			group_index: Some(gs.group_index),
+			threshold: gs.member_threshold,
+			x_coordinates: member_indices.clone(),
+			outliers: outliers.clone(),
+			digest_checked: gs.member_threshold > 1,
+			verified: decoded.is_ok(),
+			elapsed: start.elapsed(),
+		});
+		let (share, _) = decoded?;
+		groups.push(RecoveredGroup {
+			group_index: gs.group_index,
+			member_indices,
+			outliers,
+		});
+		shares.push(share);
+	}
+	// restore proper member index for groups
+	let shares = shares
 		.into_iter()
-		.map(|g| g.1)
-		// remove groups where number of shares is below the member threshold
-		.filter(|g| g.member_shares.len() >= g.member_threshold as usize)
-		.collect();
+		.map(|mut s| {
+			s.member_index = s.group_index;
+			s
+		})
+		.collect::<Vec<_>>();
+	let start = Instant::now();
+	let ems = sp.recover_secret(&shares, shares[0].group_threshold);
+	record(InterpolationRecord {
+		group_index: None,
+		threshold: shares[0].group_threshold,
+		x_coordinates: shares.iter().map(|s| s.group_index).collect(),
+		outliers: vec![],
+		digest_checked: shares[0].group_threshold > 1,
+		verified: ems.is_ok(),
+		elapsed: start.elapsed(),
+	});
+	let ems = ems?;
+	Ok(EncryptedMasterSecret {
+		identifier: ems.identifier,
+		iteration_exponent: ems.iteration_exponent,
+		format: ems.format,
+		kdf_profile: ems.kdf_profile,
+		groups,
+		value: SecretBytes::new(ems.share_value),
+	})
+}
 
-	if groups.len() < check_share.group_threshold as usize {
-		return Err(ErrorKind::Mnemonic(
-			"Insufficient number of groups with member counts that meet member threshold."
-				.to_string(),
-		))?;
+/// Outcome of a successful [`simulate_recovery`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoverySimulation {
+	/// Identifier of the share set
+	pub identifier: u16,
+	/// Iteration exponent of the share set, which determines how long the real
+	/// decryption will take
+	pub iteration_exponent: u8,
+	/// Groups (and their member shares) that would contribute to the recovery
+	pub groups: Vec<RecoveredGroup>,
+	/// Length of the master secret in bytes
+	pub secret_length: usize,
+	/// Time taken by the simulation
+	pub elapsed: Duration,
+}
+
+/// Check that the given mnemonics are sufficient and consistent to recover a master
+/// secret, running the full interpolation and digest verification but stopping before
+/// the (slow) passphrase decryption. Succeeding does not verify the passphrase
+pub fn simulate_recovery(mnemonics: &[Vec<String>]) -> Result<RecoverySimulation, Error> {
+	let start = Instant::now();
+	let ems = recover_ems(mnemonics, &SplitterConfig::new())?;
+	Ok(RecoverySimulation {
+		identifier: ems.identifier,
+		iteration_exponent: ems.iteration_exponent,
+		groups: ems.groups,
+		secret_length: ems.value.len(),
+		elapsed: start.elapsed(),
+	})
+}
+
+/// A group that contributed to the recovery of a master secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecoveredGroup {
+	/// Index of the group
+	pub group_index: u8,
+	/// Indices of the member shares from this group that were used
+	pub member_indices: Vec<u8>,
+	/// Indices of member shares that disagreed with the majority of the group's
+	/// shares, so were left out of the recovery
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub outliers: Vec<u8>,
+}
+
+/// A master secret recovered from a set of shares, along with the provenance
+/// of the recovery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredSecret {
+	/// Identifier of the share set the secret was recovered from
+	pub identifier: u16,
+	/// Iteration exponent of the share set
+	pub iteration_exponent: u8,
+	/// Groups (and their member shares) that contributed to the recovery. Groups
+	/// with too few members to meet their threshold are excluded
+	pub groups: Vec<RecoveredGroup>,
+	/// The recovered master secret
+	pub secret: SecretBytes,
+}
+
+impl RecoveredSecret {
+	/// Consume the recovery result, returning only the secret
+	pub fn into_secret(self) -> SecretBytes {
+		self.secret
 	}
+}
 
-	// TODO: Should probably return info making problem mnemonics easier to identify
-	for g in groups.iter() {
-		if g.member_shares.len() < g.member_threshold as usize {
-			return Err(ErrorKind::Mnemonic(format!(
-				"Insufficient number of mnemonics (Group {}). At least {} mnemonics \
-				 are required.",
-				g.group_index, g.member_threshold,
-			)))?;
+/// Recovers every secret that can be recovered from a mixed pile of mnemonics,
+/// which may belong to several share sets. Shares are partitioned by identifier and
+/// recovery is attempted for each set, returning one result per identifier (in
+/// ascending order of identifier), followed by an error for each mnemonic that
+/// couldn't be parsed at all.
+pub fn combine_all(
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> Vec<Result<RecoveredSecret, Error>> {
+	let passphrase = passphrase.into();
+	let mut sets: BTreeMap<u16, Vec<Vec<String>>> = BTreeMap::new();
+	let mut errors = vec![];
+	for m in mnemonics {
+		match Share::from_mnemonic(m) {
+			Ok(s) => sets.entry(s.identifier).or_default().push(m.clone()),
+			Err(e) => errors.push(Err(e)),
 		}
-		let test_share = g.member_shares[0].clone();
-		for ms in g.member_shares.iter() {
-			if test_share.member_threshold != ms.member_threshold {
-				return Err(ErrorKind::Mnemonic(
-					"Mismatching member thresholds".to_string(),
-				))?;
+	}
+	let mut results: Vec<Result<RecoveredSecret, Error>> = sets
+		.into_values()
+		.map(|mns| combine_mnemonics(&mns, &passphrase))
+		.collect();
+	results.append(&mut errors);
+	results
+}
+
+/// Recovers the secret of the share set with the given identifier from a pile of
+/// mnemonics that may include shares of other sets (e.g. stray cards from another
+/// wallet). Mnemonics of other sets, and any that can't be decoded, are ignored.
+/// Fails with [`ErrorKind::IdentifierNotFound`], listing the identifiers that were
+/// found, if no share has the identifier
+pub fn combine_mnemonics_for_identifier(
+	identifier: u16,
+	mnemonics: &[Vec<String>],
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	let mut found = BTreeSet::new();
+	let mut selected = vec![];
+	for m in mnemonics {
+		if let Ok(s) = Share::from_mnemonic(m) {
+			if s.identifier == identifier {
+				selected.push(m.clone());
 			}
+			found.insert(s.identifier);
 		}
 	}
+	if selected.is_empty() {
+		Err(ErrorKind::IdentifierNotFound(
+			identifier,
+			found.into_iter().collect(),
+		))?;
+	}
+	combine_mnemonics(&selected, passphrase)
+}
+
+/// Combines mnemonic shares provided as whole strings (e.g. as pasted or transcribed
+/// by a user). Each string is normalized with [`normalize_mnemonic`] before decoding,
+/// and the passphrase is NFKD normalized as SLIP-39 specifies.
+/// mnemonics: List of mnemonic strings.
+/// passphrase: The passphrase used to encrypt the master secret.
+/// return: The master secret.
+pub fn combine_mnemonic_strings(
+	mnemonics: &[String],
+	passphrase: impl Into<Passphrase>,
+) -> Result<RecoveredSecret, Error> {
+	let mnemonics: Vec<Vec<String>> = mnemonics.iter().map(|m| normalize_mnemonic(m)).collect();
+	let passphrase = PassphraseNormalization::Nfkd.normalize(passphrase.into().as_str());
+	combine_mnemonics(&mnemonics, passphrase)
+}
 
-	Ok(groups)
+/// Splits a mnemonic string into its words, tolerating the usual transcription
+/// noise: case is folded to lowercase, anything that isn't a letter (word numbering,
+/// punctuation, line breaks) is treated as a separator and whitespace is collapsed
+pub fn normalize_mnemonic(mnemonic: &str) -> Vec<String> {
+	mnemonic
+		.to_lowercase()
+		.split(|c: char| !c.is_alphabetic())
+		.filter(|w| !w.is_empty())
+		.map(|w| w.to_owned())
+		.collect()
+}
+
+/// Decodes all Mnemonics to a set of shares, checking they have the same length
+pub(crate) fn decode_mnemonics(mnemonics: &[Vec<String>]) -> Result<ShareSet, Error> {
+	if let Some(first) = mnemonics.first() {
+		if mnemonics.iter().any(|m| m.len() != first.len()) {
+			Err(ErrorKind::Mnemonic(
+				"Invalid set of mnemonics. All mnemonics must have the same length.".to_string(),
+			))?;
+		}
+	}
+	ShareSet::from_mnemonics(mnemonics)
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::util::hex::HexError;
 
 	fn flatten_mnemonics(nms: &[GroupShare]) -> Result<Vec<Vec<String>>, Error> {
 		let mut ret = vec![];
@@ -374,7 +1462,16 @@ mod tests {
 		}
 		let result = combine_mnemonics(&flatten_mnemonics(&mns)?, "")?;
 		println!("Single 3 of 5 Decoded: {:?}", result);
-		assert_eq!(result, master_secret);
+		assert_eq!(result.secret, master_secret);
+		assert_eq!(result.identifier, mns[0].group_id);
+		assert_eq!(
+			result.groups,
+			vec![RecoveredGroup {
+				group_index: 0,
+				member_indices: vec![0, 1, 2, 3, 4],
+				outliers: vec![],
+			}]
+		);
 
 		// Test a few distinct groups
 		let mns = generate_mnemonics(
@@ -389,7 +1486,7 @@ mod tests {
 		}
 		let result = combine_mnemonics(&flatten_mnemonics(&mns)?, "")?;
 		println!("Single 3 of 5 Decoded: {:?}", result);
-		assert_eq!(result, master_secret);
+		assert_eq!(result.secret, master_secret);
 
 		// work through some varying sized secrets
 		let mut master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
@@ -405,7 +1502,7 @@ mod tests {
 			}
 			let result = combine_mnemonics(&flatten_mnemonics(&mns)?, "")?;
 			println!("Single 3 of 5 Decoded: {:?}", result);
-			assert_eq!(result, master_secret);
+			assert_eq!(result.secret, master_secret);
 		}
 
 		// Test case for particular case which failed with different threshold lenghts
@@ -415,22 +1512,238 @@ mod tests {
 		let three = "slavery flea acrobat envelope best ceiling dragon threaten isolate headset decrease organize crunch fiction sniff carbon museum username glasses plunge";
 		let four = "slavery flea beard echo cradle rebound penalty minister literary object have hazard elephant meaning enemy empty result capture peanut believe";
 		let five = "slavery flea beard email blind lips evaluate repair decent rich mortgage swimming branch decision unkind ultimate military sugar prepare airport";
-		let mut input = vec![];
-		input.push(one.split(' ').map(|s| s.to_owned()).collect());
-		input.push(two.split(' ').map(|s| s.to_owned()).collect());
-		input.push(three.split(' ').map(|s| s.to_owned()).collect());
-		input.push(four.split(' ').map(|s| s.to_owned()).collect());
-		input.push(five.split(' ').map(|s| s.to_owned()).collect());
+		let input: Vec<Vec<String>> = [one, two, three, four, five]
+			.iter()
+			.map(|m| m.split(' ').map(|s| s.to_owned()).collect())
+			.collect();
 		let _result = combine_mnemonics(&input, "TREZOR")?;
 
 		Ok(())
 	}
 
+	#[test]
+	fn tolerant_mnemonic_strings() -> Result<(), Error> {
+		let one = "  Shadow pistol ACADEMIC always adequate wildlife fancy gross oasis cylinder\nmustang wrist rescue view short owner flip making coding armed.  ";
+		let two = "1. shadow 2. pistol 3. academic 4. acid 5. actress 6. prayer 7. class, unknown\r\n\tdaughter sweater depict flip twice unkind craft early superior advocate guest smoking";
+		assert_eq!(normalize_mnemonic(one).len(), 20);
+		assert_eq!(normalize_mnemonic(two).len(), 20);
+		assert_eq!(normalize_mnemonic(two)[6], "class");
+		let result = combine_mnemonic_strings(&[one.to_owned(), two.to_owned()], "TREZOR")?;
+		assert_eq!(
+			result.secret,
+			util::hex::from_hex("b43ceb7e57a0ea8766221624d01b0864").unwrap()
+		);
+		// a composed passphrase matches the decomposed one the shares were made with
+		let mns = generate_mnemonics(1, &[(1, 1)], &[0x42u8; 16], "cafe\u{301}", 0)?;
+		let mn = mns[0].member_shares[0].to_mnemonic()?.join(" ");
+		let result = combine_mnemonic_strings(&[mn], "caf\u{e9}")?;
+		assert_eq!(result.secret, vec![0x42u8; 16]);
+		Ok(())
+	}
+
+	#[test]
+	fn group_share_format() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let mns = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+		let gs = &mns[0];
+		assert_eq!(format!("{}", gs), gs.format(&FormatOptions::new())?);
+
+		let words = gs.member_shares[0].to_mnemonic()?;
+		let opts = FormatOptions {
+			numbered: true,
+			columns: Some(4),
+			page_breaks: true,
+			include_header: false,
+			fingerprints: true,
+		};
+		let output = gs.format(&opts)?;
+		let pages: Vec<&str> = output.split('\x0c').collect();
+		assert_eq!(pages.len(), 3);
+		let lines: Vec<&str> = pages[0].lines().collect();
+		assert_eq!(lines.len(), 6);
+		assert!(lines[0].starts_with(&format!(" 1. {}", words[0])));
+		assert!(lines[4].ends_with(&format!("20. {}", words[19])));
+		assert!(!output.contains("Group 1"));
+		let fp = gs.member_shares[0].fingerprint()?;
+		assert!(pages[0].ends_with(&format!("Fingerprint: {}\n", fp)));
+
+		// zero columns is a single line per mnemonic
+		let opts = FormatOptions {
+			columns: Some(0),
+			..FormatOptions::new()
+		};
+		assert_eq!(gs.format(&opts)?, gs.format(&FormatOptions::new())?);
+		Ok(())
+	}
+
+	#[test]
+	fn threshold_one_digest() -> Result<(), Error> {
+		let master_secret = vec![3u8; 16];
+		let config = SplitterConfig::new().with_threshold_one_digest(true);
+		let mns = generate_mnemonics_with_config(
+			1,
+			&[(1, 2), (2, 3)],
+			&master_secret,
+			"",
+			0,
+			&config,
+			&MasterSecretEncConfig::new(),
+		)?;
+		// extra tag words for both group and member level tags
+		assert_eq!(mns[0].member_shares[0].to_mnemonic()?.len(), 27);
+		let input = vec![mns[0].member_shares[1].to_mnemonic()?];
+		let result =
+			combine_mnemonics_with_config(&input, "", &config, &MasterSecretEncConfig::new())?;
+		assert_eq!(result.secret, master_secret);
+		assert!(combine_mnemonics(&input, "")?.secret != master_secret);
+		Ok(())
+	}
+
+	#[cfg(feature = "kdf-argon2")]
+	#[test]
+	fn extended_format_kdf_profile() -> Result<(), Error> {
+		let master_secret = vec![5u8; 16];
+		let enc_config = MasterSecretEncConfig::new().with_profile(KdfProfile::argon2id(4)?);
+		let mns = generate_mnemonics_with_config(
+			1,
+			&[(2, 3)],
+			&master_secret,
+			"",
+			0,
+			&SplitterConfig::new(),
+			&enc_config,
+		)?;
+		assert_eq!(mns[0].member_shares[0].format, Format::ExtendedV2);
+		let mut input = flatten_mnemonics(&mns)?;
+		// profile is detected from the shares
+		assert_eq!(combine_mnemonics(&input[1..], "")?.secret, master_secret);
+		// formats can't be mixed
+		input[0] = mns[0].member_shares[0].to_mnemonic_as(Format::Slip39)?;
+		assert!(combine_mnemonics(&input[..2], "").is_err());
+		// costs beyond the cap are refused rather than attempted
+		let config = SplitterConfig::new();
+		assert!(generate_mnemonics_with_config(
+			1,
+			&[(2, 3)],
+			&master_secret,
+			"",
+			17,
+			&config,
+			&enc_config
+		)
+		.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn combine_mixed_sets() -> Result<(), Error> {
+		let secret_1 = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let secret_2 = vec![7u8; 32];
+		let set_1 = generate_mnemonics(1, &[(2, 3)], &secret_1, "", 0)?;
+		let mut set_2 = generate_mnemonics(1, &[(3, 5)], &secret_2, "", 0)?;
+		while set_2[0].group_id == set_1[0].group_id {
+			set_2 = generate_mnemonics(1, &[(3, 5)], &secret_2, "", 0)?;
+		}
+		let set_1_mns = set_1[0].mnemonic_list()?;
+		let set_2_mns = set_2[0].mnemonic_list()?;
+		let mut pile = vec![set_2_mns[4].clone(), set_1_mns[0].clone()];
+		pile.push(set_2_mns[0].clone());
+		pile.push(vec!["notaword".to_owned(); 20]);
+		pile.push(set_1_mns[2].clone());
+		// set 2 is under threshold
+		let results = combine_all(&pile, "");
+		assert_eq!(results.len(), 3);
+		assert!(results[2].is_err());
+		let set_1_result = results
+			.iter()
+			.flatten()
+			.find(|r| r.identifier == set_1[0].group_id)
+			.unwrap();
+		assert_eq!(set_1_result.secret, secret_1);
+		assert_eq!(results.iter().filter(|r| r.is_err()).count(), 2);
+
+		pile.push(set_2_mns[2].clone());
+		let results = combine_all(&pile, "");
+		assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn combine_for_identifier() -> Result<(), Error> {
+		let secret_1 = vec![1u8; 16];
+		let set_1 = generate_mnemonics(1, &[(2, 3)], &secret_1, "", 0)?;
+		let mut set_2 = generate_mnemonics(1, &[(2, 3)], &[2u8; 16], "", 0)?;
+		while set_2[0].group_id == set_1[0].group_id {
+			set_2 = generate_mnemonics(1, &[(2, 3)], &[2u8; 16], "", 0)?;
+		}
+		let (id_1, id_2) = (set_1[0].group_id, set_2[0].group_id);
+		let set_1_mns = set_1[0].mnemonic_list()?;
+		let set_2_mns = set_2[0].mnemonic_list()?;
+		// stray cards of set 2 and an unreadable one alongside set 1
+		let pile = vec![
+			set_2_mns[0].clone(),
+			set_1_mns[1].clone(),
+			vec!["notaword".to_owned(); 20],
+			set_2_mns[1].clone(),
+			set_1_mns[2].clone(),
+		];
+		assert_eq!(
+			combine_mnemonics_for_identifier(id_1, &pile, "")?.secret,
+			secret_1
+		);
+		assert_eq!(
+			combine_mnemonics_for_identifier(id_2, &pile, "")?.identifier,
+			id_2
+		);
+
+		let missing = (0u16..).find(|i| *i != id_1 && *i != id_2).unwrap();
+		let e = combine_mnemonics_for_identifier(missing, &pile, "").unwrap_err();
+		let mut found = vec![id_1, id_2];
+		found.sort_unstable();
+		assert_eq!(e.kind(), ErrorKind::IdentifierNotFound(missing, found));
+		assert_eq!(e.code(), 22);
+		Ok(())
+	}
+
+	#[test]
+	fn group_share_labels() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let mut mns = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+		let unlabelled = mns[0].mnemonic_list()?;
+		mns[0].set_label("Safe deposit box");
+		mns[0].set_member_label(1, "Alice")?;
+		assert!(mns[0].set_member_label(7, "Bob").is_err());
+
+		// labels are never encoded into the mnemonics
+		assert_eq!(unlabelled, mns[0].mnemonic_list()?);
+		let output = format!("{}", mns[0]);
+		assert!(output.starts_with("Group 1 of 1 (Safe deposit box) - 2 of 3"));
+		assert!(output.contains("[Alice] "));
+
+		let result = combine_mnemonics(&flatten_mnemonics(&mns)?, "")?;
+		assert_eq!(result.secret, master_secret);
+		Ok(())
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn group_share_labels_serde() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let mut mns = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+		mns[0].set_label("Safe deposit box");
+		mns[0].set_member_label(0, "Alice")?;
+		let json = serde_json::to_string(&mns[0]).unwrap();
+		let deser: GroupShare = serde_json::from_str(&json).unwrap();
+		assert_eq!(deser, mns[0]);
+		assert_eq!(deser.member_shares[0].label, Some("Alice".to_owned()));
+		Ok(())
+	}
+
 	// For temporary use as we have no command-line at present
 	#[test]
 	fn split_master_secret() -> Result<(), Error> {
 		let master_secret = b"fdd99010e03f3141662adb33644d5fd2bea0238fa805a2d21e396a22b926558c";
-		let mns = generate_mnemonics(1, &[(3, 5)], &master_secret.to_vec(), "", 0)?;
+		let mns = generate_mnemonics(1, &[(3, 5)], master_secret, "", 0)?;
 		for s in &mns {
 			println!("{}", s);
 		}
@@ -439,14 +1752,449 @@ mod tests {
 		let three = "ending senior academic amazing academic carbon sheriff march ordinary advocate climate quarter explain view glasses distance scandal modify maiden welcome include webcam snapshot lilac finance faint facility quantity daughter trash formal failure execute grasp necklace trust bishop privacy library infant slim envy parcel boring mixture deploy dough deny patrol evening brave idea blessing slush lizard woman teaspoon news exclude";
 		let four = "ending senior academic arcade acquire work exceed network revenue blanket force fiber ting standard fatigue extend acid holiday raspy pink vegan survive river step golden scandal tendency spray parcel vintage amuse remove best else unknown overall mild breathe nuclear wrist criminal jury deal rescue symbolic slow predator railroad verify involve require graduate ambition unknown repair scandal hobo voice railroad";
 		let five = "ending senior academic axle acquire golden velvet depart swing endorse champion estate slush alien burning painting obesity surprise punish gasoline elephant educate declare rebuild plains making unkind carve exotic unfold counter cowboy extra fantasy cleanup pickup increase type deliver together fumes nylon acrobat fatigue listen elder toxic losing paper image aide satisfy award axis evoke capital academic violence canyon";
-		let mut input = vec![];
-		input.push(one.split(' ').map(|s| s.to_owned()).collect());
-		input.push(two.split(' ').map(|s| s.to_owned()).collect());
-		input.push(three.split(' ').map(|s| s.to_owned()).collect());
-		input.push(four.split(' ').map(|s| s.to_owned()).collect());
-		input.push(five.split(' ').map(|s| s.to_owned()).collect());
+		let input: Vec<Vec<String>> = [one, two, three, four, five]
+			.iter()
+			.map(|m| m.split(' ').map(|s| s.to_owned()).collect())
+			.collect();
 		let result = combine_mnemonics(&input, "")?;
-		println!("Result: {}", String::from_utf8(result).unwrap());
+		println!(
+			"Result: {}",
+			String::from_utf8(result.into_secret().into_vec()).unwrap()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn generate_mnemonics_from_hex_test() -> Result<(), Error> {
+		let hex_secret = "0x bb54aac4b89dc868ba37d9cc21b2cece";
+		assert!(generate_mnemonics_from_hex(1, &[(1, 1)], hex_secret, "", 0).is_err());
+		let hex_secret = "bb54aac4b89dc868ba37d9cc21b2cece";
+		let mns = generate_mnemonics_from_hex(1, &[(2, 3)], hex_secret, "", 0)?;
+		let result = combine_mnemonics(&mns[0].mnemonic_list()?[..2], "")?;
+		assert_eq!(result.secret, util::hex::from_hex(hex_secret).unwrap());
+
+		match generate_mnemonics_from_hex(1, &[(1, 1)], "bb54aac4b89dc868ba37d9cc21b2cec", "", 0) {
+			Err(e) => assert_eq!(e.kind(), ErrorKind::Hex(HexError::OddLength(31))),
+			Ok(_) => panic!("odd length hex should fail"),
+		}
+		match generate_mnemonics_from_hex(1, &[(1, 1)], "zz54aac4b89dc868ba37d9cc21b2cece", "", 0) {
+			Err(e) => assert_eq!(e.kind(), ErrorKind::Hex(HexError::InvalidChar(0, 'z'))),
+			Ok(_) => panic!("invalid hex should fail"),
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn generate_mnemonics_invalid_layouts() {
+		let ms = vec![9u8; 16];
+		let check = |gt: u8, groups: &[(u8, u8)], kind: ErrorKind| {
+			let e = generate_mnemonics(gt, groups, &ms, "", 0).unwrap_err();
+			assert_eq!(e.kind(), kind);
+		};
+		check(1, &[(1, 2)], ErrorKind::SingleMemberThreshold(0, 2));
+		check(1, &[(2, 3), (3, 2)], ErrorKind::MemberThreshold(1, 3, 2));
+		check(1, &[(2, 17)], ErrorKind::MemberThreshold(0, 2, 17));
+		check(2, &[(1, 1)], ErrorKind::GroupThreshold(2, 1));
+		check(1, &[], ErrorKind::GroupCount(0));
+		let e = generate_mnemonics(1, &[(1, 1)], &[9u8; 15], "", 0).unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::SecretLength(15));
+	}
+
+	#[test]
+	fn identifier_policy() -> Result<(), Error> {
+		let ms = vec![4u8; 16];
+		let gen = |policy: &IdentifierPolicy| {
+			generate_mnemonics_with_identifier(1, &[(2, 3)], &ms, "", 0, policy)
+		};
+		let shares = gen(&IdentifierPolicy::Fixed(1234))?;
+		assert_eq!(shares.identifier, 1234);
+		assert_eq!(shares.groups[0].group_id, 1234);
+		let result = combine_mnemonics(&shares.groups[0].mnemonic_list()?[1..], "")?;
+		assert_eq!(result.identifier, 1234);
+		assert_eq!(result.secret, ms);
+		assert!(gen(&IdentifierPolicy::Fixed(1 << 15)).is_err());
+
+		let mut existing: Vec<u16> = (0..1 << 15).collect();
+		existing.remove(77);
+		assert_eq!(IdentifierPolicy::Avoid(existing.clone()).resolve()?, 77);
+		existing.push(77);
+		assert!(IdentifierPolicy::Avoid(existing).resolve().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn simulate_recovery_test() -> Result<(), Error> {
+		let ms = vec![6u8; 32];
+		let mns = generate_mnemonics(2, &[(2, 3), (1, 1), (3, 5)], &ms, "", 1)?;
+		let mut input = mns[0].mnemonic_list()?[..2].to_vec();
+		input.extend(mns[1].mnemonic_list()?);
+		let sim = simulate_recovery(&input)?;
+		assert_eq!(sim.identifier, mns[0].group_id);
+		assert_eq!(sim.iteration_exponent, 1);
+		assert_eq!(sim.secret_length, 32);
+		assert_eq!(sim.groups.len(), 2);
+		// not enough groups
+		assert!(simulate_recovery(&input[..2]).is_err());
+		// a corrupted share value fails the digest check
+		let mut bad = mns[0].member_shares[0].clone();
+		bad.share_value[3] ^= 1;
+		input[0] = bad.to_mnemonic()?;
+		assert!(simulate_recovery(&input).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn iteration_exponent_in_header() -> Result<(), Error> {
+		// shares must record the exponent the master secret was encrypted with
+		let ms = vec![0x4au8; 16];
+		let mns = generate_mnemonics(1, &[(2, 3)], &ms, "pw", 2)?;
+		for mn in mns[0].mnemonic_list()? {
+			assert_eq!(Share::from_mnemonic(&mn)?.iteration_exponent, 2);
+		}
+		let input = mns[0].mnemonic_list()?[1..].to_vec();
+		assert_eq!(combine_mnemonics(&input, "pw")?.secret, ms);
+		Ok(())
+	}
+
+	#[test]
+	fn decrypt_with_each_test() -> Result<(), Error> {
+		let ms = vec![8u8; 16];
+		let mns = generate_mnemonics(1, &[(2, 3)], &ms, "real", 0)?;
+		let input = mns[0].mnemonic_list()?[..2].to_vec();
+		let results = decrypt_with_each(&input, ["real", "duress"])?;
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].secret, ms);
+		assert!(results[1].secret != ms);
+		assert_eq!(
+			results[1].secret,
+			combine_mnemonics(&input, "duress")?.secret
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn encrypted_master_secret_retry() -> Result<(), Error> {
+		let ms = vec![0x21u8; 16];
+		let mns = generate_mnemonics(1, &[(2, 3)], &ms, "correct", 0)?;
+		let ems = recover_ems(&mns[0].mnemonic_list()?[1..], &SplitterConfig::new())?;
+		assert_eq!(ems.groups[0].member_indices, vec![1, 2]);
+		assert!(ems.decrypt("typo")?.secret != ms);
+		assert_eq!(ems.decrypt("correct")?.secret, ms);
+		Ok(())
+	}
+
+	#[test]
+	fn ems_two_stage_combine() -> Result<(), Error> {
+		let ms = vec![0x37u8; 32];
+		let mns = generate_mnemonics(1, &[(2, 3)], &ms, "offline", 2)?;
+		let ems = combine_to_ems(&mns[0].mnemonic_list()?[..2])?;
+		let bytes = ems.to_bytes();
+		assert_eq!(bytes.len(), 6 + 32);
+		let restored = EncryptedMasterSecret::from_bytes(&bytes)?;
+		assert_eq!(restored.identifier, ems.identifier);
+		assert_eq!(restored.iteration_exponent, 2);
+		assert_eq!(restored.value, ems.value);
+		assert_eq!(decrypt_ems(&restored, "offline")?.secret, ms);
+		assert!(EncryptedMasterSecret::from_bytes(&bytes[..20]).is_err());
+		let mut bad = bytes.to_vec();
+		bad[0] = 9;
+		assert!(EncryptedMasterSecret::from_bytes(&bad).is_err());
+		// out of range header fields
+		for (i, b) in [(1, 0x80), (3, 32), (5, 0x41)] {
+			let mut bad = bytes.to_vec();
+			bad[i] = b;
+			assert!(EncryptedMasterSecret::from_bytes(&bad).is_err());
+		}
+		Ok(())
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn ems_serde() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(1, 1)], &[0x11u8; 16], "", 0)?;
+		let ems = combine_to_ems(&mns[0].mnemonic_list()?)?;
+		let json = serde_json::to_string(&ems).unwrap();
+		let deser: EncryptedMasterSecret = serde_json::from_str(&json).unwrap();
+		assert_eq!(deser, ems);
+		// out of range fields are rejected as by from_bytes
+		let mut bad = serde_json::to_value(&ems).unwrap();
+		bad["identifier"] = 0x8000.into();
+		assert!(serde_json::from_value::<EncryptedMasterSecret>(bad).is_err());
+		let mut bad = serde_json::to_value(&ems).unwrap();
+		bad["iteration_exponent"] = 32.into();
+		let err = serde_json::from_value::<EncryptedMasterSecret>(bad).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("Iteration exponent 32 is out of range"));
+		Ok(())
+	}
+
+	#[test]
+	fn ems_two_stage_generate() -> Result<(), Error> {
+		let ms = vec![0x5cu8; 16];
+		let ems = encrypt_master_secret(&ms, "later", 0)?;
+		let bytes = ems.to_bytes();
+		let mns = split_ems(&EncryptedMasterSecret::from_bytes(&bytes)?, 1, &[(2, 3)])?;
+		assert_eq!(mns[0].group_id, ems.identifier);
+		let input = mns[0].mnemonic_list()?[1..].to_vec();
+		assert_eq!(combine_mnemonics(&input, "later")?.secret, ms);
+		assert_eq!(combine_to_ems(&input)?.value, ems.value);
+		assert!(split_ems(&ems, 2, &[(2, 3)]).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn cancellable_combine() -> Result<(), Error> {
+		let master_secret = [8u8; 16];
+		let mns = flatten_mnemonics(&generate_mnemonics(1, &[(2, 3)], &master_secret, "pw", 0)?)?;
+		let cancel = CancellationToken::new();
+		let recovered = combine_mnemonics_cancellable(&mns[..2], "pw", &cancel)?;
+		assert_eq!(&recovered.secret[..], &master_secret[..]);
+
+		cancel.cancel();
+		let e = combine_mnemonics_cancellable(&mns[..2], "pw", &cancel).unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::Cancelled);
+
+		// a slow KDF is abandoned part way through when cancelled from another thread
+		let mns = flatten_mnemonics(&generate_mnemonics(1, &[(1, 1)], &master_secret, "", 0)?)?;
+		let mut share = Share::from_mnemonic(&mns[0])?;
+		share.iteration_exponent = 16;
+		let cancel = CancellationToken::new();
+		let canceller = cancel.clone();
+		let start = Instant::now();
+		let t = std::thread::spawn(move || {
+			std::thread::sleep(Duration::from_millis(100));
+			canceller.cancel();
+		});
+		let e = combine_mnemonics_cancellable(&[share.to_mnemonic()?], "", &cancel).unwrap_err();
+		t.join().unwrap();
+		assert_eq!(e.kind(), ErrorKind::Cancelled);
+		assert!(start.elapsed() < Duration::from_secs(30));
+		Ok(())
+	}
+
+	#[test]
+	fn secret_length_boundaries() -> Result<(), Error> {
+		for len in [16, 32, 64, 256, MAX_SECRET_BYTES] {
+			let master_secret: Vec<u8> = (0..len).map(|i| i as u8).collect();
+			let mns = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+			let words = mns[0].member_shares[0].to_mnemonic()?;
+			assert_eq!(
+				words.len(),
+				crate::shamir::mnemonic_word_count_for_secret(len)
+			);
+			let recovered = combine_mnemonics(&flatten_mnemonics(&mns)?[..2], "")?;
+			assert_eq!(&recovered.secret[..], &master_secret[..]);
+		}
+
+		let too_long = vec![1u8; MAX_SECRET_BYTES + 2];
+		let e = generate_mnemonics(1, &[(2, 3)], &too_long, "", 0).unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::SecretLength(MAX_SECRET_BYTES + 2));
+		assert!(format!("{}", e.kind()).contains("from 16 to 256"));
+
+		// mnemonics beyond the limit are rejected when decoding too
+		let mut share =
+			generate_mnemonics(1, &[(1, 1)], &[1u8; 16], "", 0)?[0].member_shares[0].clone();
+		share.share_value = too_long;
+		let words = share.to_mnemonic()?;
+		let e = Share::from_mnemonic(&words).unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::SecretLength(MAX_SECRET_BYTES + 2));
+		Ok(())
+	}
+
+	#[test]
+	fn group_share_integrity() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(3, 5)], &[1u8; 16], "", 0)?;
+		let mut group = mns[0].clone();
+		assert!(group.verify_integrity()?.is_empty());
+
+		// a flipped bit in a share beyond the threshold is pinpointed
+		group.member_shares[4].share_value[3] ^= 0x10;
+		assert_eq!(group.verify_integrity()?, vec![4]);
+
+		// one among the reference shares fails the digest check
+		group.member_shares[0].share_value[0] ^= 0x01;
+		assert!(group.verify_integrity().is_err());
+
+		group.member_shares.truncate(2);
+		assert!(group.verify_integrity().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn majority_vote() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(3, 5)], &[1u8; 16], "", 0)?;
+		let mut group = mns[0].clone();
+		let secret = group.decode_shares()?.share_value;
+
+		// foreign shares outvoted by the rest are reported as outliers
+		group.member_shares[1].share_value = vec![9u8; 16];
+		group.member_shares[3].share_value = vec![8u8; 16];
+		let (share, outliers) = group.decode_shares_with(&Splitter::new(None))?;
+		assert_eq!(share.share_value, secret);
+		assert_eq!(outliers, vec![1, 3]);
+		let set: ShareSet = group.member_shares.iter().cloned().collect();
+		let ems = recover_ems_from_set(&set, &SplitterConfig::new())?;
+		assert_eq!(ems.groups[0].member_indices, vec![0, 2, 4]);
+		assert_eq!(ems.groups[0].outliers, vec![1, 3]);
+
+		// with only the threshold, the bad share can't be told apart
+		group.member_shares.truncate(3);
+		assert_eq!(
+			group.decode_shares().unwrap_err().kind().code(),
+			ErrorKind::Digest(String::new()).code()
+		);
+
+		// without a majority the inconsistent shares are named
+		let mut group = generate_mnemonics(1, &[(2, 4)], &[1u8; 16], "", 0)?[0].clone();
+		group.member_shares[1].share_value = vec![9u8; 16];
+		group.member_shares[3].share_value = vec![8u8; 16];
+		let e = group.decode_shares().unwrap_err();
+		assert_eq!(e.kind(), ErrorKind::InconsistentShares(0, vec![1, 3]));
+		assert_eq!(e.code(), 21);
+		Ok(())
+	}
+
+	#[test]
+	fn group_share_iteration() -> Result<(), Error> {
+		let mns = generate_mnemonics(1, &[(3, 5)], &[1u8; 16], "", 0)?;
+		let mut group = mns[0].clone();
+		assert_eq!(group.len(), 5);
+		assert_eq!(group[2].member_index, 2);
+		assert_eq!((&group).into_iter().count(), 5);
+		assert_eq!(group.missing_members(), 0);
+		group
+			.member_shares
+			.retain(|s| s.member_index == 1 || s.member_index == 4);
+		assert!(group.find_member(4).is_some());
+		assert!(group.find_member(2).is_none());
+		assert_eq!(group.missing_members(), 1);
+		let indices: Vec<u8> = group.into_iter().map(|s| s.member_index).collect();
+		assert_eq!(indices, vec![1, 4]);
+		Ok(())
+	}
+
+	#[test]
+	fn combine_share_set_test() -> Result<(), Error> {
+		let ms = vec![3u8; 16];
+		let mns = generate_mnemonics(1, &[(3, 5)], &ms, "", 0)?;
+		let mut set = ShareSet::new();
+		for s in mns[0].iter().take(2) {
+			set.insert(s.clone());
+		}
+		assert!(combine_share_set(&set, "").is_err());
+		set.insert(mns[0][4].clone());
+		// duplicates collapse rather than breaking the interpolation
+		set.insert(mns[0][4].clone());
+		assert_eq!(combine_share_set(&set, "")?.secret, ms);
+		Ok(())
+	}
+
+	#[test]
+	fn share_ordering() -> Result<(), Error> {
+		use crate::shamir::ShareOrdering;
+		let ms = vec![9u8; 16];
+		let indices = |groups: &[GroupShare]| -> Vec<Vec<u8>> {
+			groups
+				.iter()
+				.map(|g| g.iter().map(|s| s.member_index).collect())
+				.collect()
+		};
+		let mns = generate_mnemonics(2, &[(3, 5), (2, 16)], &ms, "", 0)?;
+		assert_eq!(
+			mns.iter().map(|g| g.group_index).collect::<Vec<_>>(),
+			vec![0, 1]
+		);
+		assert_eq!(
+			indices(&mns),
+			vec![(0..5).collect(), (0..16).collect::<Vec<u8>>()]
+		);
+
+		let config = SplitterConfig::new().with_ordering(ShareOrdering::Shuffled);
+		let enc_config = MasterSecretEncConfig::new();
+		let mns = generate_mnemonics_with_config(1, &[(2, 16)], &ms, "", 0, &config, &enc_config)?;
+		let mut shuffled = indices(&mns)[0].clone();
+		assert_ne!(shuffled, (0..16).collect::<Vec<u8>>());
+		shuffled.sort_unstable();
+		assert_eq!(shuffled, (0..16).collect::<Vec<u8>>());
+		Ok(())
+	}
+
+	#[test]
+	fn passphrase_normalization() -> Result<(), Error> {
+		let ms = vec![1u8; 16];
+		let config = SplitterConfig::new();
+		let ascii = MasterSecretEncConfig::new()
+			.with_passphrase_normalization(PassphraseNormalization::PrintableAscii);
+		assert!(
+			generate_mnemonics_with_config(1, &[(1, 1)], &ms, "caf\u{e9}", 0, &config, &ascii)
+				.is_err()
+		);
+		let mns = generate_mnemonics_with_config(
+			1,
+			&[(1, 1)],
+			&ms,
+			"Correct Horse ~1",
+			0,
+			&config,
+			&ascii,
+		)?;
+		let input = mns[0].mnemonic_list()?;
+		let rec = combine_mnemonics_with_config(&input, "Correct Horse ~1", &config, &ascii)?;
+		assert_eq!(rec.secret, ms);
+		assert!(combine_mnemonics_with_config(&input, "tab\there", &config, &ascii).is_err());
+		// case is significant
+		assert!(
+			combine_mnemonics_with_config(&input, "correct horse ~1", &config, &ascii)?.secret
+				!= ms
+		);
+
+		// composed and decomposed forms recover the same secret under NFKD
+		let nfkd = MasterSecretEncConfig::new()
+			.with_passphrase_normalization(PassphraseNormalization::Nfkd);
+		let mns =
+			generate_mnemonics_with_config(1, &[(1, 1)], &ms, "caf\u{e9}", 0, &config, &nfkd)?;
+		let input = mns[0].mnemonic_list()?;
+		let rec = combine_mnemonics_with_config(&input, "cafe\u{301}", &config, &nfkd)?;
+		assert_eq!(rec.secret, ms);
+		let rec = combine_mnemonics_with_config(&input, "caf\u{e9}", &config, &nfkd)?;
+		assert_eq!(rec.secret, ms);
+		// and the EMS is encrypted under the decomposed form
+		let plain = MasterSecretEncConfig::new();
+		assert_eq!(
+			combine_mnemonics_with_config(&input, "cafe\u{301}", &config, &plain)?.secret,
+			ms
+		);
+		assert!(combine_mnemonics_with_config(&input, "caf\u{e9}", &config, &plain)?.secret != ms);
+		Ok(())
+	}
+
+	#[test]
+	fn generate_mnemonics_progress() -> Result<(), Error> {
+		use std::cell::RefCell;
+		use std::rc::Rc;
+		let reports = Rc::new(RefCell::new(vec![]));
+		let r = reports.clone();
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let groups = generate_mnemonics_with_progress(
+			2,
+			&[(2, 3), (1, 1)],
+			&master_secret,
+			"",
+			0,
+			move |p| r.borrow_mut().push(*p),
+		)?;
+		assert_eq!(groups.len(), 2);
+		let reports = reports.borrow();
+		for (group_index, share_count) in &[(None, 2), (Some(0), 3), (Some(1), 1)] {
+			let last = reports
+				.iter()
+				.rev()
+				.find(|p| p.group_index == *group_index)
+				.unwrap();
+			assert_eq!(last.shares_done, *share_count);
+			assert_eq!(last.share_count, *share_count);
+		}
 		Ok(())
 	}
 }
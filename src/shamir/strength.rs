@@ -0,0 +1,133 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sanity checks on a master secret before it is split. Length problems are
+//! errors, while signs of low entropy are only reported, since they can't be
+//! told apart from an unlucky random secret with certainty
+
+use super::limits::MAX_SECRET_BYTES;
+use super::share::ShareConfig;
+use crate::error::{Error, ErrorKind};
+use std::fmt;
+
+/// A sign that a master secret has less entropy than its length suggests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretWarning {
+	/// Every byte of the secret is zero
+	AllZero,
+	/// The secret is the given number of bytes repeated over its whole length
+	RepeatedPattern(usize),
+	/// Every byte is printable ASCII, e.g. a hex or text string passed as raw bytes
+	AsciiOnly,
+}
+
+impl fmt::Display for SecretWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SecretWarning::AllZero => write!(f, "the master secret is all zero bytes"),
+			SecretWarning::RepeatedPattern(p) => write!(
+				f,
+				"the master secret is a repeated pattern of {} byte(s)",
+				p
+			),
+			SecretWarning::AsciiOnly => write!(
+				f,
+				"the master secret is printable ASCII; was a hex or text string passed \
+				 instead of its decoded bytes?"
+			),
+		}
+	}
+}
+
+/// Report returned by [`validate_master_secret`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretStrength {
+	/// Length of the secret in bits
+	pub length_bits: usize,
+	/// Low-entropy warnings, empty if none were found
+	pub warnings: Vec<SecretWarning>,
+}
+
+impl SecretStrength {
+	/// Whether any low-entropy warning was raised
+	pub fn is_weak(&self) -> bool {
+		!self.warnings.is_empty()
+	}
+}
+
+/// Check that a master secret can be split (at least the minimum strength, an even
+/// number of bytes and no more than [`MAX_SECRET_BYTES`]), and look for obvious
+/// signs of low entropy. Length problems are reported as [`ErrorKind::SecretLength`]
+pub fn validate_master_secret(master_secret: &[u8]) -> Result<SecretStrength, Error> {
+	let min_strength_bits = ShareConfig::default().min_strength_bits as usize;
+	if master_secret.len() * 8 < min_strength_bits
+		|| master_secret.len() > MAX_SECRET_BYTES
+		|| !master_secret.len().is_multiple_of(2)
+	{
+		Err(ErrorKind::SecretLength(master_secret.len()))?;
+	}
+
+	let mut warnings = vec![];
+	if master_secret.iter().all(|b| *b == 0) {
+		warnings.push(SecretWarning::AllZero);
+	} else if let Some(p) = repeat_period(master_secret) {
+		warnings.push(SecretWarning::RepeatedPattern(p));
+	}
+	if master_secret.iter().all(|b| (0x20..0x7f).contains(b)) {
+		warnings.push(SecretWarning::AsciiOnly);
+	}
+	Ok(SecretStrength {
+		length_bits: master_secret.len() * 8,
+		warnings,
+	})
+}
+
+/// Shortest period that repeats at least twice over the whole secret, if any
+fn repeat_period(secret: &[u8]) -> Option<usize> {
+	(1..=secret.len() / 2)
+		.filter(|p| secret.len().is_multiple_of(*p))
+		.find(|p| secret.chunks(*p).all(|c| c == &secret[..*p]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validate_master_secret_test() -> Result<(), Error> {
+		let strength = validate_master_secret(&[
+			0x5a, 0x13, 0xe7, 0x02, 0x99, 0xc4, 0x3b, 0xf0, 0x81, 0x6d, 0x20, 0xae, 0x47, 0xd8,
+			0x0c, 0xb5,
+		])?;
+		assert_eq!(strength.length_bits, 128);
+		assert!(!strength.is_weak());
+
+		assert_eq!(
+			validate_master_secret(&[1u8; 14]).unwrap_err().kind(),
+			ErrorKind::SecretLength(14)
+		);
+		assert!(validate_master_secret(&[1u8; 17]).is_err());
+		assert!(validate_master_secret(&[1u8; MAX_SECRET_BYTES + 2]).is_err());
+
+		let strength = validate_master_secret(&[0u8; 16])?;
+		assert_eq!(strength.warnings, vec![SecretWarning::AllZero]);
+
+		let strength = validate_master_secret(&[0xde, 0xad, 0xbe, 0xef].repeat(4))?;
+		assert_eq!(strength.warnings, vec![SecretWarning::RepeatedPattern(4)]);
+
+		let strength = validate_master_secret(b"bb8e81cd6ae36e3f1a34e11a0bb70c3c")?;
+		assert_eq!(strength.warnings, vec![SecretWarning::AsciiOnly]);
+		Ok(())
+	}
+}
@@ -0,0 +1,107 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of shares for reading out or copying at a terminal
+
+use super::GroupShare;
+use crate::error::{Error, ErrorKind};
+
+use std::fmt::Write;
+
+/// Words per row of the terminal grid
+const GRID_COLUMNS: usize = 4;
+/// Width of the longest word in the wordlist
+const WORD_WIDTH: usize = 8;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+impl GroupShare {
+	/// Render each member share for guided transcription at a terminal: a header with
+	/// the group and member, a short fingerprint two people can compare by voice
+	/// without revealing the share, and the words numbered in a 4 column grid. With
+	/// `ansi`, the header is bold and the numbers dimmed; the layout is the same
+	pub fn render_terminal(&self, ansi: bool) -> Result<String, Error> {
+		let (bold, dim, reset) = match ansi {
+			true => (BOLD, DIM, RESET),
+			false => ("", "", ""),
+		};
+		let fmt_err = |_| ErrorKind::Value("Unable to render group share".to_string());
+		let mut out = String::new();
+		for (i, s) in self.member_shares.iter().enumerate() {
+			if i > 0 {
+				out.push('\n');
+			}
+			let mut header = format!(
+				"Group {} of {}, share {} ({} of {} needed)",
+				self.group_index + 1,
+				self.group_count,
+				s.member_index + 1,
+				self.member_threshold,
+				self.member_shares.len()
+			);
+			if let Some(l) = s.label.as_ref().or(self.label.as_ref()) {
+				write!(header, " - {}", l).map_err(fmt_err)?;
+			}
+			writeln!(out, "{}{}{}", bold, header, reset).map_err(fmt_err)?;
+			writeln!(out, "Fingerprint: {}", s.fingerprint()?).map_err(fmt_err)?;
+			let words = s.to_mnemonic()?;
+			for (row, chunk) in words.chunks(GRID_COLUMNS).enumerate() {
+				let cells: Vec<String> = chunk
+					.iter()
+					.enumerate()
+					.map(|(c, w)| {
+						format!(
+							"{}{:>2}.{} {:<width$}",
+							dim,
+							row * GRID_COLUMNS + c + 1,
+							reset,
+							w,
+							width = WORD_WIDTH
+						)
+					})
+					.collect();
+				writeln!(out, "{}", cells.join("  ").trim_end()).map_err(fmt_err)?;
+			}
+		}
+		Ok(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::generate_mnemonics;
+
+	#[test]
+	fn terminal_rendering() -> Result<(), Error> {
+		let mut groups = generate_mnemonics(1, &[(2, 3)], &[4u8; 16], "", 0)?;
+		groups[0].set_member_label(1, "Bob")?;
+		let plain = groups[0].render_terminal(false)?;
+		let words = groups[0].member_shares[1].to_mnemonic()?;
+		assert!(plain.contains("Group 1 of 1, share 2 (2 of 3 needed) - Bob\n"));
+		assert!(plain.contains(&format!(" 1. {:<8}   2. ", words[0])));
+		assert!(plain.contains(&format!("20. {}\n", words[19])));
+		let fp = groups[0].member_shares[1].fingerprint()?;
+		assert!(plain.contains(&format!("Fingerprint: {}\n", fp)));
+
+		// the ANSI rendering only adds escape sequences
+		let ansi = groups[0].render_terminal(true)?;
+		assert!(ansi.contains(BOLD));
+		let stripped = ansi.replace(BOLD, "").replace(DIM, "").replace(RESET, "");
+		assert_eq!(stripped, plain);
+		Ok(())
+	}
+}
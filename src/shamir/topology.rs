@@ -0,0 +1,266 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validated group layouts, as an alternative to hand-writing the
+//! `(member_threshold, member_count)` slices taken by `generate_mnemonics`
+
+use super::limits::{MAX_GROUP_COUNT, MAX_SHARE_COUNT};
+use crate::error::{Error, ErrorKind};
+
+/// Builds a `&'static [GroupSpec]` from `threshold of count` pairs, checked at compile
+/// time: an invalid group fails the build rather than the split.
+///
+/// ```
+/// use sssmc39::{groups, Topology};
+/// let topology = Topology::new(2, groups![2 of 3, 3 of 5, 1 of 1]).unwrap();
+/// assert_eq!(topology.groups(), vec![(2, 3), (3, 5), (1, 1)]);
+/// ```
+///
+/// ```compile_fail
+/// // threshold exceeds count
+/// let groups = sssmc39::groups![4 of 3];
+/// ```
+#[macro_export]
+macro_rules! groups {
+	( $( $t:literal of $n:literal ),+ $(,)? ) => {{
+		const GROUPS: &[$crate::GroupSpec] = &[ $( $crate::GroupSpec::preset($t, $n) ),+ ];
+		GROUPS
+	}};
+}
+
+/// A validated `member_threshold`-of-`member_count` group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupSpec {
+	threshold: u8,
+	count: u8,
+}
+
+impl GroupSpec {
+	/// A group consisting of a single share
+	pub const ONE_OF_ONE: GroupSpec = GroupSpec::preset(1, 1);
+	/// Any two of three shares
+	pub const TWO_OF_THREE: GroupSpec = GroupSpec::preset(2, 3);
+	/// Any three of five shares
+	pub const THREE_OF_FIVE: GroupSpec = GroupSpec::preset(3, 5);
+
+	/// A group of `count` shares, any `threshold` of which recover the group
+	pub fn single(threshold: u8, count: u8) -> Result<GroupSpec, Error> {
+		validate_group(0, threshold, count, false)?;
+		Ok(GroupSpec { threshold, count })
+	}
+
+	/// A group consisting of a single share, which alone recovers the group
+	pub fn any_one() -> GroupSpec {
+		GroupSpec::ONE_OF_ONE
+	}
+
+	/// As [`GroupSpec::single`], for use in constants: an invalid layout fails to
+	/// compile when evaluated in a const context
+	pub const fn preset(threshold: u8, count: u8) -> GroupSpec {
+		assert!(
+			threshold != 0 && threshold <= count && count <= MAX_SHARE_COUNT,
+			"invalid group layout"
+		);
+		assert!(
+			threshold != 1 || count == 1,
+			"member threshold 1 requires 1 member"
+		);
+		GroupSpec { threshold, count }
+	}
+
+	/// Number of member shares needed to recover the group
+	pub fn threshold(&self) -> u8 {
+		self.threshold
+	}
+
+	/// Number of member shares in the group
+	pub fn count(&self) -> u8 {
+		self.count
+	}
+}
+
+impl From<GroupSpec> for (u8, u8) {
+	fn from(g: GroupSpec) -> (u8, u8) {
+		(g.threshold, g.count)
+	}
+}
+
+/// A group threshold together with its groups, as passed to `generate_mnemonics`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+	group_threshold: u8,
+	groups: Vec<GroupSpec>,
+}
+
+impl Topology {
+	/// Require any `group_threshold` of the given groups
+	pub fn new(group_threshold: u8, groups: &[GroupSpec]) -> Result<Topology, Error> {
+		let layout: Vec<(u8, u8)> = groups.iter().map(|g| (*g).into()).collect();
+		validate_layout(group_threshold, &layout, false)?;
+		Ok(Topology {
+			group_threshold,
+			groups: groups.to_vec(),
+		})
+	}
+
+	/// Number of groups needed to recover the secret
+	pub fn group_threshold(&self) -> u8 {
+		self.group_threshold
+	}
+
+	/// The groups, in the `(member_threshold, member_count)` form used by
+	/// `generate_mnemonics`
+	pub fn groups(&self) -> Vec<(u8, u8)> {
+		self.groups.iter().map(|g| (*g).into()).collect()
+	}
+}
+
+/// Check a group layout against the SLIP-39 constraints, reporting each violation as
+/// its own [`ErrorKind`]. `allow_one_of_n` lifts the rule that a member threshold of 1
+/// requires a single member, for use with the crate-specific threshold 1 digest
+///
+/// ```
+/// use sssmc39::{validate_layout, ErrorKind};
+/// assert!(validate_layout(2, &[(2, 3), (3, 5)], false).is_ok());
+/// let e = validate_layout(3, &[(2, 3), (3, 5)], false).unwrap_err();
+/// assert_eq!(e.kind(), ErrorKind::GroupThreshold(3, 2));
+/// ```
+pub fn validate_layout(
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	allow_one_of_n: bool,
+) -> Result<(), Error> {
+	if groups.is_empty() || groups.len() > MAX_GROUP_COUNT as usize {
+		Err(ErrorKind::GroupCount(groups.len()))?;
+	}
+	if group_threshold == 0 || group_threshold as usize > groups.len() {
+		Err(ErrorKind::GroupThreshold(group_threshold, groups.len()))?;
+	}
+	for (i, (threshold, count)) in groups.iter().enumerate() {
+		validate_group(i, *threshold, *count, allow_one_of_n)?;
+	}
+	Ok(())
+}
+
+fn validate_group(
+	index: usize,
+	threshold: u8,
+	count: u8,
+	allow_one_of_n: bool,
+) -> Result<(), Error> {
+	if threshold == 0 || threshold > count || count > MAX_SHARE_COUNT {
+		Err(ErrorKind::MemberThreshold(index, threshold, count))?;
+	}
+	if threshold == 1 && count > 1 && !allow_one_of_n {
+		Err(ErrorKind::SingleMemberThreshold(index, count))?;
+	}
+	Ok(())
+}
+
+/// Layouts for the most common backup schemes
+pub mod topologies {
+	use super::{GroupSpec, Topology};
+	use crate::error::Error;
+
+	/// A single share holding the whole secret
+	pub fn one_of_one() -> Topology {
+		single_group(GroupSpec::ONE_OF_ONE)
+	}
+
+	/// A single group of shares
+	pub fn single_group(group: GroupSpec) -> Topology {
+		Topology {
+			group_threshold: 1,
+			groups: vec![group],
+		}
+	}
+
+	/// Three groups, any two of which recover the secret
+	pub fn two_of_three_groups(a: GroupSpec, b: GroupSpec, c: GroupSpec) -> Topology {
+		Topology {
+			group_threshold: 2,
+			groups: vec![a, b, c],
+		}
+	}
+
+	/// A group the owner keeps, which must always take part, alongside a group of
+	/// `threshold`-of-`count` trustees
+	pub fn owner_and_trustees(threshold: u8, count: u8) -> Result<Topology, Error> {
+		Topology::new(
+			2,
+			&[GroupSpec::ONE_OF_ONE, GroupSpec::single(threshold, count)?],
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn group_spec() -> Result<(), Error> {
+		assert_eq!(GroupSpec::single(2, 3)?, GroupSpec::TWO_OF_THREE);
+		assert_eq!(GroupSpec::any_one(), GroupSpec::preset(1, 1));
+		assert!(GroupSpec::single(0, 3).is_err());
+		assert_eq!(
+			GroupSpec::single(1, 3).unwrap_err().kind(),
+			ErrorKind::SingleMemberThreshold(0, 3)
+		);
+		assert!(GroupSpec::single(4, 3).is_err());
+		assert!(GroupSpec::single(2, 17).is_err());
+		assert!(Topology::new(3, &[GroupSpec::ONE_OF_ONE; 2]).is_err());
+		assert!(Topology::new(1, &[]).is_err());
+
+		let t = topologies::two_of_three_groups(
+			GroupSpec::TWO_OF_THREE,
+			GroupSpec::THREE_OF_FIVE,
+			GroupSpec::any_one(),
+		);
+		assert_eq!(t.group_threshold(), 2);
+		assert_eq!(t.groups(), vec![(2, 3), (3, 5), (1, 1)]);
+		assert_eq!(
+			validate_layout(1, &[(2, 3), (4, 3)], false)
+				.unwrap_err()
+				.kind(),
+			ErrorKind::MemberThreshold(1, 4, 3)
+		);
+		assert_eq!(
+			validate_layout(1, &[(1, 1); 17], false).unwrap_err().kind(),
+			ErrorKind::GroupCount(17)
+		);
+		assert_eq!(
+			validate_layout(0, &[(1, 1)], false).unwrap_err().kind(),
+			ErrorKind::GroupThreshold(0, 1)
+		);
+		assert!(validate_layout(1, &[(1, 3)], true).is_ok());
+		let t = topologies::owner_and_trustees(2, 4)?;
+		assert_eq!(t.groups(), vec![(1, 1), (2, 4)]);
+		Ok(())
+	}
+
+	#[test]
+	fn groups_macro() -> Result<(), Error> {
+		let g = groups![2 of 3, 3 of 5, 1 of 1,];
+		assert_eq!(
+			g,
+			&[
+				GroupSpec::TWO_OF_THREE,
+				GroupSpec::THREE_OF_FIVE,
+				GroupSpec::ONE_OF_ONE
+			]
+		);
+		assert_eq!(Topology::new(2, g)?.group_threshold(), 2);
+		Ok(())
+	}
+}
@@ -0,0 +1,177 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `sssmc39:` URIs, for moving a share by QR code, NFC tag or deep link with a
+//! self-describing prefix:
+//!
+//! ```text
+//! sssmc39:<identifier>/<group index>.<group threshold>.<group count>/<member index>.<member threshold>/<payload>
+//! ```
+//!
+//! The numbers are decimal, with indices counted from 0 as in the share itself.
+//! The payload is the share's [`codec`](super::codec) encoding in unpadded
+//! uppercase base32, and carries the checksum. The metadata is there so a reader
+//! can tell shares apart without decoding them, and must agree with the payload.
+//! A URI uses only ASCII, and in uppercase fits a QR code's alphanumeric mode.
+//!
+//! Parsing is strict: the scheme is case-insensitive, but there must be exactly
+//! four segments, numbers may not have leading zeros, and no whitespace, query or
+//! fragment is allowed.
+
+use super::{codec, Share};
+use crate::error::{Error, ErrorKind};
+use crate::util::base32::{from_base32, to_base32};
+
+/// Scheme of share URIs
+pub const URI_SCHEME: &str = "sssmc39";
+
+fn uri_error(detail: &str) -> Error {
+	ErrorKind::Value(format!("Invalid share URI: {}", detail)).into()
+}
+
+// a decimal number without sign or leading zeros
+fn parse_number(s: &str, max: u16) -> Result<u16, Error> {
+	if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+	{
+		return Err(uri_error(&format!("'{}' is not a number", s)));
+	}
+	match s.parse::<u16>() {
+		Ok(n) if n <= max => Ok(n),
+		_ => Err(uri_error(&format!("{} is out of range", s))),
+	}
+}
+
+// dot separated numbers, each at most 255
+fn parse_numbers(s: &str, count: usize) -> Result<Vec<u8>, Error> {
+	let numbers = s
+		.split('.')
+		.map(|n| parse_number(n, 255).map(|n| n as u8))
+		.collect::<Result<Vec<u8>, Error>>()?;
+	if numbers.len() != count {
+		return Err(uri_error(&format!("expected {} numbers in '{}'", count, s)));
+	}
+	Ok(numbers)
+}
+
+impl Share {
+	/// Encode the share as a `sssmc39:` URI
+	pub fn to_uri(&self) -> Result<String, Error> {
+		Ok(format!(
+			"{}:{}/{}.{}.{}/{}.{}/{}",
+			URI_SCHEME,
+			self.identifier,
+			self.group_index,
+			self.group_threshold,
+			self.group_count,
+			self.member_index,
+			self.member_threshold,
+			to_base32(codec::encode(self)?)
+		))
+	}
+
+	/// Decode a share from a `sssmc39:` URI, checking the metadata agrees with the
+	/// payload and verifying the checksum
+	pub fn from_uri(uri: &str) -> Result<Share, Error> {
+		let rest = match uri.split_once(':') {
+			Some((scheme, rest)) if scheme.eq_ignore_ascii_case(URI_SCHEME) => rest,
+			_ => return Err(uri_error(&format!("expected the {}: scheme", URI_SCHEME))),
+		};
+		if let Some(c) = rest
+			.chars()
+			.find(|c| !c.is_ascii_alphanumeric() && *c != '/' && *c != '.')
+		{
+			return Err(uri_error(&format!("unexpected character {:?}", c)));
+		}
+		let segments: Vec<&str> = rest.split('/').collect();
+		if segments.len() != 4 {
+			return Err(uri_error(&format!(
+				"expected 4 segments, found {}",
+				segments.len()
+			)));
+		}
+		let identifier = parse_number(segments[0], u16::MAX)?;
+		let group = parse_numbers(segments[1], 3)?;
+		let member = parse_numbers(segments[2], 2)?;
+		let share = codec::decode(&from_base32(segments[3])?)?;
+		if (
+			share.identifier,
+			share.group_index,
+			share.group_threshold,
+			share.group_count,
+			share.member_index,
+			share.member_threshold,
+		) != (
+			identifier, group[0], group[1], group[2], member[0], member[1],
+		) {
+			return Err(uri_error("metadata doesn't match the share"));
+		}
+		Ok(share)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::generate_mnemonics;
+
+	#[test]
+	fn uri_round_trip() -> Result<(), Error> {
+		let groups = generate_mnemonics(2, &[(2, 3), (3, 5)], &[9u8; 16], "", 0)?;
+		let share = &groups[1].member_shares[4];
+		let uri = share.to_uri()?;
+		let prefix = format!("sssmc39:{}/1.2.2/4.3/", share.identifier);
+		assert!(uri.starts_with(&prefix));
+		assert_eq!(&Share::from_uri(&uri)?, share);
+		assert_eq!(&Share::from_uri(&uri.to_uppercase())?, share);
+		Ok(())
+	}
+
+	#[test]
+	fn malformed_uris() -> Result<(), Error> {
+		let groups = generate_mnemonics(1, &[(2, 3)], &[9u8; 16], "", 0)?;
+		let share = &groups[0].member_shares[1];
+		let uri = share.to_uri()?;
+		let (head, payload) = uri.rsplit_once('/').unwrap();
+		let id = share.identifier;
+		let mut flipped = payload.to_string();
+		let last = if flipped.ends_with('A') { "Q" } else { "A" };
+		flipped.replace_range(10..11, if &payload[10..11] == "B" { "C" } else { "B" });
+
+		let bad = vec![
+			format!("slip39:{}", &uri[8..]),
+			uri[8..].to_string(),
+			format!("{}/{}", uri, "A"),
+			format!("{}/{}", head.rsplit_once('/').unwrap().0, payload),
+			format!("sssmc39:0{}/0.1.1/1.2/{}", id, payload),
+			format!("sssmc39:{}/0.1.1/+1.2/{}", id, payload),
+			format!("sssmc39:{}/0.1.1/1.2.3/{}", id, payload),
+			format!("sssmc39:{}/0.1.1/1.256/{}", id, payload),
+			format!("sssmc39:{}/0.1.1/1.2/{}=", id, payload),
+			format!("sssmc39:{}/0.1.1/1.2/{}1", id, payload),
+			format!("sssmc39:{}/0.1.1/2.2/{}", id, payload),
+			format!("sssmc39:{}/0.1.1/1.2/{}", id ^ 1, payload),
+			format!("sssmc39:{}/0.1.1/1.2/{}", id, flipped),
+			format!("{}/{}{}", head, &payload[..payload.len() - 1], last),
+			format!("{} ", uri),
+			format!("{}?x=1", uri),
+			format!("{}#x", uri),
+			String::new(),
+		];
+		assert!(Share::from_uri(&uri).is_ok());
+		for b in bad {
+			assert!(Share::from_uri(&b).is_err(), "accepted {}", b);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,239 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Experimental Shamir secret sharing over GF(2^16), allowing up to 65534 shares.
+//! Mirrors the SLIP-39 construction (digest share at one reserved index, secret at
+//! another), but works on 16-bit symbols. NOT part of SLIP-39: shares can't be
+//! encoded as mnemonics or recovered by any other implementation
+
+use super::{RawShare, SecretSharer};
+use crate::error::{Error, ErrorKind};
+use crate::field::gf65536::{self, Gf65536};
+use crate::util::crypto::hmac_sha256;
+use crate::util::ct;
+use crate::util::secret::SecretBytes;
+
+use rand::{thread_rng, CryptoRng, RngCore};
+/// A share index and value, as produced by [`split_secret_wide`]
+pub type WideShare = (u16, Vec<u8>);
+
+/// Largest number of shares [`split_secret_wide`] can produce
+pub const MAX_WIDE_SHARE_COUNT: u16 = 65534;
+
+const DIGEST_INDEX: u16 = 65534;
+const SECRET_INDEX: u16 = 65535;
+const DIGEST_LEN: usize = 4;
+
+fn to_symbols(bytes: &[u8]) -> Vec<u16> {
+	bytes
+		.chunks(2)
+		.map(|c| u16::from_be_bytes([c[0], c[1]]))
+		.collect()
+}
+
+fn from_symbols(symbols: &[u16]) -> Vec<u8> {
+	symbols.iter().flat_map(|s| s.to_be_bytes()).collect()
+}
+
+fn digest(random_part: &[u8], secret: &[u8]) -> [u8; DIGEST_LEN] {
+	let mut out = [0u8; DIGEST_LEN];
+	out.copy_from_slice(&hmac_sha256(random_part, secret)[..DIGEST_LEN]);
+	out
+}
+
+/// Value at x of the polynomial through the given points, each a share index and
+/// its value as 16-bit symbols
+fn interpolate(points: &[(u16, Vec<u16>)], x: u16) -> Vec<u16> {
+	let x = Gf65536::from_u16(x);
+	let mut ret = vec![0; points[0].1.len()];
+	for (i, (xi, yi)) in points.iter().enumerate() {
+		let xi = Gf65536::from_u16(*xi);
+		let mut basis = Gf65536::one();
+		for (j, (xj, _)) in points.iter().enumerate() {
+			if i != j {
+				let xj = Gf65536::from_u16(*xj);
+				basis *= (x - xj) / (xi - xj);
+			}
+		}
+		gf65536::mul_add(&mut ret, yi, basis);
+	}
+	ret
+}
+
+/// Splits a secret of an even number of bytes (at least 16) into `share_count`
+/// shares over GF(2^16), any `threshold` of which recover it
+pub fn split_secret_wide<R: RngCore + CryptoRng>(
+	threshold: u16,
+	share_count: u16,
+	secret: &[u8],
+	rng: &mut R,
+) -> Result<Vec<WideShare>, Error> {
+	if threshold == 0 || threshold > share_count || share_count > MAX_WIDE_SHARE_COUNT {
+		Err(ErrorKind::Argument(format!(
+			"Threshold {} of {} shares must satisfy 0 < threshold <= share count <= {}",
+			threshold, share_count, MAX_WIDE_SHARE_COUNT
+		)))?;
+	}
+	if secret.len() < 16 || !secret.len().is_multiple_of(2) {
+		Err(ErrorKind::Argument(
+			"Secret must be at least 16 bytes in length and a multiple of 2".to_string(),
+		))?;
+	}
+	if threshold == 1 {
+		return Ok((0..share_count).map(|i| (i, secret.to_vec())).collect());
+	}
+
+	let mut points = Vec::with_capacity(threshold as usize);
+	for i in 0..threshold - 2 {
+		let mut v = vec![0u8; secret.len()];
+		rng.fill_bytes(&mut v);
+		points.push((i, to_symbols(&v)));
+	}
+	let mut digest_share = vec![0u8; secret.len()];
+	rng.fill_bytes(&mut digest_share[DIGEST_LEN..]);
+	let d = digest(&digest_share[DIGEST_LEN..], secret);
+	digest_share[..DIGEST_LEN].copy_from_slice(&d);
+	points.push((DIGEST_INDEX, to_symbols(&digest_share)));
+	points.push((SECRET_INDEX, to_symbols(secret)));
+
+	let mut shares: Vec<WideShare> = points[..threshold as usize - 2]
+		.iter()
+		.map(|(i, v)| (*i, from_symbols(v)))
+		.collect();
+	for i in threshold - 2..share_count {
+		shares.push((i, from_symbols(&interpolate(&points, i))));
+	}
+	Ok(shares)
+}
+
+/// Recovers a secret from at least `threshold` shares created by
+/// [`split_secret_wide`], verifying the digest when the threshold is greater than 1
+pub fn recover_secret_wide(shares: &[WideShare], threshold: u16) -> Result<SecretBytes, Error> {
+	if threshold == 0 || shares.len() < threshold as usize {
+		Err(ErrorKind::Value(format!(
+			"Need {} shares to recover the secret, got {}",
+			threshold,
+			shares.len()
+		)))?;
+	}
+	let shares = &shares[..threshold as usize];
+	let len = shares[0].1.len();
+	if len < 16 || !len.is_multiple_of(2) || shares.iter().any(|s| s.1.len() != len) {
+		Err(ErrorKind::Value(
+			"Invalid set of shares. All share values must have the same, even length".to_string(),
+		))?;
+	}
+	for (i, s) in shares.iter().enumerate() {
+		if s.0 >= MAX_WIDE_SHARE_COUNT || shares[..i].iter().any(|t| t.0 == s.0) {
+			Err(ErrorKind::Value(
+				"Invalid set of shares. Share indices must be unique and in range".to_string(),
+			))?;
+		}
+	}
+	if threshold == 1 {
+		return Ok(SecretBytes::new(shares[0].1.clone()));
+	}
+	let points: Vec<(u16, Vec<u16>)> = shares.iter().map(|(i, v)| (*i, to_symbols(v))).collect();
+	let secret = SecretBytes::new(from_symbols(&interpolate(&points, SECRET_INDEX)));
+	let digest_share = from_symbols(&interpolate(&points, DIGEST_INDEX));
+	if !ct::ct_eq(
+		&digest_share[..DIGEST_LEN],
+		&digest(&digest_share[DIGEST_LEN..], &secret),
+	) {
+		Err(ErrorKind::Digest(
+			"Invalid digest of the shared secret".to_string(),
+		))?;
+	}
+	Ok(secret)
+}
+
+/// [`SecretSharer`] backend splitting over GF(2^16) with the thread RNG, for use
+/// with [`Splitter::with_backend`](crate::Splitter::with_backend). Share counts are
+/// still limited to 255 by [`RawShare`]; use [`split_secret_wide`] for more
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gf65536Sharer;
+
+impl SecretSharer for Gf65536Sharer {
+	fn split(
+		&self,
+		threshold: u8,
+		share_count: u8,
+		shared_secret: &[u8],
+	) -> Result<Vec<RawShare>, Error> {
+		Ok(split_secret_wide(
+			u16::from(threshold),
+			u16::from(share_count),
+			shared_secret,
+			&mut thread_rng(),
+		)?
+		.into_iter()
+		.map(|(i, v)| (i as u8, v))
+		.collect())
+	}
+
+	fn recover(&self, shares: &[RawShare], threshold: u8) -> Result<Vec<u8>, Error> {
+		let shares: Vec<WideShare> = shares
+			.iter()
+			.map(|(i, v)| (u16::from(*i), v.clone()))
+			.collect();
+		Ok(recover_secret_wide(&shares, u16::from(threshold))?.into_vec())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::Splitter;
+	use rand::seq::SliceRandom;
+
+	#[test]
+	fn split_recover_many_shares() -> Result<(), Error> {
+		let mut rng = thread_rng();
+		let mut secret = vec![0u8; 32];
+		rng.fill_bytes(&mut secret);
+		let shares = split_secret_wide(3, 1000, &secret, &mut rng)?;
+		assert_eq!(shares.len(), 1000);
+		for _ in 0..10 {
+			let subset: Vec<WideShare> = shares.choose_multiple(&mut rng, 3).cloned().collect();
+			assert_eq!(recover_secret_wide(&subset, 3)?.as_bytes(), &secret[..]);
+		}
+		// too few shares
+		assert!(recover_secret_wide(&shares[..2], 3).is_err());
+		// corrupt share fails the digest check
+		let mut bad = shares[..3].to_vec();
+		bad[1].1[5] ^= 1;
+		assert!(recover_secret_wide(&bad, 3).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn split_args() {
+		let secret = [7u8; 16];
+		let mut rng = thread_rng();
+		assert!(split_secret_wide(1, 3, &secret, &mut rng).is_ok());
+		assert!(split_secret_wide(0, 3, &secret, &mut rng).is_err());
+		assert!(split_secret_wide(4, 3, &secret, &mut rng).is_err());
+		assert!(split_secret_wide(2, 65535, &secret, &mut rng).is_err());
+		assert!(split_secret_wide(2, 3, &secret[..15], &mut rng).is_err());
+	}
+
+	#[test]
+	fn splitter_backend() -> Result<(), Error> {
+		let sp = Splitter::with_backend(None, Box::new(Gf65536Sharer));
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let shares = sp.split(4, 16, &secret)?;
+		assert_eq!(sp.recover(&shares[9..13], 4)?, secret);
+		Ok(())
+	}
+}
@@ -0,0 +1,305 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wordlists used to encode shares as mnemonics. The SLIP-39 English list is the
+//! default; other lists can be loaded at runtime once they pass [`Wordlist::verify`]
+
+use crate::error::{Error, ErrorKind};
+#[cfg(any(feature = "paranoid", test))]
+use crate::util::ct;
+
+use crate::util::crypto::Sha256Hasher;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Number of words a wordlist must contain
+pub const WORDLIST_LEN: usize = 1024;
+/// Length of the prefix that must identify each word uniquely
+pub const UNIQUE_PREFIX_LEN: usize = 4;
+/// SHA-256 content hash (see [`Wordlist::content_hash`]) of the SLIP-39 English list
+pub const ENGLISH_SHA256: [u8; 32] = [
+	0xbc, 0xc4, 0x55, 0x53, 0x40, 0x33, 0x2d, 0x16, 0x97, 0x18, 0xae, 0xd8, 0xbf, 0x31, 0xdd, 0x9d,
+	0x52, 0x48, 0xcb, 0x7d, 0xa6, 0xe5, 0xd3, 0x55, 0x14, 0x0e, 0xf4, 0xf1, 0xe6, 0x01, 0xee, 0xc3,
+];
+
+/// The SLIP-39 English wordlist, sorted
+pub(crate) static WORDLIST: [&str; WORDLIST_LEN] = include!("wordlists/en.rs");
+
+/// Length of the longest word in the SLIP-39 English wordlist
+#[cfg(any(feature = "paranoid", test))]
+const MAX_WORD_LEN: usize = 8;
+
+/// Index of a word in the SLIP-39 English wordlist
+#[cfg(not(feature = "paranoid"))]
+pub fn lookup_word(word: &str) -> Option<u16> {
+	WORDLIST.binary_search(&word).ok().map(|i| i as u16)
+}
+
+/// Index of a word in the SLIP-39 English wordlist. With the `paranoid` feature,
+/// every entry is compared in constant time, so the time taken (and memory touched)
+/// doesn't reveal which word it was
+#[cfg(feature = "paranoid")]
+pub fn lookup_word(word: &str) -> Option<u16> {
+	lookup_word_ct(word)
+}
+
+// scan the whole list, selecting the matching index with masks rather than branches
+#[cfg(any(feature = "paranoid", test))]
+fn lookup_word_ct(word: &str) -> Option<u16> {
+	let bytes = word.as_bytes();
+	if bytes.len() > MAX_WORD_LEN {
+		return None;
+	}
+	// the length goes in the last byte, so zero padding can't make "acid\0" match "acid"
+	let mut padded = [0u8; MAX_WORD_LEN + 1];
+	padded[..bytes.len()].copy_from_slice(bytes);
+	padded[MAX_WORD_LEN] = bytes.len() as u8;
+	let mut found = 0u16;
+	let mut index = 0u16;
+	for (i, w) in WORDLIST.iter().enumerate() {
+		let mut candidate = [0u8; MAX_WORD_LEN + 1];
+		candidate[..w.len()].copy_from_slice(w.as_bytes());
+		candidate[MAX_WORD_LEN] = w.len() as u8;
+		let eq = u16::from(ct::ct_eq(&candidate, &padded));
+		index |= eq.wrapping_neg() & i as u16;
+		found |= eq;
+	}
+	match found {
+		0 => None,
+		_ => Some(index),
+	}
+}
+
+/// A list of words mapping 10-bit values to mnemonic words
+pub trait Wordlist {
+	/// The word at the given index
+	fn word(&self, index: usize) -> Option<&str>;
+
+	/// Index of the given word
+	fn index_of(&self, word: &str) -> Option<usize>;
+
+	/// Number of words in the list
+	fn word_count(&self) -> usize;
+
+	/// Index of the only word beginning with the given prefix, if exactly one does
+	fn resolve_prefix(&self, prefix: &str) -> Option<usize> {
+		let mut matches =
+			(0..self.word_count()).filter(|i| self.word(*i).is_some_and(|w| w.starts_with(prefix)));
+		match (matches.next(), matches.next()) {
+			(Some(i), None) => Some(i),
+			_ => None,
+		}
+	}
+
+	/// SHA-256 of the words, each followed by a newline
+	fn content_hash(&self) -> [u8; 32] {
+		let mut hasher = Sha256Hasher::new();
+		for i in 0..self.word_count() {
+			hasher.update(self.word(i).unwrap_or_default().as_bytes());
+			hasher.update(b"\n");
+		}
+		hasher.finalize()
+	}
+
+	/// Check the list has exactly 1024 lowercase words, sorted, each identified by its
+	/// first 4 letters, and that lookups agree with the list
+	fn verify(&self) -> Result<(), Error> {
+		if self.word_count() != WORDLIST_LEN {
+			return Err(ErrorKind::Config(format!(
+				"The wordlist should contain {} words, but it contains {} words.",
+				WORDLIST_LEN,
+				self.word_count()
+			)))?;
+		}
+		let mut prev: Option<&str> = None;
+		for i in 0..WORDLIST_LEN {
+			let w = match self.word(i) {
+				Some(w) => w,
+				None => return Err(ErrorKind::Config(format!("Wordlist has no word {}", i)))?,
+			};
+			if w.len() < UNIQUE_PREFIX_LEN || !w.bytes().all(|b| b.is_ascii_lowercase()) {
+				return Err(ErrorKind::Config(format!(
+					"Wordlist word '{}' must be at least {} lowercase letters",
+					w, UNIQUE_PREFIX_LEN
+				)))?;
+			}
+			if let Some(p) = prev {
+				if p >= w {
+					return Err(ErrorKind::Config(format!(
+						"Wordlist is not sorted at '{}'",
+						w
+					)))?;
+				}
+				if p[..UNIQUE_PREFIX_LEN] == w[..UNIQUE_PREFIX_LEN] {
+					return Err(ErrorKind::Config(format!(
+						"Wordlist words '{}' and '{}' share a {} letter prefix",
+						p, w, UNIQUE_PREFIX_LEN
+					)))?;
+				}
+			}
+			if self.index_of(w) != Some(i) {
+				return Err(ErrorKind::Config(format!(
+					"Wordlist lookup of '{}' doesn't return {}",
+					w, i
+				)))?;
+			}
+			prev = Some(w);
+		}
+		Ok(())
+	}
+}
+
+/// The SLIP-39 English wordlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct English;
+
+impl Wordlist for English {
+	fn word(&self, index: usize) -> Option<&str> {
+		WORDLIST.get(index).copied()
+	}
+
+	fn index_of(&self, word: &str) -> Option<usize> {
+		lookup_word(word).map(usize::from)
+	}
+
+	fn word_count(&self) -> usize {
+		WORDLIST.len()
+	}
+}
+
+/// A wordlist loaded at runtime, verified on construction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomWordlist {
+	words: Vec<String>,
+	index: HashMap<String, usize>,
+}
+
+impl CustomWordlist {
+	/// Build a wordlist from the given words
+	pub fn from_words(words: Vec<String>) -> Result<CustomWordlist, Error> {
+		let index = words
+			.iter()
+			.enumerate()
+			.map(|(i, w)| (w.clone(), i))
+			.collect();
+		let list = CustomWordlist { words, index };
+		list.verify()?;
+		Ok(list)
+	}
+
+	/// Parse a whitespace separated list of words, failing unless its content hash
+	/// matches `expected_hash` (if given)
+	pub fn parse(text: &str, expected_hash: Option<&[u8; 32]>) -> Result<CustomWordlist, Error> {
+		let list = CustomWordlist::from_words(text.split_whitespace().map(|w| w.into()).collect())?;
+		if let Some(h) = expected_hash {
+			if list.content_hash() != *h {
+				return Err(ErrorKind::Config(
+					"Wordlist content hash doesn't match".to_string(),
+				))?;
+			}
+		}
+		Ok(list)
+	}
+
+	/// Read and parse a wordlist file, as [`CustomWordlist::parse`]
+	pub fn from_file<P: AsRef<Path>>(
+		path: P,
+		expected_hash: Option<&[u8; 32]>,
+	) -> Result<CustomWordlist, Error> {
+		let text = fs::read_to_string(path.as_ref()).map_err(|e| {
+			ErrorKind::Config(format!(
+				"Unable to read wordlist {}: {}",
+				path.as_ref().display(),
+				e
+			))
+		})?;
+		CustomWordlist::parse(&text, expected_hash)
+	}
+}
+
+impl Wordlist for CustomWordlist {
+	fn word(&self, index: usize) -> Option<&str> {
+		self.words.get(index).map(|w| w.as_str())
+	}
+
+	fn index_of(&self, word: &str) -> Option<usize> {
+		self.index.get(word).copied()
+	}
+
+	fn word_count(&self) -> usize {
+		self.words.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn constant_time_lookup() {
+		for (i, w) in WORDLIST.iter().enumerate() {
+			assert_eq!(lookup_word_ct(w), Some(i as u16));
+		}
+		for w in [
+			"",
+			"acad",
+			"academi",
+			"academics",
+			"zzzz",
+			"Academic",
+			"academic\0",
+			"acid\0",
+			"\0",
+		] {
+			assert_eq!(lookup_word_ct(w), None);
+			assert_eq!(lookup_word_ct(w), lookup_word(w));
+		}
+	}
+
+	#[test]
+	fn wordlist_verify() -> Result<(), Error> {
+		English.verify()?;
+		assert_eq!(English.content_hash(), ENGLISH_SHA256);
+		assert_eq!(English.resolve_prefix("acad"), English.index_of("academic"));
+
+		let text = include_str!("wordlists/en.txt");
+		let custom = CustomWordlist::parse(text, Some(&ENGLISH_SHA256))?;
+		assert_eq!(custom.index_of("zero"), English.index_of("zero"));
+		assert!(CustomWordlist::parse(text, Some(&[0u8; 32])).is_err());
+
+		let words: Vec<String> = text.split_whitespace().map(|w| w.into()).collect();
+		let mut unsorted = words.clone();
+		unsorted.swap(0, 1);
+		assert!(CustomWordlist::from_words(unsorted).is_err());
+		assert!(CustomWordlist::from_words(words[1..].to_vec()).is_err());
+		let mut prefixed = words.clone();
+		prefixed[1] = format!("{}x", prefixed[0]);
+		assert!(CustomWordlist::from_words(prefixed).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn static_wordlist() {
+		let text: Vec<&str> = include_str!("wordlists/en.txt")
+			.split_whitespace()
+			.collect();
+		assert_eq!(&WORDLIST[..], &text[..]);
+		for (i, w) in WORDLIST.iter().enumerate() {
+			assert_eq!(lookup_word(w), Some(i as u16));
+		}
+		assert_eq!(lookup_word("zebra"), None);
+		assert_eq!(lookup_word(""), None);
+	}
+}
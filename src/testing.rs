@@ -0,0 +1,264 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities to deliberately corrupt shares in controlled ways, for exercising
+//! error handling against realistic tampered or mis-transcribed backups.
+//! Only available with the `testing` feature. Also holds a reference implementation
+//! of the SLIP-39 encryption, to check the optimised one against
+
+use crate::error::{Error, ErrorKind};
+use crate::shamir::wordlist::WORDLIST;
+use crate::shamir::{lookup_word, Share};
+use crate::util::crypto;
+
+fn word_index(mnemonic: &[String], position: usize) -> Result<usize, Error> {
+	match mnemonic.get(position) {
+		Some(w) => match lookup_word(w) {
+			Some(i) => Ok(i as usize),
+			None => Err(ErrorKind::Mnemonic(format!(
+				"'{}' is not an SSSMC39 word.",
+				w
+			)))?,
+		},
+		None => Err(ErrorKind::Argument(format!(
+			"Position {} is outside of the mnemonic ({} words)",
+			position,
+			mnemonic.len()
+		)))?,
+	}
+}
+
+/// Replace the word at the given position with a different (valid) word, which
+/// invalidates the checksum
+pub fn flip_word(mnemonic: &[String], position: usize) -> Result<Vec<String>, Error> {
+	let i = word_index(mnemonic, position)?;
+	let mut ret = mnemonic.to_vec();
+	ret[position] = WORDLIST[i ^ 1].to_owned();
+	Ok(ret)
+}
+
+/// Swap the words at the two given positions
+pub fn swap_words(mnemonic: &[String], a: usize, b: usize) -> Result<Vec<String>, Error> {
+	word_index(mnemonic, a)?;
+	word_index(mnemonic, b)?;
+	let mut ret = mnemonic.to_vec();
+	ret.swap(a, b);
+	Ok(ret)
+}
+
+/// The share's mnemonic with an invalid checksum
+pub fn break_checksum(share: &Share) -> Result<Vec<String>, Error> {
+	share.with_invalid_checksum()
+}
+
+/// The share's mnemonic re-encoded (with a valid checksum) under a different
+/// group index, as a forger who knows the format might produce
+pub fn change_group_index(share: &Share, group_index: u8) -> Result<Vec<String>, Error> {
+	let mut s = share.clone();
+	s.group_index = group_index;
+	s.to_mnemonic()
+}
+
+/// The share's mnemonic re-encoded (with a valid checksum) under a different
+/// member index
+pub fn change_member_index(share: &Share, member_index: u8) -> Result<Vec<String>, Error> {
+	let mut s = share.clone();
+	s.member_index = member_index;
+	s.to_mnemonic()
+}
+
+/// The share's mnemonic re-encoded (with a valid checksum) with one bit of the
+/// share value flipped, simulating undetectable corruption of the payload
+pub fn flip_share_value_bit(share: &Share, bit: usize) -> Result<Vec<String>, Error> {
+	let mut s = share.clone();
+	match s.share_value.get_mut(bit / 8) {
+		Some(b) => *b ^= 0x80 >> (bit % 8),
+		None => {
+			Err(ErrorKind::Argument(format!(
+				"Bit {} is outside of the share value",
+				bit
+			)))?;
+		}
+	}
+	s.to_mnemonic()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+	crypto::hmac_sha256(key, data).to_vec()
+}
+
+// PBKDF2 as written in RFC 8018, section 5.2
+fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], iterations: u32, dk_len: usize) -> Vec<u8> {
+	let mut dk = vec![];
+	let mut block = 1u32;
+	while dk.len() < dk_len {
+		let mut u = hmac_sha256(password, &[salt, &block.to_be_bytes()].concat());
+		let mut t = u.clone();
+		for _ in 1..iterations {
+			u = hmac_sha256(password, &u);
+			for (a, b) in t.iter_mut().zip(&u) {
+				*a ^= b;
+			}
+		}
+		dk.extend(t);
+		block += 1;
+	}
+	dk.truncate(dk_len);
+	dk
+}
+
+// F(i, R) = PBKDF2(PRF = HMAC-SHA256, Password = (i || passphrase),
+//   Salt = ("shamir" || id || R), iterations = 2500 << e, dkLen = n/2 bytes)
+fn reference_round(i: u8, passphrase: &str, e: u8, id: u16, r: &[u8]) -> Vec<u8> {
+	let password = [&[i], passphrase.as_bytes()].concat();
+	let salt = [&b"shamir"[..], &id.to_be_bytes(), r].concat();
+	pbkdf2_hmac_sha256(&password, &salt, 2500 << e, r.len())
+}
+
+fn reference_feistel(input: &[u8], passphrase: &str, e: u8, id: u16, rounds: &[u8]) -> Vec<u8> {
+	let mut l = input[..input.len() / 2].to_vec();
+	let mut r = input[input.len() / 2..].to_vec();
+	for &i in rounds {
+		let f = reference_round(i, passphrase, e, id, &r);
+		let new_r: Vec<u8> = l.iter().zip(&f).map(|(a, b)| a ^ b).collect();
+		l = r;
+		r = new_r;
+	}
+	[r, l].concat()
+}
+
+/// SLIP-39 master secret encryption, transcribed directly from the specification
+/// with its own PBKDF2. Slow; for checking `MasterSecretEnc` against
+pub fn reference_encrypt(
+	master_secret: &[u8],
+	passphrase: &str,
+	iteration_exponent: u8,
+	identifier: u16,
+) -> Vec<u8> {
+	reference_feistel(
+		master_secret,
+		passphrase,
+		iteration_exponent,
+		identifier,
+		&[0, 1, 2, 3],
+	)
+}
+
+/// Inverse of [`reference_encrypt`]
+pub fn reference_decrypt(
+	enc_master_secret: &[u8],
+	passphrase: &str,
+	iteration_exponent: u8,
+	identifier: u16,
+) -> Vec<u8> {
+	reference_feistel(
+		enc_master_secret,
+		passphrase,
+		iteration_exponent,
+		identifier,
+		&[3, 2, 1, 0],
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::shamir::{combine_mnemonics, generate_mnemonics};
+	use crate::util::encrypt::MasterSecretEnc;
+	use crate::util::hex::from_hex;
+	use quickcheck::{QuickCheck, TestResult};
+
+	#[test]
+	fn tampered_shares() -> Result<(), Error> {
+		let master_secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let groups = generate_mnemonics(1, &[(2, 3)], &master_secret, "", 0)?;
+		let shares = &groups[0].member_shares;
+		let mn0 = shares[0].to_mnemonic()?;
+		let mn1 = shares[1].to_mnemonic()?;
+		assert_eq!(
+			combine_mnemonics(&[mn0.clone(), mn1.clone()], "")?.secret,
+			master_secret
+		);
+
+		let flipped = flip_word(&mn0, 7)?;
+		assert_ne!(flipped[7], mn0[7]);
+		assert!(Share::from_mnemonic(&flipped).is_err());
+		assert!(flip_word(&mn0, 20).is_err());
+
+		// the share is random, so swap word 5 with a later word that differs from it
+		let other = (6..mn0.len()).find(|&i| mn0[i] != mn0[5]).unwrap();
+		assert!(Share::from_mnemonic(&swap_words(&mn0, 5, other)?).is_err());
+		assert!(Share::from_mnemonic(&break_checksum(&shares[0])?).is_err());
+
+		// checksums are valid, but recovery fails
+		let moved = change_group_index(&shares[0], 1)?;
+		assert_eq!(Share::from_mnemonic(&moved)?.group_index, 1);
+		assert!(combine_mnemonics(&[moved, mn1.clone()], "").is_err());
+		let corrupt = flip_share_value_bit(&shares[0], 3)?;
+		assert!(combine_mnemonics(&[corrupt, mn1.clone()], "").is_err());
+		let dup = change_member_index(&shares[0], shares[1].member_index)?;
+		assert!(combine_mnemonics(&[dup, mn1], "").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn reference_matches_spec_vector() {
+		// first SLIP-39 test vector: a single 1-of-1 share, so its value is the
+		// encrypted master secret
+		let mnemonic = "duckling enlarge academic academic agency result length solution fridge \
+			kidney coal piece deal husband erode duke ajar critical decision keyboard";
+		let share = Share::from_mnemonic(
+			&mnemonic
+				.split_whitespace()
+				.map(String::from)
+				.collect::<Vec<_>>(),
+		)
+		.unwrap();
+		let ems = share.share_value.clone();
+		let ms = reference_decrypt(&ems, "TREZOR", 0, share.identifier);
+		assert_eq!(ms, from_hex("bb54aac4b89dc868ba37d9cc21b2cece").unwrap());
+		assert_eq!(reference_encrypt(&ms, "TREZOR", 0, 7945), ems);
+	}
+
+	fn matches_reference(
+		secret: Vec<u8>,
+		passphrase: String,
+		iteration_exponent: u8,
+		identifier: u16,
+	) -> TestResult {
+		let mut secret = secret;
+		secret.truncate(32);
+		if secret.len() % 2 == 1 {
+			secret.pop();
+		}
+		secret.resize(secret.len().max(16), 0x5a);
+		let e = iteration_exponent % 2;
+		let enc = MasterSecretEnc::default();
+		let ems = enc.encrypt(&secret, &passphrase, e, identifier);
+		if ems != reference_encrypt(&secret, &passphrase, e, identifier) {
+			return TestResult::failed();
+		}
+		TestResult::from_bool(
+			enc.decrypt(&ems, &passphrase, e, identifier)
+				== reference_decrypt(&ems, &passphrase, e, identifier),
+		)
+	}
+
+	#[test]
+	fn encryption_matches_reference() {
+		QuickCheck::new()
+			.tests(10)
+			.quickcheck(matches_reference as fn(Vec<u8>, String, u8, u16) -> TestResult);
+	}
+}
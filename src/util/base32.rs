@@ -0,0 +1,106 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! RFC 4648 base32 without padding, as used in share URIs. The uppercase alphabet
+//! and digits fit QR codes' compact alphanumeric mode
+
+use crate::error::{Error, ErrorKind};
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode the provided bytes into an unpadded, uppercase base32 string
+pub fn to_base32(bytes: impl AsRef<[u8]>) -> String {
+	let bytes = bytes.as_ref();
+	let mut s = String::with_capacity((bytes.len() * 8).div_ceil(5));
+	let (mut acc, mut bits) = (0u16, 0);
+	for b in bytes {
+		acc = acc << 8 | u16::from(*b);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			s.push(ALPHABET[(acc >> bits & 0x1f) as usize] as char);
+		}
+	}
+	if bits > 0 {
+		s.push(ALPHABET[(acc << (5 - bits) & 0x1f) as usize] as char);
+	}
+	s
+}
+
+/// Decode an unpadded base32 string, in either case. Only canonical encodings are
+/// accepted: the length must be one [`to_base32`] produces and unused trailing bits
+/// must be zero
+pub fn from_base32(s: &str) -> Result<Vec<u8>, Error> {
+	if matches!(s.len() % 8, 1 | 3 | 6) {
+		Err(ErrorKind::Value(format!(
+			"Invalid base32 length {}",
+			s.len()
+		)))?;
+	}
+	let mut out = Vec::with_capacity(s.len() * 5 / 8);
+	let (mut acc, mut bits) = (0u16, 0);
+	for (i, c) in s.chars().enumerate() {
+		let v = match c.to_ascii_uppercase() {
+			c @ 'A'..='Z' => c as u16 - 'A' as u16,
+			c @ '2'..='7' => c as u16 - '2' as u16 + 26,
+			_ => {
+				return Err(ErrorKind::Value(format!(
+					"Invalid base32 character '{}' at {}",
+					c, i
+				)))?
+			}
+		};
+		acc = (acc << 5 | v) & 0xfff;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((acc >> bits) as u8);
+		}
+	}
+	if acc & ((1 << bits) - 1) != 0 {
+		Err(ErrorKind::Value(
+			"Non-zero trailing bits in base32".to_string(),
+		))?;
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rfc4648_vectors() -> Result<(), Error> {
+		let vectors = [
+			("", ""),
+			("f", "MY"),
+			("fo", "MZXQ"),
+			("foo", "MZXW6"),
+			("foob", "MZXW6YQ"),
+			("fooba", "MZXW6YTB"),
+			("foobar", "MZXW6YTBOI"),
+		];
+		for (input, output) in vectors.iter() {
+			assert_eq!(to_base32(input), *output);
+			assert_eq!(from_base32(output)?, input.as_bytes());
+			assert_eq!(from_base32(&output.to_lowercase())?, input.as_bytes());
+		}
+		assert!(from_base32("MZXW6=").is_err());
+		assert!(from_base32("MZXW1").is_err());
+		assert!(from_base32("MZX").is_err());
+		// "MZ" also decodes to "f", but with a set trailing bit
+		assert!(from_base32("MZ").is_err());
+		Ok(())
+	}
+}
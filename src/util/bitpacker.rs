@@ -13,154 +13,119 @@
 // limitations under the License.
 
 //! Operations that allow packing bits from primitives into a bitvec
-//! Slower, but easier to follow and modify than a lot of bit twiddling
-//! BigEndian, as is bitvec default
+//! Values are written and read a whole field at a time via bitvec's
+//! `BitField` trait, with a `BitReader` for sequential field access
+//! BigEndian (most significant bit first)
 
 use bitvec::prelude::*;
 
 use crate::error::{Error, ErrorKind};
 
+/// Unsigned integer types that can be packed into and read from a BitPacker
+pub trait Packable: Copy + Into<u64> {
+	/// Width of the type in bits
+	const BITS: u8;
+	/// Truncating conversion from the low bits of a u64
+	fn from_u64(val: u64) -> Self;
+}
+
+macro_rules! impl_packable {
+	($t:ty) => {
+		impl Packable for $t {
+			const BITS: u8 = <$t>::BITS as u8;
+			fn from_u64(val: u64) -> Self {
+				val as $t
+			}
+		}
+	};
+}
+
+impl_packable!(u8);
+impl_packable!(u16);
+impl_packable!(u32);
+impl_packable!(u64);
+
 /// Simple struct that wraps a bitvec and defines packing operations on it
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct BitPacker {
-	bv: BitVec,
+	bv: BitVec<u8, Msb0>,
 }
 
-//TODO:
-// * Works, but:
-// * Faster
-// * Generics
-// * Iterator for reading values
-//
-
 impl BitPacker {
 	/// Create a new bitpacker
 	pub fn new() -> Self {
 		BitPacker { bv: BitVec::new() }
 	}
 
-	/// Remove bits from end to meet boundary (for reading in u8 arrays)
-	pub fn normalize(&mut self, radix: usize) {
-		while self.bv.len() % radix != 0 {
-			self.bv.pop();
-		}
-	}
-
 	/// Append num_bits of zero padding to the internal bitvec
 	pub fn append_padding(&mut self, num_bits: u8) {
-		for _ in 0..num_bits {
-			self.bv.push(false);
-		}
+		let len = self.bv.len() + num_bits as usize;
+		self.bv.resize(len, false);
 	}
 
 	/// Append each element of a u8 vec to the bitvec
 	pub fn append_vec_u8(&mut self, data: &[u8]) -> Result<(), Error> {
-		for b in data {
-			self.append_u8(*b, 8)?;
-		}
+		self.bv.extend_from_bitslice(data.view_bits::<Msb0>());
 		Ok(())
 	}
 
 	/// Return n u8s from bitvec
-	pub fn get_vec_u8(&mut self, start_pos: usize, len: usize) -> Result<Vec<u8>, Error> {
+	pub fn get_vec_u8(&self, start_pos: usize, len: usize) -> Result<Vec<u8>, Error> {
 		let mut retvec = vec![];
-		for i in (start_pos..len * 8).step_by(8) {
-			retvec.push(self.get_u8(i, 8)?);
+		for i in 0..len {
+			retvec.push(self.get(start_pos + i * 8, 8)?);
 		}
 		Ok(retvec)
 	}
 
-	/// Append first num_bits of a u32 to the bitvec. num_bits must be <= 32
-	pub fn append_u32(&mut self, val: u32, num_bits: u8) -> Result<(), Error> {
-		if num_bits > 32 {
-			return Err(ErrorKind::BitVec(
-				"number of bits to pack must be <= 32".to_string(),
-			))?;
-		}
-		for i in (0u8..num_bits).rev() {
-			if val & 2u32.pow(u32::from(i)) == 0 {
-				self.bv.push(false);
-			} else {
-				self.bv.push(true);
-			}
-		}
-		Ok(())
-	}
-
-	/// Append first num_bits of a u16 to the bitvec. num_bits must be <= 16
-	pub fn append_u16(&mut self, val: u16, num_bits: u8) -> Result<(), Error> {
-		if num_bits > 16 {
-			return Err(ErrorKind::BitVec(
-				"number of bits to pack must be <= 16".to_string(),
-			))?;
-		}
-		for i in (0u8..num_bits).rev() {
-			if val & 2u16.pow(u32::from(i)) == 0 {
-				self.bv.push(false);
-			} else {
-				self.bv.push(true);
-			}
+	// append the lowest num_bits of val, most significant first
+	fn append_bits(&mut self, val: u64, num_bits: u8, max_bits: u8) -> Result<(), Error> {
+		if num_bits > max_bits {
+			return Err(ErrorKind::BitVec(format!(
+				"number of bits to pack must be <= {}",
+				max_bits
+			)))?;
 		}
+		let bytes = val.to_be_bytes();
+		self.bv
+			.extend_from_bitslice(&bytes.view_bits::<Msb0>()[64 - num_bits as usize..]);
 		Ok(())
 	}
 
-	/// Append first num_bits of a u8 to the bitvec, num_bits must be <= 8
-	pub fn append_u8(&mut self, val: u8, num_bits: u8) -> Result<(), Error> {
-		if num_bits > 8 {
-			return Err(ErrorKind::BitVec(
-				"number of bits to pack must be <= 8".to_string(),
-			))?;
+	// read num_bits from index as the low bits of a u64. Bits beyond the end
+	// of the bitvec are read as 0
+	fn get_bits(&self, index: usize, num_bits: usize, max_bits: usize) -> Result<u64, Error> {
+		if num_bits > max_bits {
+			return Err(ErrorKind::BitVec(format!(
+				"number of bits to read must be <= {}",
+				max_bits
+			)))?;
 		}
-		for i in (0u8..num_bits).rev() {
-			if val & 2u8.pow(u32::from(i)) == 0 {
-				self.bv.push(false);
-			} else {
-				self.bv.push(true);
-			}
+		if num_bits == 0 {
+			return Ok(0);
 		}
-		Ok(())
-	}
-
-	/// Retrieve num_bits from the given index as a u8
-	pub fn get_u8(&self, index: usize, num_bits: usize) -> Result<u8, Error> {
-		let mut retval: u8 = 0;
-		for i in index..index + num_bits {
-			if i < self.bv.len() && self.bv[i] {
-				retval += 1;
-			}
-			if i < index + num_bits - 1 {
-				retval <<= 1;
-			}
+		let end = (index + num_bits).min(self.bv.len());
+		if index >= end {
+			return Ok(0);
 		}
-		Ok(retval)
+		let val: u64 = self.bv[index..end].load_be();
+		Ok(val << (index + num_bits - end))
 	}
 
-	/// Retrieve num_bits from the given index as a u16
-	pub fn get_u16(&self, index: usize, num_bits: usize) -> Result<u16, Error> {
-		let mut retval: u16 = 0;
-		for i in index..index + num_bits {
-			if i < self.bv.len() && self.bv[i] {
-				retval += 1;
-			}
-			if i < index + num_bits - 1 {
-				retval <<= 1;
-			}
-		}
-		Ok(retval)
+	/// Append the lowest num_bits of val to the bitvec. num_bits must be <= the bit
+	/// width of T
+	pub fn append<T: Packable>(&mut self, val: T, num_bits: u8) -> Result<(), Error> {
+		self.append_bits(val.into(), num_bits, T::BITS)
 	}
 
-	/// Retrieve num_bits from the given index as a u32
-	pub fn get_u32(&self, index: usize, num_bits: usize) -> Result<u32, Error> {
-		let mut retval: u32 = 0;
-		for i in index..index + num_bits {
-			if i < self.bv.len() && self.bv[i] {
-				retval += 1;
-			}
-			if i < index + num_bits - 1 {
-				retval <<= 1;
-			}
-		}
-		Ok(retval)
+	/// Retrieve num_bits from the given index as a T. num_bits must be <= the bit
+	/// width of T
+	pub fn get<T: Packable>(&self, index: usize, num_bits: usize) -> Result<T, Error> {
+		Ok(T::from_u64(self.get_bits(
+			index,
+			num_bits,
+			T::BITS as usize,
+		)?))
 	}
 
 	/// Return length of internal bit vector
@@ -170,19 +135,26 @@ impl BitPacker {
 
 	/// Return bitvec between m and n
 	pub fn split_out(&mut self, m: usize, n: usize) {
-		self.bv.split_off(n);
+		self.bv.truncate(n);
 		self.bv = self.bv.split_off(m);
 	}
 
-	/// Return bitvec between m and n
+	/// Remove num_bits of leading padding, which must all be 0
 	pub fn remove_padding(&mut self, num_bits: usize) -> Result<(), Error> {
-		let mut removed = self.bv.clone();
-		self.bv = removed.split_off(num_bits);
-		if removed.count_ones() > 0 {
+		if self.bv[..num_bits].any() {
 			return Err(ErrorKind::Padding)?;
 		}
+		self.bv = self.bv.split_off(num_bits);
 		Ok(())
 	}
+
+	/// Iterate over the packed bits as consecutive num_bits wide values (at most 32).
+	/// A trailing partial value is padded with 0 bits
+	pub fn chunks(&self, num_bits: usize) -> impl Iterator<Item = u32> + '_ {
+		(0..self.len())
+			.step_by(num_bits)
+			.map(move |i| self.get_bits(i, num_bits, 32).unwrap_or(0) as u32)
+	}
 }
 
 #[cfg(test)]
@@ -199,18 +171,46 @@ mod tests {
 		let val3: u8 = 15;
 		let val4: u8 = 8;
 		let val5: u16 = 934;
-		bp.append_u16(val1, 15)?;
-		bp.append_u8(val2, 5)?;
-		bp.append_u8(val3, 4)?;
-		bp.append_u8(val4, 4)?;
-		bp.append_u16(val5, 10)?;
+		bp.append(val1, 15)?;
+		bp.append(val2, 5)?;
+		bp.append(val3, 4)?;
+		bp.append(val4, 4)?;
+		bp.append(val5, 10)?;
 		assert_eq!(bp.len(), 38);
-		assert_eq!(val1, bp.get_u16(0, 15)?);
-		assert_eq!(val2, bp.get_u8(15, 5)?);
-		assert_eq!(val3, bp.get_u8(20, 4)?);
-		assert_eq!(val4, bp.get_u8(24, 4)?);
-		assert_eq!(val5, bp.get_u16(28, 10)?);
-		assert_eq!(u32::from(val5), bp.get_u32(28, 10)?);
+		assert_eq!(val1, bp.get::<u16>(0, 15)?);
+		assert_eq!(val2, bp.get::<u8>(15, 5)?);
+		assert_eq!(val3, bp.get::<u8>(20, 4)?);
+		assert_eq!(val4, bp.get::<u8>(24, 4)?);
+		assert_eq!(val5, bp.get::<u16>(28, 10)?);
+		assert!(bp.append(1u8, 9).is_err());
+		assert!(bp.append(1u64, 65).is_err());
+		assert!(bp.get::<u16>(0, 17).is_err());
+
+		let mut wide = BitPacker::new();
+		wide.append(0x2aaa_aaaau32, 30)?;
+		wide.append(0xf_0f0fu32, 20)?;
+		assert_eq!(wide.get::<u32>(0, 30)?, 0x2aaa_aaaa);
+		assert_eq!(wide.get::<u32>(30, 20)?, 0xf_0f0f);
+		assert_eq!(wide.get::<u64>(0, 50)?, 0x2aaa_aaaa << 20 | 0xf_0f0f);
+
+		// trailing partial chunk is padded with zeros
+		let chunks: Vec<u32> = bp.chunks(10).collect();
+		assert_eq!(chunks.len(), 4);
+		assert_eq!(chunks[3], u32::from(val5 & 0xff) << 2);
+		Ok(())
+	}
+
+	#[test]
+	fn bit_packer_bytes() -> Result<(), Error> {
+		let data = vec![0x84, 0x06, 0xce, 0xa0, 0x70, 0xbf];
+		let mut bp = BitPacker::new();
+		bp.append_padding(2);
+		bp.append_vec_u8(&data)?;
+		assert_eq!(bp.len(), 50);
+		assert!(bp.clone().remove_padding(3).is_err());
+		bp.remove_padding(2)?;
+		assert_eq!(bp.get_vec_u8(0, data.len())?, data);
+		assert_eq!(bp.get_vec_u8(8, 2)?, vec![0x06, 0xce]);
 		Ok(())
 	}
 }
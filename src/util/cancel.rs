@@ -0,0 +1,50 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cooperative cancellation of long running operations
+
+use crate::error::{Error, ErrorKind};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag for cancelling a long running operation (e.g. a passphrase KDF at a
+/// high iteration exponent) from another thread. Clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+	/// A token that hasn't been cancelled
+	pub fn new() -> Self {
+		CancellationToken::default()
+	}
+
+	/// Request cancellation of the operations watching this token
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	/// Whether cancellation was requested
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	/// Fail with [`ErrorKind::Cancelled`] if cancellation was requested
+	pub(crate) fn check(&self) -> Result<(), Error> {
+		if self.is_cancelled() {
+			return Err(ErrorKind::Cancelled)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,242 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HMAC, SHA-2 and PBKDF2 primitives behind one interface, so that the implementation
+//! is chosen by a feature flag in this module rather than at each call site.
+//! RustCrypto's hmac and sha2 crates are used by default; the `crypto-ring` feature
+//! routes everything through ring instead, and `crypto-aws-lc` through aws-lc-rs
+//! (taking precedence if both are enabled).
+
+use crate::error::Error;
+use crate::util::ct;
+use crate::util::secret::SecretBytes;
+
+/// Length of a SHA-256 digest in bytes
+pub const SHA256_LEN: usize = 32;
+/// Length of a SHA-512 digest in bytes
+pub const SHA512_LEN: usize = 64;
+
+#[cfg(not(any(feature = "crypto-ring", feature = "crypto-aws-lc")))]
+mod backend {
+	use super::{SHA256_LEN, SHA512_LEN};
+	use hmac::{Hmac, Mac};
+	use sha2::{Digest, Sha256, Sha512};
+
+	/// A key for HMAC-SHA256, reusable across messages
+	#[derive(Clone)]
+	pub struct HmacSha256Key(Hmac<Sha256>);
+
+	impl HmacSha256Key {
+		pub fn new(key: &[u8]) -> Self {
+			HmacSha256Key(Hmac::new_from_slice(key).expect("HMAC takes keys of any size"))
+		}
+
+		/// HMAC of the concatenation of `parts`
+		pub fn sign(&self, parts: &[&[u8]]) -> [u8; SHA256_LEN] {
+			let mut mac = self.0.clone();
+			parts.iter().for_each(|p| mac.update(p));
+			mac.finalize().into_bytes().into()
+		}
+	}
+
+	/// A key for HMAC-SHA512, reusable across messages
+	#[derive(Clone)]
+	pub struct HmacSha512Key(Hmac<Sha512>);
+
+	impl HmacSha512Key {
+		pub fn new(key: &[u8]) -> Self {
+			HmacSha512Key(Hmac::new_from_slice(key).expect("HMAC takes keys of any size"))
+		}
+
+		/// HMAC of the concatenation of `parts`
+		pub fn sign(&self, parts: &[&[u8]]) -> [u8; SHA512_LEN] {
+			let mut mac = self.0.clone();
+			parts.iter().for_each(|p| mac.update(p));
+			mac.finalize().into_bytes().into()
+		}
+	}
+
+	/// Incremental SHA-256
+	#[derive(Clone)]
+	pub struct Sha256Hasher(Sha256);
+
+	impl Sha256Hasher {
+		pub fn new() -> Self {
+			Sha256Hasher(Sha256::new())
+		}
+
+		pub fn update(&mut self, data: &[u8]) {
+			self.0.update(data);
+		}
+
+		pub fn finalize(self) -> [u8; SHA256_LEN] {
+			self.0.finalize().into()
+		}
+	}
+}
+
+// aws-lc-rs mirrors ring's API, so the same code serves both
+#[cfg(any(feature = "crypto-ring", feature = "crypto-aws-lc"))]
+mod backend {
+	use super::{SHA256_LEN, SHA512_LEN};
+	#[cfg(feature = "crypto-aws-lc")]
+	use aws_lc_rs::{digest, hmac};
+	#[cfg(not(feature = "crypto-aws-lc"))]
+	use ring::{digest, hmac};
+
+	/// A key for HMAC-SHA256, reusable across messages
+	#[derive(Clone)]
+	pub struct HmacSha256Key(hmac::Key);
+
+	impl HmacSha256Key {
+		pub fn new(key: &[u8]) -> Self {
+			HmacSha256Key(hmac::Key::new(hmac::HMAC_SHA256, key))
+		}
+
+		/// HMAC of the concatenation of `parts`
+		pub fn sign(&self, parts: &[&[u8]]) -> [u8; SHA256_LEN] {
+			let mut ctx = hmac::Context::with_key(&self.0);
+			parts.iter().for_each(|p| ctx.update(p));
+			let mut out = [0u8; SHA256_LEN];
+			out.copy_from_slice(ctx.sign().as_ref());
+			out
+		}
+	}
+
+	/// A key for HMAC-SHA512, reusable across messages
+	#[derive(Clone)]
+	pub struct HmacSha512Key(hmac::Key);
+
+	impl HmacSha512Key {
+		pub fn new(key: &[u8]) -> Self {
+			HmacSha512Key(hmac::Key::new(hmac::HMAC_SHA512, key))
+		}
+
+		/// HMAC of the concatenation of `parts`
+		pub fn sign(&self, parts: &[&[u8]]) -> [u8; SHA512_LEN] {
+			let mut ctx = hmac::Context::with_key(&self.0);
+			parts.iter().for_each(|p| ctx.update(p));
+			let mut out = [0u8; SHA512_LEN];
+			out.copy_from_slice(ctx.sign().as_ref());
+			out
+		}
+	}
+
+	/// Incremental SHA-256
+	#[derive(Clone)]
+	pub struct Sha256Hasher(digest::Context);
+
+	impl Sha256Hasher {
+		pub fn new() -> Self {
+			Sha256Hasher(digest::Context::new(&digest::SHA256))
+		}
+
+		pub fn update(&mut self, data: &[u8]) {
+			self.0.update(data);
+		}
+
+		pub fn finalize(self) -> [u8; SHA256_LEN] {
+			let mut out = [0u8; SHA256_LEN];
+			out.copy_from_slice(self.0.finish().as_ref());
+			out
+		}
+	}
+}
+
+pub use backend::{HmacSha256Key, HmacSha512Key, Sha256Hasher};
+
+/// HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; SHA256_LEN] {
+	HmacSha256Key::new(key).sign(&[data])
+}
+
+/// HMAC-SHA512 of `data` under `key`
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; SHA512_LEN] {
+	HmacSha512Key::new(key).sign(&[data])
+}
+
+/// SHA-256 of `data`
+pub fn sha256(data: &[u8]) -> [u8; SHA256_LEN] {
+	let mut h = Sha256Hasher::new();
+	h.update(data);
+	h.finalize()
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018), calling `check` with the iteration count every
+/// `check_interval` iterations so that long derivations can be abandoned
+pub fn pbkdf2_hmac_sha256(
+	password: &[u8],
+	salt: &[u8],
+	iterations: u32,
+	out: &mut [u8],
+	check_interval: u32,
+	check: &mut dyn FnMut(u32) -> Result<(), Error>,
+) -> Result<(), Error> {
+	let prf = HmacSha256Key::new(password);
+	let mut u = SecretBytes::zeroed(SHA256_LEN);
+	let mut t = SecretBytes::zeroed(SHA256_LEN);
+	for (block, chunk) in out.chunks_mut(SHA256_LEN).enumerate() {
+		u.as_mut_bytes()
+			.copy_from_slice(&prf.sign(&[salt, &(block as u32 + 1).to_be_bytes()]));
+		t.as_mut_bytes().copy_from_slice(&u);
+		for n in 1..iterations {
+			if n % check_interval == 0 {
+				check(n)?;
+			}
+			let next = prf.sign(&[&u]);
+			u.as_mut_bytes().copy_from_slice(&next);
+			ct::xor_assign(t.as_mut_bytes(), &u);
+		}
+		chunk.copy_from_slice(&t[..chunk.len()]);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::hex::from_hex;
+
+	#[test]
+	fn known_answers() -> Result<(), Error> {
+		// RFC 4231, test case 2
+		let key = b"Jefe";
+		let data = b"what do ya want for nothing?";
+		assert_eq!(
+			hmac_sha256(key, data).to_vec(),
+			from_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")?
+		);
+		assert_eq!(
+			hmac_sha512(key, data).to_vec(),
+			from_hex(
+				"164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+				 9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+			)?
+		);
+		let mut h = Sha256Hasher::new();
+		h.update(b"a");
+		h.update(b"bc");
+		assert_eq!(
+			h.finalize().to_vec(),
+			from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")?
+		);
+		let mut out = [0u8; 32];
+		pbkdf2_hmac_sha256(b"password", b"salt", 2, &mut out, 1, &mut |_| Ok(()))?;
+		assert_eq!(
+			out.to_vec(),
+			from_hex("ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43")?
+		);
+		Ok(())
+	}
+}
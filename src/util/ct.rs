@@ -0,0 +1,70 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Byte slice operations on secret data, which don't branch on its contents
+
+use std::hint::black_box;
+
+/// Compare two byte slices in time that depends only on their lengths, for
+/// checking secrets against expected values
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+	if a.len() != b.len() {
+		return false;
+	}
+	let diff = a
+		.iter()
+		.zip(b.iter())
+		.fold(0u8, |acc, (x, y)| acc | black_box(x ^ y));
+	black_box(diff) == 0
+}
+
+/// XOR `b` into `a` in place. Both slices must be the same length
+pub fn xor_assign(a: &mut [u8], b: &[u8]) {
+	assert_eq!(
+		a.len(),
+		b.len(),
+		"xor_assign on slices of different lengths"
+	);
+	for (x, y) in a.iter_mut().zip(b) {
+		*x ^= y;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_ct_eq() {
+		assert!(ct_eq(&[1, 2, 3], &[1, 2, 3]));
+		assert!(!ct_eq(&[1, 2, 3], &[1, 2, 4]));
+		assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+		assert!(ct_eq(&[], &[]));
+	}
+
+	#[test]
+	fn test_xor_assign() {
+		let mut a = [0x0f, 0xf0, 0xaa];
+		xor_assign(&mut a, &[0xff, 0xff, 0xaa]);
+		assert_eq!(a, [0xf0, 0x0f, 0x00]);
+		xor_assign(&mut a, &[0xff, 0xff, 0xaa]);
+		assert_eq!(a, [0x0f, 0xf0, 0xaa]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_xor_assign_length_mismatch() {
+		xor_assign(&mut [0, 0], &[1]);
+	}
+}
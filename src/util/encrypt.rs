@@ -14,12 +14,21 @@
 
 //! Master secret encryption
 
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
+use crate::util::cancel::CancellationToken;
+use crate::util::crypto;
+use crate::util::ct;
+use crate::util::secret::{Passphrase, SecretBytes};
+use crate::util::trace::{trace_event, Span};
+use std::fmt;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(feature = "kdf-argon2")]
+use argon2::{Algorithm, Argon2, Params, Version};
 
 #[cfg(feature = "rust_crypto_pbkdf2")]
 use hmac::Hmac;
-#[cfg(feature = "rust_crypto_pbkdf2")]
-use pbkdf2::pbkdf2;
 #[cfg(feature = "ring_pbkdf2")]
 use ring::pbkdf2;
 #[cfg(feature = "rust_crypto_pbkdf2")]
@@ -27,6 +36,274 @@ use sha2::Sha256;
 #[cfg(feature = "ring_pbkdf2")]
 use std::num::NonZeroU32;
 
+/// Iterations of PBKDF2 between checks for cancellation
+const CANCEL_CHECK_INTERVAL: u32 = 4096;
+
+#[cfg(not(any(feature = "ring_pbkdf2", feature = "rust_crypto_pbkdf2")))]
+compile_error!("At least one of the ring_pbkdf2 or rust_crypto_pbkdf2 features must be enabled");
+
+/// A PBKDF2-HMAC-SHA256 implementation supplied from outside the crate, e.g.
+/// delegated to a secure element
+pub trait ExternalKdf: Send + Sync {
+	/// Fill `out` with PBKDF2-HMAC-SHA256 of `password` and `salt` with the given
+	/// number of iterations
+	fn pbkdf2_hmac_sha256(&self, password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]);
+}
+
+/// A unit of KDF work handed to a [`KdfExecutor`]. It delivers its own result
+/// when run
+pub type KdfJob = Box<dyn FnOnce() + Send + 'static>;
+
+/// Runs the crate's long KDF work, so hosts can move it off the calling thread,
+/// e.g. onto a thread pool, a web worker or a secure enclave. A job is a whole
+/// encryption or decryption, since each Feistel round needs the previous one
+pub trait KdfExecutor: Send + Sync {
+	/// Run `job` exactly once, on any thread. May return before the job has run
+	fn execute(&self, job: KdfJob);
+}
+
+/// Runs jobs to completion on the calling thread
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SyncExecutor;
+
+impl KdfExecutor for SyncExecutor {
+	fn execute(&self, job: KdfJob) {
+		job()
+	}
+}
+
+/// PBKDF2 implementation used by the Feistel round function, selectable at runtime
+#[derive(Clone)]
+pub enum KdfBackend {
+	/// ring's PBKDF2 (recommended)
+	#[cfg(feature = "ring_pbkdf2")]
+	Ring,
+	/// RustCrypto's pbkdf2 crate
+	#[cfg(feature = "rust_crypto_pbkdf2")]
+	RustCrypto,
+	/// A caller supplied implementation
+	External(Arc<dyn ExternalKdf>),
+}
+
+impl Default for KdfBackend {
+	#[cfg(feature = "ring_pbkdf2")]
+	fn default() -> Self {
+		KdfBackend::Ring
+	}
+
+	#[cfg(not(feature = "ring_pbkdf2"))]
+	fn default() -> Self {
+		KdfBackend::RustCrypto
+	}
+}
+
+impl fmt::Debug for KdfBackend {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			#[cfg(feature = "ring_pbkdf2")]
+			KdfBackend::Ring => write!(f, "Ring"),
+			#[cfg(feature = "rust_crypto_pbkdf2")]
+			KdfBackend::RustCrypto => write!(f, "RustCrypto"),
+			KdfBackend::External(_) => write!(f, "External"),
+		}
+	}
+}
+
+impl PartialEq for KdfBackend {
+	fn eq(&self, other: &KdfBackend) -> bool {
+		match (self, other) {
+			(KdfBackend::External(a), KdfBackend::External(b)) => Arc::ptr_eq(a, b),
+			(KdfBackend::External(_), _) | (_, KdfBackend::External(_)) => false,
+			_ => std::mem::discriminant(self) == std::mem::discriminant(other),
+		}
+	}
+}
+
+impl Eq for KdfBackend {}
+
+impl KdfBackend {
+	/// Fill `out` with PBKDF2-HMAC-SHA256 using this backend
+	pub fn derive(&self, iterations: u32, salt: &[u8], password: &[u8], out: &mut [u8]) {
+		match self {
+			#[cfg(feature = "ring_pbkdf2")]
+			KdfBackend::Ring => pbkdf2::derive(
+				ring::pbkdf2::PBKDF2_HMAC_SHA256,
+				NonZeroU32::new(iterations).unwrap(),
+				salt,
+				password,
+				out,
+			),
+			#[cfg(feature = "rust_crypto_pbkdf2")]
+			KdfBackend::RustCrypto => ::pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, iterations, out)
+				.expect("HMAC error"),
+			KdfBackend::External(k) => k.pbkdf2_hmac_sha256(password, salt, iterations, out),
+		}
+	}
+}
+
+/// Round function used by the Feistel cipher. Anything other than
+/// [`KdfProfile::Slip39`] is a crate-specific extension, NOT interoperable with other
+/// SLIP-39 implementations; the profile byte identifies it in extended share formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KdfProfile {
+	/// PBKDF2-HMAC-SHA256, as specified by SLIP-39
+	#[default]
+	Slip39,
+	/// Memory-hard Argon2id (RFC 9106) with one pass, one lane and
+	/// 2^(log_m + iteration exponent) KiB of memory
+	#[cfg(feature = "kdf-argon2")]
+	Argon2id(Argon2Cost),
+}
+
+impl KdfProfile {
+	/// Argon2id profile with a base memory cost of 2^log_m KiB, see [`Argon2Cost::new`]
+	#[cfg(feature = "kdf-argon2")]
+	pub fn argon2id(log_m: u8) -> Result<KdfProfile, Error> {
+		Ok(KdfProfile::Argon2id(Argon2Cost::new(log_m)?))
+	}
+
+	/// Profile byte: 0 for SLIP-39, `0x40 | log_m` for Argon2id
+	pub fn to_byte(self) -> u8 {
+		match self {
+			KdfProfile::Slip39 => 0,
+			#[cfg(feature = "kdf-argon2")]
+			KdfProfile::Argon2id(cost) => 0x40 | cost.log_m(),
+		}
+	}
+
+	/// Profile from its byte, if known (and enabled)
+	pub fn from_byte(b: u8) -> Option<KdfProfile> {
+		match b {
+			0 => Some(KdfProfile::Slip39),
+			#[cfg(feature = "kdf-argon2")]
+			0x40..=0x5f => Argon2Cost::new(b & 0x1f).ok().map(KdfProfile::Argon2id),
+			_ => None,
+		}
+	}
+
+	/// Check the profile's cost at the given iteration exponent is within limits
+	pub fn check_exponent(self, iteration_exponent: u8) -> Result<(), Error> {
+		match self {
+			// the exponent shifts the 32 bit PBKDF2 iteration count
+			KdfProfile::Slip39 if u32::from(iteration_exponent) >= u32::BITS => {
+				Err(ErrorKind::Config(format!(
+					"Iteration exponent {} is out of range",
+					iteration_exponent
+				)))?
+			}
+			KdfProfile::Slip39 => Ok(()),
+			#[cfg(feature = "kdf-argon2")]
+			KdfProfile::Argon2id(cost) => cost.memory_kib(iteration_exponent).map(|_| ()),
+		}
+	}
+}
+
+/// Base memory cost of the [`KdfProfile::Argon2id`] profile
+#[cfg(feature = "kdf-argon2")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Cost {
+	log_m: u8,
+}
+
+#[cfg(feature = "kdf-argon2")]
+impl Argon2Cost {
+	/// Smallest base cost, the 8 KiB Argon2 minimum for one lane
+	pub const MIN_LOG_M: u8 = 3;
+	/// Largest total cost, with the iteration exponent added: 1 GiB per round
+	pub const MAX_LOG_M: u8 = 20;
+
+	/// Base memory cost of 2^log_m KiB, between [`MIN_LOG_M`](Self::MIN_LOG_M) and
+	/// [`MAX_LOG_M`](Self::MAX_LOG_M)
+	pub fn new(log_m: u8) -> Result<Argon2Cost, Error> {
+		if !(Self::MIN_LOG_M..=Self::MAX_LOG_M).contains(&log_m) {
+			return Err(ErrorKind::Config(format!(
+				"Argon2id memory cost 2^{} KiB is out of range (2^{} to 2^{})",
+				log_m,
+				Self::MIN_LOG_M,
+				Self::MAX_LOG_M
+			)))?;
+		}
+		Ok(Argon2Cost { log_m })
+	}
+
+	/// Base-2 logarithm of the base memory cost in KiB
+	pub fn log_m(self) -> u8 {
+		self.log_m
+	}
+
+	// memory used at the given iteration exponent, capped at MAX_LOG_M
+	fn memory_kib(self, iteration_exponent: u8) -> Result<u32, Error> {
+		let log_m = u32::from(self.log_m) + u32::from(iteration_exponent);
+		if log_m > u32::from(Self::MAX_LOG_M) {
+			return Err(ErrorKind::Config(format!(
+				"Argon2id memory cost 2^{} KiB at iteration exponent {} exceeds 2^{} KiB",
+				self.log_m,
+				iteration_exponent,
+				Self::MAX_LOG_M
+			)))?;
+		}
+		Ok(1 << log_m)
+	}
+
+	// derive out.len() bytes at the given iteration exponent
+	fn derive(
+		self,
+		password: &[u8],
+		salt: &[u8],
+		iteration_exponent: u8,
+		out: &mut [u8],
+	) -> Result<(), Error> {
+		let kdf_err = |e: argon2::Error| ErrorKind::Config(format!("Argon2id: {}", e));
+		let params = Params::new(self.memory_kib(iteration_exponent)?, 1, 1, Some(out.len()))
+			.map_err(kdf_err)?;
+		Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+			.hash_password_into(password, salt, out)
+			.map_err(kdf_err)?;
+		Ok(())
+	}
+}
+
+/// Check and normalization applied to a passphrase before it is used. Passphrases are
+/// case-sensitive and never lowercased; once normalized, their UTF-8 bytes are passed
+/// to the KDF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PassphraseNormalization {
+	/// Use the passphrase as given
+	#[default]
+	None,
+	/// Reject passphrases containing anything other than printable ASCII (code points
+	/// 32-126), as SLIP-39 requires. This sidesteps the Unicode composition differences
+	/// that NFKD normalization would otherwise have to resolve
+	PrintableAscii,
+	/// Apply Unicode NFKD normalization, as SLIP-39 specifies, so differently composed
+	/// forms of a passphrase (e.g. a precomposed "é" and "e" with a combining accent)
+	/// give the same secret
+	Nfkd,
+}
+
+impl PassphraseNormalization {
+	/// Check the passphrase is acceptable
+	pub fn check(self, passphrase: &str) -> Result<(), Error> {
+		if self == PassphraseNormalization::PrintableAscii
+			&& !passphrase.bytes().all(|b| (32..=126).contains(&b))
+		{
+			return Err(ErrorKind::Argument(
+				"The passphrase must contain only printable ASCII characters (code points 32-126)"
+					.to_string(),
+			))?;
+		}
+		Ok(())
+	}
+
+	/// The passphrase as it is passed to the KDF
+	pub fn normalize(self, passphrase: &str) -> Passphrase {
+		match self {
+			PassphraseNormalization::Nfkd => Passphrase::new(passphrase.nfkd().collect()),
+			_ => Passphrase::from(passphrase),
+		}
+	}
+}
+
 /// Config Struct
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MasterSecretEncConfig {
@@ -36,6 +313,12 @@ pub struct MasterSecretEncConfig {
 	pub round_count: u8,
 	/// The customization string used in the RS1024 checksum and in the PBKDF2 salt
 	pub customization_string: Vec<u8>,
+	/// The PBKDF2 implementation to use
+	pub kdf: KdfBackend,
+	/// The round function profile
+	pub profile: KdfProfile,
+	/// Check applied to passphrases
+	pub passphrase_normalization: PassphraseNormalization,
 }
 
 impl Default for MasterSecretEncConfig {
@@ -43,11 +326,17 @@ impl Default for MasterSecretEncConfig {
 		let min_iteration_count = 10000;
 		let round_count = 4;
 		let customization_string = b"shamir".to_vec();
+		let kdf = KdfBackend::default();
+		let profile = KdfProfile::default();
+		let passphrase_normalization = PassphraseNormalization::default();
 
 		MasterSecretEncConfig {
 			min_iteration_count,
 			round_count,
 			customization_string,
+			kdf,
+			profile,
+			passphrase_normalization,
 		}
 	}
 }
@@ -59,12 +348,39 @@ impl MasterSecretEncConfig {
 			..Default::default()
 		}
 	}
+
+	/// Use the given PBKDF2 implementation
+	pub fn with_kdf(mut self, kdf: KdfBackend) -> Self {
+		self.kdf = kdf;
+		self
+	}
+
+	/// Use the given round function profile
+	pub fn with_profile(mut self, profile: KdfProfile) -> Self {
+		self.profile = profile;
+		self
+	}
+
+	/// Apply the given check to passphrases
+	pub fn with_passphrase_normalization(mut self, normalization: PassphraseNormalization) -> Self {
+		self.passphrase_normalization = normalization;
+		self
+	}
 }
 /// Struct, so that config values are held
+/// Holds no secret material, so Debug only shows the configuration
 pub struct MasterSecretEnc {
 	pub config: MasterSecretEncConfig,
 }
 
+impl fmt::Debug for MasterSecretEnc {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MasterSecretEnc")
+			.field("config", &self.config)
+			.finish()
+	}
+}
+
 impl Default for MasterSecretEnc {
 	fn default() -> Self {
 		MasterSecretEnc {
@@ -74,11 +390,11 @@ impl Default for MasterSecretEnc {
 }
 
 impl MasterSecretEnc {
-	/// Create a new encoder with all defaults
-	pub fn new() -> Result<MasterSecretEnc, Error> {
-		Ok(MasterSecretEnc {
-			config: MasterSecretEncConfig::new(),
-		})
+	/// Create a new encoder with the given configuration
+	pub fn with_config(config: &MasterSecretEncConfig) -> MasterSecretEnc {
+		MasterSecretEnc {
+			config: config.clone(),
+		}
 	}
 
 	pub fn encrypt(
@@ -88,20 +404,16 @@ impl MasterSecretEnc {
 		iteration_exponent: u8,
 		identifier: u16,
 	) -> Vec<u8> {
-		let mut l = master_secret.to_owned();
-		let mut r = l.split_off(l.len() / 2);
-		let salt = self.get_salt(identifier);
-		for i in 0..self.config.round_count {
-			// TODO This can be implemented without so much cloning
-			let tmp_r = r.clone();
-			r = self.xor(
-				&l,
-				&self.round_function(i, passphrase, iteration_exponent, &salt, &r),
-			);
-			l = tmp_r;
-		}
-		r.append(&mut l);
-		r
+		self.feistel(
+			master_secret,
+			passphrase,
+			iteration_exponent,
+			identifier,
+			0..self.config.round_count,
+			None,
+		)
+		.expect("only cancellation can fail")
+		.into_vec()
 	}
 
 	pub fn decrypt(
@@ -110,84 +422,162 @@ impl MasterSecretEnc {
 		passphrase: &str,
 		iteration_exponent: u8,
 		identifier: u16,
-	) -> Vec<u8> {
-		let mut l = enc_master_secret.to_owned();
-		let mut r = l.split_off(l.len() / 2);
-		let salt = self.get_salt(identifier);
-		for i in (0..self.config.round_count).rev() {
-			// TODO This can be implemented without so much cloning
-			let tmp_r = r.clone();
-			r = self.xor(
-				&l,
-				&self.round_function(i, passphrase, iteration_exponent, &salt, &r),
-			);
-			l = tmp_r;
-		}
-		r.append(&mut l);
-		r
-	}
-
-	fn get_salt(&self, identifier: u16) -> Vec<u8> {
-		let mut retval = self.config.customization_string.clone();
-		retval.append(&mut identifier.to_be_bytes().to_vec());
-		retval
+	) -> SecretBytes {
+		self.feistel(
+			enc_master_secret,
+			passphrase,
+			iteration_exponent,
+			identifier,
+			(0..self.config.round_count).rev(),
+			None,
+		)
+		.expect("only cancellation can fail")
 	}
 
-	/// the round function used internally by the Feistel cipher
-	fn round_function(&self, i: u8, passphrase: &str, e: u8, salt: &[u8], r: &[u8]) -> Vec<u8> {
-		let iterations =
-			(self.config.min_iteration_count / u32::from(self.config.round_count)) << u32::from(e);
-		let out_length = r.len();
-		let mut salt = salt.to_owned();
-		let mut r = r.to_owned();
-		salt.append(&mut r);
-		let mut password = vec![i];
-		password.append(&mut passphrase.as_bytes().to_vec());
-		self.pbkdf2_derive(iterations, &salt, &password, out_length)
+	/// As [`decrypt`](MasterSecretEnc::decrypt), running the KDF rounds on `executor`
+	/// and passing the result to `done`, which may be called on another thread
+	pub fn decrypt_on(
+		&self,
+		executor: &dyn KdfExecutor,
+		enc_master_secret: &[u8],
+		passphrase: &str,
+		iteration_exponent: u8,
+		identifier: u16,
+		done: impl FnOnce(SecretBytes) + Send + 'static,
+	) {
+		let encoder = MasterSecretEnc::with_config(&self.config);
+		let input = SecretBytes::from(enc_master_secret);
+		let passphrase = Passphrase::from(passphrase);
+		executor.execute(Box::new(move || {
+			done(encoder.decrypt(&input, passphrase.as_str(), iteration_exponent, identifier))
+		}));
 	}
 
-	#[cfg(feature = "rust_crypto_pbkdf2")]
-	/// Rust-crypto implementation of round function
-	fn pbkdf2_derive(
+	/// As [`decrypt`](MasterSecretEnc::decrypt), failing with [`ErrorKind::Cancelled`]
+	/// soon after the token is cancelled. PBKDF2 is then computed by the crate, over
+	/// the HMAC of the crypto backend (RustCrypto, `crypto-ring` or `crypto-aws-lc`),
+	/// rather than by the configured [`KdfBackend`] so it can stop part way through;
+	/// the backends' PBKDF2 APIs can't be run in chunks. The result is the same, which
+	/// a known-answer test checks against each backend. External KDFs and other
+	/// profiles are only interrupted between Feistel rounds
+	pub fn decrypt_cancellable(
 		&self,
-		iterations: u32,
-		salt: &[u8],
-		password: &[u8],
-		out_length: usize,
-	) -> Vec<u8> {
-		let mut out = vec![0; out_length];
-		pbkdf2::<Hmac<Sha256>>(password, salt, iterations as usize, &mut out);
-		out
+		enc_master_secret: &[u8],
+		passphrase: &str,
+		iteration_exponent: u8,
+		identifier: u16,
+		cancel: &CancellationToken,
+	) -> Result<SecretBytes, Error> {
+		self.feistel(
+			enc_master_secret,
+			passphrase,
+			iteration_exponent,
+			identifier,
+			(0..self.config.round_count).rev(),
+			Some(cancel),
+		)
 	}
 
-	#[cfg(feature = "ring_pbkdf2")]
-	/// Ring implementation of round function
-	fn pbkdf2_derive(
+	/// Run the Feistel network over the given rounds. Intermediate halves are held
+	/// in SecretBytes, so they're zeroed (and locked, with the `mlock` feature), as
+	/// is the output
+	fn feistel(
 		&self,
-		iterations: u32,
-		salt: &[u8],
-		password: &[u8],
-		out_length: usize,
-	) -> Vec<u8> {
-		let mut out = vec![0; out_length];
-		pbkdf2::derive(
-			ring::pbkdf2::PBKDF2_HMAC_SHA256,
-			NonZeroU32::new(iterations).unwrap(),
-			salt,
-			password,
-			&mut out,
+		input: &[u8],
+		passphrase: &str,
+		iteration_exponent: u8,
+		identifier: u16,
+		rounds: impl Iterator<Item = u8>,
+		cancel: Option<&CancellationToken>,
+	) -> Result<SecretBytes, Error> {
+		let _span = Span::enter("kdf");
+		trace_event!(
+			"kdf: profile {:?}, iteration exponent {}, {} rounds",
+			self.config.profile,
+			iteration_exponent,
+			self.config.round_count
 		);
-		out
+		let passphrase = self.config.passphrase_normalization.normalize(passphrase);
+		let passphrase = passphrase.as_str();
+		let half = input.len() / 2;
+		let mut l = SecretBytes::from(&input[..half]);
+		let mut r = SecretBytes::from(&input[half..]);
+		let salt = self.get_salt(identifier);
+		for i in rounds {
+			let f = self.round_function(i, passphrase, iteration_exponent, &salt, &r, cancel)?;
+			ct::xor_assign(l.as_mut_bytes(), &f);
+			std::mem::swap(&mut l, &mut r);
+		}
+		let mut out = SecretBytes::zeroed(input.len());
+		out.as_mut_bytes()[..r.len()].copy_from_slice(&r);
+		out.as_mut_bytes()[r.len()..].copy_from_slice(&l);
+		Ok(out)
 	}
 
-	// xor values in both arrays, up to length of b
-	fn xor(&self, a: &[u8], b: &[u8]) -> Vec<u8> {
-		let mut retval = vec![0; b.len()];
-		for i in 0..b.len() {
-			retval[i] = a[i] ^ b[i];
-		}
+	fn get_salt(&self, identifier: u16) -> Vec<u8> {
+		let mut retval = Vec::with_capacity(self.config.customization_string.len() + 2);
+		retval.extend_from_slice(&self.config.customization_string);
+		retval.extend_from_slice(&identifier.to_be_bytes());
 		retval
 	}
+
+	/// the round function used internally by the Feistel cipher
+	fn round_function(
+		&self,
+		i: u8,
+		passphrase: &str,
+		e: u8,
+		salt: &[u8],
+		r: &[u8],
+		cancel: Option<&CancellationToken>,
+	) -> Result<SecretBytes, Error> {
+		if let Some(c) = cancel {
+			c.check()?;
+		}
+		let iterations =
+			(self.config.min_iteration_count / u32::from(self.config.round_count)) << u32::from(e);
+		let mut salt_r = SecretBytes::zeroed(salt.len() + r.len());
+		salt_r.as_mut_bytes()[..salt.len()].copy_from_slice(salt);
+		salt_r.as_mut_bytes()[salt.len()..].copy_from_slice(r);
+		let mut password = SecretBytes::zeroed(1 + passphrase.len());
+		password.as_mut_bytes()[0] = i;
+		password.as_mut_bytes()[1..].copy_from_slice(passphrase.as_bytes());
+		let mut out = SecretBytes::zeroed(r.len());
+		#[cfg(feature = "kdf-argon2")]
+		{
+			if let KdfProfile::Argon2id(cost) = self.config.profile {
+				cost.derive(&password, &salt_r, e, out.as_mut_bytes())?;
+				return Ok(out);
+			}
+		}
+		// the ring and RustCrypto backends only run PBKDF2 to completion, so the crate's
+		// own implementation stands in for them when cancellation is possible
+		match (cancel, &self.config.kdf) {
+			(Some(c), kdf) if !matches!(kdf, KdfBackend::External(_)) => {
+				pbkdf2_cancellable(iterations, &salt_r, &password, out.as_mut_bytes(), c)?
+			}
+			(_, kdf) => kdf.derive(iterations, &salt_r, &password, out.as_mut_bytes()),
+		}
+		Ok(out)
+	}
+}
+
+// PBKDF2-HMAC-SHA256 (RFC 8018), checking for cancellation as it goes
+fn pbkdf2_cancellable(
+	iterations: u32,
+	salt: &[u8],
+	password: &[u8],
+	out: &mut [u8],
+	cancel: &CancellationToken,
+) -> Result<(), Error> {
+	crypto::pbkdf2_hmac_sha256(
+		password,
+		salt,
+		iterations,
+		out,
+		CANCEL_CHECK_INTERVAL,
+		&mut |_| cancel.check(),
+	)
 }
 
 #[cfg(test)]
@@ -213,7 +603,7 @@ mod tests {
 	#[test]
 	fn roundtrip_test_vector() {
 		// from test vector
-		for e in vec![0, 6] {
+		for e in [0, 6] {
 			let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
 			roundtrip_test(secret, "", 7470, e);
 		}
@@ -265,4 +655,135 @@ mod tests {
 			roundtrip_test(s.to_vec(), "pebkac", id, 0);
 		}
 	}
+
+	struct CountingKdf(std::sync::atomic::AtomicUsize);
+
+	impl ExternalKdf for CountingKdf {
+		fn pbkdf2_hmac_sha256(
+			&self,
+			password: &[u8],
+			salt: &[u8],
+			iterations: u32,
+			out: &mut [u8],
+		) {
+			self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+			KdfBackend::default().derive(iterations, salt, password, out)
+		}
+	}
+
+	#[test]
+	fn external_kdf() {
+		let kdf = Arc::new(CountingKdf(Default::default()));
+		let config = MasterSecretEncConfig::new().with_kdf(KdfBackend::External(kdf.clone()));
+		assert_ne!(config, MasterSecretEncConfig::new());
+		let enc = MasterSecretEnc::with_config(&config);
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let encrypted = enc.encrypt(&secret, "", 0, 7470);
+		assert_eq!(kdf.0.load(std::sync::atomic::Ordering::SeqCst), 4);
+		assert_eq!(
+			encrypted,
+			MasterSecretEnc::default().encrypt(&secret, "", 0, 7470)
+		);
+	}
+
+	#[test]
+	fn cancellable_pbkdf2_matches_backends() -> Result<(), Error> {
+		// RFC 7914, section 11
+		let expected = crate::util::hex::from_hex(
+			"55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+			 49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783",
+		)?;
+		let cancel = CancellationToken::new();
+		let mut out = [0u8; 64];
+		pbkdf2_cancellable(1, b"salt", b"passwd", &mut out, &cancel)?;
+		assert_eq!(out.to_vec(), expected);
+
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let backends = vec![
+			#[cfg(feature = "ring_pbkdf2")]
+			KdfBackend::Ring,
+			#[cfg(feature = "rust_crypto_pbkdf2")]
+			KdfBackend::RustCrypto,
+		];
+		for kdf in backends {
+			let mut out = [0u8; 64];
+			kdf.derive(1, b"salt", b"passwd", &mut out);
+			assert_eq!(out.to_vec(), expected, "{:?}", kdf);
+			// on either side of the cancellation check interval, and over several blocks
+			for &(iterations, len) in &[(2, 16), (4096, 16), (4097, 33), (10000, 64)] {
+				let mut a = vec![0; len];
+				let mut b = vec![0; len];
+				kdf.derive(iterations, b"salt", b"password", &mut a);
+				pbkdf2_cancellable(iterations, b"salt", b"password", &mut b, &cancel)?;
+				assert_eq!(a, b, "{:?}, {} iterations", kdf, iterations);
+			}
+			let enc = MasterSecretEnc::with_config(&MasterSecretEncConfig::new().with_kdf(kdf));
+			let encrypted = enc.encrypt(&secret, "pw", 1, 7470);
+			assert_eq!(
+				enc.decrypt_cancellable(&encrypted, "pw", 1, 7470, &cancel)?,
+				enc.decrypt(&encrypted, "pw", 1, 7470)
+			);
+		}
+		Ok(())
+	}
+
+	// runs each job on a new thread
+	struct ThreadExecutor;
+
+	impl KdfExecutor for ThreadExecutor {
+		fn execute(&self, job: KdfJob) {
+			std::thread::spawn(job);
+		}
+	}
+
+	#[test]
+	fn kdf_executor() {
+		let enc = MasterSecretEnc::default();
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let encrypted = enc.encrypt(&secret, "pw", 0, 7470);
+		let (tx, rx) = std::sync::mpsc::channel();
+		enc.decrypt_on(&ThreadExecutor, &encrypted, "pw", 0, 7470, move |d| {
+			tx.send(d).unwrap()
+		});
+		assert_eq!(rx.recv().unwrap(), secret);
+		// the default runs before returning
+		let out = Arc::new(std::sync::Mutex::new(None));
+		let o = out.clone();
+		enc.decrypt_on(&SyncExecutor, &encrypted, "pw", 0, 7470, move |d| {
+			*o.lock().unwrap() = Some(d)
+		});
+		assert_eq!(out.lock().unwrap().take(), Some(SecretBytes::new(secret)));
+	}
+
+	#[cfg(feature = "kdf-argon2")]
+	#[test]
+	fn argon2id_profile() {
+		// profile bytes round trip exactly, out of range costs are rejected
+		for b in 0..=u8::MAX {
+			if let Some(p) = KdfProfile::from_byte(b) {
+				assert_eq!(p.to_byte(), b);
+			}
+		}
+		for log_m in 0..=u8::MAX {
+			match KdfProfile::argon2id(log_m) {
+				Ok(p) => assert_eq!(KdfProfile::from_byte(p.to_byte()), Some(p)),
+				Err(_) => assert!(!(3..=20).contains(&log_m)),
+			}
+		}
+		assert_eq!(KdfProfile::from_byte(0x5f), None);
+		let profile = KdfProfile::argon2id(4).unwrap();
+		assert!(profile.check_exponent(16).is_ok());
+		assert!(profile.check_exponent(17).is_err());
+		assert!(profile.check_exponent(31).is_err());
+		assert!(KdfProfile::Slip39.check_exponent(31).is_ok());
+		assert!(KdfProfile::Slip39.check_exponent(32).is_err());
+		let enc = MasterSecretEnc::with_config(&MasterSecretEncConfig::new().with_profile(profile));
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let encrypted = enc.encrypt(&secret, "pebkac", 1, 7470);
+		assert_ne!(
+			encrypted,
+			MasterSecretEnc::default().encrypt(&secret, "pebkac", 1, 7470)
+		);
+		assert_eq!(enc.decrypt(&encrypted, "pebkac", 1, 7470), secret);
+	}
 }
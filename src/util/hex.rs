@@ -12,43 +12,75 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-/// Implements hex-encoding from bytes to string and decoding of strings
-/// to bytes. Given that rustc-serialize is deprecated and serde doesn't
-/// provide easy hex encoding, hex is a bit in limbo right now in Rust-
-/// land. It's simple enough that we can just have our own.
-use std::fmt::Write;
-use std::num;
+//! Hex encoding of bytes to strings and decoding of strings to bytes, as
+//! used for master secrets in test vectors and wallet export formats
 
-/// Encode the provided bytes into a hex string
-pub fn to_hex(bytes: Vec<u8>) -> String {
-	let mut s = String::new();
+use std::fmt::{self, Write};
+
+/// Problems encountered while decoding a hex string
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum HexError {
+	/// The string (after removing any `0x` prefix) has an odd number of digits
+	OddLength(usize),
+	/// The character at the given position is not a hex digit
+	InvalidChar(usize, char),
+}
+
+impl fmt::Display for HexError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HexError::OddLength(l) => write!(f, "odd number of hex digits ({})", l),
+			HexError::InvalidChar(i, c) => write!(f, "invalid hex character '{}' at {}", c, i),
+		}
+	}
+}
+
+impl std::error::Error for HexError {}
+
+/// Encode the provided bytes into a lowercase hex string
+pub fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+	encode(bytes.as_ref(), false)
+}
+
+/// Encode the provided bytes into an uppercase hex string
+pub fn to_hex_upper(bytes: impl AsRef<[u8]>) -> String {
+	encode(bytes.as_ref(), true)
+}
+
+fn encode(bytes: &[u8], upper: bool) -> String {
+	let mut s = String::with_capacity(bytes.len() * 2);
 	for byte in bytes {
-		write!(&mut s, "{:02x}", byte).expect("Unable to write");
+		if upper {
+			write!(&mut s, "{:02X}", byte).expect("Unable to write");
+		} else {
+			write!(&mut s, "{:02x}", byte).expect("Unable to write");
+		}
 	}
 	s
 }
 
-/// Decode a hex string into bytes.
-pub fn from_hex(hex_str: String) -> Result<Vec<u8>, num::ParseIntError> {
-	if hex_str.len() % 2 == 1 {
-		// TODO: other way to instantiate a ParseIntError?
-		("QQQ").parse::<u64>()?;
-	}
-	let hex_trim = if &hex_str[..2] == "0x" {
-		hex_str[2..].to_owned()
+/// Decode a hex string into bytes. Surrounding whitespace and an optional
+/// `0x`/`0X` prefix are ignored, digits may be upper or lower case
+pub fn from_hex(hex_str: impl AsRef<str>) -> Result<Vec<u8>, HexError> {
+	let trimmed = hex_str.as_ref().trim();
+	let hex_trim = if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
+		&trimmed[2..]
 	} else {
-		hex_str
+		trimmed
 	};
-	split_n(hex_trim.trim(), 2)
-		.iter()
-		.map(|b| u8::from_str_radix(b, 16))
-		.collect::<Result<Vec<u8>, _>>()
-}
-
-fn split_n(s: &str, n: usize) -> Vec<&str> {
-	(0..=(s.len() - n + 1) / 2)
-		.map(|i| &s[2 * i..2 * i + n])
-		.collect()
+	if let Some((i, c)) = hex_trim
+		.char_indices()
+		.find(|(_, c)| !c.is_ascii_hexdigit())
+	{
+		return Err(HexError::InvalidChar(i, c));
+	}
+	if hex_trim.len() % 2 == 1 {
+		return Err(HexError::OddLength(hex_trim.len()));
+	}
+	Ok((0..hex_trim.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&hex_trim[i..i + 2], 16).expect("validated hex"))
+		.collect())
 }
 
 #[cfg(test)]
@@ -62,16 +94,25 @@ mod test {
 		assert_eq!(to_hex(vec![0, 0, 0, 255]), "000000ff");
 	}
 
+	#[test]
+	fn test_to_hex_upper() {
+		assert_eq!(to_hex_upper(vec![10, 11, 12, 255]), "0A0B0CFF");
+	}
+
+	#[test]
+	fn test_from_hex_errors() {
+		assert_eq!(from_hex("0x0a0B"), Ok(vec![10, 11]));
+		assert_eq!(from_hex("  0X0a0b\n"), Ok(vec![10, 11]));
+		assert_eq!(from_hex(""), Ok(vec![]));
+		assert_eq!(from_hex("0a0"), Err(HexError::OddLength(3)));
+		assert_eq!(from_hex("0a0g"), Err(HexError::InvalidChar(3, 'g')));
+		assert_eq!(from_hex("é0"), Err(HexError::InvalidChar(0, 'é')));
+	}
+
 	#[test]
 	fn test_from_hex() {
-		assert_eq!(from_hex("00000000".to_string()).unwrap(), vec![0, 0, 0, 0]);
-		assert_eq!(
-			from_hex("0a0b0c0d".to_string()).unwrap(),
-			vec![10, 11, 12, 13]
-		);
-		assert_eq!(
-			from_hex("000000ff".to_string()).unwrap(),
-			vec![0, 0, 0, 255]
-		);
+		assert_eq!(from_hex("00000000").unwrap(), vec![0, 0, 0, 0]);
+		assert_eq!(from_hex("0a0b0c0d").unwrap(), vec![10, 11, 12, 13]);
+		assert_eq!(from_hex("000000ff").unwrap(), vec![0, 0, 0, 255]);
 	}
 }
@@ -14,10 +14,17 @@
 
 //! cryptography and utility functions
 
-pub mod bitpacker;
+pub mod base32;
+pub mod base64;
+pub(crate) mod bitpacker;
+pub(crate) mod cancel;
+pub(crate) mod crypto;
+pub mod ct;
 pub mod encrypt;
 pub mod hex;
 pub mod rs1024;
+pub mod secret;
+pub(crate) mod trace;
 
 use rand::{thread_rng, Rng};
 
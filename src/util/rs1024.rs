@@ -29,43 +29,176 @@ const GEN: [u32; 10] = [
 	0x03f3_f120,
 ];
 
-/// values intepreted as a list of 10 bit integers
-fn polymod(values: &[u32]) -> u32 {
-	let mut chk = 1;
-	let mut b: u32;
-	for v in values {
-		b = chk >> 20;
-		chk = (chk & 0xfffff) << 10 ^ v;
+/// Error detection guarantees of an RS1024 checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumStrength {
+	/// Number of 10 bit checksum words
+	pub checksum_words: usize,
+	/// Any error affecting at most this many words is guaranteed to be detected
+	pub guaranteed_word_errors: usize,
+	/// Longest codeword (data and checksum words) the guarantee holds for
+	pub max_codeword_words: usize,
+	/// log2 of the chance that a random error affecting more words goes undetected
+	pub undetected_probability_log2: i32,
+}
+
+/// The guarantees of the 3 word checksum used by SLIP-39: a Reed-Solomon code over
+/// GF(1024) with minimum distance 4, so any error in up to 3 words is detected
+/// and larger errors go undetected with probability 2^-30 (less than 1 in 10^9)
+pub const fn checksum_strength() -> ChecksumStrength {
+	ChecksumStrength {
+		checksum_words: 3,
+		guaranteed_word_errors: 3,
+		max_codeword_words: 1023,
+		undetected_probability_log2: -30,
+	}
+}
+
+/// Streaming computation of the RS1024 polymod over 10 bit words, so checksums can
+/// be computed without collecting the input and updated when a single word changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Polymod {
+	chk: u32,
+}
+
+impl Default for Polymod {
+	fn default() -> Self {
+		Polymod { chk: 1 }
+	}
+}
+
+impl Polymod {
+	/// Polymod of the empty input
+	pub fn new() -> Self {
+		Polymod {
+			..Default::default()
+		}
+	}
+
+	/// Polymod of the customization string followed by the words
+	pub fn of(custom_string: &[u8], data: &[u32]) -> Self {
+		let mut p = Polymod::new();
+		p.extend(custom_string.iter().map(|d| u32::from(*d)));
+		p.extend(data.iter().copied());
+		p
+	}
+
+	/// Append a word
+	pub fn update(&mut self, v: u32) {
+		let b = self.chk >> 20;
+		self.chk = (self.chk & 0xfffff) << 10 ^ v;
 		for (i, item) in GEN.iter().enumerate() {
 			if (b >> i) & 1 == 1 {
-				chk ^= *item;
+				self.chk ^= *item;
 			}
 		}
 	}
-	chk
+
+	/// Append words
+	pub fn extend(&mut self, values: impl IntoIterator<Item = u32>) {
+		for v in values {
+			self.update(v);
+		}
+	}
+
+	/// Current value. A valid codeword leaves a residue of 1
+	pub fn residue(&self) -> u32 {
+		self.chk
+	}
+
+	/// Whether the input so far ends with a valid checksum
+	pub fn is_valid(&self) -> bool {
+		self.chk == 1
+	}
+
+	/// The polymod as if the word `distance` words from the end of the input (0
+	/// being the last word) had been `new` rather than `old`. The polymod is affine
+	/// in its input, so this only costs `distance` steps
+	pub fn with_word_changed(&self, distance: usize, old: u32, new: u32) -> Polymod {
+		Polymod {
+			chk: self.chk ^ word_error(old ^ new, distance),
+		}
+	}
 }
 
-pub fn create_checksum(custom_string: &[u8], data: &[u32], checksum_length_words: u8) -> Vec<u32> {
-	let mut values: Vec<u32> = custom_string.iter().map(|d| u32::from(*d)).collect();
-	for e in data {
-		values.push(e.to_owned());
+/// Change in the polymod caused by xoring `delta` into the word `distance` words
+/// from the end of the input
+pub fn word_error(delta: u32, distance: usize) -> u32 {
+	let mut p = Polymod { chk: 0 };
+	p.update(delta);
+	for _ in 0..distance {
+		p.update(0);
+	}
+	p.chk
+}
+
+/// Computes or verifies a checksum over words pushed one at a time, so the data
+/// doesn't need to be collected first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksummer {
+	polymod: Polymod,
+	words: usize,
+}
+
+impl Checksummer {
+	/// Start a checksum with the given customization string
+	pub fn new(custom_string: &[u8]) -> Self {
+		Checksummer {
+			polymod: Polymod::of(custom_string, &[]),
+			words: 0,
+		}
+	}
+
+	/// Append a data (or, when verifying, checksum) word
+	pub fn push(&mut self, v: u32) {
+		self.polymod.update(v);
+		self.words += 1;
+	}
+
+	/// Number of words pushed
+	pub fn len(&self) -> usize {
+		self.words
+	}
+
+	/// Whether no words have been pushed
+	pub fn is_empty(&self) -> bool {
+		self.words == 0
 	}
-	values.append(&mut vec![0; checksum_length_words as usize]);
-	let polymod = polymod(&values) ^ 1;
-	let mut retval = vec![];
-	for i in 0..checksum_length_words as usize {
-		retval.push(polymod >> (10 * (2 - i)) & 1023);
+
+	/// Checksum words that make the pushed data a valid codeword
+	pub fn finalize(mut self, checksum_length_words: u8) -> Vec<u32> {
+		for _ in 0..checksum_length_words {
+			self.polymod.update(0);
+		}
+		let polymod = self.polymod.residue() ^ 1;
+		(0..checksum_length_words as usize)
+			.map(|i| polymod >> (10 * (checksum_length_words as usize - 1 - i)) & 1023)
+			.collect()
+	}
+
+	/// Whether the pushed words, which end with their checksum, are a valid codeword
+	pub fn verify(&self) -> Result<(), Error> {
+		if !self.polymod.is_valid() {
+			return Err(ErrorKind::Config(format!(
+				"Error verifying checksum over {} words",
+				self.words
+			)))?;
+		}
+		Ok(())
 	}
-	retval
 }
 
+/// Checksum words that make the customization string and data a valid codeword
+pub fn create_checksum(custom_string: &[u8], data: &[u32], checksum_length_words: u8) -> Vec<u32> {
+	let mut c = Checksummer::new(custom_string);
+	data.iter().for_each(|v| c.push(*v));
+	c.finalize(checksum_length_words)
+}
+
+/// Verify that the data, which ends with its checksum, is a valid codeword
 pub fn verify_checksum(custom_string: &[u8], data: &[u32]) -> Result<(), Error> {
-	let mut values: Vec<u32> = custom_string.iter().map(|d| u32::from(*d)).collect();
-	for e in data {
-		values.push(e.to_owned());
-	}
-	if polymod(&values) != 1 {
-		return Err(ErrorKind::Config(format!(
+	if !Polymod::of(custom_string, data).is_valid() {
+		Err(ErrorKind::Config(format!(
 			"Error verifying checksum: {:?}",
 			data,
 		)))?;
@@ -78,6 +211,98 @@ mod tests {
 	use super::*;
 
 	use crate::error::Error;
+	use crate::shamir::max_mnemonic_words;
+	use quickcheck::*;
+
+	// whether the 30 bit errors spanning these word distances all change the polymod,
+	// i.e. the images of the 30 basis errors are linearly independent over GF(2)
+	fn all_detected(distances: &[usize]) -> bool {
+		let mut basis: Vec<u32> = vec![];
+		for d in distances {
+			for bit in 0..10 {
+				let mut v = word_error(1 << bit, *d);
+				for b in &basis {
+					v = v.min(v ^ b);
+				}
+				if v == 0 {
+					return false;
+				}
+				basis.push(v);
+				basis.sort_unstable_by(|a, b| b.cmp(a));
+			}
+		}
+		true
+	}
+
+	#[test]
+	fn detects_three_word_errors() {
+		let strength = checksum_strength();
+		assert_eq!(strength.guaranteed_word_errors, 3);
+		// any 3 words of a standard (up to 33 word) mnemonic
+		for a in 0..33 {
+			for b in a + 1..33 {
+				for c in b + 1..33 {
+					assert!(all_detected(&[a, b, c]), "{} {} {}", a, b, c);
+				}
+			}
+		}
+		// bursts of 3 words anywhere in the longest mnemonic
+		for a in 0..max_mnemonic_words() - 2 {
+			assert!(all_detected(&[a, a + 1, a + 2]));
+		}
+	}
+
+	#[test]
+	fn checksummer() -> Result<(), Error> {
+		let data = [
+			663, 96, 0, 66, 132, 27, 234, 28, 191, 405, 992, 848, 257, 36, 858, 1012, 858,
+		];
+		let mut c = Checksummer::new(b"shamir");
+		data.iter().for_each(|v| c.push(*v));
+		assert!(c.verify().is_err());
+		let checksum = c.finalize(3);
+		assert_eq!(checksum, vec![1001, 340, 369]);
+		checksum.iter().for_each(|v| c.push(*v));
+		c.verify()?;
+		assert_eq!(c.len(), 20);
+		Ok(())
+	}
+
+	#[test]
+	fn incremental_update() {
+		let data = vec![
+			663, 96, 0, 66, 132, 27, 234, 28, 191, 405, 992, 848, 257, 36, 858, 1012, 858, 1001,
+			340, 369,
+		];
+		let p = Polymod::of(b"shamir", &data);
+		assert!(p.is_valid());
+		for (i, old) in data.iter().enumerate() {
+			let mut changed = data.clone();
+			changed[i] = 517;
+			let distance = data.len() - 1 - i;
+			assert_eq!(
+				p.with_word_changed(distance, *old, 517),
+				Polymod::of(b"shamir", &changed)
+			);
+		}
+	}
+
+	quickcheck! {
+		fn undetected_only_beyond_three_words(data: Vec<u16>, errors: Vec<(u8, u16)>) -> TestResult {
+			let mut data: Vec<u32> = data.iter().map(|d| u32::from(*d) & 1023).collect();
+			if data.is_empty() {
+				return TestResult::discard();
+			}
+			let checksum = create_checksum(b"shamir", &data, 3);
+			data.extend(checksum);
+			let mut damaged = data.clone();
+			for (pos, e) in errors.iter().take(3) {
+				let pos = *pos as usize % damaged.len();
+				damaged[pos] ^= u32::from(*e) & 1023;
+			}
+			TestResult::from_bool(damaged == data || verify_checksum(b"shamir", &damaged).is_err())
+		}
+	}
 
 	#[test]
 	fn checksum() -> Result<(), Error> {
@@ -85,30 +310,30 @@ mod tests {
 			663, 96, 0, 66, 132, 27, 234, 28, 191, 405, 992, 848, 257, 36, 858, 1012, 858,
 		];
 		let expected = vec![1001, 340, 369];
-		let checksum = create_checksum(&b"shamir".to_vec(), &test_vec, 3);
+		let checksum = create_checksum(b"shamir", &test_vec, 3);
 		println!("Testvec: {:?}", test_vec);
 		assert_eq!(checksum, expected);
 		for i in checksum.iter().take(3) {
 			test_vec.push(*i);
 		}
-		verify_checksum(&b"shamir".to_vec(), &test_vec)?;
-		assert!(verify_checksum(&b"fhamir".to_vec(), &test_vec).is_err());
+		verify_checksum(b"shamir", &test_vec)?;
+		assert!(verify_checksum(b"fhamir", &test_vec).is_err());
 		test_vec[0] = 23;
-		assert!(verify_checksum(&b"shamir".to_vec(), &test_vec).is_err());
+		assert!(verify_checksum(b"shamir", &test_vec).is_err());
 		let mut test_vec = vec![
 			663, 96, 0, 66, 177, 310, 288, 156, 827, 77, 232, 34, 965, 772, 962, 966, 754,
 		];
 		let expected = vec![247, 29, 757];
-		let checksum = create_checksum(&b"shamir".to_vec(), &test_vec, 3);
+		let checksum = create_checksum(b"shamir", &test_vec, 3);
 		println!("Testvec: {:?}", test_vec);
 		assert_eq!(checksum, expected);
 		for i in checksum.iter().take(3) {
 			test_vec.push(*i);
 		}
-		verify_checksum(&b"shamir".to_vec(), &test_vec)?;
-		assert!(verify_checksum(&b"fhamir".to_vec(), &test_vec).is_err());
+		verify_checksum(b"shamir", &test_vec)?;
+		assert!(verify_checksum(b"fhamir", &test_vec).is_err());
 		test_vec[1] = 99;
-		assert!(verify_checksum(&b"shamir".to_vec(), &test_vec).is_err());
+		assert!(verify_checksum(b"shamir", &test_vec).is_err());
 
 		Ok(())
 	}
@@ -0,0 +1,335 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wrapper types for secrets and passphrases, which are zeroed on drop and
+//! never printed by their Debug implementations
+
+use crate::util::ct;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Overwrite a buffer with zeros in a way the compiler won't optimise out
+pub fn zeroize(buf: &mut [u8]) {
+	for b in buf.iter_mut() {
+		// safe, b is a valid, aligned reference
+		unsafe { std::ptr::write_volatile(b, 0) };
+	}
+	compiler_fence(Ordering::SeqCst);
+}
+
+/// Backing storage for [`SecretBytes`]. With the `mlock` feature, each buffer gets
+/// its own guarded, page-aligned allocation from `memsec`, which is locked on
+/// allocation and zeroed and unlocked on free. No two buffers share a page, so
+/// freeing one never unlocks another. If locking or allocation fails (e.g. from a
+/// low `RLIMIT_MEMLOCK`), the bytes fall back to the ordinary heap
+#[cfg(all(feature = "mlock", any(unix, windows)))]
+mod buf {
+	use std::ops::{Deref, DerefMut};
+	use std::ptr::NonNull;
+
+	pub enum Buf {
+		Locked(NonNull<[u8]>),
+		Heap(Vec<u8>),
+	}
+
+	// safe, a locked buffer is uniquely owned, like a Box<[u8]>
+	unsafe impl Send for Buf {}
+	unsafe impl Sync for Buf {}
+
+	impl Buf {
+		pub fn zeroed(len: usize) -> Buf {
+			if len == 0 {
+				return Buf::Heap(vec![]);
+			}
+			// safe, the allocation is checked and initialised before use
+			match unsafe { memsec::malloc_sized(len) } {
+				Some(mut p) => {
+					unsafe { p.as_mut() }.iter_mut().for_each(|b| *b = 0);
+					Buf::Locked(p)
+				}
+				None => Buf::Heap(vec![0; len]),
+			}
+		}
+	}
+
+	impl Deref for Buf {
+		type Target = [u8];
+		fn deref(&self) -> &[u8] {
+			match self {
+				// safe, p is a live allocation owned by self
+				Buf::Locked(p) => unsafe { p.as_ref() },
+				Buf::Heap(v) => v,
+			}
+		}
+	}
+
+	impl DerefMut for Buf {
+		fn deref_mut(&mut self) -> &mut [u8] {
+			match self {
+				// safe, as above, and self is borrowed mutably
+				Buf::Locked(p) => unsafe { p.as_mut() },
+				Buf::Heap(v) => v,
+			}
+		}
+	}
+
+	impl Drop for Buf {
+		fn drop(&mut self) {
+			if let Buf::Locked(p) = self {
+				// safe, p came from malloc_sized and is freed exactly once
+				unsafe { memsec::free(*p) };
+			}
+		}
+	}
+}
+
+#[cfg(not(all(feature = "mlock", any(unix, windows))))]
+mod buf {
+	use std::ops::{Deref, DerefMut};
+
+	pub struct Buf(Vec<u8>);
+
+	impl Buf {
+		pub fn zeroed(len: usize) -> Buf {
+			Buf(vec![0; len])
+		}
+	}
+
+	impl Deref for Buf {
+		type Target = [u8];
+		fn deref(&self) -> &[u8] {
+			&self.0
+		}
+	}
+
+	impl DerefMut for Buf {
+		fn deref_mut(&mut self) -> &mut [u8] {
+			&mut self.0
+		}
+	}
+}
+
+/// Secret byte string, e.g. a master secret. With the `mlock` feature, the bytes are
+/// kept in locked memory where the platform allows it
+pub struct SecretBytes(buf::Buf);
+
+impl SecretBytes {
+	/// Wrap the given bytes. They're copied into the secret's own storage and the
+	/// original Vec is zeroed
+	pub fn new(mut bytes: Vec<u8>) -> Self {
+		let mut s = SecretBytes::zeroed(bytes.len());
+		s.0.copy_from_slice(&bytes);
+		zeroize(&mut bytes);
+		s
+	}
+
+	/// A zero-filled secret of the given length, to be written through
+	/// [`as_mut_bytes`](SecretBytes::as_mut_bytes)
+	pub(crate) fn zeroed(len: usize) -> Self {
+		SecretBytes(buf::Buf::zeroed(len))
+	}
+
+	/// Mutably borrow the secret bytes. The length can't change, so the bytes stay
+	/// in locked memory
+	pub(crate) fn as_mut_bytes(&mut self) -> &mut [u8] {
+		&mut self.0
+	}
+
+	/// Borrow the secret bytes
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	/// Copy out into a plain Vec, which the caller is then responsible for
+	pub fn into_vec(self) -> Vec<u8> {
+		self.0.to_vec()
+	}
+}
+
+impl Default for SecretBytes {
+	fn default() -> Self {
+		SecretBytes::zeroed(0)
+	}
+}
+
+impl Clone for SecretBytes {
+	fn clone(&self) -> Self {
+		SecretBytes::from(self.as_bytes())
+	}
+}
+
+impl Drop for SecretBytes {
+	fn drop(&mut self) {
+		zeroize(&mut self.0);
+	}
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SecretBytes {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.as_bytes())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SecretBytes {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Vec::<u8>::deserialize(deserializer).map(SecretBytes::new)
+	}
+}
+
+impl fmt::Debug for SecretBytes {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "SecretBytes(<redacted, {} bytes>)", self.0.len())
+	}
+}
+
+impl Deref for SecretBytes {
+	type Target = [u8];
+	fn deref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl AsRef<[u8]> for SecretBytes {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl From<Vec<u8>> for SecretBytes {
+	fn from(bytes: Vec<u8>) -> Self {
+		SecretBytes::new(bytes)
+	}
+}
+
+impl From<&[u8]> for SecretBytes {
+	fn from(bytes: &[u8]) -> Self {
+		let mut s = SecretBytes::zeroed(bytes.len());
+		s.0.copy_from_slice(bytes);
+		s
+	}
+}
+
+impl PartialEq for SecretBytes {
+	fn eq(&self, other: &SecretBytes) -> bool {
+		ct::ct_eq(&self.0, &other.0)
+	}
+}
+
+impl Eq for SecretBytes {}
+
+impl PartialEq<Vec<u8>> for SecretBytes {
+	fn eq(&self, other: &Vec<u8>) -> bool {
+		ct::ct_eq(&self.0, other)
+	}
+}
+
+impl PartialEq<SecretBytes> for Vec<u8> {
+	fn eq(&self, other: &SecretBytes) -> bool {
+		ct::ct_eq(self, &other.0)
+	}
+}
+
+/// Passphrase used to encrypt the master secret
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Passphrase(String);
+
+impl Passphrase {
+	/// Wrap the given passphrase
+	pub fn new(passphrase: String) -> Self {
+		Passphrase(passphrase)
+	}
+
+	/// Borrow the passphrase as a str
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
+	/// Borrow the passphrase as bytes
+	pub fn as_bytes(&self) -> &[u8] {
+		self.0.as_bytes()
+	}
+}
+
+impl Drop for Passphrase {
+	fn drop(&mut self) {
+		// safe, as all zero bytes are valid UTF-8
+		zeroize(unsafe { self.0.as_bytes_mut() });
+	}
+}
+
+impl fmt::Debug for Passphrase {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Passphrase(<redacted>)")
+	}
+}
+
+impl From<&str> for Passphrase {
+	fn from(passphrase: &str) -> Self {
+		Passphrase(passphrase.to_owned())
+	}
+}
+
+impl From<String> for Passphrase {
+	fn from(passphrase: String) -> Self {
+		Passphrase(passphrase)
+	}
+}
+
+impl From<&Passphrase> for Passphrase {
+	fn from(passphrase: &Passphrase) -> Self {
+		passphrase.clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn redacted_debug() {
+		let s = SecretBytes::new(vec![0xde, 0xad, 0xbe, 0xef]);
+		assert_eq!(format!("{:?}", s), "SecretBytes(<redacted, 4 bytes>)");
+		assert_eq!(s, vec![0xde, 0xad, 0xbe, 0xef]);
+		assert_eq!(&s[..2], &[0xde, 0xad]);
+		let p = Passphrase::from("TREZOR");
+		assert_eq!(format!("{:?}", p), "Passphrase(<redacted>)");
+		assert_eq!(p.as_str(), "TREZOR");
+	}
+
+	#[test]
+	fn zeroize_buffer() {
+		let mut buf = vec![1u8, 2, 3];
+		zeroize(&mut buf);
+		assert_eq!(buf, vec![0, 0, 0]);
+		assert_eq!(SecretBytes::new(vec![1, 2]).into_vec(), vec![1, 2]);
+	}
+
+	#[test]
+	fn separate_buffers() {
+		let a = SecretBytes::new(vec![1; 16]);
+		let b = a.clone();
+		#[cfg(all(feature = "mlock", any(unix, windows)))]
+		assert_ne!(a.as_ptr() as usize / 4096, b.as_ptr() as usize / 4096);
+		drop(a);
+		// still readable and intact after the other buffer is freed
+		assert_eq!(b, vec![1; 16]);
+		assert_eq!(SecretBytes::default().len(), 0);
+		assert_eq!(SecretBytes::from(&b[..4]).as_bytes(), &[1; 4]);
+	}
+}
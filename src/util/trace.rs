@@ -0,0 +1,71 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Debugging breadcrumbs for generation, combination and the KDF, logged at debug
+//! level under the `sssmc39::trace` target with the `trace` feature and compiled
+//! out otherwise. Only ever pass non-secret values (indices, thresholds, counts,
+//! identifiers, timings) to these
+
+/// Log target of all trace output
+#[cfg(feature = "trace")]
+pub const TARGET: &str = "sssmc39::trace";
+
+/// Log a trace event
+macro_rules! trace_event {
+	($($arg:tt)*) => {
+		#[cfg(feature = "trace")]
+		debug!(target: $crate::util::trace::TARGET, $($arg)*);
+	};
+}
+
+pub(crate) use trace_event;
+
+/// A timed phase, logged when entered and, with its duration, when dropped
+pub struct Span {
+	#[cfg(feature = "trace")]
+	name: &'static str,
+	#[cfg(feature = "trace")]
+	start: std::time::Instant,
+}
+
+impl Span {
+	/// Enter the named phase
+	#[cfg(feature = "trace")]
+	pub fn enter(name: &'static str) -> Span {
+		debug!(target: TARGET, "{}: started", name);
+		Span {
+			name,
+			start: std::time::Instant::now(),
+		}
+	}
+
+	/// Enter the named phase
+	#[cfg(not(feature = "trace"))]
+	#[inline]
+	pub fn enter(_name: &'static str) -> Span {
+		Span {}
+	}
+}
+
+#[cfg(feature = "trace")]
+impl Drop for Span {
+	fn drop(&mut self) {
+		debug!(
+			target: TARGET,
+			"{}: finished in {:?}",
+			self.name,
+			self.start.elapsed()
+		);
+	}
+}
@@ -0,0 +1,294 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SLIP-39 test vectors, in the format of the upstream vectors.json file:
+//! a list of `[description, [mnemonics], master_secret_hex]` entries, where an
+//! empty master secret means decoding must fail. A fourth element (the BIP32
+//! root key in newer upstream files) is accepted and preserved
+
+use crate::error::{Error, ErrorKind};
+use crate::shamir::{combine_mnemonics, encrypt_with_config, split_ems_with, GroupShare, Splitter};
+use crate::util::encrypt::MasterSecretEncConfig;
+use crate::util::hex;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Passphrase used by the upstream test vectors
+pub const VECTOR_PASSPHRASE: &str = "TREZOR";
+
+/// A single test vector entry
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TVEntry {
+	/// Metadata (test description)
+	pub meta: String,
+	/// List of mnemonics
+	pub mnemonics: Vec<String>,
+	/// Resulting master secret in hex (decoding should fail if empty)
+	pub master_secret: String,
+	/// BIP32 root key, if provided by the vectors file
+	#[cfg_attr(
+		feature = "serde",
+		serde(default, skip_serializing_if = "String::is_empty")
+	)]
+	pub xprv: String,
+}
+
+impl TVEntry {
+	/// get mnemonics as Vec<Vec<String>>
+	pub fn mnemonics_to_vecs(&self) -> Vec<Vec<String>> {
+		self.mnemonics
+			.iter()
+			.map(|mn| mn.split_whitespace().map(|s| s.into()).collect())
+			.collect()
+	}
+
+	/// master secret to u8
+	pub fn master_secret_to_u8_vec(&self) -> Result<Vec<u8>, Error> {
+		Ok(hex::from_hex(&self.master_secret)?)
+	}
+
+	/// Whether decoding this vector is expected to succeed
+	pub fn is_valid(&self) -> bool {
+		!self.master_secret.is_empty()
+	}
+
+	/// Combine the vector's mnemonics, checking the result against the expected
+	/// master secret, or that combination fails for invalid vectors
+	pub fn check(&self) -> Result<(), Error> {
+		let result = combine_mnemonics(&self.mnemonics_to_vecs(), VECTOR_PASSPHRASE);
+		match (self.is_valid(), result) {
+			(true, Ok(ms)) => {
+				if ms.secret != self.master_secret_to_u8_vec()? {
+					return Err(ErrorKind::Value(format!(
+						"{}: recovered master secret does not match",
+						self.meta
+					)))?;
+				}
+				Ok(())
+			}
+			(true, Err(e)) => Err(ErrorKind::Value(format!(
+				"{}: combining should have succeeded: {}",
+				self.meta, e
+			)))?,
+			(false, Ok(_)) => Err(ErrorKind::Value(format!(
+				"{}: combining should have failed",
+				self.meta
+			)))?,
+			(false, Err(_)) => Ok(()),
+		}
+	}
+}
+
+/// Share layout and secret lengths of the vectors produced by [`generate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorSpec {
+	/// Master secret lengths in bytes, one batch of vectors per length
+	pub secret_lengths: Vec<usize>,
+	/// Group threshold of the vectors with group sharing
+	pub group_threshold: u8,
+	/// Groups of the vectors with group sharing
+	pub groups: Vec<(u8, u8)>,
+	/// Iteration exponent for all vectors
+	pub iteration_exponent: u8,
+}
+
+impl Default for VectorSpec {
+	fn default() -> Self {
+		VectorSpec {
+			secret_lengths: vec![16, 32],
+			group_threshold: 2,
+			groups: vec![(1, 1), (2, 3), (3, 5)],
+			iteration_exponent: 0,
+		}
+	}
+}
+
+/// Deterministically generate valid and invalid vectors from the given seed. For each
+/// secret length, this produces a valid single share and the same share with a bad
+/// checksum and with non-zero padding, then a valid group shared set along with
+/// too few members, too few groups and shares from two sets mixed together
+pub fn generate(seed: u64, spec: &VectorSpec) -> Result<Vec<TVEntry>, Error> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	let mut out = vec![];
+	let mut push = |desc: String, mnemonics: Vec<Vec<String>>, secret: &[u8]| {
+		out.push(TVEntry {
+			meta: format!("{}. {}", out.len() + 1, desc),
+			mnemonics: mnemonics.iter().map(|m| m.join(" ")).collect(),
+			master_secret: hex::to_hex(secret),
+			..Default::default()
+		});
+	};
+
+	for &len in &spec.secret_lengths {
+		let bits = len * 8;
+		let mut secret = vec![0u8; len];
+		rng.fill_bytes(&mut secret);
+		let single = generate_set(&mut rng, &secret, 1, &[(1, 1)], spec.iteration_exponent)?;
+		let share = &single[0].member_shares[0];
+		push(
+			format!("Valid mnemonic without sharing ({} bits)", bits),
+			vec![share.to_mnemonic()?],
+			&secret,
+		);
+		push(
+			format!("Mnemonic with invalid checksum ({} bits)", bits),
+			vec![share.with_invalid_checksum()?],
+			&[],
+		);
+		if let Ok(m) = share.with_invalid_padding() {
+			push(
+				format!("Mnemonic with invalid padding ({} bits)", bits),
+				vec![m],
+				&[],
+			);
+		}
+
+		let set = generate_set(
+			&mut rng,
+			&secret,
+			spec.group_threshold,
+			&spec.groups,
+			spec.iteration_exponent,
+		)?;
+		let selected = threshold_mnemonics(&set, spec.group_threshold)?;
+		push(
+			format!("Valid mnemonics with group sharing ({} bits)", bits),
+			selected.iter().flatten().cloned().collect(),
+			&secret,
+		);
+		if let Some(g) = selected.iter().position(|m| m.len() > 1) {
+			let mut under = selected.clone();
+			under[g].pop();
+			push(
+				format!("Insufficient number of member shares ({} bits)", bits),
+				under.into_iter().flatten().collect(),
+				&[],
+			);
+		}
+		if spec.group_threshold > 1 {
+			push(
+				format!("Insufficient number of groups ({} bits)", bits),
+				selected[1..].iter().flatten().cloned().collect(),
+				&[],
+			);
+		}
+		let other = generate_set(
+			&mut rng,
+			&secret,
+			spec.group_threshold,
+			&spec.groups,
+			spec.iteration_exponent,
+		)?;
+		let mut mixed = selected;
+		let last = mixed.len() - 1;
+		mixed[last] = threshold_mnemonics(&other, spec.group_threshold)?[last].clone();
+		push(
+			format!("Mnemonics with different identifiers ({} bits)", bits),
+			mixed.into_iter().flatten().collect(),
+			&[],
+		);
+	}
+	Ok(out)
+}
+
+/// Split the secret under an identifier and share randomness drawn from `rng`
+fn generate_set(
+	rng: &mut StdRng,
+	secret: &[u8],
+	group_threshold: u8,
+	groups: &[(u8, u8)],
+	iteration_exponent: u8,
+) -> Result<Vec<GroupShare>, Error> {
+	let identifier = rng.gen::<u16>() & 0x7fff;
+	let ems = encrypt_with_config(
+		secret,
+		VECTOR_PASSPHRASE,
+		iteration_exponent,
+		&MasterSecretEncConfig::new(),
+		identifier,
+	)?;
+	let splitter = Splitter::with_rng(None, StdRng::seed_from_u64(rng.gen()));
+	split_ems_with(&ems, group_threshold, groups, &splitter)
+}
+
+/// Member threshold many mnemonics from each of the first `group_threshold` groups
+fn threshold_mnemonics(
+	set: &[GroupShare],
+	group_threshold: u8,
+) -> Result<Vec<Vec<Vec<String>>>, Error> {
+	set.iter()
+		.take(group_threshold as usize)
+		.map(|g| {
+			g.member_shares
+				.iter()
+				.take(g.member_threshold as usize)
+				.map(|s| s.to_mnemonic())
+				.collect()
+		})
+		.collect()
+}
+
+/// Check each of the given vectors, returning the first failure
+pub fn check_all(vectors: &[TVEntry]) -> Result<(), Error> {
+	for tv in vectors {
+		tv.check()?;
+	}
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawEntry(
+	String,
+	Vec<String>,
+	String,
+	#[serde(default, skip_serializing_if = "String::is_empty")] String,
+);
+
+/// Parse vectors in the upstream vectors.json format
+#[cfg(feature = "serde")]
+pub fn from_json(input: &str) -> Result<Vec<TVEntry>, Error> {
+	let raw: Vec<RawEntry> = serde_json::from_str(input)
+		.map_err(|e| ErrorKind::Value(format!("Invalid test vector file: {}", e)))?;
+	Ok(raw
+		.into_iter()
+		.map(|r| TVEntry {
+			meta: r.0,
+			mnemonics: r.1,
+			master_secret: r.2,
+			xprv: r.3,
+		})
+		.collect())
+}
+
+/// Write vectors in the upstream vectors.json format
+#[cfg(feature = "serde")]
+pub fn to_json(vectors: &[TVEntry]) -> Result<String, Error> {
+	let raw: Vec<RawEntry> = vectors
+		.iter()
+		.map(|v| {
+			RawEntry(
+				v.meta.clone(),
+				v.mnemonics.clone(),
+				v.master_secret.clone(),
+				v.xprv.clone(),
+			)
+		})
+		.collect();
+	Ok(serde_json::to_string_pretty(&raw)
+		.map_err(|e| ErrorKind::Value(format!("Unable to serialize test vectors: {}", e)))?)
+}
@@ -0,0 +1,362 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Feldman verifiable secret sharing. The dealer publishes commitments to the
+//! sharing polynomials, against which each share holder can check their share
+//! without learning anything about the secret. Sharing is over the scalars of the
+//! Ristretto prime-order group, from the `curve25519-dalek` crate; the secret is
+//! cut into 31 byte chunks, each shared with its own polynomial. Shares are NOT
+//! SLIP-39 shares and can't be encoded as mnemonics. Only available with the
+//! `vss` feature.
+
+use crate::error::{Error, ErrorKind};
+use crate::util::secret::{zeroize, SecretBytes};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::MultiscalarMul;
+use rand::{CryptoRng, RngCore};
+use std::convert::TryInto;
+
+/// Length in bytes of an encoded scalar or group element. Share values and
+/// commitments hold one per chunk of the secret
+pub const VSS_ELEMENT_LEN: usize = 32;
+
+/// Bytes of the secret in each chunk, so every chunk is less than the group order
+pub const VSS_CHUNK_LEN: usize = 31;
+
+/// Longest secret that can be shared, in bytes
+pub const MAX_VSS_SECRET_LEN: usize = 255;
+
+// number of chunks the secret is cut into
+fn chunk_count(secret_len: usize) -> usize {
+	secret_len.div_ceil(VSS_CHUNK_LEN)
+}
+
+// a chunk of the secret as a scalar, little-endian
+fn chunk_scalar(chunk: &[u8]) -> Scalar {
+	let mut bytes = [0u8; VSS_ELEMENT_LEN];
+	bytes[..chunk.len()].copy_from_slice(chunk);
+	let s = Scalar::from_bytes_mod_order(bytes);
+	zeroize(&mut bytes);
+	s
+}
+
+// canonically encoded scalars, one per chunk
+fn decode_scalars(bytes: &[u8], chunks: usize) -> Option<Vec<Scalar>> {
+	if bytes.len() != chunks * VSS_ELEMENT_LEN {
+		return None;
+	}
+	bytes
+		.chunks(VSS_ELEMENT_LEN)
+		.map(|b| Option::from(Scalar::from_canonical_bytes(b.try_into().ok()?)))
+		.collect()
+}
+
+fn encode_points(points: &[RistrettoPoint]) -> Vec<u8> {
+	points
+		.iter()
+		.flat_map(|p| p.compress().to_bytes())
+		.collect()
+}
+
+/// A share of a secret split with [`split_secret_vss`]
+#[derive(Clone, PartialEq, Eq)]
+pub struct VssShare {
+	/// x coordinate of the share, from 1
+	pub index: u8,
+	/// Share value, a [`VSS_ELEMENT_LEN`] byte scalar per chunk of the secret
+	pub value: Vec<u8>,
+}
+
+impl std::fmt::Debug for VssShare {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("VssShare")
+			.field("index", &self.index)
+			.finish()
+	}
+}
+
+/// The dealer's public commitments to the sharing polynomials, one per coefficient.
+/// Publish these to every share holder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VssCommitments {
+	/// Length of the secret in bytes
+	pub secret_len: usize,
+	/// a_j * G for each coefficient a_j, as a compressed [`VSS_ELEMENT_LEN`] byte
+	/// Ristretto point per chunk of the secret. The number of commitments is the
+	/// threshold
+	pub commitments: Vec<Vec<u8>>,
+}
+
+impl VssCommitments {
+	/// Number of shares needed to recover the secret
+	pub fn threshold(&self) -> usize {
+		self.commitments.len()
+	}
+
+	fn chunks(&self) -> Result<usize, Error> {
+		if self.secret_len == 0 || self.secret_len > MAX_VSS_SECRET_LEN {
+			return Err(ErrorKind::Value("Invalid VSS secret length".to_string()))?;
+		}
+		Ok(chunk_count(self.secret_len))
+	}
+
+	// the commitments by coefficient, then chunk
+	fn elements(&self) -> Result<Vec<Vec<RistrettoPoint>>, Error> {
+		let chunks = self.chunks()?;
+		let err = || ErrorKind::Value("Invalid VSS commitment".to_string());
+		self.commitments
+			.iter()
+			.map(|c| {
+				if c.len() != chunks * VSS_ELEMENT_LEN {
+					return Err(err())?;
+				}
+				c.chunks(VSS_ELEMENT_LEN)
+					.map(|b| {
+						CompressedRistretto::from_slice(b)
+							.ok()
+							.and_then(|p| p.decompress())
+							.ok_or_else(|| err().into())
+					})
+					.collect()
+			})
+			.collect()
+	}
+}
+
+/// Splits a secret into `share_count` shares, any `threshold` of which recover
+/// it, along with commitments every share can be verified against
+pub fn split_secret_vss<R: RngCore + CryptoRng>(
+	threshold: u8,
+	share_count: u8,
+	secret: &[u8],
+	rng: &mut R,
+) -> Result<(Vec<VssShare>, VssCommitments), Error> {
+	if threshold == 0 || threshold > share_count {
+		Err(ErrorKind::Argument(format!(
+			"Threshold {} must be between 1 and the share count {}",
+			threshold, share_count
+		)))?;
+	}
+	if secret.is_empty() || secret.len() > MAX_VSS_SECRET_LEN {
+		Err(ErrorKind::Argument(format!(
+			"Secret must be between 1 and {} bytes",
+			MAX_VSS_SECRET_LEN
+		)))?;
+	}
+	// coefficients by chunk, constant term first
+	let mut coeffs: Vec<Vec<Scalar>> = secret
+		.chunks(VSS_CHUNK_LEN)
+		.map(|chunk| {
+			let mut c = vec![chunk_scalar(chunk)];
+			c.extend((1..threshold).map(|_| Scalar::random(rng)));
+			c
+		})
+		.collect();
+	let commitments = (0..threshold as usize)
+		.map(|j| {
+			let points: Vec<RistrettoPoint> = coeffs
+				.iter()
+				.map(|c| &c[j] * RISTRETTO_BASEPOINT_TABLE)
+				.collect();
+			encode_points(&points)
+		})
+		.collect();
+	let shares = (1..=share_count)
+		.map(|index| {
+			let x = Scalar::from(index);
+			// Horner's rule for each chunk's polynomial
+			let value = coeffs
+				.iter()
+				.flat_map(|c| {
+					c.iter()
+						.rev()
+						.fold(Scalar::ZERO, |acc, a| acc * x + a)
+						.to_bytes()
+				})
+				.collect();
+			VssShare { index, value }
+		})
+		.collect();
+	for c in coeffs.iter_mut().flatten() {
+		*c = Scalar::ZERO;
+	}
+	Ok((
+		shares,
+		VssCommitments {
+			secret_len: secret.len(),
+			commitments,
+		},
+	))
+}
+
+// the share's scalars, checking they lie on the committed polynomials:
+// value * G = sum(C_j * index^j) for each chunk
+fn verified_scalars(
+	share: &VssShare,
+	elements: &[Vec<RistrettoPoint>],
+	chunks: usize,
+) -> Result<Vec<Scalar>, Error> {
+	let ys = match decode_scalars(&share.value, chunks) {
+		Some(ys) if share.index != 0 => ys,
+		_ => {
+			return Err(ErrorKind::Value(format!(
+				"Invalid VSS share {}",
+				share.index
+			)))?
+		}
+	};
+	let x = Scalar::from(share.index);
+	let x_pows: Vec<Scalar> = elements
+		.iter()
+		.scan(Scalar::ONE, |acc, _| {
+			let p = *acc;
+			*acc *= x;
+			Some(p)
+		})
+		.collect();
+	for (i, y) in ys.iter().enumerate() {
+		let expected = RistrettoPoint::multiscalar_mul(&x_pows, elements.iter().map(|c| c[i]));
+		if y * RISTRETTO_BASEPOINT_TABLE != expected {
+			Err(ErrorKind::Digest(format!(
+				"VSS share {} doesn't match the commitments",
+				share.index
+			)))?;
+		}
+	}
+	Ok(ys)
+}
+
+/// Checks the share lies on the polynomials the commitments were made to
+pub fn verify_share(share: &VssShare, commitments: &VssCommitments) -> Result<(), Error> {
+	let elements = commitments.elements()?;
+	verified_scalars(share, &elements, commitments.chunks()?)?;
+	Ok(())
+}
+
+/// Recovers the secret from at least a threshold of shares, verifying each one
+/// and the result against the commitments
+pub fn recover_secret_vss(
+	shares: &[VssShare],
+	commitments: &VssCommitments,
+) -> Result<SecretBytes, Error> {
+	let threshold = commitments.threshold();
+	if threshold == 0 || shares.len() < threshold {
+		Err(ErrorKind::Value(format!(
+			"Need {} shares to recover the secret, got {}",
+			threshold,
+			shares.len()
+		)))?;
+	}
+	let elements = commitments.elements()?;
+	let chunks = commitments.chunks()?;
+	let shares = &shares[..threshold];
+	let mut values = vec![];
+	for (i, s) in shares.iter().enumerate() {
+		if shares[..i].iter().any(|t| t.index == s.index) {
+			Err(ErrorKind::Value(
+				"Invalid set of shares. Share indices must be unique".to_string(),
+			))?;
+		}
+		values.push(verified_scalars(s, &elements, chunks)?);
+	}
+	// Lagrange interpolation at 0
+	let bases: Vec<Scalar> = shares
+		.iter()
+		.map(|s| {
+			let xi = Scalar::from(s.index);
+			let (num, den) = shares
+				.iter()
+				.filter(|t| t.index != s.index)
+				.map(|t| Scalar::from(t.index))
+				.fold((Scalar::ONE, Scalar::ONE), |(n, d), xj| {
+					(n * xj, d * (xj - xi))
+				});
+			num * den.invert()
+		})
+		.collect();
+	// each chunk is VSS_CHUNK_LEN bytes, except perhaps the last
+	let mut secret = SecretBytes::zeroed(commitments.secret_len);
+	for (i, out) in secret.as_mut_bytes().chunks_mut(VSS_CHUNK_LEN).enumerate() {
+		let s = values
+			.iter()
+			.zip(&bases)
+			.fold(Scalar::ZERO, |acc, (v, b)| acc + v[i] * b);
+		if &s * RISTRETTO_BASEPOINT_TABLE != elements[0][i] {
+			Err(ErrorKind::Digest(
+				"Recovered secret doesn't match the commitments".to_string(),
+			))?;
+		}
+		if s.as_bytes()[out.len()..].iter().any(|b| *b != 0) {
+			Err(ErrorKind::Value(
+				"Recovered secret is longer than committed".to_string(),
+			))?;
+		}
+		out.copy_from_slice(&s.as_bytes()[..out.len()]);
+	}
+	for v in values.iter_mut().flatten() {
+		*v = Scalar::ZERO;
+	}
+	Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::thread_rng;
+
+	#[test]
+	fn split_verify_recover() -> Result<(), Error> {
+		let secret = b"\x0c\x94\x90\xbcn\xd6\xbc\xbf\xac>\xbe}\xeeV\xf2P".to_vec();
+		let (shares, commitments) = split_secret_vss(3, 5, &secret, &mut thread_rng())?;
+		assert_eq!(shares.len(), 5);
+		assert_eq!(commitments.threshold(), 3);
+		verify_share(&shares[4], &commitments)?;
+
+		let mut bad = shares[1].clone();
+		bad.value[0] ^= 1;
+		assert!(verify_share(&bad, &commitments).is_err());
+		// non-canonical scalars are rejected
+		let mut non_canonical = shares[1].clone();
+		non_canonical.value[VSS_ELEMENT_LEN - 1] = 0xff;
+		assert!(verify_share(&non_canonical, &commitments).is_err());
+
+		let subset = vec![shares[4].clone(), shares[0].clone(), shares[2].clone()];
+		assert_eq!(
+			recover_secret_vss(&subset, &commitments)?.as_bytes(),
+			&secret[..]
+		);
+		assert!(
+			recover_secret_vss(&[bad, shares[0].clone(), shares[2].clone()], &commitments).is_err()
+		);
+		assert!(recover_secret_vss(&shares[..2], &commitments).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn multi_chunk_secrets() -> Result<(), Error> {
+		for len in [1, 31, 32, 64, MAX_VSS_SECRET_LEN] {
+			let secret: Vec<u8> = (0..len).map(|i| (i * 7 + 0xf0) as u8).collect();
+			let (shares, commitments) = split_secret_vss(2, 3, &secret, &mut thread_rng())?;
+			assert_eq!(shares[0].value.len(), chunk_count(len) * VSS_ELEMENT_LEN);
+			assert_eq!(
+				recover_secret_vss(&shares[1..], &commitments)?.as_bytes(),
+				&secret[..]
+			);
+		}
+		assert!(split_secret_vss(2, 3, &[0; MAX_VSS_SECRET_LEN + 1], &mut thread_rng()).is_err());
+		Ok(())
+	}
+}
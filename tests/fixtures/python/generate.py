@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+# Generates deterministic interop fixtures with the python reference
+# implementation (pip install shamir-mnemonic). Only layouts in which every
+# threshold is 1 are deterministic once the identifier is fixed, since any
+# other split draws random share values. Usage:
+#
+#   python3 generate.py > deterministic.json
+#
+# UNFINISHED: deterministic.json has not been generated by this script yet,
+# as the reference package couldn't be installed where it was written. It
+# holds only the two "without sharing" vectors copied from the upstream
+# SLIP-39 test vectors (the first two CASES). Run the script and commit its
+# output to cover the remaining topologies, passphrases and exponents.
+
+import json
+
+from shamir_mnemonic import EncryptedMasterSecret
+from shamir_mnemonic.shamir import split_ems
+
+CASES = [
+    # description, master secret, passphrase, identifier, exponent, group threshold, groups
+    ("1. Valid mnemonic without sharing (128 bits)",
+     "bb54aac4b89dc868ba37d9cc21b2cece", "TREZOR", 7945, 0, 1, [(1, 1)]),
+    ("20. Valid mnemonic without sharing (256 bits)",
+     "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92", "TREZOR", 29172, 0, 1, [(1, 1)]),
+    ("1-of-3 members, empty passphrase",
+     "00112233445566778899aabbccddeeff", "", 1, 0, 1, [(1, 3)]),
+    ("1-of-2 groups, exponent 1",
+     "ffeeddccbbaa99887766554433221100", "TREZOR", 21845, 1, 1, [(1, 1), (1, 2)]),
+    ("1-of-3 groups, exponent 2, 256 bits",
+     "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "correct horse", 32767, 2, 1, [(1, 1), (1, 1), (1, 2)]),
+]
+
+
+def fixture(description, secret, passphrase, identifier, exponent, group_threshold, groups):
+    ems = EncryptedMasterSecret.from_master_secret(
+        bytes.fromhex(secret), passphrase.encode(), identifier, False, exponent
+    )
+    shares = split_ems(group_threshold, groups, ems)
+    return {
+        "description": description,
+        "master_secret": secret,
+        "passphrase": passphrase,
+        "identifier": identifier,
+        "iteration_exponent": exponent,
+        "group_threshold": group_threshold,
+        "groups": [list(g) for g in groups],
+        "mnemonics": [[s.mnemonic() for s in group] for group in shares],
+    }
+
+
+if __name__ == "__main__":
+    print(json.dumps([fixture(*c) for c in CASES], indent=2))